@@ -0,0 +1,466 @@
+use crate::config::{Schema, VariableType};
+use clap::ValueEnum;
+
+/// A language `codegen` writes a typed config module for.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Language {
+    /// A `Config` struct with `Config::from_env()`
+    Rust,
+    /// A `Config` interface with `loadConfig()`
+    Typescript,
+    /// A `Config` dataclass with `Config.from_env()`
+    Python,
+}
+
+impl Language {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Language::Rust => "rs",
+            Language::Typescript => "ts",
+            Language::Python => "py",
+        }
+    }
+}
+
+/// One schema variable as a field of the generated `Config`.
+struct Field<'a> {
+    key: &'a str,
+    description: Option<&'a str>,
+    value_type: VariableType,
+    required: bool,
+}
+
+const HEADER: &str = "Generated by envMatch from schema.yaml. Do not edit; run `envMatch codegen` again after changing the schema.";
+
+/// Renders a module giving typed access to the variables `schema` declares.
+/// Variables without a declared type are strings; ones that aren't required
+/// are optional.
+pub fn render(language: Language, schema: &Schema) -> String {
+    let fields: Vec<Field> = schema
+        .variables
+        .iter()
+        .map(|(key, variable)| Field {
+            key,
+            description: variable.description.as_deref(),
+            value_type: variable.value_type.unwrap_or(VariableType::String),
+            required: variable.required,
+        })
+        .collect();
+    match language {
+        Language::Rust => render_rust(&fields),
+        Language::Typescript => render_typescript(&fields),
+        Language::Python => render_python(&fields),
+    }
+}
+
+fn render_rust(fields: &[Field]) -> String {
+    let mut output = format!("// {}\n\n", HEADER);
+    output.push_str("/// Configuration read from environment variables.\n");
+    output.push_str("#[derive(Clone, Debug, PartialEq)]\npub struct Config {\n");
+    for field in fields {
+        if let Some(description) = field.description {
+            for line in description.lines() {
+                output.push_str(&format!("    /// {}\n", line));
+            }
+        }
+        let value_type = match field.value_type {
+            VariableType::String => "String",
+            VariableType::Number => "f64",
+            VariableType::Bool => "bool",
+        };
+        let value_type = match field.required {
+            true => value_type.to_string(),
+            false => format!("Option<{}>", value_type),
+        };
+        output.push_str(&format!(
+            "    pub {}: {},\n",
+            rust_name(field.key),
+            value_type
+        ));
+    }
+    output.push_str("}\n\n");
+
+    output.push_str(
+        "/// A variable that's missing or doesn't read as its type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigError {
+    pub key: &'static str,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, \"{}: {}\", self.key, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Reads every variable from the process environment.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self {
+",
+    );
+    for field in fields {
+        let read = match (field.value_type, field.required) {
+            (VariableType::String, true) => format!("required({:?})?", field.key),
+            (VariableType::String, false) => format!("optional({:?})", field.key),
+            (_, true) => format!("parse({0:?}, required({0:?})?)?", field.key),
+            (_, false) => format!(
+                "optional({0:?}).map(|value| parse({0:?}, value)).transpose()?",
+                field.key
+            ),
+        };
+        output.push_str(&format!(
+            "            {}: {},\n",
+            rust_name(field.key),
+            read
+        ));
+    }
+    output.push_str(
+        "        })
+    }
+}
+
+#[allow(dead_code)]
+fn optional(key: &'static str) -> Option<String> {
+    std::env::var(key).ok()
+}
+
+#[allow(dead_code)]
+fn required(key: &'static str) -> Result<String, ConfigError> {
+    optional(key).ok_or_else(|| ConfigError {
+        key,
+        message: \"is not set\".to_string(),
+    })
+}
+
+#[allow(dead_code)]
+fn parse<T: std::str::FromStr>(key: &'static str, value: String) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError {
+        key,
+        message: format!(\"'{}' is not a valid {}\", value, std::any::type_name::<T>()),
+    })
+}
+",
+    );
+    output
+}
+
+fn render_typescript(fields: &[Field]) -> String {
+    let mut output = format!("// {}\n\n", HEADER);
+    output.push_str(
+        "/** Configuration read from environment variables. */\nexport interface Config {\n",
+    );
+    for field in fields {
+        if let Some(description) = field.description {
+            output.push_str(&format!("  /** {} */\n", description.replace("*/", "* /")));
+        }
+        let value_type = match field.value_type {
+            VariableType::String => "string",
+            VariableType::Number => "number",
+            VariableType::Bool => "boolean",
+        };
+        let optional = if field.required { "" } else { "?" };
+        output.push_str(&format!(
+            "  {}{}: {};\n",
+            camel_case(field.key),
+            optional,
+            value_type
+        ));
+    }
+    output.push_str(
+        "}
+
+type Env = Record<string, string | undefined>;
+
+/** Reads every variable, throwing when one is missing or doesn't read as its type. */
+export function loadConfig(env: Env = process.env): Config {
+  return {
+",
+    );
+    for field in fields {
+        let read = match field.value_type {
+            VariableType::String => "asString",
+            VariableType::Number => "asNumber",
+            VariableType::Bool => "asBoolean",
+        };
+        let get = if field.required {
+            "required"
+        } else {
+            "optional"
+        };
+        output.push_str(&format!(
+            "    {}: {}(env, {:?}, {}),\n",
+            camel_case(field.key),
+            get,
+            field.key,
+            read
+        ));
+    }
+    output.push_str(
+        "  };
+}
+
+type Read<T> = (key: string, value: string) => T;
+
+function required<T>(env: Env, key: string, read: Read<T>): T {
+  const value = env[key];
+  if (value === undefined) {
+    throw new Error(`${key} is not set`);
+  }
+  return read(key, value);
+}
+
+function optional<T>(env: Env, key: string, read: Read<T>): T | undefined {
+  const value = env[key];
+  return value === undefined ? undefined : read(key, value);
+}
+
+function asString(_key: string, value: string): string {
+  return value;
+}
+
+function asNumber(key: string, value: string): number {
+  const number = Number(value);
+  if (value.trim() === \"\" || Number.isNaN(number)) {
+    throw new Error(`${key}: '${value}' is not a number`);
+  }
+  return number;
+}
+
+function asBoolean(key: string, value: string): boolean {
+  if (value === \"true\" || value === \"false\") {
+    return value === \"true\";
+  }
+  throw new Error(`${key}: '${value}' is not true or false`);
+}
+",
+    );
+    output
+}
+
+fn render_python(fields: &[Field]) -> String {
+    let mut output = format!("# {}\n\n", HEADER);
+    output.push_str(
+        "from __future__ import annotations
+
+import os
+from dataclasses import dataclass
+from typing import Callable, Mapping, Optional, TypeVar
+
+T = TypeVar(\"T\")
+
+
+class ConfigError(Exception):
+    \"\"\"A variable that's missing or doesn't read as its type.\"\"\"
+
+
+@dataclass(frozen=True)
+class Config:
+    \"\"\"Configuration read from environment variables.\"\"\"
+
+",
+    );
+    // Dataclass fields with defaults have to come after the ones without
+    let ordered: Vec<&Field> = fields
+        .iter()
+        .filter(|field| field.required)
+        .chain(fields.iter().filter(|field| !field.required))
+        .collect();
+    for field in &ordered {
+        if let Some(description) = field.description {
+            for line in description.lines() {
+                output.push_str(&format!("    # {}\n", line));
+            }
+        }
+        let value_type = match field.value_type {
+            VariableType::String => "str",
+            VariableType::Number => "float",
+            VariableType::Bool => "bool",
+        };
+        match field.required {
+            true => output.push_str(&format!("    {}: {}\n", python_name(field.key), value_type)),
+            false => output.push_str(&format!(
+                "    {}: Optional[{}] = None\n",
+                python_name(field.key),
+                value_type
+            )),
+        }
+    }
+    output.push_str(
+        "
+    @classmethod
+    def from_env(cls, env: Mapping[str, str] = os.environ) -> Config:
+        \"\"\"Reads every variable, raising ConfigError when one is missing or doesn't read as its type.\"\"\"
+        return cls(
+",
+    );
+    for field in &ordered {
+        let read = match field.value_type {
+            VariableType::String => "_as_str",
+            VariableType::Number => "_as_float",
+            VariableType::Bool => "_as_bool",
+        };
+        let get = if field.required {
+            "_required"
+        } else {
+            "_optional"
+        };
+        output.push_str(&format!(
+            "            {}={}(env, {:?}, {}),\n",
+            python_name(field.key),
+            get,
+            field.key,
+            read
+        ));
+    }
+    output.push_str(
+        "        )
+
+
+def _required(env: Mapping[str, str], key: str, read: Callable[[str, str], T]) -> T:
+    if key not in env:
+        raise ConfigError(f\"{key} is not set\")
+    return read(key, env[key])
+
+
+def _optional(env: Mapping[str, str], key: str, read: Callable[[str, str], T]) -> Optional[T]:
+    return read(key, env[key]) if key in env else None
+
+
+def _as_str(key: str, value: str) -> str:
+    return value
+
+
+def _as_float(key: str, value: str) -> float:
+    try:
+        return float(value)
+    except ValueError:
+        raise ConfigError(f\"{key}: '{value}' is not a number\") from None
+
+
+def _as_bool(key: str, value: str) -> bool:
+    if value not in (\"true\", \"false\"):
+        raise ConfigError(f\"{key}: '{value}' is not true or false\")
+    return value == \"true\"
+",
+    );
+    output
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "yield",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+    "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda",
+    "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+/// `DATABASE_URL` as `database_url`; characters that can't appear in a name
+/// become underscores, and a leading digit gets one in front.
+fn snake_case(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '_',
+        })
+        .collect();
+    match name.starts_with(|c: char| c.is_ascii_digit()) || name.is_empty() {
+        true => format!("_{}", name),
+        false => name,
+    }
+}
+
+fn rust_name(key: &str) -> String {
+    let name = snake_case(key);
+    match RUST_KEYWORDS.contains(&name.as_str()) {
+        true => format!("r#{}", name),
+        false => name,
+    }
+}
+
+fn python_name(key: &str) -> String {
+    let name = snake_case(key);
+    match PYTHON_KEYWORDS.contains(&name.as_str()) {
+        true => format!("{}_", name),
+        false => name,
+    }
+}
+
+/// `DATABASE_URL` as `databaseUrl`.
+fn camel_case(key: &str) -> String {
+    let mut name = String::new();
+    for (index, word) in snake_case(key)
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .enumerate()
+    {
+        match index {
+            0 => name.push_str(word),
+            _ => {
+                let mut chars = word.chars();
+                name.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+                name.push_str(chars.as_str());
+            }
+        }
+    }
+    match name.starts_with(|c: char| c.is_ascii_digit()) || name.is_empty() {
+        true => format!("_{}", name),
+        false => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VariableSchema;
+
+    #[test]
+    fn test_render_typed_accessors() {
+        let mut schema = Schema::default();
+        schema.variables.insert(
+            "DATABASE_URL".to_string(),
+            VariableSchema {
+                description: Some("Primary database".to_string()),
+                required: true,
+                ..Default::default()
+            },
+        );
+        schema.variables.insert(
+            "PORT".to_string(),
+            VariableSchema {
+                value_type: Some(VariableType::Number),
+                ..Default::default()
+            },
+        );
+        schema
+            .variables
+            .insert("TYPE".to_string(), Default::default());
+
+        let rust = render(Language::Rust, &schema);
+        assert!(rust.contains("    /// Primary database\n    pub database_url: String,\n"));
+        assert!(rust.contains("    pub port: Option<f64>,\n"));
+        assert!(rust.contains("    pub r#type: Option<String>,\n"));
+        assert!(rust.contains("database_url: required(\"DATABASE_URL\")?,"));
+
+        let typescript = render(Language::Typescript, &schema);
+        assert!(typescript.contains("  databaseUrl: string;\n"));
+        assert!(typescript.contains("  port?: number;\n"));
+        assert!(typescript.contains("port: optional(env, \"PORT\", asNumber),"));
+
+        let python = render(Language::Python, &schema);
+        assert!(python.contains("    database_url: str\n"));
+        assert!(python.contains("    port: Optional[float] = None\n"));
+
+        assert_eq!(camel_case("2FA_SECRET"), "_2faSecret");
+        assert_eq!(python_name("class"), "class_");
+    }
+}
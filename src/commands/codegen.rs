@@ -0,0 +1,44 @@
+use super::EnvMatchCommands;
+use crate::codegen::{self, Language};
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+impl EnvMatchCommands {
+    /// Generates a typed config module for `language` from the schema,
+    /// writing it to `output` (with the language's extension added if it
+    /// has none) or printing it.
+    pub fn codegen(&self, language: Language, output: Option<&Path>) -> Result<String> {
+        let schema = self.config_manager.load_schema()?;
+        let rendered = codegen::render(language, &schema);
+        let Some(path) = output else {
+            print!("{}", rendered);
+            return Ok(rendered);
+        };
+
+        let path = match path.extension() {
+            Some(_) => path.to_path_buf(),
+            None => path.with_extension(language.extension()),
+        };
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(|e| EnvMatchError::storage(parent, e))?;
+        }
+        fs::write(&path, &rendered).map_err(|e| EnvMatchError::storage(&path, e))?;
+        if !self.quiet {
+            println!(
+                "{} {} {} {}",
+                "✅ Wrote config module for".bright_green().bold(),
+                format!("{} variable(s)", schema.variables.len())
+                    .bright_green()
+                    .bold(),
+                "to".bright_white(),
+                path.display().to_string().bright_cyan()
+            );
+        }
+        Ok(rendered)
+    }
+}
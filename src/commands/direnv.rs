@@ -0,0 +1,74 @@
+use super::EnvMatchCommands;
+use crate::config;
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const ENVRC_FILE: &str = ".envrc";
+
+impl EnvMatchCommands {
+    /// Makes direnv load the current environment in the project: writes the
+    /// envMatch block of `.envrc` and allows it. With `watch_stamp`, direnv
+    /// also reloads whenever variables or the current environment change.
+    pub fn direnv_setup(&self, watch_stamp: bool) -> Result<PathBuf> {
+        if !self.is_initialized() {
+            return Err(EnvMatchError::NotInitialized);
+        }
+        let project_dir = self
+            .config_manager
+            .project_dir()
+            .ok_or(EnvMatchError::NotInitialized)?;
+        let envrc = project_dir.join(ENVRC_FILE);
+        let existing = match fs::read_to_string(&envrc) {
+            Ok(existing) => existing,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(EnvMatchError::storage(&envrc, e)),
+        };
+
+        let watch_line = format!(
+            "watch_file {}",
+            self.config_manager.watch_stamp_path().display()
+        );
+        let mut lines = Vec::new();
+        if watch_stamp {
+            self.config_manager.create_watch_stamp()?;
+            lines.push(watch_line.as_str());
+        }
+        lines.push(r#"eval "$(envMatch shell)""#);
+        fs::write(&envrc, config::with_managed_block(&existing, &lines))
+            .map_err(|e| EnvMatchError::storage(&envrc, e))?;
+
+        let allowed = direnv_allow(&envrc);
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "✅ Wrote".bright_green().bold(),
+                envrc.display().to_string().bright_cyan()
+            );
+            match allowed {
+                Ok(()) => println!("{}", "🔓 Allowed it with direnv".bright_blue()),
+                Err(reason) => println!(
+                    "{} {}",
+                    format!("⚠️  {}, then run:", reason).bright_yellow(),
+                    "direnv allow".bright_cyan()
+                ),
+            }
+        }
+        Ok(envrc)
+    }
+}
+
+fn direnv_allow(envrc: &Path) -> std::result::Result<(), String> {
+    match Command::new("direnv").arg("allow").arg(envrc).output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(format!(
+            "direnv allow failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err("direnv isn't installed".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
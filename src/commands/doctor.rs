@@ -0,0 +1,56 @@
+use super::EnvMatchCommands;
+use crate::config::{Finding, Severity};
+use crate::error::{EnvMatchError, Result};
+use crate::sync;
+use colored::*;
+use std::collections::BTreeMap;
+
+impl EnvMatchCommands {
+    /// Diagnoses the project and prints how to fix what's wrong.
+    /// Fails if any errors were found; warnings alone don't fail.
+    pub fn doctor(&self) -> Result<Vec<Finding>> {
+        let mut findings = self.config_manager.diagnose();
+
+        if self.is_initialized() {
+            let mut remotes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for (env_name, remote) in self.config_manager.list_sync_remotes()? {
+                remotes.entry(remote).or_default().push(env_name);
+            }
+            for (remote, env_names) in remotes {
+                findings.push(match sync::check_remote(&remote) {
+                    Ok(()) => Finding::ok(format!("{} is reachable", remote)),
+                    Err(reason) => Finding::warning(
+                        format!("Can't reach {}: {}", remote, reason),
+                        format!(
+                            "Check your network and credentials before syncing {}",
+                            env_names.join(", ")
+                        ),
+                    ),
+                });
+            }
+        }
+
+        println!("{}", "🩺 envMatch doctor".bright_blue().bold());
+        println!("{}", "─".repeat(40).bright_blue());
+        for finding in &findings {
+            let line = match finding.severity {
+                Severity::Ok => format!("✅ {}", finding.message).bright_green(),
+                Severity::Warning => format!("⚠️  {}", finding.message).bright_yellow(),
+                Severity::Error => format!("❌ {}", finding.message).bright_red(),
+            };
+            println!("{}", line);
+            if let Some(fix) = &finding.fix {
+                println!("   {} {}", "💡".bright_magenta(), fix.bright_cyan());
+            }
+        }
+
+        let errors = findings
+            .iter()
+            .filter(|finding| finding.severity == Severity::Error)
+            .count();
+        if errors > 0 {
+            return Err(EnvMatchError::DoctorFoundProblems { count: errors });
+        }
+        Ok(findings)
+    }
+}
@@ -0,0 +1,119 @@
+use super::EnvMatchCommands;
+use crate::config::VariableSchema;
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// Version of the `dump` document, raised only when a field is
+/// removed or changes meaning; new fields may appear at any time.
+pub const DUMP_VERSION: u32 = 1;
+
+/// Everything editor plugins need for completions and hovers, in one
+/// document. Values are left out, so nothing secret leaves the project.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Dump {
+    pub version: u32,
+    pub project: Option<PathBuf>,
+    pub current_environment: String,
+    /// Every key set in an environment or declared in the schema, sorted
+    pub keys: Vec<String>,
+    pub environments: Vec<DumpEnvironment>,
+    pub schema: BTreeMap<String, VariableSchema>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DumpEnvironment {
+    pub name: String,
+    pub variables: Vec<DumpVariable>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DumpVariable {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    pub secret: bool,
+}
+
+impl EnvMatchCommands {
+    /// Describes the project's environments and schema for tools, printing
+    /// the document as JSON.
+    pub fn dump(&self) -> Result<Dump> {
+        let schema = self.config_manager.load_schema()?;
+        let mut keys: BTreeSet<String> = schema.variables.keys().cloned().collect();
+        let mut environments = Vec::new();
+        for name in self.config_manager.list_environments()? {
+            let env_config = self.config_manager.load_effective_environment(&name)?;
+            let variables = env_config
+                .variables
+                .keys()
+                .map(|key| {
+                    let metadata = env_config.metadata.get(key).cloned().unwrap_or_default();
+                    DumpVariable {
+                        key: key.clone(),
+                        description: metadata
+                            .description
+                            .or_else(|| schema.variables.get(key)?.description.clone()),
+                        tags: metadata.tags,
+                        secret: super::is_secret(&env_config, &schema, key),
+                    }
+                })
+                .collect();
+            keys.extend(env_config.variables.into_keys());
+            environments.push(DumpEnvironment { name, variables });
+        }
+
+        let dump = Dump {
+            version: DUMP_VERSION,
+            project: self.project_dir().map(|dir| dir.to_path_buf()),
+            current_environment: self.config_manager.active_environment()?,
+            keys: keys.into_iter().collect(),
+            environments,
+            schema: schema.variables,
+        };
+        println!("{}", serde_json::to_string_pretty(&dump)?);
+        Ok(dump)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigManager, VariableMetadata};
+
+    #[test]
+    fn test_dump_leaves_out_values() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("production").unwrap();
+        let secret = VariableMetadata {
+            secret: true,
+            description: Some("Payments".to_string()),
+            ..Default::default()
+        };
+        commands
+            .set_variables_with_metadata(
+                &[("STRIPE_KEY".to_string(), "sk_live".to_string())],
+                &secret,
+                "production",
+            )
+            .unwrap();
+        commands.set_variable("PORT", "80", "development").unwrap();
+
+        let dump = commands.dump().unwrap();
+        assert_eq!(dump.version, DUMP_VERSION);
+        assert_eq!(dump.current_environment, "development");
+        assert_eq!(dump.keys, ["PORT", "STRIPE_KEY"]);
+        let production = &dump.environments[1];
+        assert_eq!(production.name, "production");
+        assert!(production.variables[0].secret);
+        assert_eq!(
+            production.variables[0].description.as_deref(),
+            Some("Payments")
+        );
+        assert!(!serde_json::to_string(&dump).unwrap().contains("sk_live"));
+    }
+}
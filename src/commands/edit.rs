@@ -0,0 +1,236 @@
+use super::prompt::confirm;
+use super::{print_changes, EnvMatchCommands};
+use crate::config::{Schema, Settings};
+use crate::diff::{self, VariableChange};
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+use indexmap::IndexMap;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::process::Command;
+
+impl EnvMatchCommands {
+    /// Opens an environment's variables in the `editor` setting, else
+    /// `$VISUAL`/`$EDITOR`, as a YAML map and saves what was changed once the editor exits. The edited
+    /// file must parse, use valid names and respect the schema; otherwise
+    /// the problems are listed and, in a terminal, the file can be edited
+    /// again. Returns the changes made.
+    pub fn edit_environment(&self, env_name: Option<&str>) -> Result<Vec<VariableChange>> {
+        let env_name = self.resolve_environment(env_name)?;
+        if !self.config_manager.environment_exists(&env_name) {
+            return Err(self.config_manager.environment_not_found(&env_name));
+        }
+        let env_config = self.config_manager.load_environment(&env_name)?;
+        let settings = self.settings()?;
+        let schema = self.config_manager.load_schema()?;
+
+        // Created private to the owner, and removed when dropped
+        let file = tempfile::Builder::new()
+            .prefix(&format!("envMatch-{}-", env_name))
+            .suffix(".yaml")
+            .tempfile()?;
+        let original = format!(
+            "# Variables of '{}'. Save and quit to apply, or quit without saving to cancel.\n{}",
+            env_name,
+            if env_config.variables.is_empty() {
+                String::new()
+            } else {
+                serde_yaml::to_string(&env_config.variables)?
+            }
+        );
+        fs::write(file.path(), &original)?;
+
+        let variables = loop {
+            open_editor(file.path(), settings.editor())?;
+            let content = fs::read_to_string(file.path())?;
+            if content == original {
+                println!("{}", "No changes".bright_black());
+                return Ok(vec![]);
+            }
+            match check_edit(&content, &settings, &schema) {
+                Ok(variables) => break variables,
+                Err(problems) => {
+                    println!(
+                        "{}",
+                        format!("❌ {} problem(s) in the edited file:", problems.len())
+                            .bright_red()
+                            .bold()
+                    );
+                    for problem in &problems {
+                        println!("  {}", problem.bright_red());
+                    }
+                    if !io::stdin().is_terminal() || !confirm("Edit again?", true)? {
+                        return Err(EnvMatchError::EditRejected {
+                            env: env_name,
+                            problems: problems.len(),
+                        });
+                    }
+                }
+            }
+        };
+
+        let changes = diff::diff_variables(&env_config.variables, &variables);
+        let command = format!("edit -e {}", env_name);
+        self.transaction(&command, |tx| {
+            for change in &changes {
+                match change {
+                    VariableChange::Added { key, value }
+                    | VariableChange::Changed {
+                        key, new: value, ..
+                    } => {
+                        tx.set(&env_name, key, value)?;
+                    }
+                    VariableChange::Removed { key, .. } => {
+                        tx.unset(&env_name, key)?;
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        print_changes(&changes);
+        Ok(changes)
+    }
+}
+
+/// Runs the user's editor on `path`. The editor may come with arguments,
+/// as in `EDITOR="code --wait"`.
+fn open_editor(path: &std::path::Path, configured: Option<&str>) -> Result<()> {
+    let editor = choose_editor(
+        configured,
+        std::env::var("VISUAL").ok(),
+        std::env::var("EDITOR").ok(),
+    );
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|source| EnvMatchError::RunError {
+            command: editor.clone(),
+            source,
+        })?;
+    if !status.success() {
+        return Err(EnvMatchError::RunError {
+            command: editor,
+            source: io::Error::other(format!("exited with {}", status)),
+        });
+    }
+    Ok(())
+}
+
+/// The configured editor, else `$VISUAL`, else `$EDITOR`, else `vi`; the
+/// way git picks one, `core.editor` winning over the environment.
+fn choose_editor(
+    configured: Option<&str>,
+    visual: Option<String>,
+    editor: Option<String>,
+) -> String {
+    configured
+        .map(str::to_string)
+        .into_iter()
+        .chain(visual)
+        .chain(editor)
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Reads an edited file back into variables, or lists everything wrong
+/// with it: YAML errors, values that aren't scalars, invalid names, and
+/// values that break the schema.
+fn check_edit(
+    content: &str,
+    settings: &Settings,
+    schema: &Schema,
+) -> std::result::Result<IndexMap<String, String>, Vec<String>> {
+    let parsed: Option<IndexMap<String, serde_yaml::Value>> =
+        serde_yaml::from_str(content).map_err(|error| vec![error.to_string()])?;
+
+    let mut problems = Vec::new();
+    let mut variables = IndexMap::new();
+    for (key, value) in parsed.unwrap_or_default() {
+        if let Err(error) = settings.key_names().check(&key) {
+            problems.push(error.to_string());
+        }
+        let value = match value {
+            serde_yaml::Value::String(value) => value,
+            serde_yaml::Value::Number(number) => number.to_string(),
+            serde_yaml::Value::Bool(flag) => flag.to_string(),
+            serde_yaml::Value::Null => {
+                problems.push(format!("{} has no value; use '' for an empty one", key));
+                continue;
+            }
+            _ => {
+                problems.push(format!("{} must be a single value, not a list or map", key));
+                continue;
+            }
+        };
+        variables.insert(key, value);
+    }
+
+    for (key, variable) in &schema.variables {
+        if variable.required && !variables.contains_key(key) {
+            problems.push(format!("{} is required by the schema", key));
+        }
+    }
+    for (key, value_type) in schema.types() {
+        if variables
+            .get(&key)
+            .is_some_and(|value| !value_type.accepts(value))
+        {
+            let type_name = format!("{:?}", value_type).to_lowercase();
+            problems.push(format!(
+                "{} should be a {} according to the schema",
+                key, type_name
+            ));
+        }
+    }
+
+    match problems.is_empty() {
+        true => Ok(variables),
+        false => Err(problems),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_edit() {
+        let settings = Settings::default();
+        let schema: Schema = serde_yaml::from_str(
+            "variables:\n  API_URL:\n    required: true\n  PORT:\n    type: number\n",
+        )
+        .unwrap();
+
+        let variables = check_edit(
+            "# comment\nAPI_URL: http://api\nPORT: 80\n",
+            &settings,
+            &schema,
+        )
+        .unwrap();
+        assert_eq!(variables["PORT"], "80");
+
+        let problems = check_edit(
+            "PORT: eighty\n1BAD: x\nLIST: [1, 2]\nEMPTY:\n",
+            &settings,
+            &schema,
+        )
+        .unwrap_err();
+        assert_eq!(problems.len(), 5);
+        assert!(check_edit("API_URL: [", &settings, &schema).is_err());
+    }
+
+    #[test]
+    fn test_choose_editor() {
+        let var = |value: &str| Some(value.to_string());
+        assert_eq!(
+            choose_editor(Some("code --wait"), var("emacs"), var("nano")),
+            "code --wait"
+        );
+        assert_eq!(choose_editor(None, var("emacs"), var("nano")), "emacs");
+        assert_eq!(choose_editor(None, var(" "), var("nano")), "nano");
+        assert_eq!(choose_editor(None, None, None), "vi");
+    }
+}
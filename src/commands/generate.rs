@@ -0,0 +1,78 @@
+use super::EnvMatchCommands;
+use crate::config::VariableMetadata;
+use crate::crypto::generate::Generator;
+use crate::error::Result;
+use colored::*;
+
+impl EnvMatchCommands {
+    /// Sets each key to a new random value, flagged secret. Values are only
+    /// printed with `show`; otherwise they stay hidden until read with `get`.
+    pub fn generate_variables(
+        &self,
+        keys: &[String],
+        generator: Generator,
+        metadata: &VariableMetadata,
+        env_name: &str,
+        show: bool,
+    ) -> Result<Vec<(String, String)>> {
+        let metadata = VariableMetadata {
+            secret: true,
+            ..metadata.clone()
+        };
+        let entries: Vec<_> = keys
+            .iter()
+            .map(|key| (key.clone(), generator.generate(), metadata.clone()))
+            .collect();
+        self.store_variables(&entries, env_name)?;
+
+        for (key, value, _) in &entries {
+            if show {
+                println!("{}={}", key, value);
+            } else if !self.quiet {
+                println!(
+                    "{} {} {} {} {}",
+                    "✅ Generated".bright_green().bold(),
+                    key.bright_cyan().bold(),
+                    format!("({}, hidden)", generator).bright_black(),
+                    "in environment".bright_white(),
+                    format!("'{}'", env_name).bright_green().bold()
+                );
+            }
+        }
+        Ok(entries
+            .into_iter()
+            .map(|(key, value, _)| (key, value))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_generate_variables() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+
+        let keys = ["SESSION_SECRET".to_string(), "CSRF_SECRET".to_string()];
+        let generated = commands
+            .generate_variables(
+                &keys,
+                Generator::Hex(16),
+                &VariableMetadata::default(),
+                "development",
+                false,
+            )
+            .unwrap();
+
+        assert_ne!(generated[0].1, generated[1].1);
+        let env_config = commands
+            .config_manager
+            .load_environment("development")
+            .unwrap();
+        assert_eq!(env_config.variables["SESSION_SECRET"], generated[0].1);
+        assert!(env_config.metadata["CSRF_SECRET"].secret);
+    }
+}
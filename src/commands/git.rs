@@ -0,0 +1,159 @@
+use super::EnvMatchCommands;
+use crate::config::{EnvConfig, StorageFormat, MERGE_DRIVER};
+use crate::crypto;
+use crate::error::{EnvMatchError, Result};
+use crate::sync;
+use colored::*;
+use std::collections::btree_map::Entry;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+impl EnvMatchCommands {
+    /// Prepares `.envMatch` to be committed and shared through git.
+    ///
+    /// Safe to run again, e.g. by every teammate after cloning, since each
+    /// step only does what's missing.
+    pub fn git_init(&self) -> Result<()> {
+        if !self.is_initialized() {
+            return Err(EnvMatchError::NotInitialized);
+        }
+
+        if self.config_manager.is_encrypted() {
+            println!("{}", "🔐 Environments are already encrypted".bright_blue());
+        } else {
+            let passphrase = crypto::read_new_passphrase()?;
+            let count = self.config_manager.enable_encryption(&passphrase)?;
+            println!(
+                "{} {}",
+                "🔐 Encrypted".bright_green().bold(),
+                format!("{} environment(s)", count).bright_white()
+            );
+        }
+
+        let mut schema = self.config_manager.load_schema()?;
+        let mut added = 0;
+        for env_name in self.config_manager.list_environments()? {
+            let env_config = self.config_manager.load_environment(&env_name)?;
+            for key in env_config.variables.into_keys() {
+                if let Entry::Vacant(entry) = schema.variables.entry(key) {
+                    entry.insert(Default::default());
+                    added += 1;
+                }
+            }
+        }
+        self.config_manager.save_schema(&schema)?;
+        println!(
+            "{} {}",
+            "📄 Plaintext schema in .envMatch/schema.yaml".bright_blue(),
+            format!("({} new key(s))", added).bright_black()
+        );
+
+        if self.config_manager.update_gitignore()? {
+            println!("{}", "🙈 Updated .envMatch/.gitignore".bright_blue());
+        }
+        if self.config_manager.update_gitattributes()? {
+            println!("{}", "🔀 Updated .envMatch/.gitattributes".bright_blue());
+        }
+
+        let git_config = [
+            ("name", "envMatch key-by-key merge"),
+            ("driver", "envMatch merge-driver %O %A %B"),
+        ];
+        let configured = self.config_manager.project_dir().is_some_and(|dir| {
+            git_config.iter().all(|(setting, value)| {
+                Command::new("git")
+                    .arg("-C")
+                    .arg(dir)
+                    .args([
+                        "config",
+                        &format!("merge.{}.{}", MERGE_DRIVER, setting),
+                        value,
+                    ])
+                    .output()
+                    .is_ok_and(|output| output.status.success())
+            })
+        });
+        if configured {
+            println!("{}", "🔀 Configured the git merge driver".bright_blue());
+        } else {
+            println!(
+                "{}",
+                "⚠️  Could not configure git (not a git repository?). Run:".bright_yellow()
+            );
+            for (setting, value) in git_config {
+                println!(
+                    "   {}",
+                    format!(
+                        "git config merge.{}.{} \"{}\"",
+                        MERGE_DRIVER, setting, value
+                    )
+                    .bright_cyan()
+                );
+            }
+        }
+
+        println!(
+            "{} {}",
+            "✅ Ready to commit:".bright_green().bold(),
+            "git add .envMatch".bright_cyan()
+        );
+        Ok(())
+    }
+}
+
+/// Git merge driver for environment files: merges `theirs` into `ours` key
+/// by key, using `base` as the common ancestor, and writes the result to `ours`.
+///
+/// Works on the stored values directly; encryption is deterministic, so equal
+/// values have equal ciphertexts. Git's temporary files have no extension,
+/// so the format is told from the content, and ours is kept. Returns the
+/// keys changed on both sides, which keep our value.
+pub fn merge_environment_files(base: &Path, ours: &Path, theirs: &Path) -> Result<Vec<String>> {
+    let read = |path: &Path| -> Result<(StorageFormat, EnvConfig)> {
+        let content = fs::read_to_string(path).map_err(|e| EnvMatchError::storage(path, e))?;
+        match StorageFormat::detect::<Option<EnvConfig>>(&content) {
+            Some((format, env_config)) => Ok((format, env_config.unwrap_or_default())),
+            // Report the error as YAML, the default format
+            None => Ok((
+                StorageFormat::Yaml,
+                serde_yaml::from_str::<Option<EnvConfig>>(&content)?.unwrap_or_default(),
+            )),
+        }
+    };
+    let ((_, base), (format, our_config), (_, their_config)) =
+        (read(base)?, read(ours)?, read(theirs)?);
+
+    let merge = sync::merge(
+        &base.variables,
+        &our_config.variables,
+        &their_config.variables,
+    );
+    // Metadata follows the same rule per key: ours if we changed it, else theirs
+    let metadata = merge
+        .merged
+        .keys()
+        .filter_map(|key| {
+            let ours = our_config.metadata.get(key);
+            let chosen = if ours != base.metadata.get(key) {
+                ours
+            } else {
+                their_config.metadata.get(key)
+            };
+            chosen.map(|metadata| (key.clone(), metadata.clone()))
+        })
+        .collect();
+    // Profiles are kept whole: ours if we changed them, else theirs
+    let profiles = match our_config.profiles != base.profiles {
+        true => our_config.profiles,
+        false => their_config.profiles,
+    };
+    let merged = EnvConfig {
+        variables: merge.merged,
+        metadata,
+        profiles,
+    };
+    fs::write(ours, format.serialize(&merged)?).map_err(|e| EnvMatchError::storage(ours, e))?;
+
+    Ok(merge.conflicts)
+}
@@ -0,0 +1,46 @@
+use super::EnvMatchCommands;
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+
+impl EnvMatchCommands {
+    /// Warns when other users can read files under `.envMatch`, or fails
+    /// with `strict`.
+    pub fn check_permissions(&self, strict: bool) -> Result<()> {
+        let paths = self.config_manager.loose_permissions();
+        if paths.is_empty() {
+            return Ok(());
+        }
+        if strict {
+            return Err(EnvMatchError::InsecurePermissions { paths });
+        }
+        eprintln!(
+            "{}",
+            format!(
+                "⚠️  {} path(s) under .envMatch can be read by other users, run 'envMatch harden'",
+                paths.len()
+            )
+            .bright_yellow()
+        );
+        Ok(())
+    }
+
+    /// Makes `.envMatch` readable by its owner only.
+    pub fn harden(&self) -> Result<usize> {
+        if !self.is_initialized() {
+            return Err(EnvMatchError::NotInitialized);
+        }
+        let changed = self.config_manager.harden_permissions()?;
+
+        if !self.quiet {
+            match changed {
+                0 => println!("{}", "🔒 .envMatch is already private".bright_blue()),
+                _ => println!(
+                    "{} {}",
+                    "🔒 Restricted".bright_green().bold(),
+                    format!("{} path(s) to their owner", changed).bright_white()
+                ),
+            }
+        }
+        Ok(changed)
+    }
+}
@@ -0,0 +1,490 @@
+use super::EnvMatchCommands;
+use crate::error::{EnvMatchError, Result};
+use crate::export::escape;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use colored::*;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Set by the hook to the environment it loaded, for prompts to show. Set by
+/// hand, it picks the environment for the shell session.
+const ENV_VAR: &str = "ENVMATCH_ENV";
+/// What the hook loaded, so it can tell when to reload and undo it on leaving.
+const STATE_VAR: &str = "_ENVMATCH_STATE";
+/// Variables that change how the shell itself runs, never set by the hook
+const SHELL_CONTROL_VARS: &[&str] = &[
+    "PATH",
+    "PROMPT_COMMAND",
+    "BASH_ENV",
+    "ENV",
+    "IFS",
+    "CDPATH",
+    "SHELLOPTS",
+    "BASHOPTS",
+    "ZDOTDIR",
+];
+/// Prefixes of more of them: the dynamic loader's `LD_*` and `DYLD_*`
+const SHELL_CONTROL_PREFIXES: &[&str] = &["LD_", "DYLD_"];
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum HookShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// Kept in [`STATE_VAR`] as base64 JSON between prompts.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+struct HookState {
+    /// Project whose environment is loaded
+    dir: Option<PathBuf>,
+    env: Option<String>,
+    /// Keyed fingerprint of the loaded variables, to notice edits
+    hash: Option<String>,
+    /// The project couldn't be loaded; it's reported once, not on every prompt
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    failed: bool,
+    /// Variables the hook set
+    keys: Vec<String>,
+    /// Values those variables had before, restored on leaving
+    saved: BTreeMap<String, String>,
+}
+
+impl HookState {
+    fn decode(encoded: &str) -> Option<Self> {
+        serde_json::from_slice(&STANDARD.decode(encoded).ok()?).ok()
+    }
+
+    fn encode(&self) -> String {
+        STANDARD.encode(serde_json::to_vec(self).expect("hook state serializes"))
+    }
+}
+
+/// The environment the shell session asks for in `ENVMATCH_ENV`. The value
+/// the hook exports itself doesn't count, or switching would never reach a
+/// hooked shell; the one it shadowed does.
+pub fn session_environment() -> Option<String> {
+    requested_environment(|key| std::env::var(key).ok())
+}
+
+/// [`session_environment`], reading the shell's variables with `current`.
+fn requested_environment(current: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let value = current(ENV_VAR)?;
+    let state = current(STATE_VAR).and_then(|encoded| HookState::decode(&encoded));
+    match state {
+        Some(state)
+            if state.keys.iter().any(|key| key == ENV_VAR)
+                && state.env.as_deref() == Some(value.as_str()) =>
+        {
+            state.saved.get(ENV_VAR).cloned()
+        }
+        _ => Some(value),
+    }
+}
+
+/// What the shell should have loaded where it is now.
+enum Target {
+    Outside,
+    Failed(PathBuf),
+    Loaded {
+        dir: PathBuf,
+        env: String,
+        variables: IndexMap<String, String>,
+        /// Keyed fingerprint of the variables, to notice edits without
+        /// putting a plain hash of them in the shell's environment
+        hash: String,
+    },
+}
+
+impl EnvMatchCommands {
+    /// Prints shell code that runs `envMatch hook-env` before each prompt,
+    /// for the shell's startup file to `eval`.
+    pub fn hook(&self, shell: HookShell) -> &'static str {
+        let script = match shell {
+            HookShell::Bash => {
+                r#"_envmatch_hook() {
+  local previous_exit_status=$?
+  eval "$(envMatch hook-env bash </dev/null)"
+  return $previous_exit_status
+}
+if [[ ";${PROMPT_COMMAND[*]:-};" != *";_envmatch_hook;"* ]]; then
+  PROMPT_COMMAND="_envmatch_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
+"#
+            }
+            HookShell::Zsh => {
+                r#"_envmatch_hook() {
+  eval "$(envMatch hook-env zsh </dev/null)"
+}
+typeset -ag precmd_functions chpwd_functions
+if (( ! ${precmd_functions[(I)_envmatch_hook]} )); then
+  precmd_functions=(_envmatch_hook $precmd_functions)
+fi
+if (( ! ${chpwd_functions[(I)_envmatch_hook]} )); then
+  chpwd_functions=(_envmatch_hook $chpwd_functions)
+fi
+"#
+            }
+            HookShell::Fish => {
+                r#"function __envmatch_hook --on-event fish_prompt --on-variable PWD
+    envMatch hook-env fish </dev/null | source
+end
+"#
+            }
+            HookShell::Powershell => {
+                r#"if (-not $global:_envmatch_prompt) {
+  $global:_envmatch_prompt = $function:prompt
+  function global:prompt {
+    $previous_exit_code = $global:LASTEXITCODE
+    $null | envMatch hook-env powershell | Out-String | Invoke-Expression
+    $global:LASTEXITCODE = $previous_exit_code
+    & $global:_envmatch_prompt
+  }
+}
+"#
+            }
+        };
+        print!("{}", script);
+        script
+    }
+
+    /// Lets the hook load this project's environments as they are now.
+    pub fn hook_allow(&self) -> Result<()> {
+        if !self.is_initialized() {
+            return Err(EnvMatchError::NotInitialized);
+        }
+        let project_dir = self.config_manager.trust_project()?;
+        println!(
+            "✅ The shell hook will load {} until its environments change",
+            project_dir.display().to_string().cyan()
+        );
+        Ok(())
+    }
+
+    /// Stops the hook from loading this project.
+    pub fn hook_deny(&self) -> Result<bool> {
+        let denied = self.config_manager.distrust_project()?;
+        match denied {
+            true => println!("🚫 The shell hook no longer loads this project"),
+            false => println!("This project wasn't allowed"),
+        }
+        Ok(denied)
+    }
+
+    /// Prints shell code loading the project's current environment, or
+    /// undoing what was loaded once the shell has left the project. Prints
+    /// nothing when the shell already has what it needs.
+    ///
+    /// A project is only loaded once `hook allow` has been run in it, and
+    /// again after each change to its environments, so cloning a repository
+    /// and `cd`-ing into it doesn't export whatever it ships.
+    pub fn hook_env(&self, shell: HookShell) -> String {
+        let state = std::env::var(STATE_VAR)
+            .ok()
+            .and_then(|encoded| HookState::decode(&encoded))
+            .unwrap_or_default();
+        let target = match self.config_manager.project_dir() {
+            Some(dir) if self.is_initialized() => {
+                let loaded = self
+                    .allowed(dir)
+                    .and_then(|_| self.resolve_environment(None))
+                    .and_then(|env| {
+                        let variables = self.resolve_layers(std::slice::from_ref(&env))?;
+                        let hash = self
+                            .config_manager
+                            .fingerprint(&serde_json::to_string(&variables)?)?;
+                        Ok((env, variables, hash))
+                    });
+                match loaded {
+                    Ok((env, variables, hash)) => Target::Loaded {
+                        dir: dir.to_path_buf(),
+                        env,
+                        variables,
+                        hash,
+                    },
+                    Err(error) => {
+                        if !(state.failed && state.dir.as_deref() == Some(dir)) {
+                            eprintln!("envMatch: not loading {}: {}", dir.display(), error);
+                        }
+                        Target::Failed(dir.to_path_buf())
+                    }
+                }
+            }
+            _ => Target::Outside,
+        };
+        let script =
+            transition(&state, target, shell, |key| std::env::var(key).ok()).unwrap_or_default();
+        print!("{}", script);
+        script
+    }
+
+    fn allowed(&self, dir: &std::path::Path) -> Result<()> {
+        match self.config_manager.is_project_trusted()? {
+            true => Ok(()),
+            false => Err(EnvMatchError::ProjectNotAllowed {
+                path: dir.to_path_buf(),
+            }),
+        }
+    }
+}
+
+/// The code taking the shell from `state` to `target`, or `None` if it's
+/// already there. `current` reads the shell's variables.
+fn transition(
+    state: &HookState,
+    target: Target,
+    shell: HookShell,
+    current: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    let (mut next, variables) = match target {
+        Target::Outside => (HookState::default(), IndexMap::new()),
+        Target::Failed(dir) => {
+            let next = HookState {
+                dir: Some(dir),
+                failed: true,
+                ..Default::default()
+            };
+            (next, IndexMap::new())
+        }
+        Target::Loaded {
+            dir,
+            env,
+            mut variables,
+            hash,
+        } => {
+            variables.insert(ENV_VAR.to_string(), env.clone());
+            let next = HookState {
+                dir: Some(dir),
+                env: Some(env),
+                hash: Some(hash),
+                ..Default::default()
+            };
+            (next, variables)
+        }
+    };
+    if (&next.dir, &next.env, &next.hash, next.failed)
+        == (&state.dir, &state.env, &state.hash, state.failed)
+    {
+        return None;
+    }
+
+    let mut script = String::new();
+    for key in &state.keys {
+        match state.saved.get(key) {
+            Some(value) => script.push_str(&export(shell, key, value)),
+            None => script.push_str(&unset(shell, key)),
+        }
+    }
+    // What the shell holds once the previous environment is undone
+    let before = |key: &str| match state.keys.iter().any(|loaded| loaded == key) {
+        true => state.saved.get(key).cloned(),
+        false => current(key),
+    };
+
+    for (key, value) in &variables {
+        if !is_shell_name(key) {
+            eprintln!(
+                "envMatch: skipping '{}', not a valid shell variable name",
+                key
+            );
+            continue;
+        }
+        if is_shell_control(key) {
+            eprintln!(
+                "envMatch: skipping '{}', it controls the shell itself; use 'envMatch run' for it",
+                key
+            );
+            continue;
+        }
+        if let Some(previous) = before(key) {
+            next.saved.insert(key.clone(), previous);
+        }
+        next.keys.push(key.clone());
+        script.push_str(&export(shell, key, value));
+    }
+
+    match next.dir.is_some() {
+        true => script.push_str(&export(shell, STATE_VAR, &next.encode())),
+        false => script.push_str(&unset(shell, STATE_VAR)),
+    }
+    Some(script)
+}
+
+fn export(shell: HookShell, key: &str, value: &str) -> String {
+    match shell {
+        HookShell::Bash | HookShell::Zsh => {
+            format!("export {}={};\n", key, escape::single_quoted(value))
+        }
+        HookShell::Fish => format!(
+            "set -gx {} '{}';\n",
+            key,
+            value.replace('\\', "\\\\").replace('\'', "\\'")
+        ),
+        HookShell::Powershell => format!("$env:{} = \"{}\";\n", key, escape::powershell(value)),
+    }
+}
+
+fn unset(shell: HookShell, key: &str) -> String {
+    match shell {
+        HookShell::Bash | HookShell::Zsh => format!("unset {};\n", key),
+        HookShell::Fish => format!("set -e {};\n", key),
+        HookShell::Powershell => {
+            format!("Remove-Item Env:{} -ErrorAction SilentlyContinue;\n", key)
+        }
+    }
+}
+
+fn is_shell_name(key: &str) -> bool {
+    key.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether setting `key` changes what the shell runs or how: the search
+/// path, startup files, prompts (`PS1` to `PS4`, `PROMPT_COMMAND`), word
+/// splitting and the dynamic loader.
+fn is_shell_control(key: &str) -> bool {
+    SHELL_CONTROL_VARS.contains(&key)
+        || SHELL_CONTROL_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+        || key
+            .strip_prefix("PS")
+            .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_transition_loads_and_restores() {
+        let shell_before = |key: &str| (key == "PORT").then(|| "3000".to_string());
+        let target = || Target::Loaded {
+            dir: PathBuf::from("/work/app"),
+            env: "development".to_string(),
+            variables: [("PORT", "8080"), ("NAME", "it's")]
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            hash: "1".to_string(),
+        };
+
+        let script = transition(
+            &HookState::default(),
+            target(),
+            HookShell::Bash,
+            shell_before,
+        )
+        .unwrap();
+        assert!(script.starts_with(
+            "export PORT='8080';\nexport NAME='it'\\''s';\nexport ENVMATCH_ENV='development';\n"
+        ));
+        let encoded = script.rsplit("_ENVMATCH_STATE='").next().unwrap();
+        let state = HookState::decode(encoded.trim_end_matches("';\n")).unwrap();
+        assert_eq!(
+            state.saved,
+            [("PORT".to_string(), "3000".to_string())].into()
+        );
+
+        // Another prompt in the same place changes nothing
+        let loaded = |key: &str| Some(format!("{} as loaded", key));
+        assert_eq!(transition(&state, target(), HookShell::Bash, loaded), None);
+
+        let script = transition(&state, Target::Outside, HookShell::Fish, loaded).unwrap();
+        assert_eq!(
+            script,
+            "set -gx PORT '3000';\nset -e NAME;\nset -e ENVMATCH_ENV;\nset -e _ENVMATCH_STATE;\n"
+        );
+        let script = transition(&state, Target::Outside, HookShell::Powershell, loaded).unwrap();
+        assert!(script.starts_with(
+            "$env:PORT = \"3000\";\nRemove-Item Env:NAME -ErrorAction SilentlyContinue;\n"
+        ));
+    }
+
+    #[test]
+    fn test_requested_environment_ignores_the_hooks_own() {
+        let loaded = |saved: Option<&str>| HookState {
+            env: Some("development".to_string()),
+            keys: vec![ENV_VAR.to_string()],
+            saved: saved
+                .map(|value| (ENV_VAR.to_string(), value.to_string()))
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let shell = |value: &'static str, state: Option<HookState>| {
+            move |key: &str| match key {
+                ENV_VAR => Some(value.to_string()),
+                STATE_VAR => state.as_ref().map(HookState::encode),
+                _ => None,
+            }
+        };
+
+        assert_eq!(
+            requested_environment(shell("staging", None)),
+            Some("staging".to_string())
+        );
+        assert_eq!(
+            requested_environment(shell("development", Some(loaded(None)))),
+            None
+        );
+        assert_eq!(
+            requested_environment(shell("development", Some(loaded(Some("qa"))))),
+            Some("qa".to_string())
+        );
+        // Changed by hand since the hook set it
+        assert_eq!(
+            requested_environment(shell("staging", Some(loaded(None)))),
+            Some("staging".to_string())
+        );
+    }
+
+    #[test]
+    fn test_untrusted_project_emits_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::with_project_dir(
+            temp_dir.path().to_path_buf(),
+        ));
+        commands.init().unwrap();
+        commands
+            .set_variable("PORT", "8080", "development")
+            .unwrap();
+
+        let script = commands.hook_env(HookShell::Bash);
+        let lines: Vec<_> = script.lines().collect();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("export _ENVMATCH_STATE="));
+        assert!(!script.contains("8080"));
+    }
+
+    #[test]
+    fn test_shell_control_variables_are_skipped() {
+        let target = Target::Loaded {
+            dir: PathBuf::from("/work/app"),
+            env: "development".to_string(),
+            variables: [
+                ("PATH", "/tmp/evil"),
+                ("LD_PRELOAD", "/tmp/evil.so"),
+                ("PS1", "$(evil)"),
+                ("PROMPT_COMMAND", "evil"),
+                ("PSQL_HOST", "db"),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+            hash: "1".to_string(),
+        };
+
+        let script = transition(&HookState::default(), target, HookShell::Bash, |_| None).unwrap();
+        assert!(script.starts_with("export PSQL_HOST='db';\nexport ENVMATCH_ENV='development';\n"));
+        assert!(!script.contains("evil"));
+    }
+}
@@ -0,0 +1,333 @@
+use super::EnvMatchCommands;
+use crate::config::VariableMetadata;
+use crate::error::{EnvMatchError, Result};
+use crate::template;
+use clap::ValueEnum;
+use colored::*;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Kinds of file `import` reads variables from.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ImportFormat {
+    /// KEY=VALUE lines, as in a .env file
+    Dotenv,
+    Json,
+    Toml,
+    Yaml,
+    /// JSON from `doppler secrets download` or `doppler secrets --json`
+    Doppler,
+    /// JSON from `infisical export --format json`
+    Infisical,
+}
+
+/// Variables Doppler adds to every download to name the config it came
+/// from; they don't belong to the app.
+const DOPPLER_KEYS: [&str; 3] = ["DOPPLER_PROJECT", "DOPPLER_CONFIG", "DOPPLER_ENVIRONMENT"];
+
+/// A variable read from a file, with the description the file gave it.
+pub type ImportEntry = (String, String, VariableMetadata);
+
+impl ImportFormat {
+    /// Guesses the format from a file's extension, falling back to dotenv.
+    pub fn of_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::Json,
+            Some("toml") => Self::Toml,
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Dotenv,
+        }
+    }
+}
+
+impl EnvMatchCommands {
+    /// Reads variables from a file and sets them in an environment, all at
+    /// once. Nested objects become `PARENT<separator>CHILD` keys, and notes
+    /// from Doppler or Infisical become descriptions. Returns the variables
+    /// imported.
+    pub fn import_file(
+        &self,
+        path: &Path,
+        format: Option<ImportFormat>,
+        separator: &str,
+        env_name: Option<&str>,
+    ) -> Result<Vec<ImportEntry>> {
+        let env_name = self.resolve_environment(env_name)?;
+        let entries = read_import(path, format, separator)?;
+        if entries.is_empty() {
+            println!("No variables found in {}", path.display());
+            return Ok(entries);
+        }
+
+        self.store_variables(&entries, &env_name)?;
+        if !self.quiet {
+            println!(
+                "{} {}",
+                format!(
+                    "📥 Imported {} variable(s) from {} into",
+                    entries.len(),
+                    path.display()
+                )
+                .bright_green()
+                .bold(),
+                format!("'{}'", env_name).bright_green().bold()
+            );
+        }
+        Ok(entries)
+    }
+
+    /// Sets variables read by [`read_import`] without printing anything.
+    /// With `keep_existing`, variables the environment already sets keep
+    /// their values. Returns the keys set.
+    pub fn import_entries(
+        &self,
+        entries: Vec<ImportEntry>,
+        env_name: &str,
+        keep_existing: bool,
+    ) -> Result<Vec<String>> {
+        let existing = self.config_manager.load_environment(env_name)?.variables;
+        let entries: Vec<_> = entries
+            .into_iter()
+            .filter(|(key, ..)| !keep_existing || !existing.contains_key(key))
+            .collect();
+        if !entries.is_empty() {
+            self.store_variables(&entries, env_name)?;
+        }
+        Ok(entries.into_iter().map(|(key, ..)| key).collect())
+    }
+}
+
+/// Reads the variables of a file, in the format its extension suggests
+/// unless `format` is given.
+pub fn read_import(
+    path: &Path,
+    format: Option<ImportFormat>,
+    separator: &str,
+) -> Result<Vec<ImportEntry>> {
+    let content = fs::read_to_string(path).map_err(|e| EnvMatchError::storage(path, e))?;
+    let format = format.unwrap_or_else(|| ImportFormat::of_path(path));
+    parse_import(&content, format, separator, path)
+}
+
+/// Reads the variables of a file in `format`, flattening nested objects.
+/// `path` only names the file in errors.
+pub fn parse_import(
+    content: &str,
+    format: ImportFormat,
+    separator: &str,
+    path: &Path,
+) -> Result<Vec<ImportEntry>> {
+    let document: Value = match format {
+        ImportFormat::Dotenv => {
+            let vars = template::parse_dotenv(content)?;
+            return Ok(vars.into_iter().map(plain).collect());
+        }
+        ImportFormat::Doppler => return parse_doppler(serde_json::from_str(content)?, path),
+        ImportFormat::Infisical => return parse_infisical(serde_json::from_str(content)?, path),
+        ImportFormat::Json => serde_json::from_str(content)?,
+        ImportFormat::Toml => {
+            toml::from_str(content).map_err(|source| EnvMatchError::TomlParseError {
+                path: Some(path.to_path_buf()),
+                source,
+            })?
+        }
+        ImportFormat::Yaml => serde_yaml::from_str::<Option<Value>>(content)
+            .map_err(|source| EnvMatchError::YamlParseError {
+                path: Some(path.to_path_buf()),
+                line: source.location().map(|location| location.line()),
+                source,
+            })?
+            .unwrap_or_default(),
+    };
+
+    let mut vars = Vec::new();
+    match document {
+        Value::Object(map) => flatten(map, None, separator, &mut vars),
+        Value::Null => {}
+        _ => {
+            return Err(EnvMatchError::NotAVariableMap {
+                path: path.to_path_buf(),
+                format: format!("{:?}", format).to_uppercase(),
+            })
+        }
+    }
+    Ok(vars.into_iter().map(plain).collect())
+}
+
+fn plain((key, value): (String, String)) -> ImportEntry {
+    (key, value, VariableMetadata::default())
+}
+
+fn described(key: String, value: String, note: Option<&str>) -> ImportEntry {
+    let metadata = VariableMetadata {
+        description: note.filter(|note| !note.is_empty()).map(str::to_string),
+        ..Default::default()
+    };
+    (key, value, metadata)
+}
+
+/// Reads a Doppler download, either flat (`{"KEY": "value"}`) or with
+/// details per secret (`{"KEY": {"computed": "value", "note": "..."}}`).
+fn parse_doppler(document: Value, path: &Path) -> Result<Vec<ImportEntry>> {
+    let Value::Object(secrets) = document else {
+        return Err(EnvMatchError::NotAVariableMap {
+            path: path.to_path_buf(),
+            format: "Doppler".to_string(),
+        });
+    };
+    let mut entries = Vec::new();
+    for (key, secret) in secrets {
+        if DOPPLER_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let (value, note) = match &secret {
+            Value::Object(details) => (
+                details.get("computed").or_else(|| details.get("raw")),
+                details.get("note").and_then(Value::as_str),
+            ),
+            value => (Some(value), None),
+        };
+        entries.push(described(key, scalar(value), note));
+    }
+    Ok(entries)
+}
+
+/// Reads an Infisical export: a list of `{"key", "value", "comment"}`
+/// objects, also accepted with the API's `secretKey`-style names.
+fn parse_infisical(document: Value, path: &Path) -> Result<Vec<ImportEntry>> {
+    let Value::Array(secrets) = document else {
+        return Err(not_infisical(path));
+    };
+    let field = |secret: &Value, name: &str, api_name: &str| {
+        secret
+            .get(name)
+            .or_else(|| secret.get(api_name))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let mut entries = Vec::new();
+    for secret in &secrets {
+        let Some(key) = field(secret, "key", "secretKey") else {
+            return Err(not_infisical(path));
+        };
+        let value = field(secret, "value", "secretValue").unwrap_or_default();
+        let comment = field(secret, "comment", "secretComment");
+        entries.push(described(key, value, comment.as_deref()));
+    }
+    Ok(entries)
+}
+
+/// A JSON value as a variable's value: strings as they are, null as empty,
+/// anything else as its JSON text.
+fn not_infisical(path: &Path) -> EnvMatchError {
+    EnvMatchError::NotAVariableMap {
+        path: path.to_path_buf(),
+        format: "Infisical".to_string(),
+    }
+}
+
+fn scalar(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(value)) => value.clone(),
+        None | Some(Value::Null) => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Adds the scalars of `map` to `vars`, naming nested ones after their
+/// parents. Lists are kept whole, as JSON.
+fn flatten(
+    map: serde_json::Map<String, Value>,
+    prefix: Option<&str>,
+    separator: &str,
+    vars: &mut Vec<(String, String)>,
+) {
+    for (key, value) in map {
+        let key = match prefix {
+            Some(prefix) => format!("{}{}{}", prefix, separator, key),
+            None => key,
+        };
+        match value {
+            Value::Object(nested) => flatten(nested, Some(&key), separator, vars),
+            other => vars.push((key, scalar(Some(&other)))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nested_formats() {
+        let path = Path::new("config");
+        let expected = [
+            ("DB__HOST", "localhost"),
+            ("DB__PORT", "5432"),
+            ("DEBUG", "true"),
+            ("HOSTS", r#"["a","b"]"#),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        let json =
+            r#"{"DB": {"HOST": "localhost", "PORT": 5432}, "DEBUG": true, "HOSTS": ["a", "b"]}"#;
+        let toml =
+            "DEBUG = true\nHOSTS = [\"a\", \"b\"]\n[DB]\nHOST = \"localhost\"\nPORT = 5432\n";
+        let yaml = "DB:\n  HOST: localhost\n  PORT: 5432\nDEBUG: true\nHOSTS: [a, b]\n";
+        for (content, format) in [
+            (json, ImportFormat::Json),
+            (toml, ImportFormat::Toml),
+            (yaml, ImportFormat::Yaml),
+        ] {
+            let mut vars: Vec<_> = parse_import(content, format, "__", path)
+                .unwrap()
+                .into_iter()
+                .map(|(key, value, _)| (key, value))
+                .collect();
+            vars.sort();
+            assert_eq!(vars, expected, "{:?}", format);
+        }
+
+        let vars = parse_import(json, ImportFormat::Json, "_", path).unwrap();
+        assert!(vars.iter().any(|(key, ..)| key == "DB_HOST"));
+        assert!(parse_import("[1, 2]", ImportFormat::Json, "__", path).is_err());
+        assert_eq!(
+            ImportFormat::of_path(Path::new(".env")),
+            ImportFormat::Dotenv
+        );
+    }
+
+    #[test]
+    fn test_parse_secret_manager_exports() {
+        let path = Path::new("secrets.json");
+        let doppler = r#"{
+            "API_KEY": {"raw": "${SECRET}", "computed": "abc", "note": "Payments API"},
+            "DOPPLER_CONFIG": {"computed": "dev"},
+            "PORT": "80"
+        }"#;
+        let entries = parse_import(doppler, ImportFormat::Doppler, "__", path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            (entries[0].0.as_str(), entries[0].1.as_str()),
+            ("API_KEY", "abc")
+        );
+        assert_eq!(entries[0].2.description.as_deref(), Some("Payments API"));
+        assert_eq!(entries[1].1, "80");
+
+        let infisical = r#"[
+            {"key": "DB_URL", "value": "postgres://db", "comment": "Primary", "type": "shared"},
+            {"secretKey": "PORT", "secretValue": "80"}
+        ]"#;
+        let entries = parse_import(infisical, ImportFormat::Infisical, "__", path).unwrap();
+        assert_eq!(entries[0].2.description.as_deref(), Some("Primary"));
+        assert_eq!(
+            (entries[1].0.as_str(), entries[1].1.as_str()),
+            ("PORT", "80")
+        );
+        assert!(parse_import("[{}]", ImportFormat::Infisical, "__", path).is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use super::EnvMatchCommands;
+use crate::crypto;
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+
+impl EnvMatchCommands {
+    /// Saves the project's encryption key in the OS keychain.
+    pub fn key_set(&self) -> Result<()> {
+        self.require_encryption()?;
+        let passphrase = crypto::read_passphrase()?;
+        self.config_manager.remember_key(&passphrase)?;
+
+        if !self.quiet {
+            println!(
+                "{}",
+                "✅ Key saved in the OS keychain, no passphrase needed on this machine"
+                    .bright_green()
+                    .bold()
+            );
+        }
+        Ok(())
+    }
+
+    /// Removes the project's encryption key from the OS keychain.
+    pub fn key_forget(&self) -> Result<()> {
+        self.require_encryption()?;
+
+        let removed = self.config_manager.forget_key()?;
+        if self.quiet {
+            return Ok(());
+        }
+        if removed {
+            println!(
+                "{}",
+                "✅ Key removed from the OS keychain".bright_green().bold()
+            );
+        } else {
+            println!("{}", "No key stored for this project.".bright_black());
+        }
+        Ok(())
+    }
+
+    fn require_encryption(&self) -> Result<()> {
+        if !self.is_initialized() {
+            return Err(EnvMatchError::NotInitialized);
+        }
+        if !self.config_manager.is_encrypted() {
+            return Err(EnvMatchError::EncryptionError {
+                message: "this project is not encrypted, run 'envMatch git-init' first".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
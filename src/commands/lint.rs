@@ -0,0 +1,197 @@
+use super::EnvMatchCommands;
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What kind of problem `lint` found.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintKind {
+    /// Set in some environments of a parity group but not in others
+    Missing,
+    /// A `per_environment` variable with the same value in several environments
+    SharedValue,
+    Empty,
+}
+
+impl LintKind {
+    fn label(self) -> &'static str {
+        match self {
+            LintKind::Missing => "missing",
+            LintKind::SharedValue => "shared-value",
+            LintKind::Empty => "empty",
+        }
+    }
+}
+
+/// A problem with one key, in the environments listed.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LintFinding {
+    pub kind: LintKind,
+    pub key: String,
+    pub environments: Vec<String>,
+    pub message: String,
+}
+
+impl EnvMatchCommands {
+    /// Looks across environments for keys missing from some environments of
+    /// a parity group, `per_environment` variables sharing a value and empty
+    /// values, printing one line per problem, or the list as JSON with
+    /// `json`. Fails when anything was found, so CI can run it as is.
+    pub fn lint(&self, json: bool) -> Result<Vec<LintFinding>> {
+        let schema = self.config_manager.load_schema()?;
+        let environments = self.config_manager.list_environments()?;
+        let mut variables = BTreeMap::new();
+        for env in &environments {
+            let env_config = self.config_manager.load_effective_environment(env)?;
+            variables.insert(env.clone(), env_config.variables);
+        }
+
+        let mut findings = BTreeSet::new();
+        for group in schema.parity_groups(&environments) {
+            let mut group_variables = Vec::new();
+            for env in &group {
+                let env_variables = variables
+                    .get(env)
+                    .ok_or_else(|| EnvMatchError::environment_not_found(env, &environments))?;
+                group_variables.push((env, env_variables));
+            }
+
+            let keys: BTreeSet<&String> = group_variables
+                .iter()
+                .flat_map(|(_, env_variables)| env_variables.keys())
+                .collect();
+            for key in keys {
+                let (setting, missing): (Vec<String>, Vec<String>) = group_variables
+                    .iter()
+                    .map(|(env, _)| env.to_string())
+                    .partition(|env| variables[env].contains_key(key));
+                if !missing.is_empty() {
+                    findings.insert(LintFinding {
+                        kind: LintKind::Missing,
+                        key: key.clone(),
+                        environments: missing,
+                        message: format!("set only in {}", setting.join(", ")),
+                    });
+                }
+            }
+
+            for (key, _) in schema
+                .variables
+                .iter()
+                .filter(|(_, variable)| variable.per_environment)
+            {
+                let mut sharing: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+                for (env, env_variables) in &group_variables {
+                    match env_variables.get(key) {
+                        Some(value) if !value.trim().is_empty() => {
+                            sharing.entry(value).or_default().push(env.to_string())
+                        }
+                        _ => {}
+                    }
+                }
+                for envs in sharing.into_values().filter(|envs| envs.len() > 1) {
+                    findings.insert(LintFinding {
+                        kind: LintKind::SharedValue,
+                        key: key.clone(),
+                        message: format!("same value in {}", envs.join(" and ")),
+                        environments: envs,
+                    });
+                }
+            }
+        }
+
+        let mut empty: BTreeMap<&String, Vec<String>> = BTreeMap::new();
+        for (env, env_variables) in &variables {
+            for (key, value) in env_variables {
+                if value.trim().is_empty() {
+                    empty.entry(key).or_default().push(env.clone());
+                }
+            }
+        }
+        for (key, envs) in empty {
+            findings.insert(LintFinding {
+                kind: LintKind::Empty,
+                key: key.clone(),
+                message: format!("empty in {}", envs.join(", ")),
+                environments: envs,
+            });
+        }
+
+        let findings: Vec<LintFinding> = findings.into_iter().collect();
+        if json {
+            println!("{}", serde_json::to_string_pretty(&findings)?);
+        } else if findings.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "✅ No lint problems in {} environment(s)",
+                    environments.len()
+                )
+                .bright_green()
+                .bold()
+            );
+        } else {
+            for finding in &findings {
+                println!(
+                    "{} {}: {}",
+                    format!("[{}]", finding.kind.label()).bright_yellow(),
+                    finding.key.bright_cyan().bold(),
+                    finding.message
+                );
+            }
+        }
+
+        if !findings.is_empty() {
+            return Err(EnvMatchError::LintFoundProblems {
+                count: findings.len(),
+            });
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_lint() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("staging").unwrap();
+        commands.create_environment("production").unwrap();
+        commands
+            .config_manager
+            .save_schema(
+                &serde_yaml::from_str(
+                    "variables:\n  DB_PASSWORD:\n    per_environment: true\nparity:\n  - [staging, production]\n",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        let set = |key: &str, value: &str, env: &str| {
+            commands.set_variable(key, value, env).unwrap();
+        };
+        set("DB_PASSWORD", "hunter2", "staging");
+        set("DB_PASSWORD", "hunter2", "production");
+        set("SENTRY_DSN", "https://sentry", "production");
+        set("LOCAL_ONLY", "1", "development");
+        set("FEATURE_FLAGS", "", "staging");
+        set("FEATURE_FLAGS", "", "production");
+
+        let Err(EnvMatchError::LintFoundProblems { count }) = commands.lint(true) else {
+            panic!("lint should fail");
+        };
+        assert_eq!(count, 3);
+        commands.unset_variable("SENTRY_DSN", "production").unwrap();
+        commands.unset_variable("FEATURE_FLAGS", "staging").unwrap();
+        commands
+            .unset_variable("FEATURE_FLAGS", "production")
+            .unwrap();
+        set("DB_PASSWORD", "correct-horse", "production");
+        assert!(commands.lint(false).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::error::{EnvMatchError, Result};
+use crate::export::{self, ExportFormat, ExportOptions};
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use regex::Regex;
+
+/// How `list` prints variables.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum ListFormat {
+    /// Colored `KEY=value` lines under a header, masked with `mask_values`
+    #[default]
+    Table,
+    /// KEY="value" lines
+    Dotenv,
+    /// A JSON object of string values
+    Json,
+    /// A YAML map of string values
+    Yaml,
+}
+
+/// The order `list` prints variables in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum ListSort {
+    /// The order they were added in
+    #[default]
+    Added,
+    /// By key
+    Name,
+    /// Most recently changed first
+    Modified,
+}
+
+/// Selects variables by key: a glob where `*` matches any run of characters
+/// and `?` a single one, or a regular expression between slashes.
+#[derive(Clone, Debug)]
+pub struct KeyFilter(Regex);
+
+impl KeyFilter {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let regex = match pattern
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            Some(regex) => regex.to_string(),
+            None => {
+                let mut regex = String::from("^");
+                for c in pattern.chars() {
+                    match c {
+                        '*' => regex.push_str(".*"),
+                        '?' => regex.push('.'),
+                        c => regex.push_str(&regex::escape(&c.to_string())),
+                    }
+                }
+                regex + "$"
+            }
+        };
+        Regex::new(&regex)
+            .map(Self)
+            .map_err(|e| EnvMatchError::InvalidFilter {
+                pattern: pattern.to_string(),
+                message: e.to_string(),
+            })
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        self.0.is_match(key)
+    }
+}
+
+/// Renders variables for one of the machine-readable formats.
+pub(super) fn render(format: ListFormat, vars: &[(String, String)]) -> Result<String> {
+    let options = ExportOptions::default();
+    match format {
+        ListFormat::Table | ListFormat::Dotenv => {
+            export::render(ExportFormat::Dotenv, vars, &options)
+        }
+        ListFormat::Json => export::render(ExportFormat::Json, vars, &options),
+        ListFormat::Yaml => {
+            let values: IndexMap<&str, &str> = vars
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect();
+            Ok(serde_yaml::to_string(&values)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_filter() {
+        let glob = KeyFilter::parse("AWS_*").unwrap();
+        assert!(glob.matches("AWS_REGION"));
+        assert!(!glob.matches("MY_AWS_KEY"));
+        assert!(KeyFilter::parse("DB_?").unwrap().matches("DB_1"));
+        assert!(KeyFilter::parse("A.B").unwrap().matches("A.B"));
+        assert!(!KeyFilter::parse("A.B").unwrap().matches("AXB"));
+
+        let regex = KeyFilter::parse("/_(URL|HOST)$/").unwrap();
+        assert!(regex.matches("DATABASE_URL"));
+        assert!(!regex.matches("URL_PREFIX"));
+        assert!(matches!(
+            KeyFilter::parse("/(/"),
+            Err(EnvMatchError::InvalidFilter { .. })
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+use super::{parse_snapshot_id, EnvMatchCommands};
+use crate::config::KeyVersion;
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+
+impl EnvMatchCommands {
+    /// Shows how one variable changed over time, one line per snapshot that
+    /// changed it. Values are shown as the audit log's keyed fingerprints,
+    /// or not at all for secrets and in encrypted projects.
+    pub fn key_log(&self, key: &str, env_name: Option<&str>) -> Result<Vec<KeyVersion>> {
+        let env_name = self.resolve_environment(env_name)?;
+        let versions = self.config_manager.key_history(&env_name, key)?;
+        if versions.is_empty() {
+            println!("No recorded changes to '{}' in '{}'.", key, env_name);
+            return Ok(versions);
+        }
+
+        println!(
+            "{} {} {} {}",
+            "🕘 History of".bright_blue().bold(),
+            key.bright_cyan().bold(),
+            "in".bright_blue().bold(),
+            format!("'{}'", env_name).bright_green().bold()
+        );
+        println!("{}", "─".repeat(60).bright_blue());
+        let hidden = self.config_manager.is_encrypted()
+            || super::is_secret(
+                &self.config_manager.load_environment(&env_name)?,
+                &self.config_manager.load_schema()?,
+                key,
+            );
+        for version in &versions {
+            let value = match &version.value {
+                Some(_) if hidden => "= ***".bright_white(),
+                Some(value) => {
+                    format!("= {}", &self.config_manager.fingerprint(value)?[..12]).bright_white()
+                }
+                None => "removed".bright_red(),
+            };
+            println!(
+                "{} {} {} {} {}",
+                format!("#{:<4}", version.snapshot).bright_yellow().bold(),
+                version
+                    .timestamp
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+                    .bright_black(),
+                version.user.bright_cyan(),
+                value,
+                version.command.bright_black()
+            );
+        }
+        Ok(versions)
+    }
+
+    /// Puts one variable back to its value as of a snapshot, leaving the
+    /// rest of the environment alone. Removes it if it wasn't set then.
+    pub fn revert_key(&self, key: &str, env_name: Option<&str>, snapshot_id: &str) -> Result<()> {
+        let env_name = self.resolve_environment(env_name)?;
+        let snapshot = self
+            .config_manager
+            .load_snapshot(parse_snapshot_id(snapshot_id)?)?;
+        let value = snapshot
+            .environments
+            .get(&env_name)
+            .and_then(|env_config| env_config.variables.get(key));
+        let current = self.config_manager.load_environment(&env_name)?;
+
+        match value {
+            Some(value) => self.set_variable(key, value, &env_name),
+            None if current.variables.contains_key(key) => {
+                self.unset_variables(&[key.to_string()], &env_name)
+            }
+            None => Err(EnvMatchError::variable_not_found(
+                key,
+                &env_name,
+                current.variables.keys(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_key_log_and_revert() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("production").unwrap();
+        commands
+            .set_variable("DATABASE_URL", "postgres://one", "production")
+            .unwrap();
+        commands.set_variable("PORT", "80", "production").unwrap();
+        commands
+            .set_variable("DATABASE_URL", "postgres://two", "production")
+            .unwrap();
+        commands
+            .unset_variables(&["DATABASE_URL".to_string()], "production")
+            .unwrap();
+
+        let versions = commands.key_log("DATABASE_URL", None).unwrap();
+        let values: Vec<_> = versions
+            .iter()
+            .map(|version| version.value.as_deref())
+            .collect();
+        assert_eq!(
+            values,
+            [Some("postgres://one"), Some("postgres://two"), None]
+        );
+
+        let first = versions[0].snapshot.to_string();
+        commands.revert_key("DATABASE_URL", None, &first).unwrap();
+        let reverted = commands.resolve_layers(&[]).unwrap();
+        assert_eq!(reverted["DATABASE_URL"], "postgres://one");
+        assert_eq!(reverted["PORT"], "80");
+    }
+}
@@ -0,0 +1,169 @@
+use super::prompt::prompt_line;
+use super::{mask, print_changes, EnvMatchCommands};
+use crate::diff::VariableChange;
+use crate::error::Result;
+use clap::ValueEnum;
+use colored::*;
+
+/// How `env merge` settles a key both environments set to different values.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum MergeStrategy {
+    /// Take the merged environment's value
+    Theirs,
+    /// Keep the target environment's value
+    Ours,
+    /// Ask for each conflicting key
+    Interactive,
+}
+
+impl EnvMatchCommands {
+    /// Folds `source` into `target`: keys only `source` has are added, and
+    /// keys set differently in both are settled by `strategy`. Keys only
+    /// `target` has are left alone. Returns the changes made to `target`.
+    pub fn merge_environments(
+        &self,
+        source: &str,
+        target: &str,
+        strategy: MergeStrategy,
+    ) -> Result<Vec<VariableChange>> {
+        for env_name in [source, target] {
+            if !self.config_manager.environment_exists(env_name) {
+                return Err(self.config_manager.environment_not_found(env_name));
+            }
+        }
+        let theirs = self.config_manager.load_environment(source)?;
+        let ours = self.config_manager.load_environment(target)?;
+        let mask_values = self.settings()?.mask_values();
+        let shown = |value: &str| match mask_values {
+            true => mask(value),
+            false => value.to_string(),
+        };
+
+        println!(
+            "{} {} {} {}",
+            "🔀 Merging".bright_blue().bold(),
+            format!("'{}'", source).bright_green().bold(),
+            "into".bright_blue().bold(),
+            format!("'{}'", target).bright_green().bold()
+        );
+
+        let mut changes = Vec::new();
+        let mut entries = Vec::new();
+        for (key, value) in &theirs.variables {
+            let change = match ours.variables.get(key) {
+                None => VariableChange::Added {
+                    key: key.clone(),
+                    value: value.clone(),
+                },
+                Some(current) if current == value => continue,
+                Some(current) => {
+                    let take_theirs = match strategy {
+                        MergeStrategy::Theirs => true,
+                        MergeStrategy::Ours => false,
+                        MergeStrategy::Interactive => {
+                            println!(
+                                "{} {}",
+                                "⚔️  Conflict:".bright_yellow().bold(),
+                                key.bright_cyan().bold()
+                            );
+                            println!("  ours   ({}): {}", target, shown(current));
+                            println!("  theirs ({}): {}", source, shown(value));
+                            ask_theirs()?
+                        }
+                    };
+                    if !take_theirs {
+                        continue;
+                    }
+                    VariableChange::Changed {
+                        key: key.clone(),
+                        old: current.clone(),
+                        new: value.clone(),
+                    }
+                }
+            };
+            let metadata = theirs.metadata.get(key).cloned().unwrap_or_default();
+            entries.push((key.clone(), value.clone(), metadata));
+            changes.push(change);
+        }
+
+        if !entries.is_empty() {
+            self.store_variables(&entries, target)?;
+        }
+        print_changes(&changes);
+        Ok(changes)
+    }
+}
+
+/// Asks whether to take their value, until the answer is one or the other.
+fn ask_theirs() -> Result<bool> {
+    loop {
+        match prompt_line("  Keep (o)urs or take (t)heirs?", "ours")?
+            .to_lowercase()
+            .as_str()
+        {
+            "o" | "ours" => return Ok(false),
+            "t" | "theirs" => return Ok(true),
+            _ => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+    use crate::error::EnvMatchError;
+
+    #[test]
+    fn test_merge_environments() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("feature-x").unwrap();
+        commands.create_environment("feature-y").unwrap();
+        let set = |env: &str, vars: &[(&str, &str)]| {
+            let vars: Vec<_> = vars
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            commands.set_variables(&vars, env).unwrap();
+        };
+        set(
+            "development",
+            &[("API_URL", "http://dev"), ("PORT", "3000")],
+        );
+        set(
+            "feature-x",
+            &[
+                ("API_URL", "http://feature"),
+                ("PORT", "3000"),
+                ("FLAG", "on"),
+            ],
+        );
+        set("feature-y", &[("API_URL", "http://feature-y")]);
+
+        let changes = commands
+            .merge_environments("feature-x", "development", MergeStrategy::Ours)
+            .unwrap();
+        assert_eq!(
+            changes,
+            [VariableChange::Added {
+                key: "FLAG".to_string(),
+                value: "on".to_string()
+            }]
+        );
+
+        commands
+            .merge_environments("feature-y", "development", MergeStrategy::Theirs)
+            .unwrap();
+        let merged = commands
+            .resolve_layers(&["development".to_string()])
+            .unwrap();
+        assert_eq!(merged["API_URL"], "http://feature-y");
+        assert_eq!(merged["FLAG"], "on");
+
+        assert!(matches!(
+            commands.merge_environments("missing", "development", MergeStrategy::Ours),
+            Err(EnvMatchError::EnvironmentNotFound { .. })
+        ));
+    }
+}
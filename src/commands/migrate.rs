@@ -0,0 +1,161 @@
+use super::EnvMatchCommands;
+use crate::error::Result;
+use colored::*;
+
+/// A deprecated variable renamed to its replacement in one environment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyMigration {
+    pub env: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl EnvMatchCommands {
+    /// Renames every deprecated variable whose note names a replacement
+    /// (`use KEY`) to that replacement, with its metadata, in every
+    /// environment at once. Environments already setting the replacement to
+    /// another value are left alone and reported. With `dry_run`, only lists
+    /// the renames. Returns them.
+    pub fn migrate_keys(&self, dry_run: bool) -> Result<Vec<KeyMigration>> {
+        let schema = self.config_manager.load_schema()?;
+        let renames: Vec<(&str, &str)> = schema
+            .variables
+            .iter()
+            .filter_map(|(key, variable)| Some((key.as_str(), variable.replacement()?)))
+            .collect();
+
+        let mut migrations = Vec::new();
+        let mut conflicts = Vec::new();
+        for env in self.config_manager.list_environments()? {
+            let env_config = self.config_manager.load_environment(&env)?;
+            for (from, to) in &renames {
+                let Some(value) = env_config.variables.get(*from) else {
+                    continue;
+                };
+                match env_config.variables.get(*to) {
+                    Some(existing) if existing != value => {
+                        conflicts.push(format!("{}: {} and {} differ", env, from, to));
+                    }
+                    _ => migrations.push(KeyMigration {
+                        env: env.clone(),
+                        from: from.to_string(),
+                        to: to.to_string(),
+                    }),
+                }
+            }
+        }
+
+        if !dry_run && !migrations.is_empty() {
+            self.transaction("migrate-keys", |tx| {
+                for migration in &migrations {
+                    let env_config = tx.environment(&migration.env)?;
+                    let metadata = env_config
+                        .metadata
+                        .get(&migration.from)
+                        .cloned()
+                        .unwrap_or_default();
+                    let value = tx.unset(&migration.env, &migration.from)?;
+                    tx.set_with_metadata(&migration.env, &migration.to, &value, &metadata)?;
+                }
+                Ok(())
+            })?;
+        }
+
+        if migrations.is_empty() && conflicts.is_empty() {
+            println!(
+                "{}",
+                "✅ No deprecated variables to migrate"
+                    .bright_green()
+                    .bold()
+            );
+        }
+        let verb = if dry_run { "Would rename" } else { "Renamed" };
+        for migration in &migrations {
+            println!(
+                "{} {} {} {} {}",
+                format!("🔁 {}", verb).bright_blue().bold(),
+                migration.from.bright_cyan().bold(),
+                "to".bright_white(),
+                migration.to.bright_cyan().bold(),
+                format!("in '{}'", migration.env).bright_white()
+            );
+        }
+        for conflict in &conflicts {
+            println!(
+                "{}",
+                format!("⚠️  Skipped {}; settle them by hand", conflict).bright_yellow()
+            );
+        }
+        Ok(migrations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_migrate_keys() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("production").unwrap();
+        commands
+            .config_manager
+            .save_schema(
+                &serde_yaml::from_str(
+                    "variables:\n  DB_URL:\n    deprecated: use DATABASE_URL\n  LEGACY:\n    deprecated: no longer read\n",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        commands
+            .set_variables(
+                &[
+                    ("DB_URL".to_string(), "pg://dev".to_string()),
+                    ("LEGACY".to_string(), "1".to_string()),
+                ],
+                "development",
+            )
+            .unwrap();
+        commands
+            .set_variables(
+                &[
+                    ("DB_URL".to_string(), "pg://old".to_string()),
+                    ("DATABASE_URL".to_string(), "pg://new".to_string()),
+                ],
+                "production",
+            )
+            .unwrap();
+
+        assert_eq!(commands.migrate_keys(true).unwrap().len(), 1);
+        let development = commands
+            .config_manager
+            .load_environment("development")
+            .unwrap();
+        assert!(development.variables.contains_key("DB_URL"));
+
+        let migrations = commands.migrate_keys(false).unwrap();
+        assert_eq!(
+            migrations,
+            [KeyMigration {
+                env: "development".to_string(),
+                from: "DB_URL".to_string(),
+                to: "DATABASE_URL".to_string(),
+            }]
+        );
+        let development = commands
+            .config_manager
+            .load_environment("development")
+            .unwrap();
+        assert_eq!(development.variables["DATABASE_URL"], "pg://dev");
+        assert!(!development.variables.contains_key("DB_URL"));
+        assert!(development.variables.contains_key("LEGACY"));
+        // The conflicting environment keeps both
+        let production = commands
+            .config_manager
+            .load_environment("production")
+            .unwrap();
+        assert_eq!(production.variables.len(), 2);
+    }
+}
@@ -1,27 +1,218 @@
-use crate::config::ConfigManager;
+use crate::config::{
+    self, AuditEntry, ConfigManager, EnvConfig, Schema, Settings, Snapshot, StorageFormat,
+    VariableMetadata, VariablesView,
+};
+use crate::diff::{self, VariableChange};
 use crate::error::{EnvMatchError, Result};
+use crate::export::{self, ExportFormat, ExportOptions};
+use crate::storage::BackendKind;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use colored::*;
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+
+mod codegen;
+mod direnv;
+mod doctor;
+mod dump;
+mod edit;
+mod generate;
+mod git;
+mod harden;
+mod hook;
+mod import;
+mod key;
+mod lint;
+mod list;
+mod log;
+mod merge;
+mod migrate;
+mod profiles;
+mod projects;
+mod prompt;
+mod prune;
+mod push;
+mod resolve;
+mod rotate;
+mod run;
+mod schema;
+mod serve;
+mod stats;
+mod status;
+mod switch;
+mod sync;
+mod template;
+mod transaction;
+mod validation;
+mod watch;
+mod wizard;
+
+pub use git::merge_environment_files;
+pub use hook::{session_environment, HookShell};
+pub use import::{read_import, ImportEntry, ImportFormat};
+pub use list::{KeyFilter, ListFormat, ListSort};
+pub use merge::MergeStrategy;
+pub use prune::PruneOptions;
+pub use run::Inheritance;
+pub use schema::SchemaFormat;
+pub use status::ProjectStatus;
+pub use switch::SwitchPreview;
+pub use validation::{IssueKind, ValidationIssue};
+
+/// What `env create --from --strip-secrets` puts in place of secret values
+pub const SECRET_PLACEHOLDER: &str = "change-me";
+
+/// Which variables `list` shows and how.
+#[derive(Clone, Debug, Default)]
+pub struct ListOptions {
+    /// Also show each variable's metadata
+    pub long: bool,
+    /// Show variables in sections by group
+    pub group: bool,
+    /// Only show variables whose key matches
+    pub filter: Option<KeyFilter>,
+    /// Print just the keys, one per line
+    pub keys_only: bool,
+    /// Print just the values, one per line
+    pub values_only: bool,
+    pub format: ListFormat,
+    pub sort: ListSort,
+}
 
 #[derive(Debug)]
 pub struct EnvMatchCommands {
     config_manager: ConfigManager,
+    /// Skip confirmations from commands that change things (`--quiet`)
+    quiet: bool,
+    /// Warn when a value that looks like a secret is stored in plain text
+    secret_scan: bool,
+    /// Refuse values breaking a schema policy of error severity (`--enforce`)
+    enforce_policies: bool,
+    /// Read exports back and fail unless they match (`export --check`)
+    check_exports: bool,
 }
 
 impl EnvMatchCommands {
     pub fn new() -> Self {
         Self {
             config_manager: ConfigManager::new(),
+            quiet: false,
+            secret_scan: true,
+            enforce_policies: false,
+            check_exports: false,
+        }
+    }
+
+    /// Operates on the project rooted at `project_dir` instead of discovering one.
+    pub fn with_project_dir(project_dir: PathBuf) -> Self {
+        Self {
+            config_manager: ConfigManager::with_project_dir(project_dir),
+            quiet: false,
+            secret_scan: true,
+            enforce_policies: false,
+            check_exports: false,
         }
     }
 
+    /// Lets commands carry on with an empty environment when its file is corrupt.
+    pub fn set_force_empty(&mut self, force_empty: bool) {
+        self.config_manager.set_force_empty(force_empty);
+    }
+
+    /// Works on a package of a monorepo, see [`ConfigManager::set_scope`].
+    pub fn set_scope(&mut self, scope: Option<&Path>) -> Result<()> {
+        self.config_manager.set_scope(scope)
+    }
+
+    /// Works on `env_name` instead of the project's current environment, see
+    /// [`ConfigManager::set_session_environment`].
+    pub fn set_session_environment(&mut self, env_name: Option<String>) {
+        self.config_manager.set_session_environment(env_name);
+    }
+
+    pub fn session_environment(&self) -> Option<&str> {
+        self.config_manager.session_environment()
+    }
+
+    /// Chooses where `init` keeps the project's files.
+    pub fn set_backend(&mut self, backend: BackendKind) {
+        self.config_manager.set_backend(backend);
+    }
+
+    /// Chooses the format `init` stores environments in.
+    pub fn set_storage_format(&mut self, storage_format: Option<StorageFormat>) {
+        self.config_manager.set_storage_format(storage_format);
+    }
+
+    /// Keeps commands that change things from printing confirmations, so
+    /// only their errors and requested output remain.
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    /// Turns off the warnings about secret-looking values (`--no-secret-scan`).
+    pub fn set_secret_scan(&mut self, secret_scan: bool) {
+        self.secret_scan = secret_scan;
+    }
+
+    /// Makes values breaking a schema policy of error severity fail instead
+    /// of only warning (`set --enforce`).
+    pub fn set_enforce_policies(&mut self, enforce_policies: bool) {
+        self.enforce_policies = enforce_policies;
+    }
+
+    /// Makes exports read their output back and fail, writing nothing,
+    /// unless every value survives unchanged (`export --check`).
+    pub fn set_check_exports(&mut self, check_exports: bool) {
+        self.check_exports = check_exports;
+    }
+
     #[cfg(test)]
     pub fn with_config_manager(config_manager: ConfigManager) -> Self {
-        Self { config_manager }
+        Self {
+            config_manager,
+            quiet: false,
+            secret_scan: true,
+            enforce_policies: false,
+            check_exports: false,
+        }
     }
 
-    pub fn init_with_environment(&self, env_name: &str) -> Result<()> {
+    /// Initializes the project with the default environment from the settings.
+    pub fn init(&self) -> Result<()> {
         self.config_manager.initialize()?;
+        let config = self.config_manager.load_global_config()?;
+        self.record(
+            &format!("init {}", config.current_environment),
+            vec![AuditEntry::new(
+                "init",
+                &config.current_environment,
+                None,
+                None,
+                None,
+            )],
+        )?;
+        self.print_init_summary(&config.current_environment);
+        Ok(())
+    }
+
+    pub fn init_with_environment(&self, env_name: &str) -> Result<()> {
+        self.config_manager.initialize_with_environment(env_name)?;
+        self.record(
+            &format!("init {}", env_name),
+            vec![AuditEntry::new("init", env_name, None, None, None)],
+        )?;
+        self.print_init_summary(env_name);
+        Ok(())
+    }
 
+    fn print_init_summary(&self, env_name: &str) {
+        if self.quiet {
+            return;
+        }
         println!(
             "{}",
             "✅ envMatch initialized successfully!"
@@ -35,71 +226,245 @@ impl EnvMatchCommands {
             env_name.bright_green().bold()
         );
 
-        // Create the initial environment if it's not the default "development"
-        if env_name != "development" {
-            let env_config = crate::config::EnvConfig::default();
-            self.config_manager
-                .save_environment(env_name, &env_config)?;
-            println!(
-                "{} {}",
-                "🆕 Created environment:".bright_cyan(),
-                env_name.bright_green().bold()
-            );
-        }
-
         println!(
             "{} {}",
             "💡 Try:".bright_magenta(),
             format!("envMatch set API_KEY your-key-here -e {}", env_name).bright_cyan()
         );
-
-        Ok(())
     }
 
     pub fn is_initialized(&self) -> bool {
         self.config_manager.is_initialized()
     }
 
-    pub fn set_variable(&self, key: &str, value: &str, env_name: &str) -> Result<()> {
-        let mut env_config = self.config_manager.load_environment(env_name)?;
-        env_config
-            .variables
-            .insert(key.to_string(), value.to_string());
+    /// Root of the project on disk, unless it lives in memory.
+    pub fn project_dir(&self) -> Option<&Path> {
+        self.config_manager.project_dir()
+    }
+
+    /// The project's `.envMatch` directory on disk, if it has one.
+    pub fn env_match_dir(&self) -> Option<PathBuf> {
+        self.config_manager.env_match_dir()
+    }
+
+    /// Makes the next reads go to storage, after files changed behind our back.
+    pub fn invalidate_cache(&self) {
+        self.config_manager.invalidate_cache();
+    }
+
+    /// Effective user and project settings.
+    pub fn settings(&self) -> Result<Settings> {
+        self.config_manager.load_settings()
+    }
+
+    /// Remembers the TUI's variables panel layout in the user config.
+    pub fn save_variables_view(&self, view: VariablesView) -> Result<()> {
         self.config_manager
-            .save_environment(env_name, &env_config)?;
+            .update_user_settings(|settings| settings.variables_view = Some(view))
+    }
 
-        println!(
-            "{} {}={} {} {}",
-            "✅ Set".bright_green().bold(),
-            key.bright_cyan().bold(),
-            value.bright_yellow(),
-            "in environment".bright_white(),
-            format!("'{}'", env_name).bright_green().bold()
-        );
-        Ok(())
+    /// Returns `env_name` if given, otherwise the project's active environment
+    /// (see [`ConfigManager::active_environment`]).
+    pub fn resolve_environment(&self, env_name: Option<&str>) -> Result<String> {
+        match env_name {
+            Some(env_name) => Ok(env_name.to_string()),
+            None => self.config_manager.active_environment(),
+        }
+    }
+
+    pub fn set_variable(&self, key: &str, value: &str, env_name: &str) -> Result<()> {
+        self.set_variables(&[(key.to_string(), value.to_string())], env_name)
+    }
+
+    /// Sets several variables at once, writing the environment file a single time.
+    pub fn set_variables(&self, vars: &[(String, String)], env_name: &str) -> Result<()> {
+        self.set_variables_with_metadata(vars, &VariableMetadata::default(), env_name)
     }
 
-    pub fn get_variable(&self, key: &str, env_name: &str) -> Result<String> {
+    /// Sets several variables, applying the description, tags and secret flag
+    /// in `metadata` to each of them.
+    pub fn set_variables_with_metadata(
+        &self,
+        vars: &[(String, String)],
+        metadata: &VariableMetadata,
+        env_name: &str,
+    ) -> Result<()> {
+        let entries: Vec<_> = vars
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone(), metadata.clone()))
+            .collect();
+        self.store_variables(&entries, env_name)?;
+        if self.quiet {
+            return Ok(());
+        }
+
         let env_config = self.config_manager.load_environment(env_name)?;
+        let schema = self.config_manager.load_schema()?;
+        for (key, value) in vars {
+            let value = match is_secret(&env_config, &schema, key) {
+                true => mask(value),
+                false => value.clone(),
+            };
+            println!(
+                "{} {}={} {} {}",
+                "✅ Set".bright_green().bold(),
+                key.bright_cyan().bold(),
+                value.bright_yellow(),
+                "in environment".bright_white(),
+                format!("'{}'", env_name).bright_green().bold()
+            );
+        }
+        Ok(())
+    }
+
+    /// Copies variables, with their descriptions, tags and secret flags, from
+    /// one environment to another. Keys missing from `from` are skipped.
+    /// Returns how many were copied.
+    pub fn copy_variables(&self, keys: &[String], from: &str, to: &str) -> Result<usize> {
+        let source = self.config_manager.load_environment(from)?;
+        let entries: Vec<_> = keys
+            .iter()
+            .filter_map(|key| {
+                let value = source.variables.get(key)?;
+                let metadata = source.metadata.get(key).cloned().unwrap_or_default();
+                Some((key.clone(), value.clone(), metadata))
+            })
+            .collect();
+        if !entries.is_empty() {
+            self.store_variables(&entries, to)?;
+        }
+        Ok(entries.len())
+    }
 
-        match env_config.variables.get(key) {
-            Some(value) => {
-                println!("{}", value);
-                Ok(value.clone())
+    /// Puts variables back the way they were: each key gets the given value
+    /// and metadata, or is removed when there's none. Used to undo changes.
+    pub fn restore_variables(
+        &self,
+        states: &[(String, Option<(String, VariableMetadata)>)],
+        env_name: &str,
+        command: &str,
+    ) -> Result<()> {
+        self.transaction(command, |tx| {
+            for (key, state) in states {
+                tx.restore(env_name, key, state.as_ref())?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Saves variables with their metadata updates and records the change,
+    /// without printing anything.
+    fn store_variables(
+        &self,
+        entries: &[(String, String, VariableMetadata)],
+        env_name: &str,
+    ) -> Result<()> {
+        let keys: Vec<&str> = entries.iter().map(|(key, ..)| key.as_str()).collect();
+        let command = format!("set {} -e {}", keys.join(" "), env_name);
+        self.transaction(&command, |tx| {
+            let settings = self.settings()?;
+            for (key, ..) in entries {
+                settings.key_names().check(key)?;
+            }
+            if self.secret_scan && !self.config_manager.is_encrypted() {
+                let env_config = tx.environment(env_name)?;
+                warn_plain_secrets(entries, env_config, settings.secret_patterns())?;
+            }
+            self.check_entry_policies(entries, tx.environment(env_name)?, env_name)?;
+            for (key, value, metadata) in entries {
+                tx.set_with_metadata(env_name, key, value, metadata)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Prints a variable's value. Secrets are masked when printing to a
+    /// terminal, unless `reveal`; piped output always gets the value.
+    pub fn get_variable(&self, key: &str, env_name: &str, reveal: bool) -> Result<String> {
+        let value = self.lookup_variable(key, env_name)?;
+        let env_config = self.config_manager.load_effective_environment(env_name)?;
+        let schema = self.config_manager.load_schema()?;
+        if !reveal && io::stdout().is_terminal() && is_secret(&env_config, &schema, key) {
+            println!("{}", mask(&value));
+        } else {
+            println!("{}", value);
+        }
+        Ok(value)
+    }
+
+    /// Prints a variable's value in every environment side by side, marking
+    /// the ones that don't set it and masking secrets unless `reveal`.
+    /// Returns the values in environment order.
+    pub fn get_variable_across_environments(
+        &self,
+        key: &str,
+        reveal: bool,
+    ) -> Result<Vec<(String, Option<String>)>> {
+        let current = self.config_manager.active_environment()?;
+        let schema = self.config_manager.load_schema()?;
+        let mut values = Vec::new();
+        let mut secret = Vec::new();
+        for env_name in self.config_manager.list_environments()? {
+            let env_config = self.config_manager.load_effective_environment(&env_name)?;
+            secret.push(!reveal && is_secret(&env_config, &schema, key));
+            values.push((env_name, env_config.variables.get(key).cloned()));
+        }
+
+        let width = values.iter().map(|(env, _)| env.len()).max().unwrap_or(0);
+        println!("{} {}", "🔑".bright_blue(), key.bright_cyan().bold());
+        println!("{}", "─".repeat(40).bright_blue());
+        for ((env_name, value), secret) in values.iter().zip(secret) {
+            let marker = if *env_name == current { "*" } else { " " };
+            let name = format!("{} {:width$}", marker, env_name, width = width);
+            match value {
+                Some(value) if secret => println!("{}  {}", name.bright_green(), mask(value)),
+                Some(value) => println!("{}  {}", name.bright_green(), value),
+                None => println!("{}  {}", name.bright_green(), "(unset)".bright_black()),
             }
-            None => Err(EnvMatchError::VariableNotFound {
-                key: key.to_string(),
-                env: env_name.to_string(),
-            }),
         }
+        Ok(values)
+    }
+
+    /// Decodes a variable stored with `set --base64`, ignoring line breaks
+    /// in the encoded value.
+    pub fn decode_variable(&self, key: &str, env_name: &str) -> Result<Vec<u8>> {
+        let value = self.lookup_variable(key, env_name)?;
+        let encoded: String = value.split_ascii_whitespace().collect();
+        STANDARD
+            .decode(encoded)
+            .map_err(|_| EnvMatchError::NotBase64 {
+                key: key.to_string(),
+            })
+    }
+
+    /// Returns a variable's value without printing it.
+    pub fn lookup_variable(&self, key: &str, env_name: &str) -> Result<String> {
+        let env_config = self.config_manager.load_effective_environment(env_name)?;
+
+        env_config.variables.get(key).cloned().ok_or_else(|| {
+            EnvMatchError::variable_not_found(key, env_name, env_config.variables.keys())
+        })
     }
 
+    #[cfg_attr(not(test), allow(dead_code))]
     pub fn unset_variable(&self, key: &str, env_name: &str) -> Result<()> {
-        let mut env_config = self.config_manager.load_environment(env_name)?;
+        self.unset_variables(&[key.to_string()], env_name)
+    }
+
+    /// Removes several variables at once. Nothing is removed if any key is missing.
+    pub fn unset_variables(&self, keys: &[String], env_name: &str) -> Result<()> {
+        let command = format!("unset {} -e {}", keys.join(" "), env_name);
+        self.transaction(&command, |tx| {
+            for key in keys {
+                tx.unset(env_name, key)?;
+            }
+            Ok(())
+        })?;
+        if self.quiet {
+            return Ok(());
+        }
 
-        if env_config.variables.remove(key).is_some() {
-            self.config_manager
-                .save_environment(env_name, &env_config)?;
+        for key in keys {
             println!(
                 "{} {} {} {}",
                 "✅ Removed".bright_green().bold(),
@@ -107,13 +472,8 @@ impl EnvMatchCommands {
                 "from environment".bright_white(),
                 format!("'{}'", env_name).bright_green().bold()
             );
-            Ok(())
-        } else {
-            Err(EnvMatchError::VariableNotFound {
-                key: key.to_string(),
-                env: env_name.to_string(),
-            })
         }
+        Ok(())
     }
 
     pub fn switch_environment(&self, env_name: &str) -> Result<()> {
@@ -123,24 +483,115 @@ impl EnvMatchCommands {
         let mut config = self.config_manager.load_global_config()?;
         config.current_environment = env_name.to_string();
         self.config_manager.save_global_config(&config)?;
+        self.record(
+            &format!("switch {}", env_name),
+            vec![AuditEntry::new("switch", env_name, None, None, None)],
+        )?;
 
-        println!(
-            "{} {}",
-            "✅ Switched to environment".bright_green().bold(),
-            format!("'{}'", env_name).bright_green().bold().underline()
-        );
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "✅ Switched to environment".bright_green().bold(),
+                format!("'{}'", env_name).bright_green().bold().underline()
+            );
+        }
+        if let Some(session) = self.config_manager.session_environment() {
+            if session != env_name {
+                println!(
+                    "{} {}",
+                    "⚠️  ENVMATCH_ENV still selects".bright_yellow(),
+                    format!("'{}' in this shell", session)
+                        .bright_yellow()
+                        .bold()
+                );
+            }
+        } else if let Some(rule) = self.config_manager.matching_rule()? {
+            if rule.environment != env_name {
+                println!(
+                    "{} {}",
+                    format!("⚠️  Rule '{}' still selects", rule.pattern).bright_yellow(),
+                    format!("'{}' here", rule.environment)
+                        .bright_yellow()
+                        .bold()
+                );
+            }
+        }
         Ok(())
     }
 
-    pub fn list_variables(&self, env_name: Option<&str>) -> Result<Vec<(String, String)>> {
-        let config = self.config_manager.load_global_config()?;
-        let env_name = env_name.unwrap_or(&config.current_environment);
-        let env_config = self.config_manager.load_environment(env_name)?;
+    /// Lists an environment's variables in the order they were added or, when
+    /// grouping, by group and then in that order.
+    pub fn list_variables(
+        &self,
+        env_name: Option<&str>,
+        options: ListOptions,
+    ) -> Result<Vec<(String, String)>> {
+        let env_name = self.resolve_environment(env_name)?;
+        let env_config = self.config_manager.load_effective_environment(&env_name)?;
+        // Variables a scope doesn't set itself come from the project
+        let own = match self.config_manager.scope() {
+            Some(_) => Some(self.config_manager.load_environment(&env_name)?),
+            None => None,
+        };
+        let mask_values = self.settings()?.mask_values();
+        let schema = self.config_manager.load_schema()?;
+        let now = chrono::Utc::now();
+
+        let mut vars: Vec<_> = env_config
+            .variables
+            .iter()
+            .filter(|(key, _)| options.filter.as_ref().is_none_or(|f| f.matches(key)))
+            .map(|(key, value)| {
+                let group = options
+                    .group
+                    .then(|| config::group_of(key, env_config.metadata.get(key)))
+                    .flatten();
+                (group, key, value)
+            })
+            .collect();
+        match options.sort {
+            ListSort::Added => {}
+            ListSort::Name => vars.sort_by_key(|(_, key, _)| *key),
+            ListSort::Modified => vars.sort_by_key(|(_, key, _)| {
+                // Never-changed variables go last
+                std::cmp::Reverse(
+                    env_config
+                        .metadata
+                        .get(*key)
+                        .and_then(|metadata| metadata.updated_at.or(metadata.created_at)),
+                )
+            }),
+        }
+        // Ungrouped variables go last
+        vars.sort_by_key(|(group, ..)| (group.is_none(), *group));
+
+        let result: Vec<(String, String)> = vars
+            .iter()
+            .map(|(_, k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        // Plain output is for scripts: no header, colors or masking
+        if options.format != ListFormat::Table {
+            print!("{}", list::render(options.format, &result)?);
+            return Ok(result);
+        }
+        if options.keys_only || options.values_only {
+            for (key, value) in &result {
+                println!("{}", if options.keys_only { key } else { value });
+            }
+            return Ok(result);
+        }
 
+        let scope = self
+            .config_manager
+            .scope()
+            .map(|scope| format!(" ({})", scope.display()))
+            .unwrap_or_default();
         println!(
-            "{} {}",
+            "{} {}{}",
             "📋 Environment:".bright_blue().bold(),
-            env_name.bright_green().bold()
+            env_name.bright_green().bold(),
+            scope.bright_black()
         );
         println!("{}", "─".repeat(40).bright_blue());
 
@@ -148,65 +599,177 @@ impl EnvMatchCommands {
             println!("{}", "(no variables set)".bright_black());
             return Ok(vec![]);
         }
+        if vars.is_empty() {
+            println!("{}", "(no variables match)".bright_black());
+            return Ok(vec![]);
+        }
 
-        let mut vars: Vec<_> = env_config.variables.iter().collect();
-        vars.sort_by_key(|(k, _)| *k);
-
-        let result: Vec<(String, String)> = vars
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect();
+        for (index, (group, key, value)) in vars.iter().enumerate() {
+            if options.group && (index == 0 || vars[index - 1].0 != *group) {
+                let count = vars.iter().filter(|(other, ..)| other == group).count();
+                println!(
+                    "{} {} {}",
+                    "▾".bright_blue(),
+                    group.unwrap_or("Other").bright_magenta().bold(),
+                    format!("({})", count).bright_black()
+                );
+            }
 
-        for (key, value) in &vars {
-            println!("{}={}", key.bright_cyan().bold(), value.bright_green());
+            let indent = if options.group { "  " } else { "" };
+            let value = if mask_values || is_secret(&env_config, &schema, key) {
+                mask(value)
+            } else {
+                value.to_string()
+            };
+            let expiry = env_config
+                .metadata
+                .get(*key)
+                .and_then(|metadata| metadata.expiry(now, config::ROTATION_WINDOW_DAYS))
+                .map(|expiry| format!("  ⏰ {}", expiry_label(expiry)))
+                .unwrap_or_default();
+            let inherited = match &own {
+                Some(own) if !own.variables.contains_key(*key) => "  (inherited)",
+                _ => "",
+            };
+            let deprecated = schema
+                .variables
+                .get(*key)
+                .and_then(|variable| variable.deprecated.as_deref())
+                .map(|note| format!("  ⚠️  deprecated: {}", note))
+                .unwrap_or_default();
+            println!(
+                "{}{}={}{}{}{}",
+                indent,
+                key.bright_cyan().bold(),
+                value.bright_green(),
+                expiry,
+                deprecated.bright_yellow(),
+                inherited.bright_black()
+            );
+            if let Some(metadata) = env_config.metadata.get(*key).filter(|_| options.long) {
+                print_metadata(metadata, &format!("{}    ", indent));
+            }
         }
 
         Ok(result)
     }
 
-    pub fn show_current_environment(&self) -> Result<String> {
-        let config = self.config_manager.load_global_config()?;
-        println!("{}", config.current_environment);
-        Ok(config.current_environment)
+    /// The deprecation notes of the schema's deprecated variables, by key.
+    pub fn deprecations(&self) -> Result<BTreeMap<String, String>> {
+        Ok(self.config_manager.load_schema()?.deprecations())
     }
 
-    pub fn validate_environment(&self, required: Option<&str>) -> Result<()> {
-        let config = self.config_manager.load_global_config()?;
-        let env_config = self
-            .config_manager
-            .load_environment(&config.current_environment)?;
+    /// Metadata of an environment's variables, by key.
+    pub fn variable_metadata(&self, env_name: &str) -> Result<BTreeMap<String, VariableMetadata>> {
+        Ok(self.config_manager.load_environment(env_name)?.metadata)
+    }
 
-        if let Some(required_vars) = required {
-            let required_list: Vec<&str> = required_vars.split(',').map(|s| s.trim()).collect();
-            let missing: Vec<String> = required_list
-                .iter()
-                .filter(|&&var| !env_config.variables.contains_key(var))
-                .map(|&var| var.to_string())
-                .collect();
+    pub fn show_current_environment(&self) -> Result<String> {
+        let env_name = self.config_manager.active_environment()?;
+        println!("{}", env_name);
+        Ok(env_name)
+    }
 
-            if missing.is_empty() {
+    /// Checks that the current environment sets the required variables: the
+    /// `required` list if given, else the ones the schema marks as required,
+    /// that none has expired and that no value breaks a schema policy of
+    /// error severity there. With `fix`, asks for each missing value and
+    /// saves the ones entered.
+    pub fn validate_environment(&self, required: Option<&str>, fix: bool) -> Result<()> {
+        let env_name = self.config_manager.active_environment()?;
+        let env_config = self.config_manager.load_effective_environment(&env_name)?;
+        let schema = self.config_manager.load_schema()?;
+
+        let now = chrono::Utc::now();
+        let mut expired = Vec::new();
+        for (key, metadata) in &env_config.metadata {
+            match metadata.expiry(now, config::ROTATION_WINDOW_DAYS) {
+                Some(expiry) if expiry.is_expired() => expired.push(key.clone()),
+                Some(expiry) => println!(
+                    "{}",
+                    format!("⚠️  {} {}, rotate it soon", key, expiry).bright_yellow()
+                ),
+                None => {}
+            }
+        }
+        for (key, note) in schema.deprecations() {
+            if env_config.variables.contains_key(&key) {
                 println!(
-                    "✅ All required variables are set in environment '{}'",
-                    config.current_environment
+                    "{}",
+                    format!("⚠️  {} is deprecated: {}", key, note).bright_yellow()
                 );
-                Ok(())
-            } else {
-                Err(EnvMatchError::MissingRequiredVariables {
-                    env: config.current_environment,
-                    variables: missing,
-                })
             }
-        } else {
+        }
+        let mut broken = Vec::new();
+        for violation in self.policy_violations(&env_name, &env_config, &schema)? {
+            let message = format!("{}: {}", violation.key, violation.message);
+            match violation.severity {
+                config::PolicyLevel::Error => broken.push(message),
+                config::PolicyLevel::Warn => {
+                    println!("{}", format!("⚠️  {}", message).bright_yellow())
+                }
+            }
+        }
+        let problems = || match (expired.is_empty(), broken.is_empty()) {
+            (false, _) => Err(EnvMatchError::ExpiredVariables {
+                env: env_name.clone(),
+                variables: expired.clone(),
+            }),
+            (true, false) => Err(EnvMatchError::PolicyViolations {
+                env: env_name.clone(),
+                violations: broken.clone(),
+            }),
+            (true, true) => Ok(()),
+        };
+
+        let required_list: Vec<String> = match required {
+            Some(required_vars) => required_vars
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .collect(),
+            None => schema
+                .variables
+                .iter()
+                .filter(|(_, variable)| variable.required)
+                .map(|(key, _)| key.clone())
+                .collect(),
+        };
+
+        if required_list.is_empty() {
+            problems()?;
             let var_count = env_config.variables.len();
             println!(
                 "✅ Environment '{}' has {} variable(s)",
-                config.current_environment, var_count
+                env_name, var_count
+            );
+            return Ok(());
+        }
+
+        let mut missing: Vec<String> = required_list
+            .into_iter()
+            .filter(|var| !env_config.variables.contains_key(var))
+            .collect();
+        if fix && !missing.is_empty() {
+            missing = self.prompt_missing_variables(&env_name, &missing, &schema)?;
+        }
+
+        if missing.is_empty() {
+            problems()?;
+            println!(
+                "✅ All required variables are set in environment '{}'",
+                env_name
             );
             Ok(())
+        } else {
+            Err(EnvMatchError::MissingRequiredVariables {
+                env: env_name,
+                variables: missing,
+            })
         }
     }
 
-    pub fn list_environments(&self) -> Result<Vec<String>> {
+    /// Lists the environments, followed by the archived ones with `all`.
+    pub fn list_environments(&self, all: bool) -> Result<Vec<String>> {
         let environments = self.config_manager.list_environments()?;
         let config = self.config_manager.load_global_config()?;
 
@@ -225,72 +788,891 @@ impl EnvMatchCommands {
                 println!("• {}", env);
             }
         }
+        if all {
+            for env in self.config_manager.list_archived_environments()? {
+                println!("{}", format!("• {} (archived)", env).bright_black());
+            }
+        }
 
         Ok(environments)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::ConfigManager;
-    use tempfile::TempDir;
+    pub fn environment_exists(&self, env_name: &str) -> bool {
+        self.config_manager.environment_exists(env_name)
+    }
 
-    fn create_test_commands() -> (EnvMatchCommands, TempDir) {
-        let temp_dir = TempDir::new().unwrap();
-        let base_dir = temp_dir.path().join(".envMatch");
-        let config_manager = ConfigManager::with_base_dir(base_dir);
-        let commands = EnvMatchCommands::with_config_manager(config_manager);
-        (commands, temp_dir)
+    pub fn create_environment(&self, env_name: &str) -> Result<()> {
+        self.config_manager.create_environment(env_name)?;
+        self.record(
+            &format!("env create {}", env_name),
+            vec![AuditEntry::new("create", env_name, None, None, None)],
+        )?;
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "✅ Created environment".bright_green().bold(),
+                format!("'{}'", env_name).bright_green().bold()
+            );
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_init_command() {
-        let (commands, _temp_dir) = create_test_commands();
+    /// Creates an environment holding a copy of another's variables, with
+    /// their descriptions, tags and secret flags. With `strip_secrets`,
+    /// secrets (flagged, or declared secret in the schema) get
+    /// [`SECRET_PLACEHOLDER`] instead of their value.
+    pub fn create_environment_from(
+        &self,
+        env_name: &str,
+        source: &str,
+        strip_secrets: bool,
+    ) -> Result<()> {
+        let source_config = self.config_manager.load_environment(source)?;
+        let schema = self.config_manager.load_schema()?;
+        self.config_manager.create_environment(env_name)?;
 
-        commands.init_with_environment("development").unwrap();
+        let mut stripped = 0;
+        let command = format!("env create {} --from {}", env_name, source);
+        self.transaction(&command, |tx| {
+            let env_config = tx.environment_mut(env_name)?;
+            for (key, value) in &source_config.variables {
+                let mut metadata = source_config.metadata.get(key).cloned();
+                let value = if strip_secrets && is_secret(&source_config, &schema, key) {
+                    stripped += 1;
+                    if let Some(metadata) = &mut metadata {
+                        metadata.expires_at = None;
+                    }
+                    SECRET_PLACEHOLDER
+                } else {
+                    value.as_str()
+                };
+                env_config.variables.insert(key.clone(), value.to_string());
+                if let Some(metadata) = metadata {
+                    env_config.metadata.insert(key.clone(), metadata);
+                }
+            }
+            tx.audit([AuditEntry::new("create", env_name, None, None, None)]);
+            Ok(())
+        })?;
 
-        // Should fail to init again
-        assert!(matches!(
-            commands.init_with_environment("development"),
-            Err(EnvMatchError::AlreadyInitialized)
-        ));
+        if !self.quiet {
+            println!(
+                "{} {} {}",
+                "✅ Created environment".bright_green().bold(),
+                format!("'{}'", env_name).bright_green().bold(),
+                format!(
+                    "from '{}' ({} variables)",
+                    source,
+                    source_config.variables.len()
+                )
+                .bright_white()
+            );
+            if stripped > 0 {
+                println!(
+                    "{}",
+                    format!(
+                        "🔒 {} secret(s) set to '{}'; fill them in before use",
+                        stripped, SECRET_PLACEHOLDER
+                    )
+                    .bright_yellow()
+                );
+            }
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_set_and_get_variable() {
-        let (commands, _temp_dir) = create_test_commands();
-        commands.init_with_environment("development").unwrap();
-
-        commands
-            .set_variable("TEST_KEY", "test_value", "development")
-            .unwrap();
-        let value = commands.get_variable("TEST_KEY", "development").unwrap();
+    /// Moves an environment out of the way, keeping it for reference.
+    pub fn archive_environment(&self, env_name: &str) -> Result<()> {
+        self.config_manager.archive_environment(env_name)?;
+        self.record(
+            &format!("env archive {}", env_name),
+            vec![AuditEntry::new("archive", env_name, None, None, None)],
+        )?;
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "🗄️  Archived environment".bright_green().bold(),
+                format!("'{}'", env_name).bright_green().bold()
+            );
+        }
+        Ok(())
+    }
 
-        assert_eq!(value, "test_value");
+    pub fn unarchive_environment(&self, env_name: &str) -> Result<()> {
+        self.config_manager.unarchive_environment(env_name)?;
+        self.record(
+            &format!("env unarchive {}", env_name),
+            vec![AuditEntry::new("unarchive", env_name, None, None, None)],
+        )?;
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "✅ Restored environment".bright_green().bold(),
+                format!("'{}'", env_name).bright_green().bold()
+            );
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_unset_variable() {
-        let (commands, _temp_dir) = create_test_commands();
-        commands.init_with_environment("development").unwrap();
+    pub fn show_history(&self) -> Result<Vec<Snapshot>> {
+        let snapshots = self.config_manager.list_snapshots()?;
 
-        commands
-            .set_variable("TEST_KEY", "test_value", "development")
-            .unwrap();
+        if snapshots.is_empty() {
+            println!("No history recorded yet.");
+            return Ok(snapshots);
+        }
+
+        println!("{}", "🕘 History:".bright_blue().bold());
+        println!("{}", "─".repeat(60).bright_blue());
+        for snapshot in &snapshots {
+            println!(
+                "{} {} {} {}",
+                format!("#{:<4}", snapshot.id).bright_yellow().bold(),
+                snapshot
+                    .timestamp
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+                    .bright_black(),
+                snapshot.user.bright_cyan(),
+                snapshot.command.bright_white()
+            );
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Shows the changes to an environment since the given snapshot.
+    pub fn diff_with_snapshot(
+        &self,
+        snapshot_id: &str,
+        env_name: Option<&str>,
+    ) -> Result<Vec<VariableChange>> {
+        let snapshot = self
+            .config_manager
+            .load_snapshot(parse_snapshot_id(snapshot_id)?)?;
+        let env_name = self.resolve_environment(env_name)?;
+        let current = self.config_manager.load_environment(&env_name)?;
+        let previous = snapshot
+            .environments
+            .get(&env_name)
+            .cloned()
+            .unwrap_or_default();
+
+        let changes = diff::diff_variables(&previous.variables, &current.variables);
+        println!(
+            "{} {} {} {}",
+            "🔍 Changes to".bright_blue().bold(),
+            format!("'{}'", env_name).bright_green().bold(),
+            "since snapshot".bright_blue().bold(),
+            format!("#{}", snapshot.id).bright_yellow().bold()
+        );
+        print_changes(&changes);
+
+        Ok(changes)
+    }
+
+    /// Compares an environment with the variables of the running process.
+    ///
+    /// Changes go from the environment to the process: `Removed` is missing
+    /// from the process, `Added` is set only in the process and `Changed`
+    /// differs. Only extra variables that share a prefix with one of the
+    /// environment's keys (`DATABASE_POOL` for `DATABASE_URL`) are reported,
+    /// to leave out the rest of the shell's variables.
+    pub fn diff_with_os(&self, env_name: Option<&str>) -> Result<Vec<VariableChange>> {
+        let env_name = self.resolve_environment(env_name)?;
+        let env_config = self.config_manager.load_environment(&env_name)?;
+        let mask_values = self.settings()?.mask_values();
+        let os_vars = std::env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+            .collect();
+
+        let changes = os_changes(&env_config.variables, &os_vars);
+        println!(
+            "{} {} {}",
+            "🔍 Comparing".bright_blue().bold(),
+            format!("'{}'", env_name).bright_green().bold(),
+            "with the process environment".bright_blue().bold()
+        );
+        if changes.is_empty() {
+            println!("{}", "(everything matches)".bright_black());
+        }
+        let shown = |value: &str| {
+            if mask_values {
+                mask(value)
+            } else {
+                value.to_string()
+            }
+        };
+        for change in &changes {
+            match change {
+                VariableChange::Removed { key, value } => println!(
+                    "{} {}={}",
+                    "missing ".bright_red().bold(),
+                    key.bright_cyan().bold(),
+                    shown(value)
+                ),
+                VariableChange::Added { key, value } => println!(
+                    "{} {}={}",
+                    "extra   ".bright_yellow().bold(),
+                    key.bright_cyan().bold(),
+                    shown(value)
+                ),
+                VariableChange::Changed { key, old, new } => println!(
+                    "{} {}: {} {} {} {}",
+                    "differs ".bright_magenta().bold(),
+                    key.bright_cyan().bold(),
+                    shown(old),
+                    "(file) vs".bright_black(),
+                    shown(new),
+                    "(process)".bright_black()
+                ),
+            }
+        }
+
+        Ok(changes)
+    }
+
+    pub fn rollback(&self, snapshot_id: &str) -> Result<()> {
+        let snapshot = self
+            .config_manager
+            .load_snapshot(parse_snapshot_id(snapshot_id)?)?;
+
+        let mut audit = Vec::new();
+        let existing = self.config_manager.list_environments()?;
+        let mut env_names = existing.clone();
+        env_names.extend(snapshot.environments.keys().cloned());
+        env_names.sort();
+        env_names.dedup();
+        for env_name in &env_names {
+            let before = if existing.contains(env_name) {
+                self.config_manager.load_environment(env_name)?
+            } else {
+                Default::default()
+            };
+            let after = snapshot
+                .environments
+                .get(env_name)
+                .cloned()
+                .unwrap_or_default();
+            let changes = diff::diff_variables(&before.variables, &after.variables);
+            audit.extend(audit_changes("rollback", env_name, &changes));
+        }
+
+        self.config_manager.restore_snapshot(&snapshot)?;
+        let command = format!("rollback {}", snapshot.id);
+        self.record_touching(&command, env_names, audit)?;
+        if self.quiet {
+            return Ok(());
+        }
+
+        println!(
+            "{} {} {}",
+            "✅ Rolled back to snapshot".bright_green().bold(),
+            format!("#{}", snapshot.id).bright_yellow().bold(),
+            format!("({})", snapshot.command).bright_white()
+        );
+        Ok(())
+    }
+
+    /// Shows audit log entries, optionally filtered by environment and key.
+    pub fn show_audit_log(
+        &self,
+        env_name: Option<&str>,
+        key: Option<&str>,
+        verify: bool,
+    ) -> Result<Vec<AuditEntry>> {
+        if verify {
+            let count = self.config_manager.verify_audit_log()?;
+            println!(
+                "{} {}",
+                "✅ Audit log intact:".bright_green().bold(),
+                format!("{} entries verified", count).bright_white()
+            );
+        }
+
+        let entries: Vec<AuditEntry> = self
+            .config_manager
+            .read_audit_log()?
+            .into_iter()
+            .filter(|entry| env_name.is_none_or(|env| entry.env == env))
+            .filter(|entry| key.is_none_or(|key| entry.key.as_deref() == Some(key)))
+            .collect();
+
+        if entries.is_empty() {
+            println!("No audit entries found.");
+            return Ok(entries);
+        }
+
+        for entry in &entries {
+            let short = |hash: &Option<String>| {
+                hash.as_deref()
+                    .map(|hash| hash[..12.min(hash.len())].to_string())
+                    .unwrap_or_else(|| "∅".to_string())
+            };
+            let change = match entry.key {
+                Some(ref key) => format!(
+                    "{} {} → {}",
+                    key.bright_cyan().bold(),
+                    short(&entry.old_hash),
+                    short(&entry.new_hash)
+                ),
+                None => String::new(),
+            };
+            println!(
+                "{} {} {} {} {}",
+                entry
+                    .timestamp
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+                    .bright_black(),
+                entry.user.bright_cyan(),
+                entry.command.bright_yellow(),
+                entry.env.bright_green(),
+                change
+            );
+        }
+
+        Ok(entries)
+    }
+
+    /// Records a history snapshot and the matching audit log entries. The
+    /// snapshot stores the environments the entries are about.
+    fn record(&self, command: &str, audit: Vec<AuditEntry>) -> Result<()> {
+        self.record_touching(command, Vec::new(), audit)
+    }
+
+    /// Like [`record`](Self::record), also storing the environments in
+    /// `touched` in the snapshot.
+    fn record_touching(
+        &self,
+        command: &str,
+        mut touched: Vec<String>,
+        audit: Vec<AuditEntry>,
+    ) -> Result<()> {
+        touched.extend(audit.iter().map(|entry| entry.env.clone()));
+        self.config_manager.record_snapshot(command, &touched)?;
+        self.config_manager.append_audit(audit)
+    }
+
+    /// Renders an environment in the given format, to stdout or to `output`.
+    /// Exports the given environments merged in order (later ones win), or
+    /// the current environment if none are given.
+    pub fn export_environment(
+        &self,
+        env_names: &[String],
+        format: ExportFormat,
+        name: Option<&str>,
+        namespace: Option<&str>,
+        output: Option<&Path>,
+        resolved: bool,
+    ) -> Result<String> {
+        let mut variables = self.resolve_layers(env_names)?;
+        if resolved {
+            variables = self.resolve_values(variables)?;
+        }
+        let vars: Vec<(String, String)> = variables.into_iter().collect();
+        let env_name = match env_names.last() {
+            Some(env_name) => env_name.clone(),
+            None => self.resolve_environment(None)?,
+        };
+
+        let options = ExportOptions {
+            name: name.unwrap_or(&env_name).to_string(),
+            namespace: namespace.map(str::to_string),
+            types: self.config_manager.load_schema()?.types(),
+        };
+        let rendered = export::render(format, &vars, &options)?;
+        if self.check_exports {
+            export::check(format, &vars, &rendered)?;
+        }
+
+        match output {
+            Some(path) => {
+                fs::write(path, &rendered).map_err(|e| EnvMatchError::storage(path, e))?;
+                println!(
+                    "{} {} {} {}",
+                    "✅ Exported environment".bright_green().bold(),
+                    format!("'{}'", env_name).bright_green().bold(),
+                    "to".bright_white(),
+                    path.display().to_string().bright_cyan()
+                );
+            }
+            None => print!("{}", rendered),
+        }
+
+        Ok(rendered)
+    }
+}
+
+/// Prints variable changes as `+` added, `-` removed and `~` changed lines.
+pub fn print_changes(changes: &[VariableChange]) {
+    if changes.is_empty() {
+        println!("{}", "(no changes)".bright_black());
+        return;
+    }
+
+    for change in changes {
+        match change {
+            VariableChange::Added { key, value } => {
+                println!("{}", format!("+ {}={}", key, value).bright_green())
+            }
+            VariableChange::Removed { key, value } => {
+                println!("{}", format!("- {}={}", key, value).bright_red())
+            }
+            VariableChange::Changed { key, old, new } => {
+                println!(
+                    "{}",
+                    format!("~ {}: {} → {}", key, old, new).bright_yellow()
+                )
+            }
+        }
+    }
+}
+
+/// Warns about values that look like secrets but aren't flagged as such,
+/// since they're about to be written in plain text.
+fn warn_plain_secrets(
+    entries: &[(String, String, VariableMetadata)],
+    env_config: &EnvConfig,
+    patterns: &[config::SecretPattern],
+) -> Result<()> {
+    let scanner = config::SecretScanner::new(patterns)?;
+    for (key, value, metadata) in entries {
+        let flagged = metadata.secret || env_config.metadata.get(key).is_some_and(|m| m.secret);
+        if let Some(kind) = scanner.detect(value).filter(|_| !flagged) {
+            eprintln!(
+                "{} {}",
+                format!(
+                    "⚠️  {} looks like a secret ({}) and will be stored in plain text.",
+                    key, kind
+                )
+                .bright_yellow(),
+                "Use --secret, enable encryption with git-init, or pass --no-secret-scan"
+                    .bright_black()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// An expiry note, red once expired and yellow while coming up.
+fn expiry_label(expiry: config::Expiry) -> ColoredString {
+    match expiry.is_expired() {
+        true => expiry.to_string().bright_red().bold(),
+        false => expiry.to_string().bright_yellow(),
+    }
+}
+
+/// One audit entry per changed variable.
+fn print_metadata(metadata: &VariableMetadata, indent: &str) {
+    if let Some(description) = &metadata.description {
+        println!("{}{}", indent, description.bright_white());
+    }
+    let mut details = Vec::new();
+    if metadata.secret {
+        details.push("🔒 secret".to_string());
+    }
+    if !metadata.tags.is_empty() {
+        details.push(format!("tags: {}", metadata.tags.join(", ")));
+    }
+    if let Some(expires_at) = metadata.expires_at {
+        details.push(format!(
+            "expires {}",
+            expires_at.with_timezone(&chrono::Local).format("%Y-%m-%d")
+        ));
+    }
+    if let Some(updated_at) = metadata.updated_at {
+        details.push(format!(
+            "updated {}",
+            updated_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+        ));
+    }
+    if let Some(created_at) = metadata.created_at {
+        details.push(format!(
+            "created {}",
+            created_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+        ));
+    }
+    if !details.is_empty() {
+        println!("{}{}", indent, details.join(" · ").bright_black());
+    }
+}
+
+/// Differences between an environment and process variables, keeping only
+/// the extra process variables related to the environment's keys.
+fn os_changes(
+    env_vars: &IndexMap<String, String>,
+    os_vars: &IndexMap<String, String>,
+) -> Vec<VariableChange> {
+    let prefix = |key: &str| key.split('_').next().unwrap_or_default().to_string();
+    let prefixes: HashSet<String> = env_vars
+        .keys()
+        .filter(|key| key.contains('_'))
+        .map(|key| prefix(key))
+        .filter(|prefix| !prefix.is_empty())
+        .collect();
+    let related: IndexMap<String, String> = os_vars
+        .iter()
+        .filter(|(key, _)| env_vars.contains_key(*key) || prefixes.contains(&prefix(key)))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    diff::diff_variables(env_vars, &related)
+}
+
+fn audit_changes(command: &str, env_name: &str, changes: &[VariableChange]) -> Vec<AuditEntry> {
+    changes
+        .iter()
+        .map(|change| match change {
+            VariableChange::Added { key, value } => {
+                AuditEntry::new(command, env_name, Some(key), None, Some(value))
+            }
+            VariableChange::Removed { key, value } => {
+                AuditEntry::new(command, env_name, Some(key), Some(value), None)
+            }
+            VariableChange::Changed { key, old, new } => {
+                AuditEntry::new(command, env_name, Some(key), Some(old), Some(new))
+            }
+        })
+        .collect()
+}
+
+fn parse_snapshot_id(input: &str) -> Result<u64> {
+    input
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| EnvMatchError::SnapshotNotFound {
+            id: input.to_string(),
+        })
+}
+
+/// Hides a value for display as 4 to 12 asterisks. The count follows the
+/// length only within that range, so short and long secrets don't give
+/// their length away.
+pub fn mask(value: &str) -> String {
+    "*".repeat(value.chars().count().clamp(4, 12))
+}
+
+/// Whether a variable holds a secret: flagged with `--secret`, or declared
+/// `secret: true` in the schema.
+fn is_secret(env_config: &EnvConfig, schema: &Schema, key: &str) -> bool {
+    env_config
+        .metadata
+        .get(key)
+        .is_some_and(|metadata| metadata.secret)
+        || schema
+            .variables
+            .get(key)
+            .is_some_and(|declared| declared.secret)
+}
+
+/// Parses `set` arguments into key/value pairs.
+///
+/// Accepts either the classic `KEY VALUE` form or any number of `KEY=VALUE`
+/// assignments. Every assignment is checked before anything is returned, so a
+/// single malformed entry rejects the whole batch.
+pub fn parse_assignments(args: &[String]) -> Result<Vec<(String, String)>> {
+    if let [key, value] = args {
+        if !key.contains('=') {
+            return Ok(vec![(key.clone(), value.clone())]);
+        }
+    }
+
+    args.iter()
+        .map(|arg| match arg.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+            _ => Err(EnvMatchError::InvalidAssignment { input: arg.clone() }),
+        })
+        .collect()
+}
+
+/// Replaces each value with its base64 encoding, for `set --base64`. A value
+/// of `-` is read from stdin as raw bytes, so binary files can be stored.
+pub fn encode_base64_values(vars: &mut [(String, String)]) -> Result<()> {
+    for (_, value) in vars.iter_mut() {
+        let bytes = if value == "-" {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        } else {
+            value.as_bytes().to_vec()
+        };
+        *value = STANDARD.encode(bytes);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+    use tempfile::TempDir;
+
+    fn create_test_commands() -> EnvMatchCommands {
+        EnvMatchCommands::with_config_manager(ConfigManager::in_memory())
+    }
+
+    #[test]
+    fn test_init_command() {
+        let commands = create_test_commands();
+
+        commands.init_with_environment("development").unwrap();
+
+        // Should fail to init again
+        assert!(matches!(
+            commands.init_with_environment("development"),
+            Err(EnvMatchError::AlreadyInitialized)
+        ));
+    }
+
+    #[test]
+    fn test_set_and_get_variable() {
+        let commands = create_test_commands();
+        commands.init_with_environment("development").unwrap();
+
+        commands
+            .set_variable("TEST_KEY", "test_value", "development")
+            .unwrap();
+        let value = commands
+            .get_variable("TEST_KEY", "development", false)
+            .unwrap();
+
+        assert_eq!(value, "test_value");
+    }
+
+    #[test]
+    fn test_get_variable_across_environments() {
+        let commands = create_test_commands();
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("production").unwrap();
+        commands.set_variable("PORT", "443", "production").unwrap();
+
+        let mut values = commands
+            .get_variable_across_environments("PORT", false)
+            .unwrap();
+        values.sort();
+        assert_eq!(
+            values,
+            vec![
+                ("development".to_string(), None),
+                ("production".to_string(), Some("443".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unset_variable() {
+        let commands = create_test_commands();
+        commands.init_with_environment("development").unwrap();
+
+        commands
+            .set_variable("TEST_KEY", "test_value", "development")
+            .unwrap();
         commands.unset_variable("TEST_KEY", "development").unwrap();
 
-        let result = commands.get_variable("TEST_KEY", "development");
+        let result = commands.get_variable("TEST_KEY", "development", false);
+        assert!(matches!(
+            result,
+            Err(EnvMatchError::VariableNotFound { .. })
+        ));
+
+        // A typo of an existing key suggests it
+        commands
+            .set_variable("DATABASE_URL", "postgres://", "development")
+            .unwrap();
+        let error = commands
+            .unset_variable("DATABSE_URL", "development")
+            .unwrap_err();
+        assert!(matches!(
+            &error,
+            EnvMatchError::VariableNotFound { suggestion: Some(key), .. } if key == "DATABASE_URL"
+        ));
+        assert!(error
+            .to_string()
+            .ends_with(". Did you mean 'DATABASE_URL'?"));
+    }
+
+    #[test]
+    fn test_bulk_set_and_unset() {
+        let commands = create_test_commands();
+        commands.init_with_environment("development").unwrap();
+
+        let vars = parse_assignments(&[
+            "KEY1=value1".to_string(),
+            "KEY2=a=b".to_string(),
+            "KEY3=".to_string(),
+        ])
+        .unwrap();
+        commands.set_variables(&vars, "development").unwrap();
+
+        assert_eq!(
+            commands.get_variable("KEY2", "development", false).unwrap(),
+            "a=b"
+        );
+        assert_eq!(
+            commands.get_variable("KEY3", "development", false).unwrap(),
+            ""
+        );
+
+        // A missing key aborts the whole removal
+        let result =
+            commands.unset_variables(&["KEY1".to_string(), "MISSING".to_string()], "development");
         assert!(matches!(
             result,
             Err(EnvMatchError::VariableNotFound { .. })
         ));
+        assert!(commands.get_variable("KEY1", "development", false).is_ok());
+
+        commands
+            .unset_variables(&["KEY1".to_string(), "KEY2".to_string()], "development")
+            .unwrap();
+        assert_eq!(
+            commands
+                .list_variables(None, ListOptions::default())
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_copy_variables() {
+        let commands = create_test_commands();
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("staging").unwrap();
+        let secret = VariableMetadata {
+            secret: true,
+            ..Default::default()
+        };
+        commands
+            .set_variables_with_metadata(
+                &[("API_KEY".to_string(), "abc".to_string())],
+                &secret,
+                "development",
+            )
+            .unwrap();
+        commands.set_variable("PORT", "80", "development").unwrap();
+
+        let keys = ["API_KEY", "MISSING"].map(String::from);
+        let copied = commands
+            .copy_variables(&keys, "development", "staging")
+            .unwrap();
+        assert_eq!(copied, 1);
+
+        let staging = commands.config_manager.load_environment("staging").unwrap();
+        assert_eq!(staging.variables.len(), 1);
+        assert_eq!(staging.variables["API_KEY"], "abc");
+        assert!(staging.metadata["API_KEY"].secret);
+    }
+
+    #[test]
+    fn test_create_environment_from() {
+        let commands = create_test_commands();
+        commands.init_with_environment("production").unwrap();
+        let secret = VariableMetadata {
+            secret: true,
+            ..Default::default()
+        };
+        commands
+            .set_variables_with_metadata(
+                &[("API_KEY".to_string(), "abc".to_string())],
+                &secret,
+                "production",
+            )
+            .unwrap();
+        commands.set_variable("PORT", "80", "production").unwrap();
+
+        commands
+            .create_environment_from("staging", "production", true)
+            .unwrap();
+        let staging = commands.config_manager.load_environment("staging").unwrap();
+        assert_eq!(staging.variables["PORT"], "80");
+        assert_eq!(staging.variables["API_KEY"], SECRET_PLACEHOLDER);
+        assert!(staging.metadata["API_KEY"].secret);
+
+        commands
+            .create_environment_from("mirror", "production", false)
+            .unwrap();
+        let mirror = commands.config_manager.load_environment("mirror").unwrap();
+        assert_eq!(mirror.variables["API_KEY"], "abc");
+        assert!(commands
+            .create_environment_from("staging", "production", false)
+            .is_err());
+    }
+
+    #[test]
+    fn test_restore_variables() {
+        let commands = create_test_commands();
+        commands.init_with_environment("development").unwrap();
+        commands.set_variable("PORT", "80", "development").unwrap();
+        let before = commands
+            .config_manager
+            .load_environment("development")
+            .unwrap();
+
+        commands
+            .set_variable("PORT", "8080", "development")
+            .unwrap();
+        commands
+            .set_variable("HOST", "local", "development")
+            .unwrap();
+        let states = vec![
+            (
+                "PORT".to_string(),
+                Some(("80".to_string(), before.metadata["PORT"].clone())),
+            ),
+            ("HOST".to_string(), None),
+        ];
+        commands
+            .restore_variables(&states, "development", "undo")
+            .unwrap();
+
+        let after = commands
+            .config_manager
+            .load_environment("development")
+            .unwrap();
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn test_parse_assignments() {
+        let pair = parse_assignments(&["KEY".to_string(), "value".to_string()]).unwrap();
+        assert_eq!(pair, vec![("KEY".to_string(), "value".to_string())]);
+
+        let result = parse_assignments(&["KEY1=value".to_string(), "KEY2".to_string()]);
+        assert!(matches!(
+            result,
+            Err(EnvMatchError::InvalidAssignment { .. })
+        ));
+
+        let result = parse_assignments(&["=value".to_string()]);
+        assert!(matches!(
+            result,
+            Err(EnvMatchError::InvalidAssignment { .. })
+        ));
+    }
+
+    #[test]
+    fn test_init_with_custom_environment() {
+        let commands = create_test_commands();
+        commands.init_with_environment("local").unwrap();
+
+        assert_eq!(commands.show_current_environment().unwrap(), "local");
+        assert_eq!(commands.list_environments(false).unwrap(), vec!["local"]);
+        assert_eq!(commands.resolve_environment(None).unwrap(), "local");
+        assert_eq!(commands.resolve_environment(Some("prod")).unwrap(), "prod");
     }
 
     #[test]
     fn test_switch_environment() {
-        let (commands, _temp_dir) = create_test_commands();
+        let commands = create_test_commands();
         commands.init_with_environment("development").unwrap();
+        commands.create_environment("production").unwrap();
 
         commands.switch_environment("production").unwrap();
         let current = commands.show_current_environment().unwrap();
@@ -300,7 +1682,7 @@ mod tests {
 
     #[test]
     fn test_list_variables() {
-        let (commands, _temp_dir) = create_test_commands();
+        let commands = create_test_commands();
         commands.init_with_environment("development").unwrap();
 
         commands
@@ -310,16 +1692,138 @@ mod tests {
             .set_variable("KEY2", "value2", "development")
             .unwrap();
 
-        let variables = commands.list_variables(None).unwrap();
+        let variables = commands
+            .list_variables(None, ListOptions::default())
+            .unwrap();
 
         assert_eq!(variables.len(), 2);
         assert!(variables.contains(&("KEY1".to_string(), "value1".to_string())));
         assert!(variables.contains(&("KEY2".to_string(), "value2".to_string())));
     }
 
+    #[test]
+    fn test_os_changes() {
+        let pairs = |pairs: &[(&str, &str)]| -> IndexMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let env_vars = pairs(&[("DATABASE_URL", "pg"), ("API_KEY", "a"), ("PORT", "80")]);
+        let os_vars = pairs(&[
+            ("DATABASE_URL", "mysql"),
+            ("DATABASE_POOL", "5"),
+            ("PORT", "80"),
+            ("HOME", "/root"),
+            ("PORTAL", "x"),
+        ]);
+
+        assert_eq!(
+            os_changes(&env_vars, &os_vars),
+            vec![
+                VariableChange::Removed {
+                    key: "API_KEY".to_string(),
+                    value: "a".to_string()
+                },
+                VariableChange::Added {
+                    key: "DATABASE_POOL".to_string(),
+                    value: "5".to_string()
+                },
+                VariableChange::Changed {
+                    key: "DATABASE_URL".to_string(),
+                    old: "pg".to_string(),
+                    new: "mysql".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_variables_grouped() {
+        let commands = create_test_commands();
+        commands.init_with_environment("development").unwrap();
+        commands
+            .set_variables(
+                &parse_assignments(&[
+                    "PORT=80".to_string(),
+                    "DB__USER=app".to_string(),
+                    "AWS__REGION=eu".to_string(),
+                    "DB__HOST=db".to_string(),
+                ])
+                .unwrap(),
+                "development",
+            )
+            .unwrap();
+        // An explicit tag takes precedence over the prefix
+        let tagged = VariableMetadata {
+            tags: vec!["AWS".to_string()],
+            ..Default::default()
+        };
+        commands
+            .set_variables_with_metadata(
+                &[("S3_BUCKET".to_string(), "assets".to_string())],
+                &tagged,
+                "development",
+            )
+            .unwrap();
+
+        let options = ListOptions {
+            group: true,
+            ..Default::default()
+        };
+        let keys: Vec<String> = commands
+            .list_variables(None, options)
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(
+            keys,
+            ["AWS__REGION", "S3_BUCKET", "DB__USER", "DB__HOST", "PORT"]
+        );
+    }
+
+    #[test]
+    fn test_variable_metadata() {
+        let commands = create_test_commands();
+        commands.init_with_environment("development").unwrap();
+
+        let metadata = VariableMetadata {
+            description: Some("Primary database".to_string()),
+            tags: vec!["db".to_string()],
+            secret: true,
+            ..Default::default()
+        };
+        let vars = [("DATABASE_URL".to_string(), "postgres://a".to_string())];
+        commands
+            .set_variables_with_metadata(&vars, &metadata, "development")
+            .unwrap();
+        let created = commands.variable_metadata("development").unwrap()["DATABASE_URL"].clone();
+        assert_eq!(created.description.as_deref(), Some("Primary database"));
+        assert!(created.secret);
+        assert!(created.created_at.is_some());
+
+        // A plain set keeps the details and only moves the update time
+        commands
+            .set_variable("DATABASE_URL", "postgres://b", "development")
+            .unwrap();
+        let updated = commands.variable_metadata("development").unwrap()["DATABASE_URL"].clone();
+        assert_eq!(updated.tags, vec!["db"]);
+        assert_eq!(updated.created_at, created.created_at);
+        assert!(updated.updated_at >= created.updated_at);
+
+        commands
+            .unset_variable("DATABASE_URL", "development")
+            .unwrap();
+        assert!(commands
+            .variable_metadata("development")
+            .unwrap()
+            .is_empty());
+    }
+
     #[test]
     fn test_validate_environment() {
-        let (commands, _temp_dir) = create_test_commands();
+        let commands = create_test_commands();
         commands.init_with_environment("development").unwrap();
 
         commands
@@ -327,26 +1831,137 @@ mod tests {
             .unwrap();
 
         // Should pass validation
-        commands.validate_environment(Some("REQUIRED_VAR")).unwrap();
+        commands
+            .validate_environment(Some("REQUIRED_VAR"), false)
+            .unwrap();
 
         // Should fail validation for missing variable
-        let result = commands.validate_environment(Some("MISSING_VAR"));
+        let result = commands.validate_environment(Some("MISSING_VAR"), false);
         assert!(matches!(
             result,
             Err(EnvMatchError::MissingRequiredVariables { .. })
         ));
     }
 
+    #[test]
+    fn test_export_environment() {
+        let commands = create_test_commands();
+        let temp_dir = TempDir::new().unwrap();
+        commands.init_with_environment("development").unwrap();
+
+        commands.set_variable("B_KEY", "b", "development").unwrap();
+        commands.set_variable("A_KEY", "a", "development").unwrap();
+
+        let rendered = commands
+            .export_environment(&[], ExportFormat::Dotenv, None, None, None, false)
+            .unwrap();
+        // Variables keep the order they were added in
+        assert_eq!(rendered, "B_KEY=\"b\"\nA_KEY=\"a\"\n");
+
+        let path = temp_dir.path().join("secret.yaml");
+        commands
+            .export_environment(
+                &["development".to_string()],
+                ExportFormat::K8sSecret,
+                Some("my-app-secrets"),
+                None,
+                Some(&path),
+                false,
+            )
+            .unwrap();
+        let written = fs::read_to_string(path).unwrap();
+        assert!(written.contains("name: my-app-secrets"));
+        assert!(written.contains("A_KEY: YQ=="));
+    }
+
+    #[test]
+    fn test_history_diff_and_rollback() {
+        let commands = create_test_commands();
+        commands.init_with_environment("development").unwrap();
+
+        commands
+            .set_variable("DATABASE_URL", "postgres://old", "development")
+            .unwrap();
+        commands
+            .set_variable("DATABASE_URL", "postgres://new", "development")
+            .unwrap();
+        commands.create_environment("production").unwrap();
+        commands.switch_environment("production").unwrap();
+
+        let history = commands.show_history().unwrap();
+        let commands_run: Vec<&str> = history.iter().map(|s| s.command.as_str()).collect();
+        assert_eq!(
+            commands_run,
+            vec![
+                "init development",
+                "set DATABASE_URL -e development",
+                "set DATABASE_URL -e development",
+                "env create production",
+                "switch production"
+            ]
+        );
+
+        let changes = commands
+            .diff_with_snapshot("#2", Some("development"))
+            .unwrap();
+        assert_eq!(
+            changes,
+            vec![VariableChange::Changed {
+                key: "DATABASE_URL".to_string(),
+                old: "postgres://old".to_string(),
+                new: "postgres://new".to_string()
+            }]
+        );
+
+        commands.rollback("2").unwrap();
+        assert_eq!(
+            commands
+                .get_variable("DATABASE_URL", "development", false)
+                .unwrap(),
+            "postgres://old"
+        );
+        assert_eq!(commands.show_current_environment().unwrap(), "development");
+        assert!(matches!(
+            commands.rollback("abc"),
+            Err(EnvMatchError::SnapshotNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let commands = create_test_commands();
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("production").unwrap();
+
+        commands
+            .set_variable("API_KEY", "one", "development")
+            .unwrap();
+        commands
+            .set_variable("API_KEY", "two", "development")
+            .unwrap();
+        commands.unset_variable("API_KEY", "development").unwrap();
+        commands.set_variable("OTHER", "x", "production").unwrap();
+
+        let entries = commands
+            .show_audit_log(Some("development"), Some("API_KEY"), true)
+            .unwrap();
+        let commands_run: Vec<&str> = entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands_run, vec!["set", "set", "unset"]);
+        assert!(entries[2].new_hash.is_none());
+        assert_eq!(entries[1].old_hash, entries[0].new_hash);
+    }
+
     #[test]
     fn test_list_environments() {
-        let (commands, _temp_dir) = create_test_commands();
+        let commands = create_test_commands();
         commands.init_with_environment("development").unwrap();
+        commands.create_environment("production").unwrap();
 
         commands
             .set_variable("TEST", "value", "production")
             .unwrap();
 
-        let environments = commands.list_environments().unwrap();
+        let environments = commands.list_environments(false).unwrap();
 
         assert!(environments.contains(&"development".to_string()));
         assert!(environments.contains(&"production".to_string()));
@@ -0,0 +1,191 @@
+use super::EnvMatchCommands;
+use crate::config::{AuditEntry, Profile};
+use crate::error::{closest_match, EnvMatchError, Result};
+use colored::*;
+use indexmap::IndexMap;
+
+impl EnvMatchCommands {
+    /// Merges environment layers like [`resolve_layers`](Self::resolve_layers)
+    /// and narrows the result down to a profile.
+    pub fn resolve_profile(
+        &self,
+        env_names: &[String],
+        profile: &str,
+    ) -> Result<IndexMap<String, String>> {
+        let variables = self.resolve_layers(env_names)?;
+        Ok(self.find_profile(env_names, profile)?.apply(variables))
+    }
+
+    /// A profile as defined by the last of the layers that has it.
+    pub(super) fn find_profile(&self, env_names: &[String], name: &str) -> Result<Profile> {
+        let env_names = match env_names {
+            [] => vec![self.resolve_environment(None)?],
+            env_names => env_names.to_vec(),
+        };
+        let mut known = Vec::new();
+        for env_name in env_names.iter().rev() {
+            let mut env_config = self.config_manager.load_effective_environment(env_name)?;
+            if let Some(profile) = env_config.profiles.remove(name) {
+                return Ok(profile);
+            }
+            known.extend(env_config.profiles.into_keys());
+        }
+        Err(EnvMatchError::ProfileNotFound {
+            name: name.to_string(),
+            env: env_names.join(", "),
+            suggestion: closest_match(name, known.iter().map(String::as_str)).map(str::to_string),
+        })
+    }
+
+    /// Prints an environment's profiles and what each selects.
+    pub fn list_profiles(&self, env_name: Option<&str>) -> Result<Vec<String>> {
+        let env_name = self.resolve_environment(env_name)?;
+        let profiles = self
+            .config_manager
+            .load_effective_environment(&env_name)?
+            .profiles;
+        if profiles.is_empty() {
+            println!(
+                "No profiles in '{}'. Add one with 'envMatch profile set <name>'",
+                env_name
+            );
+            return Ok(vec![]);
+        }
+
+        println!(
+            "{} {}",
+            "🧩 Profiles in".bright_blue().bold(),
+            env_name.bright_green().bold()
+        );
+        println!("{}", "─".repeat(40).bright_blue());
+        for (name, profile) in &profiles {
+            let include = match profile.include.is_empty() {
+                true => "all variables".to_string(),
+                false => profile.include.join(", "),
+            };
+            let overrides = match profile.overrides.len() {
+                0 => String::new(),
+                count => format!("  (+{} override(s))", count),
+            };
+            println!(
+                "• {}  {}{}",
+                name.bright_cyan().bold(),
+                include,
+                overrides.bright_black()
+            );
+        }
+        Ok(profiles.into_keys().collect())
+    }
+
+    /// Defines a profile, replacing any by that name.
+    pub fn set_profile(
+        &self,
+        env_name: &str,
+        name: &str,
+        include: &[String],
+        overrides: &[(String, String)],
+    ) -> Result<()> {
+        let key_names = self.settings()?.key_names();
+        for (key, _) in overrides {
+            key_names.check(key)?;
+        }
+        let profile = Profile {
+            include: include.to_vec(),
+            overrides: overrides.iter().cloned().collect(),
+        };
+        let command = format!("profile set {} -e {}", name, env_name);
+        self.transaction(&command, |tx| {
+            tx.environment_mut(env_name)?
+                .profiles
+                .insert(name.to_string(), profile);
+            tx.audit([AuditEntry::new("profile set", env_name, None, None, None)]);
+            Ok(())
+        })?;
+        if !self.quiet {
+            println!(
+                "{} {} {} {}",
+                "✅ Saved profile".bright_green().bold(),
+                name.bright_cyan().bold(),
+                "in".bright_white(),
+                env_name.bright_green().bold()
+            );
+        }
+        Ok(())
+    }
+
+    pub fn remove_profile(&self, env_name: &str, name: &str) -> Result<()> {
+        let command = format!("profile remove {} -e {}", name, env_name);
+        self.transaction(&command, |tx| {
+            let profiles = &mut tx.environment_mut(env_name)?.profiles;
+            if profiles.remove(name).is_none() {
+                return Err(EnvMatchError::ProfileNotFound {
+                    name: name.to_string(),
+                    env: env_name.to_string(),
+                    suggestion: closest_match(name, profiles.keys().map(String::as_str))
+                        .map(str::to_string),
+                });
+            }
+            tx.audit([AuditEntry::new(
+                "profile remove",
+                env_name,
+                None,
+                None,
+                None,
+            )]);
+            Ok(())
+        })?;
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "🗑️  Removed profile".bright_green().bold(),
+                name.bright_cyan().bold()
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_profiles() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("production").unwrap();
+        commands
+            .set_variable("DATABASE_URL", "pg", "production")
+            .unwrap();
+        commands
+            .set_variable("QUEUE_URL", "redis", "production")
+            .unwrap();
+        commands.set_variable("PORT", "80", "production").unwrap();
+        commands
+            .set_profile(
+                "production",
+                "worker",
+                &["QUEUE_*".to_string(), "DATABASE_URL".to_string()],
+                &[("CONCURRENCY".to_string(), "4".to_string())],
+            )
+            .unwrap();
+
+        let layers = ["production".to_string()];
+        let worker = commands.resolve_profile(&layers, "worker").unwrap();
+        assert_eq!(
+            worker.keys().collect::<Vec<_>>(),
+            ["DATABASE_URL", "QUEUE_URL", "CONCURRENCY"]
+        );
+        assert_eq!(commands.list_profiles(None).unwrap(), ["worker"]);
+
+        assert!(matches!(
+            commands.resolve_profile(&layers, "wroker"),
+            Err(EnvMatchError::ProfileNotFound {
+                suggestion: Some(_),
+                ..
+            })
+        ));
+        commands.remove_profile("production", "worker").unwrap();
+        assert!(commands.remove_profile("production", "worker").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use super::EnvMatchCommands;
+use crate::config::ConfigManager;
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+use std::path::{Path, PathBuf};
+
+impl EnvMatchCommands {
+    /// Remembers this project for `projects` and the TUI's project switcher.
+    /// Does nothing outside an initialized project.
+    pub fn register_project(&self) -> Result<()> {
+        match self.config_manager.project_dir() {
+            Some(project_dir) if self.is_initialized() => {
+                self.config_manager.register_project(project_dir)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The registered projects, most recently used first, without printing.
+    pub fn registered_projects(&self) -> Result<Vec<PathBuf>> {
+        self.config_manager.registered_projects()
+    }
+
+    /// Prints the registered projects, marking this one and those that no
+    /// longer exist.
+    pub fn list_projects(&self) -> Result<Vec<PathBuf>> {
+        let projects = self.registered_projects()?;
+        if projects.is_empty() {
+            println!("No projects registered. Run 'envMatch projects add' in one");
+            return Ok(projects);
+        }
+
+        let current = self
+            .config_manager
+            .project_dir()
+            .and_then(|dir| dir.canonicalize().ok());
+        println!("📂 Known projects:");
+        println!("{}", "─".repeat(30));
+        for project in &projects {
+            let line = format!("• {}", project.display());
+            if Some(project) == current.as_ref() {
+                println!("{} (current)", line);
+            } else if ConfigManager::discover(project).as_ref() != Some(project) {
+                println!("{}", format!("{} (missing)", line).bright_black());
+            } else {
+                println!("{}", line);
+            }
+        }
+        Ok(projects)
+    }
+
+    /// Registers the project `dir` is in.
+    pub fn add_project(&self, dir: &Path) -> Result<PathBuf> {
+        let dir = dir
+            .canonicalize()
+            .map_err(|e| EnvMatchError::storage(dir, e))?;
+        let project_dir = ConfigManager::discover(&dir).ok_or(EnvMatchError::NotInitialized)?;
+        self.config_manager.register_project(&project_dir)?;
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "✅ Registered project".bright_green().bold(),
+                project_dir.display().to_string().bright_cyan()
+            );
+        }
+        Ok(project_dir)
+    }
+
+    /// Forgets a registered project, leaving its files alone.
+    pub fn remove_project(&self, dir: &Path) -> Result<()> {
+        if !self.config_manager.unregister_project(dir)? {
+            return Err(EnvMatchError::ProjectNotRegistered {
+                path: dir.to_path_buf(),
+            });
+        }
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "🗑️  Forgot project".bright_green().bold(),
+                dir.display().to_string().bright_cyan()
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use super::EnvMatchCommands;
+use crate::config::{Schema, VariableMetadata};
+use crate::error::Result;
+use colored::*;
+use std::io::{self, IsTerminal, Write};
+
+impl EnvMatchCommands {
+    /// Asks for a value for each missing variable and saves the ones entered.
+    /// Returns the variables that were skipped.
+    pub(super) fn prompt_missing_variables(
+        &self,
+        env_name: &str,
+        missing: &[String],
+        schema: &Schema,
+    ) -> Result<Vec<String>> {
+        println!(
+            "{} {} {}",
+            "🧩".bright_yellow(),
+            format!(
+                "{} required variable(s) missing in '{}'.",
+                missing.len(),
+                env_name
+            )
+            .bright_white()
+            .bold(),
+            "Enter a value, or leave it empty to skip.".bright_black()
+        );
+
+        let mut entries = Vec::new();
+        let mut skipped = Vec::new();
+        for key in missing {
+            let declared = schema.variables.get(key).cloned().unwrap_or_default();
+            if let Some(description) = &declared.description {
+                println!("  {}", description.bright_black());
+            }
+            match prompt_value(key, declared.secret)? {
+                Some(value) => {
+                    let metadata = VariableMetadata {
+                        secret: declared.secret,
+                        ..Default::default()
+                    };
+                    entries.push((key.clone(), value, metadata));
+                }
+                None => skipped.push(key.clone()),
+            }
+        }
+
+        if !entries.is_empty() {
+            self.store_variables(&entries, env_name)?;
+            let keys: Vec<&str> = entries.iter().map(|(key, ..)| key.as_str()).collect();
+            println!(
+                "{} {}",
+                "✅ Saved".bright_green().bold(),
+                keys.join(", ").bright_cyan().bold()
+            );
+        }
+        Ok(skipped)
+    }
+}
+
+/// Reads a value for `key` from stdin, hiding it for secrets when typed in a
+/// terminal. Returns `None` for an empty answer or end of input.
+pub(super) fn prompt_value(key: &str, secret: bool) -> Result<Option<String>> {
+    let value = if secret && io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("  🔒 {}: ", key))?
+    } else {
+        print!("  {}: ", key.bright_cyan().bold());
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(None);
+        }
+        line.trim_end_matches(['\r', '\n']).to_string()
+    };
+    Ok(Some(value).filter(|value| !value.is_empty()))
+}
+
+/// Asks a question on stdin, returning `default` for an empty answer or end
+/// of input.
+pub(super) fn prompt_line(question: &str, default: &str) -> Result<String> {
+    print!(
+        "{} {} ",
+        question.bright_white().bold(),
+        format!("[{}]", default).bright_black()
+    );
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        println!();
+    }
+    let answer = line.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// Asks a yes/no question, returning `default` for an empty answer.
+pub(super) fn confirm(question: &str, default: bool) -> Result<bool> {
+    let answer = prompt_line(question, if default { "Y/n" } else { "y/N" })?;
+    Ok(match answer.to_lowercase().as_str() {
+        "y" | "yes" => true,
+        "n" | "no" => false,
+        _ => default,
+    })
+}
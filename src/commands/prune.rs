@@ -0,0 +1,119 @@
+use super::prompt::confirm;
+use super::EnvMatchCommands;
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+
+/// Where `prune` looks for the variables still in use.
+#[derive(Debug, Default)]
+pub struct PruneOptions {
+    /// Variables to keep regardless
+    pub keep: Vec<String>,
+    /// Search the project's files for the variables' names
+    pub scan: bool,
+    /// Only show what would be removed
+    pub dry_run: bool,
+    /// Remove without asking
+    pub yes: bool,
+}
+
+impl EnvMatchCommands {
+    /// Removes the variables that are neither declared in the schema, kept
+    /// explicitly, nor (with `scan`) mentioned in the project's files. Lists
+    /// them first and asks before removing anything. Returns the unused keys.
+    pub fn prune(&self, env_name: Option<&str>, options: &PruneOptions) -> Result<Vec<String>> {
+        let env_name = self.resolve_environment(env_name)?;
+        let env_config = self.config_manager.load_environment(&env_name)?;
+        let schema = self.config_manager.load_schema()?;
+        if schema.variables.is_empty() && options.keep.is_empty() && !options.scan {
+            return Err(EnvMatchError::NoUsageInformation);
+        }
+
+        let keys: Vec<String> = env_config.variables.keys().cloned().collect();
+        let referenced = match options.scan {
+            true => self.config_manager.find_references(&keys),
+            false => Default::default(),
+        };
+        let unused: Vec<String> = keys
+            .into_iter()
+            .filter(|key| {
+                !schema.variables.contains_key(key)
+                    && !options.keep.contains(key)
+                    && !referenced.contains(key)
+            })
+            .collect();
+
+        if unused.is_empty() {
+            if !self.quiet {
+                println!(
+                    "{}",
+                    format!("✅ No unused variables in '{}'", env_name)
+                        .bright_green()
+                        .bold()
+                );
+            }
+            return Ok(unused);
+        }
+
+        println!(
+            "{} {}",
+            format!("🧹 {} unused variable(s) in", unused.len())
+                .bright_blue()
+                .bold(),
+            format!("'{}'", env_name).bright_green().bold()
+        );
+        for key in &unused {
+            println!("{}", format!("- {}", key).bright_red());
+        }
+        if options.dry_run {
+            println!("{}", "(dry run, nothing was removed)".bright_black());
+            return Ok(unused);
+        }
+        if !options.yes && !confirm(&format!("Remove them from '{}'?", env_name), false)? {
+            println!("{}", "Nothing was removed".bright_black());
+            return Ok(unused);
+        }
+
+        self.unset_variables(&unused, &env_name)?;
+        Ok(unused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigManager, Schema};
+
+    #[test]
+    fn test_prune_keeps_declared_variables() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("staging").unwrap();
+        let vars: Vec<_> = ["API_URL", "LEGACY_FLAG", "DEBUG", "OLD_TOKEN"]
+            .into_iter()
+            .map(|key| (key.to_string(), "x".to_string()))
+            .collect();
+        commands.set_variables(&vars, "staging").unwrap();
+        let mut schema = Schema::default();
+        schema
+            .variables
+            .insert("API_URL".to_string(), Default::default());
+        commands.config_manager.save_schema(&schema).unwrap();
+
+        let options = PruneOptions {
+            keep: vec!["DEBUG".to_string()],
+            dry_run: true,
+            ..Default::default()
+        };
+        let unused = commands.prune(Some("staging"), &options).unwrap();
+        assert_eq!(unused, ["LEGACY_FLAG", "OLD_TOKEN"]);
+        assert_eq!(commands.resolve_layers(&[]).unwrap().len(), 4);
+
+        let options = PruneOptions {
+            dry_run: false,
+            yes: true,
+            ..options
+        };
+        commands.prune(Some("staging"), &options).unwrap();
+        let left: Vec<_> = commands.resolve_layers(&[]).unwrap().into_keys().collect();
+        assert_eq!(left, ["API_URL", "DEBUG"]);
+    }
+}
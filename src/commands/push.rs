@@ -0,0 +1,174 @@
+use super::{print_changes, EnvMatchCommands};
+use crate::config::EnvConfig;
+use crate::diff::{self, VariableChange};
+use crate::error::Result;
+use crate::sync::{GithubEnvironment, GithubState};
+use colored::*;
+use indexmap::IndexMap;
+
+/// What pushing an environment to GitHub Actions would change.
+#[derive(Debug, Default, PartialEq)]
+pub struct GithubPlan {
+    /// Secret variables by key, with whether GitHub already has them. Their
+    /// values can't be read back, so they're always sent.
+    pub secrets: Vec<(String, bool)>,
+    /// Plain variables added or changed; nothing is deleted on GitHub
+    pub variables: Vec<VariableChange>,
+}
+
+impl GithubPlan {
+    /// Variables flagged secret become GitHub secrets, the others GitHub variables.
+    fn new(env_config: &EnvConfig, state: &GithubState) -> Self {
+        let is_secret = |key: &str| {
+            env_config
+                .metadata
+                .get(key)
+                .is_some_and(|metadata| metadata.secret)
+        };
+        let secrets = env_config
+            .variables
+            .keys()
+            .filter(|key| is_secret(key))
+            .map(|key| (key.clone(), state.secrets.contains(key)))
+            .collect();
+        let plain: IndexMap<String, String> = env_config
+            .variables
+            .iter()
+            .filter(|(key, _)| !is_secret(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let variables = diff::diff_variables(&state.variables, &plain)
+            .into_iter()
+            .filter(|change| !matches!(change, VariableChange::Removed { .. }))
+            .collect();
+        Self { secrets, variables }
+    }
+}
+
+impl EnvMatchCommands {
+    /// Pushes an environment to a GitHub Actions environment: secret
+    /// variables as encrypted secrets, the rest as plain variables. With
+    /// `dry_run`, only shows what would change.
+    pub fn push_github(
+        &self,
+        repo: &str,
+        env_name: Option<&str>,
+        github_env: Option<&str>,
+        token: &str,
+        dry_run: bool,
+    ) -> Result<GithubPlan> {
+        let env_name = self.resolve_environment(env_name)?;
+        let github_env = github_env.unwrap_or(&env_name);
+        let env_config = self.config_manager.load_environment(&env_name)?;
+        let github = GithubEnvironment::new(repo, github_env, token)?;
+        let state = github.fetch()?;
+        let plan = GithubPlan::new(&env_config, &state);
+
+        println!(
+            "{} {} {} {}",
+            "🐙 Pushing".bright_blue().bold(),
+            format!("'{}'", env_name).bright_green().bold(),
+            "to GitHub environment".bright_blue().bold(),
+            format!("{}:{}", repo, github_env).bright_cyan()
+        );
+        println!("{}", "Variables:".bright_yellow().bold());
+        print_changes(&plan.variables);
+        if !plan.secrets.is_empty() {
+            println!(
+                "{}",
+                "Secrets (values can't be compared):".bright_yellow().bold()
+            );
+            for (key, exists) in &plan.secrets {
+                match exists {
+                    true => println!("{}", format!("~ {}", key).bright_yellow()),
+                    false => println!("{}", format!("+ {}", key).bright_green()),
+                }
+            }
+        }
+        if dry_run {
+            println!("{}", "(dry run, nothing was pushed)".bright_black());
+            return Ok(plan);
+        }
+
+        let secrets: Vec<_> = plan
+            .secrets
+            .iter()
+            .filter_map(|(key, _)| env_config.variables.get_key_value(key))
+            .collect();
+        github.put_secrets(&secrets)?;
+        let variables: Vec<_> = plan
+            .variables
+            .iter()
+            .filter_map(|change| match change {
+                VariableChange::Added { key, .. } | VariableChange::Changed { key, .. } => {
+                    env_config.variables.get_key_value(key)
+                }
+                VariableChange::Removed { .. } => None,
+            })
+            .collect();
+        github.put_variables(&variables, &state.variables)?;
+
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "✅ Pushed".bright_green().bold(),
+                format!(
+                    "{} secret(s) and {} variable(s)",
+                    secrets.len(),
+                    variables.len()
+                )
+                .bright_white()
+            );
+        }
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VariableMetadata;
+
+    #[test]
+    fn test_github_plan() {
+        let mut env_config = EnvConfig::default();
+        for (key, value) in [
+            ("API_URL", "new"),
+            ("PORT", "80"),
+            ("TOKEN", "t"),
+            ("KEY", "k"),
+        ] {
+            env_config.set(key, value);
+        }
+        for key in ["TOKEN", "KEY"] {
+            env_config.metadata.insert(
+                key.to_string(),
+                VariableMetadata {
+                    secret: true,
+                    ..Default::default()
+                },
+            );
+        }
+        let state = GithubState {
+            secrets: ["TOKEN".to_string()].into(),
+            variables: [("API_URL", "old"), ("PORT", "80"), ("ONLY_ON_GITHUB", "x")]
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        };
+
+        let plan = GithubPlan::new(&env_config, &state);
+        assert_eq!(
+            plan.secrets,
+            vec![("TOKEN".to_string(), true), ("KEY".to_string(), false)]
+        );
+        assert_eq!(
+            plan.variables,
+            vec![VariableChange::Changed {
+                key: "API_URL".to_string(),
+                old: "old".to_string(),
+                new: "new".to_string(),
+            }]
+        );
+    }
+}
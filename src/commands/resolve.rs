@@ -0,0 +1,216 @@
+use super::EnvMatchCommands;
+use crate::config::CommandValues;
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+use indexmap::IndexMap;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+
+/// Marks a value produced by running a command, as in `!cmd "aws sts ..."`
+const COMMAND_PREFIX: &str = "!cmd ";
+/// Marks a value read from a file, as in `!file ./secrets/ca.pem`
+const FILE_PREFIX: &str = "!file ";
+/// Files referenced by `!file` values that haven't changed in this long get
+/// a warning, since certificates and keys are meant to be renewed
+const STALE_FILE_DAYS: u64 = 90;
+
+/// Where a value that isn't stored comes from.
+#[derive(Debug, PartialEq)]
+enum Reference<'a> {
+    Command(&'a str),
+    File(&'a str),
+}
+
+impl EnvMatchCommands {
+    /// Replaces values that refer to something else with what they refer to,
+    /// for `run` and `export --resolved`. The results are never saved.
+    pub fn resolve_values(
+        &self,
+        variables: IndexMap<String, String>,
+    ) -> Result<IndexMap<String, String>> {
+        let policy = self.settings()?.command_values();
+        variables
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match reference(&value) {
+                    Some(Reference::Command(command)) => {
+                        self.run_value_command(&key, command, policy)?
+                    }
+                    Some(Reference::File(path)) => self.read_value_file(&key, path)?,
+                    None => value,
+                };
+                Ok((key, value))
+            })
+            .collect()
+    }
+
+    fn run_value_command(&self, key: &str, command: &str, policy: CommandValues) -> Result<String> {
+        let unresolved = |reason: String| EnvMatchError::UnresolvedValue {
+            key: key.to_string(),
+            reason,
+        };
+        match policy {
+            CommandValues::Off => {
+                return Err(unresolved(
+                    "running commands for values is off; set command_values in your user config"
+                        .to_string(),
+                ))
+            }
+            CommandValues::Confirm if !confirm_command(key, command)? => {
+                return Err(unresolved(format!("running '{}' was declined", command)));
+            }
+            _ => {}
+        }
+
+        debug!(key, command, "resolving a value from a command");
+        let output = shell(command)
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|source| EnvMatchError::RunError {
+                command: command.to_string(),
+                source,
+            })?;
+        if !output.status.success() {
+            return Err(unresolved(format!(
+                "'{}' failed ({})",
+                command, output.status
+            )));
+        }
+        let value = String::from_utf8(output.stdout)
+            .map_err(|_| unresolved(format!("'{}' printed something other than text", command)))?;
+        Ok(value.trim_end_matches(['\r', '\n']).to_string())
+    }
+
+    /// Reads a `!file` value's file, relative to the project root, warning
+    /// when it hasn't changed in a long time.
+    fn read_value_file(&self, key: &str, path: &str) -> Result<String> {
+        let path = match path.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+            None => PathBuf::from(path),
+        };
+        let path = match self.project_dir() {
+            Some(project_dir) if path.is_relative() => project_dir.join(path),
+            _ => path,
+        };
+        let unresolved = |e: io::Error| EnvMatchError::UnresolvedValue {
+            key: key.to_string(),
+            reason: format!("cannot read {}: {}", path.display(), e),
+        };
+
+        debug!(key, path = %path.display(), "resolving a value from a file");
+        let value = fs::read_to_string(&path).map_err(unresolved)?;
+        let age = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        if age.is_some_and(|age| age > Duration::from_secs(STALE_FILE_DAYS * 24 * 60 * 60)) {
+            eprintln!(
+                "{}",
+                format!(
+                    "⚠️  {} comes from {}, unchanged for over {} days. Is it still current?",
+                    key,
+                    path.display(),
+                    STALE_FILE_DAYS
+                )
+                .bright_yellow()
+            );
+        }
+        Ok(value)
+    }
+}
+
+/// What a `!cmd` or `!file` value refers to, without the quotes around it
+/// if any.
+fn reference(value: &str) -> Option<Reference<'_>> {
+    if let Some(command) = value.strip_prefix(COMMAND_PREFIX) {
+        Some(Reference::Command(unquote(command)))
+    } else {
+        value
+            .strip_prefix(FILE_PREFIX)
+            .map(|path| Reference::File(unquote(path)))
+    }
+}
+
+fn unquote(target: &str) -> &str {
+    let target = target.trim();
+    target
+        .strip_prefix('"')
+        .and_then(|target| target.strip_suffix('"'))
+        .unwrap_or(target)
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.args(["-c", command]);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+/// Asks on stderr, so the prompt stays out of exported output. Declines
+/// when there's no terminal to ask.
+fn confirm_command(key: &str, command: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    eprint!(
+        "{} {} {} {} ",
+        "Run".bright_white().bold(),
+        command.bright_cyan(),
+        format!("to get {}?", key).bright_white().bold(),
+        "[y/N]".bright_black()
+    );
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_resolve_command_values() {
+        assert_eq!(
+            reference("!cmd \"echo hi\""),
+            Some(Reference::Command("echo hi"))
+        );
+        assert_eq!(reference("!cmd date"), Some(Reference::Command("date")));
+        assert_eq!(
+            reference("!file ./ca.pem"),
+            Some(Reference::File("./ca.pem"))
+        );
+        assert_eq!(reference("plain !cmd"), None);
+
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init().unwrap();
+        let plain: IndexMap<String, String> = [("PORT".to_string(), "80".to_string())].into();
+        assert_eq!(commands.resolve_values(plain.clone()).unwrap(), plain);
+        // Off unless the user config says otherwise
+        let token: IndexMap<String, String> =
+            [("TOKEN".to_string(), "!cmd \"echo secret\"".to_string())].into();
+        assert!(matches!(
+            commands.resolve_values(token),
+            Err(EnvMatchError::UnresolvedValue { .. })
+        ));
+
+        let value = commands.run_value_command("TOKEN", "echo secret", CommandValues::Allow);
+        assert_eq!(value.unwrap(), "secret");
+        assert!(commands
+            .run_value_command("TOKEN", "exit 1", CommandValues::Allow)
+            .is_err());
+    }
+}
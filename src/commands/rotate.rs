@@ -0,0 +1,104 @@
+use super::{expiry_label, EnvMatchCommands};
+use crate::config::Expiry;
+use crate::error::Result;
+use colored::*;
+
+impl EnvMatchCommands {
+    /// Lists the variables that have expired or will within `within_days`,
+    /// across every environment unless one is given, soonest first.
+    pub fn rotation_due(
+        &self,
+        env_name: Option<&str>,
+        within_days: i64,
+    ) -> Result<Vec<(String, String, Expiry)>> {
+        let environments = match env_name {
+            Some(env_name) => vec![env_name.to_string()],
+            None => self.config_manager.list_environments()?,
+        };
+        let now = chrono::Utc::now();
+
+        let mut due = Vec::new();
+        for env_name in environments {
+            let env_config = self.config_manager.load_environment(&env_name)?;
+            for (key, metadata) in &env_config.metadata {
+                if let Some(expiry) = metadata.expiry(now, within_days) {
+                    due.push((metadata.expires_at, env_name.clone(), key.clone(), expiry));
+                }
+            }
+        }
+        due.sort_by_key(|(expires_at, ..)| *expires_at);
+
+        if due.is_empty() {
+            if !self.quiet {
+                println!(
+                    "{}",
+                    format!("✅ Nothing to rotate in the next {} day(s)", within_days)
+                        .bright_green()
+                        .bold()
+                );
+            }
+        } else {
+            println!("{}", "⏰ Due for rotation:".bright_blue().bold());
+            println!("{}", "─".repeat(50).bright_blue());
+            for (_, env_name, key, expiry) in &due {
+                println!(
+                    "{} {} {}",
+                    format!("{:<14}", env_name).bright_green(),
+                    key.bright_cyan().bold(),
+                    expiry_label(*expiry)
+                );
+            }
+        }
+
+        Ok(due
+            .into_iter()
+            .map(|(_, env_name, key, expiry)| (env_name, key, expiry))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{parse_expiry, ConfigManager, VariableMetadata};
+
+    #[test]
+    fn test_rotation_due() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("production").unwrap();
+
+        let expiring = |when: &str| VariableMetadata {
+            expires_at: Some(parse_expiry(when, chrono::Utc::now()).unwrap()),
+            ..Default::default()
+        };
+        let set = |key: &str, when: &str, env: &str| {
+            commands
+                .set_variables_with_metadata(
+                    &[(key.to_string(), "x".to_string())],
+                    &expiring(when),
+                    env,
+                )
+                .unwrap()
+        };
+        set("API_KEY", "5d", "development");
+        set("DB_PASSWORD", "2000-01-01", "production");
+        set("LONG_LIVED", "365d", "production");
+
+        let due = commands.rotation_due(None, 14).unwrap();
+        let due: Vec<_> = due
+            .iter()
+            .map(|(env, key, expiry)| (env.as_str(), key.as_str(), expiry.is_expired()))
+            .collect();
+        assert_eq!(
+            due,
+            [
+                ("production", "DB_PASSWORD", true),
+                ("development", "API_KEY", false)
+            ]
+        );
+
+        let due = commands.rotation_due(Some("production"), 400).unwrap();
+        assert_eq!(due.len(), 2);
+    }
+}
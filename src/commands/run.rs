@@ -0,0 +1,409 @@
+use super::{mask, EnvMatchCommands};
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+use indexmap::IndexMap;
+use std::process::Command;
+use tracing::debug;
+
+/// Which of envMatch's own environment variables `run` passes on to its
+/// command, besides the ones it sets.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Inheritance {
+    /// All of them
+    #[default]
+    All,
+    /// Only these; none for a hermetic run
+    Only(Vec<String>),
+}
+
+impl Inheritance {
+    pub fn passes(&self, key: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(allowed) => allowed.iter().any(|allowed| allowed == key),
+        }
+    }
+}
+
+/// Where a variable `run` passes to its command got its value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableOrigin {
+    pub key: String,
+    pub value: String,
+    /// Layers that set it, in order; the last one's value wins
+    pub layers: Vec<String>,
+    /// The different value the command would have inherited without envMatch
+    pub inherited: Option<String>,
+}
+
+impl EnvMatchCommands {
+    /// Merges environments in order, later ones overriding earlier ones.
+    /// With no environments, uses the current one.
+    pub fn resolve_layers(&self, env_names: &[String]) -> Result<IndexMap<String, String>> {
+        if env_names.is_empty() {
+            let env_name = self.resolve_environment(None)?;
+            return Ok(self
+                .config_manager
+                .load_effective_environment(&env_name)?
+                .variables);
+        }
+
+        debug!(layers = ?env_names, "resolving environment layers");
+        let mut variables = IndexMap::new();
+        for env_name in env_names {
+            // Reading a layer mustn't create it, so a typo fails instead
+            if !self.config_manager.environment_exists(env_name) {
+                return Err(self.config_manager.environment_not_found(env_name));
+            }
+            variables.extend(
+                self.config_manager
+                    .load_effective_environment(env_name)?
+                    .variables,
+            );
+        }
+        Ok(variables)
+    }
+
+    /// Works out which layer each variable's value comes from and which
+    /// inherited values it replaces, without printing anything.
+    pub fn explain_layers(
+        &self,
+        env_names: &[String],
+        profile: Option<&str>,
+        inheritance: &Inheritance,
+    ) -> Result<Vec<VariableOrigin>> {
+        let profile = profile
+            .map(|name| Ok::<_, EnvMatchError>((name, self.find_profile(env_names, name)?)))
+            .transpose()?;
+        let env_names = match env_names {
+            [] => vec![self.resolve_environment(None)?],
+            env_names => env_names.to_vec(),
+        };
+
+        let mut origins: IndexMap<String, VariableOrigin> = IndexMap::new();
+        for env_name in &env_names {
+            if !self.config_manager.environment_exists(env_name) {
+                return Err(self.config_manager.environment_not_found(env_name));
+            }
+            let env_config = self.config_manager.load_effective_environment(env_name)?;
+            for (key, value) in env_config.variables {
+                let origin = origins
+                    .entry(key.clone())
+                    .or_insert_with(|| VariableOrigin {
+                        key,
+                        value: String::new(),
+                        layers: Vec::new(),
+                        inherited: None,
+                    });
+                origin.value = value;
+                origin.layers.push(env_name.clone());
+            }
+        }
+        if let Some((name, profile)) = &profile {
+            origins.retain(|key, _| profile.includes(key));
+            for (key, value) in &profile.overrides {
+                let origin = origins
+                    .entry(key.clone())
+                    .or_insert_with(|| VariableOrigin {
+                        key: key.clone(),
+                        value: String::new(),
+                        layers: Vec::new(),
+                        inherited: None,
+                    });
+                origin.value = value.clone();
+                origin.layers.push(format!("profile {}", name));
+            }
+        }
+
+        Ok(origins
+            .into_values()
+            .map(|origin| VariableOrigin {
+                inherited: std::env::var(&origin.key)
+                    .ok()
+                    .filter(|_| inheritance.passes(&origin.key))
+                    .filter(|inherited| *inherited != origin.value),
+                ..origin
+            })
+            .collect())
+    }
+
+    /// Prints where each variable `run` would set comes from, to stderr so
+    /// it doesn't mix with the command's output.
+    pub fn explain_run(
+        &self,
+        env_names: &[String],
+        profile: Option<&str>,
+        inheritance: &Inheritance,
+    ) -> Result<Vec<VariableOrigin>> {
+        let origins = self.explain_layers(env_names, profile, inheritance)?;
+        let mask_values = self.settings()?.mask_values();
+
+        eprintln!(
+            "{}",
+            "🔍 Where the variables come from:".bright_blue().bold()
+        );
+        eprintln!("{}", "─".repeat(40).bright_blue());
+        if origins.is_empty() {
+            eprintln!("{}", "(no variables set)".bright_black());
+        }
+        for origin in &origins {
+            let value = if mask_values {
+                mask(&origin.value)
+            } else {
+                origin.value.clone()
+            };
+            let (winner, overridden) = origin.layers.split_last().expect("set by a layer");
+            let mut source = format!("from {}", winner);
+            if !overridden.is_empty() {
+                source.push_str(&format!(", overriding {}", overridden.join(", ")));
+            }
+            if origin.inherited.is_some() {
+                source.push_str(", replacing the inherited value");
+            }
+            eprintln!(
+                "{}={}  {}",
+                origin.key.bright_cyan().bold(),
+                value.bright_green(),
+                source.bright_black()
+            );
+        }
+        Ok(origins)
+    }
+
+    /// Runs a command with the variables of the given environment layers,
+    /// narrowed down to `profile` if given, added to the variables it
+    /// inherits. Returns the command's exit code;
+    /// on Unix the command replaces envMatch instead, so this only returns
+    /// if it couldn't be started.
+    pub fn run(
+        &self,
+        env_names: &[String],
+        profile: Option<&str>,
+        inheritance: &Inheritance,
+        program: &str,
+        args: &[String],
+    ) -> Result<i32> {
+        let variables = match profile {
+            Some(profile) => self.resolve_profile(env_names, profile)?,
+            None => self.resolve_layers(env_names)?,
+        };
+        let variables = self.resolve_values(variables)?;
+
+        let mut command = program_command(program);
+        if let Inheritance::Only(allowed) = inheritance {
+            debug!(?allowed, "running without the inherited environment");
+            command.env_clear().envs(
+                allowed
+                    .iter()
+                    .filter_map(|key| Some((key, std::env::var_os(key)?))),
+            );
+        }
+        command.args(args).envs(&variables);
+        exec(command).map_err(|source| EnvMatchError::RunError {
+            command: program.to_string(),
+            source,
+        })
+    }
+}
+
+/// A command running `program`. Windows only looks for `.exe` files on the
+/// PATH by itself, so there the other PATHEXT extensions are tried as cmd
+/// would, finding `npm.cmd` for `npm`. Arguments to `.cmd` and `.bat` files
+/// are then quoted for cmd by the standard library, which refuses any it
+/// can't pass on safely.
+pub(super) fn program_command(program: &str) -> Command {
+    #[cfg(windows)]
+    if let Some(path) = find_program(
+        program,
+        std::env::var_os("PATH"),
+        std::env::var("PATHEXT").ok(),
+    ) {
+        return Command::new(path);
+    }
+    Command::new(program)
+}
+
+/// Where Windows would find `program` given PATH and PATHEXT: in its own
+/// directory if it names one, else in each PATH directory, with the first
+/// extension that exists. `None` when it already has an extension.
+#[cfg(any(windows, test))]
+fn find_program(
+    program: &str,
+    path: Option<std::ffi::OsString>,
+    pathext: Option<String>,
+) -> Option<std::path::PathBuf> {
+    use std::path::{Path, PathBuf};
+
+    let program = Path::new(program);
+    if program.extension().is_some() {
+        return None;
+    }
+    let dirs: Vec<PathBuf> = match program.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => vec![PathBuf::new()],
+        _ => std::env::split_paths(&path?).collect(),
+    };
+    let pathext = pathext.unwrap_or_else(|| ".COM;.EXE;.BAT;.CMD".to_string());
+    dirs.iter()
+        .flat_map(|dir| {
+            pathext
+                .split(';')
+                .filter(|extension| !extension.is_empty())
+                .map(move |extension| {
+                    let mut name = program.as_os_str().to_owned();
+                    name.push(extension);
+                    dir.join(name)
+                })
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+/// Turns envMatch into the command, keeping its process id. Signals from the
+/// terminal or a process supervisor then reach the command itself, and the
+/// caller sees its exit status as is, including death by a signal.
+#[cfg(unix)]
+fn exec(mut command: Command) -> std::io::Result<i32> {
+    use std::os::unix::process::CommandExt;
+
+    Err(command.exec())
+}
+
+/// Runs the command and waits for it. Ctrl+C reaches every process on the
+/// console, so envMatch ignores it meanwhile to outlive the command and
+/// report its exit code. On Windows the command runs in a job object that
+/// dies with envMatch, so a supervisor killing envMatch kills the command
+/// and everything it started too, instead of orphaning them.
+#[cfg(not(unix))]
+fn exec(mut command: Command) -> std::io::Result<i32> {
+    #[cfg(windows)]
+    if let Err(error) = kill_children_on_exit() {
+        debug!(%error, "running the command outside a job object");
+    }
+    let ignore_ctrl_c = tokio::runtime::Handle::try_current()
+        .ok()
+        .map(|runtime| runtime.spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} }));
+    let status = command.status();
+    if let Some(task) = ignore_ctrl_c {
+        task.abort();
+    }
+    Ok(status?.code().unwrap_or(1))
+}
+
+/// Puts envMatch in a new job object with `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE`.
+/// Processes started afterwards join it before they run, and Windows closes
+/// the job's only handle, killing them all, when envMatch exits however it
+/// does. The handle is therefore never closed here.
+#[cfg(windows)]
+fn kill_children_on_exit() -> std::io::Result<()> {
+    use std::io::Error;
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    // SAFETY: the job handle is checked before use, and the limits are a
+    // plain struct passed with its own size
+    unsafe {
+        let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+        if job.is_null() {
+            return Err(Error::last_os_error());
+        }
+        let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+        limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        let joined = SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &limits as *const JOBOBJECT_EXTENDED_LIMIT_INFORMATION as *const _,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        ) != 0
+            && AssignProcessToJobObject(job, GetCurrentProcess()) != 0;
+        if !joined {
+            let error = Error::last_os_error();
+            CloseHandle(job);
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_resolve_layers_later_wins() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("base").unwrap();
+        commands.create_environment("staging").unwrap();
+        commands.create_environment("local").unwrap();
+        commands.set_variable("HOST", "base", "base").unwrap();
+        commands.set_variable("PORT", "80", "base").unwrap();
+        commands.set_variable("HOST", "staging", "staging").unwrap();
+        commands.set_variable("HOST", "mine", "local").unwrap();
+
+        let layers = ["base", "staging", "local"].map(String::from);
+        let variables = commands.resolve_layers(&layers).unwrap();
+        assert_eq!(variables["HOST"], "mine");
+        assert_eq!(variables["PORT"], "80");
+
+        let variables = commands.resolve_layers(&[]).unwrap();
+        assert_eq!(variables["HOST"], "base");
+
+        let typo = ["stagign".to_string()];
+        assert!(matches!(
+            commands.resolve_layers(&typo),
+            Err(EnvMatchError::EnvironmentNotFound { .. })
+        ));
+        assert!(!commands.config_manager.environment_exists("stagign"));
+    }
+
+    #[test]
+    fn test_explain_layers() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("base").unwrap();
+        commands.create_environment("local").unwrap();
+        commands.set_variable("HOST", "base", "base").unwrap();
+        commands.set_variable("PORT", "80", "base").unwrap();
+        commands.set_variable("HOST", "mine", "local").unwrap();
+        commands.set_variable("PATH", "/opt/bin", "local").unwrap();
+
+        let layers = ["base", "local"].map(String::from);
+        let origins = commands
+            .explain_layers(&layers, None, &Inheritance::All)
+            .unwrap();
+        assert_eq!(origins.len(), 3);
+        assert_eq!(origins[0].key, "HOST");
+        assert_eq!(origins[0].value, "mine");
+        assert_eq!(origins[0].layers, layers);
+        assert_eq!(origins[1].layers, ["base"]);
+        assert!(origins[2].inherited.is_some());
+
+        let isolated = Inheritance::Only(vec!["HOME".to_string()]);
+        let origins = commands.explain_layers(&layers, None, &isolated).unwrap();
+        assert!(origins[2].inherited.is_none());
+    }
+
+    #[test]
+    fn test_find_program_tries_pathext() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("npm.cmd"), "").unwrap();
+        let path = std::env::join_paths([dir.path()]).ok();
+        let pathext = Some(".EXE;.cmd".to_string());
+
+        assert_eq!(
+            find_program("npm", path.clone(), pathext.clone()),
+            Some(dir.path().join("npm.cmd"))
+        );
+        assert_eq!(find_program("npm.cmd", path.clone(), pathext.clone()), None);
+        assert_eq!(find_program("node", path, pathext.clone()), None);
+
+        let npm = dir.path().join("npm");
+        assert_eq!(
+            find_program(npm.to_str().unwrap(), None, pathext),
+            Some(dir.path().join("npm.cmd"))
+        );
+    }
+}
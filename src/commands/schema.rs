@@ -0,0 +1,46 @@
+use super::EnvMatchCommands;
+use crate::error::{EnvMatchError, Result};
+use clap::ValueEnum;
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+/// A format `schema export` writes the schema in.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum SchemaFormat {
+    /// A JSON Schema (draft 2020-12) document for an object of variables
+    #[default]
+    JsonSchema,
+}
+
+impl EnvMatchCommands {
+    /// Writes the schema in another format to `output`, or prints it.
+    pub fn export_schema(&self, format: SchemaFormat, output: Option<&Path>) -> Result<String> {
+        let schema = self.config_manager.load_schema()?;
+        let rendered = match format {
+            SchemaFormat::JsonSchema => {
+                serde_json::to_string_pretty(&schema.to_json_schema())? + "\n"
+            }
+        };
+        match output {
+            Some(path) => {
+                fs::write(path, &rendered).map_err(|e| EnvMatchError::storage(path, e))?;
+                if !self.quiet {
+                    println!(
+                        "{} {} {}",
+                        format!(
+                            "✅ Exported schema of {} variable(s)",
+                            schema.variables.len()
+                        )
+                        .bright_green()
+                        .bold(),
+                        "to".bright_white(),
+                        path.display().to_string().bright_cyan()
+                    );
+                }
+            }
+            None => print!("{}", rendered),
+        }
+        Ok(rendered)
+    }
+}
@@ -0,0 +1,305 @@
+use super::EnvMatchCommands;
+use crate::crypto;
+use crate::error::{EnvMatchError, Result};
+use crate::sync::{EnvironmentBody, EnvironmentList, EnvironmentSummary, ENVIRONMENTS_PATH};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use colored::*;
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use std::io;
+use std::net::SocketAddr;
+use tiny_http::{Header, Method, Response, Server};
+use tracing::debug;
+
+/// What a request needs to be answered.
+struct Access {
+    /// Bearer token every request must carry
+    token: String,
+    /// Host names the server answers to, when it listens on loopback only.
+    /// A web page whose own name was rebound to 127.0.0.1 (DNS rebinding)
+    /// sends its name, not one of these.
+    hosts: Option<Vec<String>>,
+}
+
+impl Access {
+    fn new(addr: SocketAddr, token: String) -> Self {
+        let hosts = addr.ip().is_loopback().then(|| {
+            let ip = match addr.ip() {
+                std::net::IpAddr::V6(ip) => format!("[{}]", ip),
+                ip => ip.to_string(),
+            };
+            vec![
+                "localhost".to_string(),
+                "127.0.0.1".to_string(),
+                "[::1]".to_string(),
+                ip,
+            ]
+        });
+        Self { token, hosts }
+    }
+
+    /// The status and error refusing a request, if it's refused.
+    fn check(&self, host: Option<&str>, authorization: Option<&str>) -> Option<(u16, Value)> {
+        if let Some(hosts) = &self.hosts {
+            let name = host.map(strip_port).unwrap_or_default();
+            if !hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(name))
+            {
+                return Some((403, error("unexpected Host")));
+            }
+        }
+        let presented = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        match crypto::constant_time_eq(presented, &self.token) {
+            true => None,
+            false => Some((401, error("missing or wrong token"))),
+        }
+    }
+}
+
+/// `localhost` of `localhost:7878`, `[::1]` of `[::1]:7878`.
+fn strip_port(host: &str) -> &str {
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    }
+}
+
+impl EnvMatchCommands {
+    /// Serves the project's environments read-only over HTTP, using the
+    /// protocol `sync http` speaks (see [`ENVIRONMENTS_PATH`]). Runs until
+    /// interrupted.
+    ///
+    /// Every request needs the token, which is generated and printed when
+    /// none is given. Listening on loopback, requests must also name it in
+    /// their `Host`, so a web page can't reach the server by rebinding its
+    /// own name to 127.0.0.1.
+    pub fn serve(&self, addr: &str, token: Option<&str>) -> Result<()> {
+        if !self.is_initialized() {
+            return Err(EnvMatchError::NotInitialized);
+        }
+        let server = Server::http(addr).map_err(io::Error::other)?;
+        let bound = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| io::Error::other("not listening on an IP address"))?;
+        let token = match token {
+            Some(token) => token.to_string(),
+            None => {
+                let token = URL_SAFE_NO_PAD.encode(crypto::random_key());
+                eprintln!("🔑 No --token given; requests need this one: {}", token);
+                token
+            }
+        };
+        let access = Access::new(bound, token);
+        if !self.quiet {
+            println!(
+                "{} {}",
+                "🌐 Serving environments read-only on".bright_blue(),
+                format!("http://{}{}", bound, ENVIRONMENTS_PATH).bright_cyan()
+            );
+        }
+
+        let content_type =
+            Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+        for request in server.incoming_requests() {
+            let header = |name: &'static str| {
+                request
+                    .headers()
+                    .iter()
+                    .find(|header| header.field.equiv(name))
+                    .map(|header| header.value.as_str().to_string())
+            };
+            let (host, authorization) = (header("Host"), header("Authorization"));
+            let (status, body) = self.respond(
+                request.method(),
+                request.url(),
+                host.as_deref(),
+                authorization.as_deref(),
+                &access,
+            );
+            debug!(method = %request.method(), url = request.url(), status, "served request");
+
+            let response = Response::from_string(body.to_string())
+                .with_status_code(status)
+                .with_header(content_type.clone());
+            // A client that went away is its own problem
+            request.respond(response).ok();
+        }
+        Ok(())
+    }
+
+    /// The status and JSON body answering one request.
+    fn respond(
+        &self,
+        method: &Method,
+        url: &str,
+        host: Option<&str>,
+        authorization: Option<&str>,
+        access: &Access,
+    ) -> (u16, Value) {
+        if let Some(refused) = access.check(host, authorization) {
+            return refused;
+        }
+
+        let path = url.split('?').next().unwrap_or_default();
+        let Some(rest) = path.strip_prefix(ENVIRONMENTS_PATH) else {
+            return (404, error("not found"));
+        };
+        let name = match rest.trim_start_matches('/') {
+            "" => None,
+            name if rest.starts_with('/') => Some(name),
+            _ => return (404, error("not found")),
+        };
+        if *method != Method::Get {
+            return (405, error("this server is read-only"));
+        }
+
+        let result = match name {
+            None => self.environment_list().map(|list| json!(list)),
+            Some(name) => match self.served_environment(name) {
+                Ok(Some(body)) => Ok(json!(body)),
+                Ok(None) => return (404, error(&format!("no environment '{}'", name))),
+                Err(e) => Err(e),
+            },
+        };
+        match result {
+            Ok(body) => (200, body),
+            Err(e) => (500, error(&e.to_string())),
+        }
+    }
+
+    fn environment_list(&self) -> Result<EnvironmentList> {
+        let mut environments = Vec::new();
+        for name in self.config_manager.list_environments()? {
+            let variables = self.config_manager.load_environment(&name)?.variables;
+            environments.push(EnvironmentSummary {
+                version: self.version_of(&variables)?,
+                name,
+            });
+        }
+        Ok(EnvironmentList { environments })
+    }
+
+    fn served_environment(&self, name: &str) -> Result<Option<EnvironmentBody>> {
+        // Loading a missing environment would create it
+        if self.config_manager.validate_environment_name(name).is_err()
+            || !self.config_manager.environment_exists(name)
+        {
+            return Ok(None);
+        }
+        let variables = self.config_manager.load_environment(name)?.variables;
+        Ok(Some(EnvironmentBody {
+            version: Some(self.version_of(&variables)?),
+            variables,
+        }))
+    }
+
+    /// Changes whenever the variables do. Keyed, so the ETag of a small
+    /// environment can't be checked against guessed values.
+    fn version_of(&self, variables: &IndexMap<String, String>) -> Result<String> {
+        let mut hash = self
+            .config_manager
+            .fingerprint(&serde_json::to_string(variables)?)?;
+        hash.truncate(16);
+        Ok(hash)
+    }
+}
+
+fn error(message: &str) -> Value {
+    json!({ "error": message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_respond() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        commands
+            .set_variable("API_URL", "https://example.com", "development")
+            .unwrap();
+        let access = Access::new("127.0.0.1:7878".parse().unwrap(), "s3cret".to_string());
+        let get = |url: &str| {
+            commands.respond(
+                &Method::Get,
+                url,
+                Some("localhost:7878"),
+                Some("Bearer s3cret"),
+                &access,
+            )
+        };
+
+        let (status, body) = get("/v1/environments");
+        assert_eq!(status, 200);
+        assert_eq!(body["environments"][0]["name"], "development");
+
+        let (status, body) = get("/v1/environments/development");
+        assert_eq!(status, 200);
+        assert_eq!(body["variables"]["API_URL"], "https://example.com");
+        // The ETag is keyed, not a plain hash of the values
+        let variables = serde_json::to_string(&body["variables"]).unwrap();
+        let unkeyed = crate::config::hash_value(&variables);
+        assert_eq!(body["version"].as_str().unwrap().len(), 16);
+        assert_ne!(body["version"], &unkeyed[..16]);
+
+        let missing = get("/v1/environments/staging");
+        assert_eq!(missing.0, 404);
+        assert!(!commands.config_manager.environment_exists("staging"));
+
+        let url = "/v1/environments/development";
+        let put = commands.respond(
+            &Method::Put,
+            url,
+            Some("127.0.0.1:7878"),
+            Some("Bearer s3cret"),
+            &access,
+        );
+        assert_eq!(put.0, 405);
+    }
+
+    #[test]
+    fn test_respond_refuses_wrong_host_and_token() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        let access = Access::new("127.0.0.1:7878".parse().unwrap(), "s3cret".to_string());
+        let url = "/v1/environments/development";
+        let status = |host: Option<&str>, authorization: Option<&str>| {
+            commands
+                .respond(&Method::Get, url, host, authorization, &access)
+                .0
+        };
+
+        assert_eq!(status(Some("[::1]:7878"), Some("Bearer s3cret")), 200);
+        // A page on a rebound name sends that name
+        assert_eq!(
+            status(Some("evil.example:7878"), Some("Bearer s3cret")),
+            403
+        );
+        assert_eq!(status(None, Some("Bearer s3cret")), 403);
+        assert_eq!(status(Some("localhost"), None), 401);
+        assert_eq!(status(Some("localhost"), Some("Bearer wrong")), 401);
+        assert_eq!(status(Some("localhost"), Some("s3cret")), 401);
+
+        // Listening on every interface, any name will do, the token still not
+        let open = Access::new("0.0.0.0:7878".parse().unwrap(), "s3cret".to_string());
+        let respond = |authorization| {
+            commands
+                .respond(
+                    &Method::Get,
+                    url,
+                    Some("config.internal"),
+                    authorization,
+                    &open,
+                )
+                .0
+        };
+        assert_eq!(respond(Some("Bearer s3cret")), 200);
+        assert_eq!(respond(None), 401);
+    }
+}
@@ -0,0 +1,202 @@
+use super::EnvMatchCommands;
+use crate::error::Result;
+use chrono::{DateTime, Duration, Utc};
+use colored::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Width of the longest bar in the report
+const BAR_WIDTH: usize = 20;
+
+/// What the audit log says about how a project is used.
+#[derive(Debug, Default, PartialEq)]
+pub struct UsageStats {
+    /// Audit entries looked at
+    pub entries: usize,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+    /// Times each environment was switched to, most first
+    pub switches: Vec<(String, usize)>,
+    /// Changes to each variable, most first, with the environments involved
+    pub churn: Vec<(String, usize, Vec<String>)>,
+    /// Audit entries recorded by each command, most first
+    pub commands: Vec<(String, usize)>,
+}
+
+impl EnvMatchCommands {
+    /// Sums up the audit log, or its last `days` days: how often each
+    /// environment was switched to, the `top` most changed variables and
+    /// how much each command was used. Everything is read locally; nothing
+    /// is sent anywhere.
+    pub fn stats(&self, days: Option<u32>, top: usize) -> Result<UsageStats> {
+        let since = days.map(|days| Utc::now() - Duration::days(days.into()));
+        let entries: Vec<_> = self
+            .config_manager
+            .read_audit_log()?
+            .into_iter()
+            .filter(|entry| since.is_none_or(|since| entry.timestamp >= since))
+            .collect();
+
+        let mut switches: BTreeMap<String, usize> = BTreeMap::new();
+        let mut churn: BTreeMap<String, (usize, BTreeSet<String>)> = BTreeMap::new();
+        let mut commands: BTreeMap<String, usize> = BTreeMap::new();
+        for entry in &entries {
+            *commands.entry(entry.command.clone()).or_default() += 1;
+            if entry.command == "switch" {
+                *switches.entry(entry.env.clone()).or_default() += 1;
+            }
+            if let Some(key) = &entry.key {
+                let (count, envs) = churn.entry(key.clone()).or_default();
+                *count += 1;
+                envs.insert(entry.env.clone());
+            }
+        }
+
+        let mut stats = UsageStats {
+            entries: entries.len(),
+            first: entries.first().map(|entry| entry.timestamp),
+            last: entries.last().map(|entry| entry.timestamp),
+            switches: most_first(switches),
+            churn: churn
+                .into_iter()
+                .map(|(key, (count, envs))| (key, count, envs.into_iter().collect()))
+                .collect(),
+            commands: most_first(commands),
+        };
+        // Stable, so ties stay in alphabetical order
+        stats
+            .churn
+            .sort_by_key(|(_, count, _)| std::cmp::Reverse(*count));
+        stats.churn.truncate(top);
+
+        print_stats(&stats);
+        Ok(stats)
+    }
+}
+
+fn most_first(counts: BTreeMap<String, usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts
+}
+
+fn print_stats(stats: &UsageStats) {
+    let (Some(first), Some(last)) = (stats.first, stats.last) else {
+        println!("No audit entries to summarize.");
+        return;
+    };
+    let date = |timestamp: DateTime<Utc>| {
+        timestamp
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d")
+            .to_string()
+    };
+    println!(
+        "{} {}",
+        "📊 envMatch stats".bright_blue().bold(),
+        format!(
+            "({} audit entries, {} to {})",
+            stats.entries,
+            date(first),
+            date(last)
+        )
+        .bright_black()
+    );
+    println!("{}", "─".repeat(40).bright_blue());
+
+    let counts = |counts: Vec<(&str, usize, String)>| {
+        let width = counts
+            .iter()
+            .map(|(name, ..)| name.len())
+            .max()
+            .unwrap_or(0);
+        let most = counts.iter().map(|(_, count, _)| *count).max().unwrap_or(1);
+        for (name, count, note) in counts {
+            let bar = "█".repeat((count * BAR_WIDTH).div_ceil(most));
+            println!(
+                "  {:<width$}  {:>5}  {} {}",
+                name.bright_cyan(),
+                count,
+                bar.bright_blue(),
+                note.bright_black(),
+                width = width
+            );
+        }
+    };
+
+    println!("{}", "Environment switches".bold());
+    if stats.switches.is_empty() {
+        println!("  {}", "(none)".bright_black());
+    }
+    counts(
+        stats
+            .switches
+            .iter()
+            .map(|(env, count)| (env.as_str(), *count, String::new()))
+            .collect(),
+    );
+
+    println!("{}", "Most changed variables".bold());
+    if stats.churn.is_empty() {
+        println!("  {}", "(none)".bright_black());
+    }
+    counts(
+        stats
+            .churn
+            .iter()
+            .map(|(key, count, envs)| (key.as_str(), *count, envs.join(", ")))
+            .collect(),
+    );
+
+    println!("{}", "Commands".bold());
+    counts(
+        stats
+            .commands
+            .iter()
+            .map(|(command, count)| (command.as_str(), *count, String::new()))
+            .collect(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_stats() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("production").unwrap();
+        commands
+            .set_variable("API_URL", "a", "development")
+            .unwrap();
+        commands
+            .set_variable("API_URL", "b", "development")
+            .unwrap();
+        commands.set_variable("API_URL", "c", "production").unwrap();
+        commands.set_variable("PORT", "80", "production").unwrap();
+        commands.switch_environment("production").unwrap();
+        commands.switch_environment("development").unwrap();
+        commands.switch_environment("production").unwrap();
+
+        let stats = commands.stats(None, 1).unwrap();
+        assert_eq!(
+            stats.switches,
+            [
+                ("production".to_string(), 2),
+                ("development".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            stats.churn,
+            [(
+                "API_URL".to_string(),
+                3,
+                vec!["development".to_string(), "production".to_string()]
+            )]
+        );
+        assert_eq!(stats.commands[0], ("set".to_string(), 4));
+
+        assert_eq!(commands.stats(Some(0), 10).unwrap().entries, 0);
+    }
+}
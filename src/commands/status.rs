@@ -0,0 +1,198 @@
+use super::EnvMatchCommands;
+use crate::config::{self, Expiry};
+use crate::diff;
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use colored::*;
+
+/// A summary of an environment's health, for `status` and the TUI header.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProjectStatus {
+    pub environment: String,
+    pub variables: usize,
+    /// Variables the schema requires that aren't set
+    pub missing_required: Vec<String>,
+    /// Variables whose value doesn't read as their declared type
+    pub wrong_type: Vec<String>,
+    /// Variables expired or expiring within the rotation window
+    pub expiring: Vec<(String, Expiry)>,
+    /// Remotes with local changes since the last sync, and how many
+    pub unsynced: Vec<(String, usize)>,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+impl ProjectStatus {
+    pub fn schema_violations(&self) -> usize {
+        self.missing_required.len() + self.wrong_type.len()
+    }
+}
+
+impl EnvMatchCommands {
+    /// Works out the status of an environment (the active one by default)
+    /// without printing anything.
+    pub fn project_status(&self, env_name: Option<&str>) -> Result<ProjectStatus> {
+        let environment = self.resolve_environment(env_name)?;
+        let env_config = self.config_manager.load_environment(&environment)?;
+        let schema = self.config_manager.load_schema()?;
+        let now = Utc::now();
+
+        let missing_required = schema
+            .variables
+            .iter()
+            .filter(|(key, variable)| variable.required && !env_config.variables.contains_key(*key))
+            .map(|(key, _)| key.clone())
+            .collect();
+        let wrong_type = schema
+            .types()
+            .into_iter()
+            .filter(|(key, value_type)| {
+                env_config
+                    .variables
+                    .get(key)
+                    .is_some_and(|value| !value_type.accepts(value))
+            })
+            .map(|(key, _)| key)
+            .collect();
+        let expiring = env_config
+            .metadata
+            .iter()
+            .filter_map(|(key, metadata)| {
+                let expiry = metadata.expiry(now, config::ROTATION_WINDOW_DAYS)?;
+                Some((key.clone(), expiry))
+            })
+            .collect();
+        let unsynced = self
+            .config_manager
+            .load_sync_states(&environment)?
+            .into_iter()
+            .map(|(remote, state)| {
+                let changes = diff::diff_variables(&state.variables, &env_config.variables);
+                (remote, changes.len())
+            })
+            .filter(|(_, changes)| *changes > 0)
+            .collect();
+        // The audit log also sees removals; older projects may only have metadata
+        let last_modified = self
+            .config_manager
+            .read_audit_log()?
+            .into_iter()
+            .filter(|entry| entry.env == environment)
+            .map(|entry| entry.timestamp)
+            .next_back()
+            .or_else(|| {
+                env_config
+                    .metadata
+                    .values()
+                    .filter_map(|metadata| metadata.updated_at)
+                    .max()
+            });
+
+        Ok(ProjectStatus {
+            environment,
+            variables: env_config.variables.len(),
+            missing_required,
+            wrong_type,
+            expiring,
+            unsynced,
+            last_modified,
+        })
+    }
+
+    pub fn show_status(&self, env_name: Option<&str>) -> Result<ProjectStatus> {
+        let status = self.project_status(env_name)?;
+        let label = |text: &str| format!("{:<16}", text).bright_white().bold();
+        let ok = |text: &str| text.bright_green();
+
+        println!(
+            "{} {}",
+            label("Environment:"),
+            status.environment.bright_green().bold()
+        );
+        println!("{} {}", label("Variables:"), status.variables);
+
+        let mut problems = Vec::new();
+        if !status.missing_required.is_empty() {
+            problems.push(format!("missing {}", status.missing_required.join(", ")));
+        }
+        if !status.wrong_type.is_empty() {
+            problems.push(format!("wrong type: {}", status.wrong_type.join(", ")));
+        }
+        match problems.is_empty() {
+            true => println!("{} {}", label("Schema:"), ok("✅ valid")),
+            false => println!("{} {}", label("Schema:"), problems.join("; ").bright_red()),
+        }
+
+        match status.expiring.is_empty() {
+            true => println!("{} {}", label("Expiry:"), ok("✅ nothing due")),
+            false => {
+                let due: Vec<_> = status
+                    .expiring
+                    .iter()
+                    .map(|(key, expiry)| format!("{} ({})", key, expiry))
+                    .collect();
+                println!("{} {}", label("Expiry:"), due.join(", ").bright_yellow());
+            }
+        }
+
+        let synced = self.config_manager.load_sync_states(&status.environment)?;
+        if synced.is_empty() {
+            println!("{} {}", label("Sync:"), "no remotes".bright_black());
+        } else if status.unsynced.is_empty() {
+            println!("{} {}", label("Sync:"), ok("✅ up to date"));
+        } else {
+            let pending: Vec<_> = status
+                .unsynced
+                .iter()
+                .map(|(remote, changes)| format!("{} ({} change(s))", remote, changes))
+                .collect();
+            println!("{} {}", label("Sync:"), pending.join(", ").bright_yellow());
+        }
+
+        let modified = status
+            .last_modified
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "never".to_string());
+        println!("{} {}", label("Last modified:"), modified.bright_black());
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigManager, Schema};
+
+    #[test]
+    fn test_project_status() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        let schema: Schema = serde_yaml::from_str(
+            "variables:\n  API_URL:\n    required: true\n  PORT:\n    type: number\n",
+        )
+        .unwrap();
+        commands.config_manager.save_schema(&schema).unwrap();
+
+        let status = commands.project_status(None).unwrap();
+        assert_eq!(status.missing_required, ["API_URL"]);
+
+        commands
+            .set_variables(
+                &[
+                    ("API_URL".to_string(), "http://api".to_string()),
+                    ("PORT".to_string(), "eighty".to_string()),
+                ],
+                "development",
+            )
+            .unwrap();
+        let status = commands.project_status(None).unwrap();
+        assert_eq!(status.variables, 2);
+        assert!(status.missing_required.is_empty());
+        assert_eq!(status.wrong_type, ["PORT"]);
+        assert_eq!(status.schema_violations(), 1);
+        assert!(status.last_modified.is_some());
+    }
+}
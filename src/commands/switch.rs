@@ -0,0 +1,68 @@
+use super::EnvMatchCommands;
+use crate::diff::{self, VariableChange};
+use crate::error::Result;
+
+/// What switching to another environment would change, for the TUI to
+/// confirm before switching.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwitchPreview {
+    pub from: String,
+    pub to: String,
+    /// Variables that differ going from `from` to `to`
+    pub changes: Vec<VariableChange>,
+    /// What else reacts to the switch, one line each
+    pub effects: Vec<String>,
+}
+
+impl EnvMatchCommands {
+    /// Works out what switching to `env_name` would change, without
+    /// switching or printing anything.
+    pub fn switch_preview(&self, env_name: &str) -> Result<SwitchPreview> {
+        let from = self.config_manager.active_environment()?;
+        let current = self.config_manager.load_environment(&from)?;
+        let target = self.config_manager.load_environment(env_name)?;
+
+        let mut effects = Vec::new();
+        if self.config_manager.uses_watch_stamp() {
+            effects.push("direnv reloads .envrc with the new variables".to_string());
+        }
+        if let Some(rule) = self.config_manager.matching_rule()? {
+            if rule.environment != env_name {
+                effects.push(format!(
+                    "rule '{}' still selects '{}' here",
+                    rule.pattern, rule.environment
+                ));
+            }
+        }
+
+        Ok(SwitchPreview {
+            changes: diff::diff_variables(&current.variables, &target.variables),
+            from,
+            to: env_name.to_string(),
+            effects,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_switch_preview() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("production").unwrap();
+        commands.set_variable("PORT", "80", "development").unwrap();
+        commands.set_variable("PORT", "443", "production").unwrap();
+        commands.set_variable("CDN", "on", "production").unwrap();
+        commands.config_manager.create_watch_stamp().unwrap();
+
+        let preview = commands.switch_preview("production").unwrap();
+        assert_eq!(preview.from, "development");
+        assert_eq!(preview.changes.len(), 2);
+        assert_eq!(preview.effects.len(), 1);
+        assert_eq!(commands.show_current_environment().unwrap(), "development");
+    }
+}
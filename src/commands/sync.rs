@@ -0,0 +1,436 @@
+use super::{audit_changes, print_changes, EnvMatchCommands};
+use crate::config::{AuditEntry, SyncState};
+use crate::diff;
+use crate::error::{EnvMatchError, Result};
+use crate::sync::{self, RemoteProvider, SyncAction, Transfer};
+use chrono::Utc;
+use colored::*;
+
+impl EnvMatchCommands {
+    /// Key for the versions remotes derive themselves, so they don't give
+    /// away what they're derived from.
+    pub fn fingerprint_key(&self) -> Result<[u8; 32]> {
+        self.config_manager.fingerprint_key()
+    }
+
+    /// Synchronizes an environment with a remote secret store.
+    ///
+    /// Both sides are compared with the state recorded at the last sync, so
+    /// changes made on only one side are carried over and changes made on
+    /// both sides are reported as conflicts. With `force`, conflicts are
+    /// resolved in favour of the side being synced from.
+    ///
+    /// `transfer` sets how many requests run at once and how often failed
+    /// ones are retried. When some keys can't be written, the others still
+    /// are, the failures are reported together, and neither the local
+    /// environment nor the sync state changes, so the next sync picks up
+    /// where this one left off.
+    pub fn sync(
+        &self,
+        remote: &dyn RemoteProvider,
+        env_name: Option<&str>,
+        action: SyncAction,
+        force: bool,
+        transfer: &Transfer,
+    ) -> Result<()> {
+        let env_name = self.resolve_environment(env_name)?;
+        let remote_id = remote.id();
+        let env_config = self.config_manager.load_environment(&env_name)?;
+        let last_sync = self.config_manager.load_sync_state(&env_name, &remote_id)?;
+        let base = last_sync
+            .as_ref()
+            .map(|state| state.variables.clone())
+            .unwrap_or_default();
+        let snapshot = transfer.retry(|| remote.fetch(transfer))?;
+        let mut merge = sync::merge(&base, &env_config.variables, &snapshot.variables);
+
+        if action == SyncAction::Status {
+            println!(
+                "{} {} {} {}",
+                "🔄 Sync status of".bright_blue().bold(),
+                format!("'{}'", env_name).bright_green().bold(),
+                "with".bright_blue().bold(),
+                remote_id.bright_cyan()
+            );
+            match &last_sync {
+                Some(state) => println!(
+                    "{} {}",
+                    "Last synced:".bright_white(),
+                    state
+                        .synced_at
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string()
+                        .bright_black()
+                ),
+                None => println!("{}", "Never synced".bright_black()),
+            }
+            println!("{}", "Local changes:".bright_yellow().bold());
+            print_changes(&diff::diff_variables(&base, &env_config.variables));
+            println!("{}", "Remote changes:".bright_yellow().bold());
+            print_changes(&diff::diff_variables(&base, &snapshot.variables));
+            if !merge.conflicts.is_empty() {
+                println!(
+                    "{} {}",
+                    "⚠️  Conflicts:".bright_red().bold(),
+                    merge.conflicts.join(", ").bright_red()
+                );
+            }
+            return Ok(());
+        }
+
+        let pushing = action == SyncAction::Push;
+        if !merge.conflicts.is_empty() {
+            if !force {
+                return Err(EnvMatchError::SyncConflict {
+                    remote: remote_id,
+                    keys: merge.conflicts,
+                });
+            }
+            // The merge keeps local values, which is what a forced push wants
+            if !pushing {
+                for key in &merge.conflicts {
+                    match snapshot.variables.get(key) {
+                        Some(value) => merge.merged.insert(key.clone(), value.clone()),
+                        None => merge.merged.shift_remove(key),
+                    };
+                }
+            }
+        }
+
+        let remote_changes = diff::diff_variables(&snapshot.variables, &merge.merged);
+        let (synced, version) = if pushing && !remote_changes.is_empty() {
+            let version = remote.store(&merge.merged, snapshot.version.as_deref(), transfer)?;
+            (merge.merged.clone(), version)
+        } else {
+            (snapshot.variables, snapshot.version)
+        };
+
+        let local_changes = diff::diff_variables(&env_config.variables, &merge.merged);
+        let verb = if pushing { "push" } else { "pull" };
+        let mut audit = audit_changes(&format!("sync {}", verb), &env_name, &local_changes);
+        if pushing && !remote_changes.is_empty() {
+            audit.push(AuditEntry::new("sync push", &env_name, None, None, None));
+        }
+        let command = format!("sync {} {} -e {}", verb, remote_id, env_name);
+        self.transaction(&command, |tx| {
+            tx.environment_mut(&env_name)?.variables = merge.merged;
+            tx.audit(audit);
+            Ok(())
+        })?;
+        self.config_manager.save_sync_state(
+            &env_name,
+            &remote_id,
+            SyncState {
+                synced_at: Utc::now(),
+                version,
+                variables: synced,
+            },
+        )?;
+        if self.quiet {
+            return Ok(());
+        }
+
+        if pushing {
+            println!(
+                "{} {} {} {}",
+                "✅ Pushed".bright_green().bold(),
+                format!("'{}'", env_name).bright_green().bold(),
+                "to".bright_white(),
+                remote_id.bright_cyan()
+            );
+            print_changes(&remote_changes);
+        }
+        if !pushing || !local_changes.is_empty() {
+            println!(
+                "{} {} {} {}",
+                "✅ Pulled".bright_green().bold(),
+                remote_id.bright_cyan(),
+                "into".bright_white(),
+                format!("'{}'", env_name).bright_green().bold()
+            );
+            print_changes(&local_changes);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ListOptions;
+    use crate::config::ConfigManager;
+    use crate::sync::RemoteSnapshot;
+    use indexmap::IndexMap;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// A remote that lives in memory, writes one key per request like a
+    /// key vault, and bumps its version on every store.
+    #[derive(Default)]
+    struct MemoryRemote {
+        snapshot: Mutex<RemoteSnapshot>,
+        /// Requests still to fail for each key, `usize::MAX` for all of them
+        failures: Mutex<HashMap<String, usize>>,
+        in_flight: AtomicUsize,
+        most_in_flight: AtomicUsize,
+    }
+
+    impl RemoteProvider for MemoryRemote {
+        fn id(&self) -> String {
+            "memory:test".to_string()
+        }
+
+        fn fetch(&self, _transfer: &Transfer) -> Result<RemoteSnapshot> {
+            Ok(self.snapshot.lock().unwrap().clone())
+        }
+
+        fn store(
+            &self,
+            variables: &IndexMap<String, String>,
+            expected_version: Option<&str>,
+            transfer: &Transfer,
+        ) -> Result<Option<String>> {
+            let current = self.fetch(transfer)?;
+            assert_eq!(current.version.as_deref(), expected_version);
+            let changes: Vec<(String, Option<String>)> = current
+                .variables
+                .keys()
+                .filter(|key| !variables.contains_key(*key))
+                .map(|key| (key.clone(), None))
+                .chain(
+                    variables
+                        .iter()
+                        .filter(|(key, value)| current.variables.get(*key) != Some(value))
+                        .map(|(key, value)| (key.clone(), Some(value.clone()))),
+                )
+                .collect();
+
+            let result = transfer.run(&self.id(), "Writing", &changes, |key, value| {
+                self.write(key, value.as_deref())
+            });
+            let mut snapshot = self.snapshot.lock().unwrap();
+            let version = snapshot
+                .version
+                .as_deref()
+                .map_or(1, |v| v.parse::<u64>().unwrap() + 1);
+            snapshot.version = Some(version.to_string());
+            result.map(|_| snapshot.version.clone())
+        }
+    }
+
+    impl MemoryRemote {
+        fn write(&self, key: &str, value: Option<&str>) -> Result<()> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(5));
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            if let Some(left) = self.failures.lock().unwrap().get_mut(key) {
+                if *left > 0 {
+                    *left = left.saturating_sub(1);
+                    return Err(EnvMatchError::RemoteError {
+                        remote: self.id(),
+                        message: format!("{} timed out", key),
+                    });
+                }
+            }
+            let mut snapshot = self.snapshot.lock().unwrap();
+            match value {
+                Some(value) => snapshot
+                    .variables
+                    .insert(key.to_string(), value.to_string()),
+                None => snapshot.variables.shift_remove(key),
+            };
+            Ok(())
+        }
+
+        fn fail(&self, key: &str, times: usize) {
+            self.failures.lock().unwrap().insert(key.to_string(), times);
+        }
+
+        fn set(&self, key: &str, value: &str) {
+            self.snapshot
+                .lock()
+                .unwrap()
+                .variables
+                .insert(key.to_string(), value.to_string());
+        }
+
+        fn get(&self, key: &str) -> Option<String> {
+            self.snapshot.lock().unwrap().variables.get(key).cloned()
+        }
+    }
+
+    fn transfer(jobs: usize, retries: u32) -> Transfer {
+        Transfer {
+            jobs,
+            retries,
+            backoff: Duration::from_millis(1),
+            progress: false,
+        }
+    }
+
+    #[test]
+    fn test_sync_pull_push_and_conflicts() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init().unwrap();
+        let remote = MemoryRemote::default();
+        remote.set("API_KEY", "remote-1");
+
+        commands
+            .sync(&remote, None, SyncAction::Pull, false, &Transfer::default())
+            .unwrap();
+        commands
+            .set_variable("DEBUG", "true", "development")
+            .unwrap();
+        remote.set("API_KEY", "remote-2");
+
+        // Changes on different keys merge cleanly in both directions
+        commands
+            .sync(&remote, None, SyncAction::Push, false, &Transfer::default())
+            .unwrap();
+        assert_eq!(remote.get("DEBUG"), Some("true".to_string()));
+        assert_eq!(remote.get("API_KEY"), Some("remote-2".to_string()));
+        let vars = commands
+            .list_variables(None, ListOptions::default())
+            .unwrap();
+        assert!(vars.contains(&("API_KEY".to_string(), "remote-2".to_string())));
+
+        // The same key changed on both sides is a conflict
+        commands
+            .set_variable("API_KEY", "local-3", "development")
+            .unwrap();
+        remote.set("API_KEY", "remote-3");
+        assert!(matches!(
+            commands.sync(&remote, None, SyncAction::Pull, false, &Transfer::default()),
+            Err(EnvMatchError::SyncConflict { keys, .. }) if keys == vec!["API_KEY"]
+        ));
+        assert!(matches!(
+            commands.sync(&remote, None, SyncAction::Push, false, &Transfer::default()),
+            Err(EnvMatchError::SyncConflict { .. })
+        ));
+
+        commands
+            .sync(&remote, None, SyncAction::Pull, true, &Transfer::default())
+            .unwrap();
+        let vars = commands
+            .list_variables(None, ListOptions::default())
+            .unwrap();
+        assert!(vars.contains(&("API_KEY".to_string(), "remote-3".to_string())));
+    }
+
+    #[test]
+    fn test_history_and_sync_bases_are_encrypted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let commands = EnvMatchCommands::with_project_dir(temp_dir.path().to_path_buf());
+        commands.init().unwrap();
+        commands
+            .set_variable("API_KEY", "before-encryption", "development")
+            .unwrap();
+        let remote = MemoryRemote::default();
+        commands
+            .sync(&remote, None, SyncAction::Push, false, &Transfer::default())
+            .unwrap();
+
+        commands
+            .config_manager
+            .enable_encryption("passphrase")
+            .unwrap();
+        commands
+            .set_variable("API_KEY", "supersecretvalue123", "development")
+            .unwrap();
+        commands
+            .sync(&remote, None, SyncAction::Push, false, &Transfer::default())
+            .unwrap();
+
+        let env_match_dir = temp_dir.path().join(".envMatch");
+        for dir in ["history", "sync"] {
+            for entry in std::fs::read_dir(env_match_dir.join(dir)).unwrap() {
+                let content = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+                assert!(!content.contains("supersecretvalue123"), "{}", content);
+                assert!(!content.contains("before-encryption"), "{}", content);
+            }
+        }
+
+        // Both still read back in the clear
+        let snapshots = commands.config_manager.list_snapshots().unwrap();
+        let last = &snapshots.last().unwrap().environments["development"];
+        assert_eq!(last.variables["API_KEY"], "supersecretvalue123");
+        let state = commands
+            .config_manager
+            .load_sync_state("development", &remote.id())
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.variables["API_KEY"], "supersecretvalue123");
+    }
+
+    #[test]
+    fn test_sync_push_runs_keys_concurrently_and_reports_failures() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init().unwrap();
+        for index in 0..20 {
+            commands
+                .set_variable(&format!("KEY_{}", index), "value", "development")
+                .unwrap();
+        }
+        let remote = MemoryRemote::default();
+        remote.fail("KEY_3", usize::MAX);
+        remote.fail("KEY_12", usize::MAX);
+
+        let error = commands
+            .sync(&remote, None, SyncAction::Push, false, &transfer(4, 1))
+            .unwrap_err();
+        // Every other key was still written, and both failures are reported
+        match &error {
+            EnvMatchError::RemoteKeysFailed { failures, .. } => {
+                let keys: Vec<_> = failures.iter().map(|(key, _)| key.as_str()).collect();
+                assert_eq!(keys, ["KEY_3", "KEY_12"]);
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+        assert!(error
+            .to_string()
+            .contains("KEY_12: Sync with memory:test failed"));
+        assert_eq!(remote.snapshot.lock().unwrap().variables.len(), 18);
+        assert!(remote.get("KEY_3").is_none());
+
+        let most_in_flight = remote.most_in_flight.load(Ordering::SeqCst);
+        assert!((2..=4).contains(&most_in_flight), "{}", most_in_flight);
+
+        // Nothing was recorded as synced, so the next push finishes the job
+        assert!(commands
+            .config_manager
+            .load_sync_state("development", &remote.id())
+            .unwrap()
+            .is_none());
+        remote.failures.lock().unwrap().clear();
+        commands
+            .sync(&remote, None, SyncAction::Push, false, &transfer(4, 0))
+            .unwrap();
+        assert_eq!(remote.snapshot.lock().unwrap().variables.len(), 20);
+    }
+
+    #[test]
+    fn test_sync_retries_with_backoff() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init().unwrap();
+        commands
+            .set_variable("API_KEY", "one", "development")
+            .unwrap();
+        let remote = MemoryRemote::default();
+
+        remote.fail("API_KEY", 2);
+        assert!(matches!(
+            commands.sync(&remote, None, SyncAction::Push, false, &transfer(1, 1)),
+            Err(EnvMatchError::RemoteKeysFailed { .. })
+        ));
+        remote.fail("API_KEY", 2);
+        commands
+            .sync(&remote, None, SyncAction::Push, false, &transfer(1, 2))
+            .unwrap();
+        assert_eq!(remote.get("API_KEY"), Some("one".to_string()));
+    }
+}
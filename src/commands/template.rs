@@ -0,0 +1,147 @@
+use super::EnvMatchCommands;
+use crate::error::{EnvMatchError, Result};
+use crate::template::{self, TemplateEntry};
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+impl EnvMatchCommands {
+    /// Writes a `.env.example`-style template of an environment's keys, or of
+    /// the schema's with `from_schema`.
+    ///
+    /// Descriptions come from the variable's metadata, else the schema.
+    /// Secrets are left blank; other variables get the schema's example, else
+    /// their value in the environment.
+    pub fn template(
+        &self,
+        env_name: Option<&str>,
+        from_schema: bool,
+        output: Option<&Path>,
+    ) -> Result<String> {
+        let schema = self.config_manager.load_schema()?;
+        let (source, entries): (String, Vec<TemplateEntry>) = if from_schema {
+            let entries = schema
+                .variables
+                .into_iter()
+                .map(|(key, variable)| TemplateEntry {
+                    key,
+                    description: variable.description,
+                    value: variable
+                        .example
+                        .filter(|_| !variable.secret)
+                        .unwrap_or_default(),
+                })
+                .collect();
+            ("schema.yaml".to_string(), entries)
+        } else {
+            let env_name = self.resolve_environment(env_name)?;
+            let mut env_config = self.config_manager.load_environment(&env_name)?;
+            let vars: Vec<(String, String)> = env_config.variables.drain(..).collect();
+
+            let entries = vars
+                .into_iter()
+                .map(|(key, value)| {
+                    let metadata = env_config.metadata.remove(&key).unwrap_or_default();
+                    let declared = schema.variables.get(&key).cloned().unwrap_or_default();
+                    let value = if metadata.secret || declared.secret {
+                        String::new()
+                    } else {
+                        declared.example.unwrap_or(value)
+                    };
+                    TemplateEntry {
+                        description: metadata.description.or(declared.description),
+                        key,
+                        value,
+                    }
+                })
+                .collect();
+            (format!("'{}'", env_name), entries)
+        };
+
+        let rendered = template::render(&source, &entries);
+        match output {
+            Some(path) => {
+                fs::write(path, &rendered).map_err(|e| EnvMatchError::storage(path, e))?;
+                if !self.quiet {
+                    println!(
+                        "{} {} {} {}",
+                        "✅ Wrote template of".bright_green().bold(),
+                        source.bright_green().bold(),
+                        "to".bright_white(),
+                        path.display().to_string().bright_cyan()
+                    );
+                }
+            }
+            None => print!("{}", rendered),
+        }
+        Ok(rendered)
+    }
+
+    /// Checks that an environment sets every key listed in a template file.
+    pub fn check_template(&self, path: &Path, env_name: Option<&str>) -> Result<()> {
+        let env_name = self.resolve_environment(env_name)?;
+        let env_config = self.config_manager.load_environment(&env_name)?;
+        let content = fs::read_to_string(path).map_err(|e| EnvMatchError::storage(path, e))?;
+        let keys = template::parse_keys(&content)?;
+
+        let missing: Vec<String> = keys
+            .iter()
+            .filter(|key| !env_config.variables.contains_key(*key))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(EnvMatchError::MissingRequiredVariables {
+                env: env_name,
+                variables: missing,
+            });
+        }
+
+        println!(
+            "✅ Environment '{}' sets all {} variable(s) in {}",
+            env_name,
+            keys.len(),
+            path.display()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigManager, VariableMetadata};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_template_and_check() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("production").unwrap();
+        commands.set_variable("PORT", "8080", "production").unwrap();
+        let secret = VariableMetadata {
+            description: Some("Payments API key".to_string()),
+            secret: true,
+            ..Default::default()
+        };
+        commands
+            .set_variables_with_metadata(
+                &[("API_KEY".to_string(), "sk_live_123".to_string())],
+                &secret,
+                "production",
+            )
+            .unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env.example");
+        let rendered = commands.template(None, false, Some(&path)).unwrap();
+        assert!(rendered.contains("# Payments API key\nAPI_KEY=\n"));
+        assert!(rendered.contains("PORT=\"8080\""));
+        assert!(!rendered.contains("sk_live_123"));
+
+        commands.check_template(&path, None).unwrap();
+        commands.unset_variable("PORT", "production").unwrap();
+        assert!(matches!(
+            commands.check_template(&path, None),
+            Err(EnvMatchError::MissingRequiredVariables { variables, .. }) if variables == ["PORT"]
+        ));
+    }
+}
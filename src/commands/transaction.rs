@@ -0,0 +1,198 @@
+use super::EnvMatchCommands;
+use crate::config::{AuditEntry, EnvConfig, Settings, VariableMetadata};
+use crate::error::{EnvMatchError, Result};
+use indexmap::IndexMap;
+
+/// Changes staged in memory by [`EnvMatchCommands::transaction`]. Nothing
+/// is written until the transaction's closure returns successfully.
+pub struct Transaction<'a> {
+    commands: &'a EnvMatchCommands,
+    settings: Settings,
+    /// Each environment read so far: as loaded, and with the staged changes
+    environments: IndexMap<String, (EnvConfig, EnvConfig)>,
+    audit: Vec<AuditEntry>,
+}
+
+impl EnvMatchCommands {
+    /// Runs `f` against staged copies of the environments it touches, then
+    /// writes each changed environment file exactly once and records a single
+    /// snapshot, under `command`, for all of it. If `f` fails, nothing is
+    /// written.
+    pub fn transaction<T>(
+        &self,
+        command: &str,
+        f: impl FnOnce(&mut Transaction) -> Result<T>,
+    ) -> Result<T> {
+        let mut transaction = Transaction {
+            commands: self,
+            settings: self.settings()?,
+            environments: IndexMap::new(),
+            audit: Vec::new(),
+        };
+        let result = f(&mut transaction)?;
+
+        let mut changed = Vec::new();
+        for (env_name, (loaded, staged)) in &transaction.environments {
+            if staged != loaded {
+                self.config_manager.save_environment(env_name, staged)?;
+                changed.push(env_name.clone());
+            }
+        }
+        if !transaction.audit.is_empty() {
+            self.record_touching(command, changed, transaction.audit)?;
+        }
+        Ok(result)
+    }
+}
+
+impl Transaction<'_> {
+    /// The staged state of an environment.
+    pub fn environment(&mut self, env_name: &str) -> Result<&EnvConfig> {
+        Ok(self.environment_mut(env_name)?)
+    }
+
+    /// Edits the staged state of an environment directly, without an audit
+    /// entry; pair it with [`Transaction::audit`].
+    pub fn environment_mut(&mut self, env_name: &str) -> Result<&mut EnvConfig> {
+        if !self.environments.contains_key(env_name) {
+            let env_config = self.commands.config_manager.load_environment(env_name)?;
+            self.environments
+                .insert(env_name.to_string(), (env_config.clone(), env_config));
+        }
+        Ok(&mut self.environments[env_name].1)
+    }
+
+    /// Records audit entries for changes made through `environment_mut`.
+    pub fn audit(&mut self, entries: impl IntoIterator<Item = AuditEntry>) {
+        self.audit.extend(entries);
+    }
+
+    /// Sets a variable, returning its previous value.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn set(&mut self, env_name: &str, key: &str, value: &str) -> Result<Option<String>> {
+        self.set_with_metadata(env_name, key, value, &VariableMetadata::default())
+    }
+
+    /// Sets a variable and applies the metadata fields that `metadata` sets.
+    pub fn set_with_metadata(
+        &mut self,
+        env_name: &str,
+        key: &str,
+        value: &str,
+        metadata: &VariableMetadata,
+    ) -> Result<Option<String>> {
+        self.settings.key_names().check(key)?;
+        let env_config = self.environment_mut(env_name)?;
+        let old_value = env_config.set(key, value);
+        if *metadata != VariableMetadata::default() {
+            env_config
+                .metadata
+                .entry(key.to_string())
+                .or_default()
+                .update(metadata);
+        }
+        self.audit.push(AuditEntry::new(
+            "set",
+            env_name,
+            Some(key),
+            old_value.as_deref(),
+            Some(value),
+        ));
+        Ok(old_value)
+    }
+
+    /// Removes a variable, returning its value. Fails if it isn't set,
+    /// which abandons the whole transaction unless the caller handles it.
+    pub fn unset(&mut self, env_name: &str, key: &str) -> Result<String> {
+        let env_config = self.environment_mut(env_name)?;
+        let old_value = env_config.remove(key).ok_or_else(|| {
+            EnvMatchError::variable_not_found(key, env_name, env_config.variables.keys())
+        })?;
+        self.audit.push(AuditEntry::new(
+            "unset",
+            env_name,
+            Some(key),
+            Some(&old_value),
+            None,
+        ));
+        Ok(old_value)
+    }
+
+    /// Puts a variable back to the given value and metadata exactly, or
+    /// removes it when there's none, as undo does.
+    pub fn restore(
+        &mut self,
+        env_name: &str,
+        key: &str,
+        state: Option<&(String, VariableMetadata)>,
+    ) -> Result<()> {
+        let env_config = self.environment_mut(env_name)?;
+        let old_value = match state {
+            Some((value, metadata)) => {
+                let old_value = env_config.variables.insert(key.to_string(), value.clone());
+                env_config
+                    .metadata
+                    .insert(key.to_string(), metadata.clone());
+                old_value
+            }
+            None => env_config.remove(key),
+        };
+        let new_value = state.map(|(value, _)| value.as_str());
+        if old_value.as_deref() != new_value {
+            let action = if new_value.is_some() { "set" } else { "unset" };
+            self.audit.push(AuditEntry::new(
+                action,
+                env_name,
+                Some(key),
+                old_value.as_deref(),
+                new_value,
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_transaction_writes_once_or_not_at_all() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("development").unwrap();
+        commands.create_environment("staging").unwrap();
+        let snapshots = || commands.config_manager.list_snapshots().unwrap().len();
+        let before = snapshots();
+
+        commands
+            .transaction("bulk edit", |tx| {
+                tx.set("development", "API_URL", "http://api")?;
+                tx.set("development", "PORT", "80")?;
+                tx.set("staging", "PORT", "8080")?;
+                tx.unset("development", "PORT")?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(snapshots(), before + 1);
+        let development = commands
+            .resolve_layers(&["development".to_string()])
+            .unwrap();
+        assert_eq!(development.keys().collect::<Vec<_>>(), ["API_URL"]);
+        assert_eq!(
+            commands.resolve_layers(&["staging".to_string()]).unwrap()["PORT"],
+            "8080"
+        );
+
+        let failed = commands.transaction("bad edit", |tx| {
+            tx.set("development", "DEBUG", "true")?;
+            tx.unset("development", "MISSING")
+        });
+        assert!(matches!(
+            failed,
+            Err(EnvMatchError::VariableNotFound { .. })
+        ));
+        assert_eq!(snapshots(), before + 1);
+        assert!(commands.lookup_variable("DEBUG", "development").is_err());
+    }
+}
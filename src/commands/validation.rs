@@ -0,0 +1,279 @@
+use super::EnvMatchCommands;
+use crate::config::{self, EnvConfig, PolicyLevel, PolicyViolation, Schema, VariableMetadata};
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+
+/// What's wrong with a variable, as the TUI's validation view groups it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IssueKind {
+    /// Required by the schema but not set
+    Missing,
+    /// Set, but with a name or value the settings or schema reject
+    Invalid,
+    /// Expired, or expiring within the rotation window
+    Expiring,
+    /// Still set, though the schema marks it deprecated
+    Deprecated,
+}
+
+/// A problem with one variable of an environment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationIssue {
+    pub kind: IssueKind,
+    pub key: String,
+    pub message: String,
+}
+
+impl EnvMatchCommands {
+    /// Everything `validate` and the schema would flag in an environment,
+    /// variable by variable, without printing anything. Sorted by kind,
+    /// then key.
+    pub fn validation_issues(&self, env_name: &str) -> Result<Vec<ValidationIssue>> {
+        let status = self.project_status(Some(env_name))?;
+        let env_config = self.config_manager.load_environment(env_name)?;
+        let key_names = self.settings()?.key_names();
+        let schema = self.config_manager.load_schema()?;
+        let schema_types = schema.types();
+
+        let mut issues = Vec::new();
+        for key in status.missing_required {
+            issues.push(ValidationIssue {
+                kind: IssueKind::Missing,
+                key,
+                message: "required by the schema".to_string(),
+            });
+        }
+        for key in env_config.variables.keys() {
+            if let Err(EnvMatchError::InvalidVariableKey { reason, .. }) = key_names.check(key) {
+                issues.push(ValidationIssue {
+                    kind: IssueKind::Invalid,
+                    key: key.clone(),
+                    message: format!("invalid name: {}", reason),
+                });
+            }
+        }
+        for key in status.wrong_type {
+            let type_name = schema_types
+                .get(&key)
+                .map(|value_type| format!("{:?}", value_type).to_lowercase())
+                .unwrap_or_default();
+            issues.push(ValidationIssue {
+                kind: IssueKind::Invalid,
+                key,
+                message: format!("should be a {} according to the schema", type_name),
+            });
+        }
+        for violation in self.policy_violations(env_name, &env_config, &schema)? {
+            issues.push(ValidationIssue {
+                kind: IssueKind::Invalid,
+                key: violation.key,
+                message: violation.message,
+            });
+        }
+        for (key, expiry) in status.expiring {
+            issues.push(ValidationIssue {
+                kind: IssueKind::Expiring,
+                key,
+                message: expiry.to_string(),
+            });
+        }
+        for (key, note) in schema.deprecations() {
+            if env_config.variables.contains_key(&key) {
+                issues.push(ValidationIssue {
+                    kind: IssueKind::Deprecated,
+                    key,
+                    message: format!("deprecated: {}", note),
+                });
+            }
+        }
+
+        issues.sort_by(|a, b| (a.kind, &a.key).cmp(&(b.kind, &b.key)));
+        Ok(issues)
+    }
+
+    /// The schema policies an environment's values break.
+    pub(super) fn policy_violations(
+        &self,
+        env_name: &str,
+        env_config: &EnvConfig,
+        schema: &Schema,
+    ) -> Result<Vec<PolicyViolation>> {
+        let mut violations = Vec::new();
+        for (key, value) in &env_config.variables {
+            let secret = super::is_secret(env_config, schema, key);
+            violations.extend(config::check_policies(
+                &schema.policies,
+                env_name,
+                key,
+                value,
+                secret,
+            )?);
+        }
+        Ok(violations)
+    }
+
+    /// What the schema would say about setting `key` to `value` in
+    /// `env_name`, for the TUI to check input as it's typed: a value not of
+    /// the declared type fails as an error, then come the policies it breaks.
+    /// `None` when the schema declares no type and no policy for the key.
+    pub fn value_problems(
+        &self,
+        key: &str,
+        value: &str,
+        env_name: &str,
+    ) -> Result<Option<Vec<PolicyViolation>>> {
+        let schema = self.config_manager.load_schema()?;
+        let env_config = self.config_manager.load_environment(env_name)?;
+        let secret = super::is_secret(&env_config, &schema, key);
+        let value_type = schema.variables.get(key).and_then(|v| v.value_type);
+        if value_type.is_none()
+            && !schema
+                .policies
+                .iter()
+                .any(|policy| policy.applies_to(env_name, key, secret))
+        {
+            return Ok(None);
+        }
+
+        let mut problems = Vec::new();
+        if let Some(value_type) = value_type {
+            if !value_type.accepts(value) {
+                problems.push(PolicyViolation {
+                    key: key.to_string(),
+                    message: format!(
+                        "should be a {} according to the schema",
+                        format!("{:?}", value_type).to_lowercase()
+                    ),
+                    severity: PolicyLevel::Error,
+                });
+            }
+        }
+        problems.extend(config::check_policies(
+            &schema.policies,
+            env_name,
+            key,
+            value,
+            secret,
+        )?);
+        Ok(Some(problems))
+    }
+
+    /// Checks values about to be stored against the schema policies, warning
+    /// about each one broken. With `--enforce`, breaking one of error
+    /// severity fails instead.
+    pub(super) fn check_entry_policies(
+        &self,
+        entries: &[(String, String, VariableMetadata)],
+        env_config: &EnvConfig,
+        env_name: &str,
+    ) -> Result<()> {
+        let schema = self.config_manager.load_schema()?;
+        let mut blocking = Vec::new();
+        for (key, value, metadata) in entries {
+            let secret = metadata.secret || super::is_secret(env_config, &schema, key);
+            for violation in config::check_policies(&schema.policies, env_name, key, value, secret)?
+            {
+                if self.enforce_policies && violation.severity == PolicyLevel::Error {
+                    blocking.push(format!("{}: {}", key, violation.message));
+                } else {
+                    eprintln!(
+                        "{}",
+                        format!("⚠️  {} breaks a policy: {}", key, violation.message)
+                            .bright_yellow()
+                    );
+                }
+            }
+        }
+        match blocking.is_empty() {
+            true => Ok(()),
+            false => Err(EnvMatchError::PolicyViolations {
+                env: env_name.to_string(),
+                violations: blocking,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_validation_issues() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        let commands = EnvMatchCommands::with_config_manager(config_manager);
+        commands
+            .config_manager
+            .save_schema(
+                &serde_yaml::from_str(
+                    "variables:\n  API_URL:\n    required: true\n  PORT:\n    type: number\n",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        commands
+            .set_variable("PORT", "eighty", "development")
+            .unwrap();
+
+        let issues = commands.validation_issues("development").unwrap();
+        let found: Vec<_> = issues
+            .iter()
+            .map(|issue| (issue.kind, issue.key.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (IssueKind::Missing, "API_URL"),
+                (IssueKind::Invalid, "PORT")
+            ]
+        );
+        assert!(issues[1].message.contains("number"));
+    }
+
+    #[test]
+    fn test_value_problems() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        let commands = EnvMatchCommands::with_config_manager(config_manager);
+        commands
+            .config_manager
+            .save_schema(
+                &serde_yaml::from_str(
+                    "variables:\n  PORT:\n    type: number\npolicies:\n  - message: must match ^https://\n    keys: [\"*_URL\"]\n    pattern: \"^https://\"\n",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let problems = |key, value| {
+            commands
+                .value_problems(key, value, "development")
+                .unwrap()
+                .map(|problems| {
+                    problems
+                        .into_iter()
+                        .map(|problem| (problem.message, problem.severity))
+                        .collect::<Vec<_>>()
+                })
+        };
+        assert_eq!(
+            problems("PORT", "80a"),
+            Some(vec![(
+                "should be a number according to the schema".to_string(),
+                PolicyLevel::Error
+            )])
+        );
+        assert_eq!(problems("PORT", "8080"), Some(vec![]));
+        assert_eq!(
+            problems("API_URL", "http://example.com"),
+            Some(vec![(
+                "must match ^https://".to_string(),
+                PolicyLevel::Error
+            )])
+        );
+        assert_eq!(problems("API_URL", "https://example.com"), Some(vec![]));
+        assert_eq!(problems("NAME", "anything"), None);
+    }
+}
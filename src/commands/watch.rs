@@ -0,0 +1,185 @@
+use super::EnvMatchCommands;
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+use indexmap::IndexMap;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// How often to check whether the command has exited on its own
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// Time for a burst of file writes (a save, a sync) to settle before reloading
+const DEBOUNCE: Duration = Duration::from_millis(100);
+/// Time the command gets to exit after SIGTERM before it's killed
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+impl EnvMatchCommands {
+    /// Runs a command like [`run`](Self::run), with `!cmd` and `!file`
+    /// values resolved the same way, restarting it with fresh variables
+    /// whenever the environments it uses change on disk. Runs until
+    /// interrupted, then stops the command too.
+    ///
+    /// On Unix the command gets a process group of its own, so a restart
+    /// stops whatever it started as well, like the server `cargo run` or
+    /// `npm start` leaves running.
+    pub fn watch(&self, env_names: &[String], program: &str, args: &[String]) -> Result<()> {
+        let env_match_dir = self
+            .config_manager
+            .env_match_dir()
+            .ok_or(EnvMatchError::NotInitialized)?;
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(&env_match_dir, RecursiveMode::Recursive)?;
+
+        // Outside the command's process group, Ctrl+C only reaches envMatch,
+        // which has to pass it on
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let on_interrupt = tokio::runtime::Handle::try_current().ok().map(|runtime| {
+            let interrupted = interrupted.clone();
+            runtime.spawn(async move {
+                interrupt().await;
+                interrupted.store(true, Ordering::SeqCst);
+            })
+        });
+
+        let mut variables = self.resolve_layers(env_names)?;
+        let mut child = Some(spawn(
+            program,
+            args,
+            &self.resolve_values(variables.clone())?,
+        )?);
+        eprintln!(
+            "{} {}",
+            "👀 Watching for changes, running".bright_blue(),
+            program.bright_cyan().bold()
+        );
+
+        let result = (|| loop {
+            if interrupted.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            match rx.recv_timeout(POLL_INTERVAL) {
+                Ok(Ok(event)) => {
+                    if !matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) {
+                        continue;
+                    }
+                    thread::sleep(DEBOUNCE);
+                    while rx.try_recv().is_ok() {}
+
+                    // Two writes within one tick of a coarse clock can look
+                    // alike to the cache, so read everything afresh
+                    self.config_manager.invalidate_cache();
+                    // History and audit writes land here too; only restart
+                    // when the variables themselves changed
+                    let fresh = match self.resolve_layers(env_names) {
+                        Ok(fresh) if fresh == variables => continue,
+                        fresh => fresh.and_then(|fresh| {
+                            let resolved = self.resolve_values(fresh.clone())?;
+                            Ok((fresh, resolved))
+                        }),
+                    };
+                    match fresh {
+                        Ok((fresh, resolved)) => {
+                            variables = fresh;
+                            if let Some(running) = child.take() {
+                                stop(running)?;
+                            }
+                            eprintln!("{}", "🔄 Environment changed, restarting".bright_yellow());
+                            child = Some(spawn(program, args, &resolved)?);
+                        }
+                        Err(error) => eprintln!(
+                            "{} {}",
+                            "⚠️  Could not reload, keeping the current variables:".bright_yellow(),
+                            error
+                        ),
+                    }
+                }
+                Ok(Err(error)) => return Err(error.into()),
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Some(running) = child.as_mut() {
+                        if let Some(status) = running.try_wait()? {
+                            let how = status
+                                .code()
+                                .map_or("was killed by a signal".to_string(), |code| {
+                                    format!("exited with code {}", code)
+                                });
+                            eprintln!(
+                                "{}",
+                                format!("⏸️  Command {}, waiting for changes", how).bright_blue()
+                            );
+                            child = None;
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        })();
+
+        if let Some(task) = on_interrupt {
+            task.abort();
+        }
+        if let Some(running) = child {
+            stop(running)?;
+        }
+        result
+    }
+}
+
+fn spawn(program: &str, args: &[String], variables: &IndexMap<String, String>) -> Result<Child> {
+    let mut command = super::run::program_command(program);
+    command.args(args).envs(variables);
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    command.spawn().map_err(|source| EnvMatchError::RunError {
+        command: program.to_string(),
+        source,
+    })
+}
+
+/// Stops the command. On Unix, its whole process group gets SIGTERM, then
+/// SIGKILL once [`GRACE_PERIOD`] is up, so nothing it started outlives it.
+fn stop(mut child: Child) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let group = -(child.id() as libc::pid_t);
+        // SAFETY: kill only sends a signal, to the group `spawn` created
+        unsafe { libc::kill(group, libc::SIGTERM) };
+        let deadline = std::time::Instant::now() + GRACE_PERIOD;
+        loop {
+            child.try_wait()?;
+            // Signal 0 only checks whether anything is left in the group
+            let empty = unsafe { libc::kill(group, 0) } != 0;
+            if empty || std::time::Instant::now() >= deadline {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        unsafe { libc::kill(group, libc::SIGKILL) };
+    }
+    child.kill().ok();
+    child.wait()?;
+    Ok(())
+}
+
+/// Waits for Ctrl+C or, on Unix, a SIGTERM from a process supervisor.
+async fn interrupt() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+            return;
+        }
+    }
+    tokio::signal::ctrl_c().await.ok();
+}
@@ -0,0 +1,207 @@
+use super::prompt::{confirm, prompt_line};
+use super::EnvMatchCommands;
+use crate::config::{AuditEntry, EnvConfig, VariableMetadata};
+use crate::error::{EnvMatchError, Result};
+use crate::template::{self, ProjectTemplate};
+use clap::ValueEnum;
+use colored::*;
+use std::fs;
+
+const DOTENV_FILE: &str = ".env";
+
+impl EnvMatchCommands {
+    /// Sets up a project step by step: which environments to create, whether
+    /// to import an existing `.env`, a starter template and `.gitignore`.
+    ///
+    /// Every question is asked before anything is written, so a bad answer
+    /// leaves the directory untouched.
+    pub fn init_interactive(
+        &self,
+        environment: Option<&str>,
+        template: Option<ProjectTemplate>,
+    ) -> Result<()> {
+        if self.is_initialized() {
+            return Err(EnvMatchError::AlreadyInitialized);
+        }
+        println!("{}", "🧙 envMatch setup".bright_blue().bold());
+        println!("{}", "─".repeat(40).bright_blue());
+
+        let settings = self.settings()?;
+        let default_environment = environment.unwrap_or(settings.default_environment());
+        let answer = prompt_line(
+            "Environments to create (comma-separated)",
+            default_environment,
+        )?;
+        let mut environments: Vec<String> = Vec::new();
+        for env_name in answer
+            .split(',')
+            .map(str::trim)
+            .filter(|env| !env.is_empty())
+        {
+            self.config_manager.validate_environment_name(env_name)?;
+            if !environments.iter().any(|env| env == env_name) {
+                environments.push(env_name.to_string());
+            }
+        }
+        if environments.is_empty() {
+            environments.push(default_environment.to_string());
+        }
+        let first = environments[0].clone();
+
+        let mut imported = Vec::new();
+        let dotenv = self
+            .config_manager
+            .project_dir()
+            .map(|dir| dir.join(DOTENV_FILE))
+            .filter(|path| path.exists());
+        if let Some(path) = dotenv {
+            let content =
+                fs::read_to_string(&path).map_err(|e| EnvMatchError::storage(&path, e))?;
+            match template::parse_dotenv(&content) {
+                Ok(vars) => {
+                    let question = format!(
+                        "Found {} with {} variable(s). Import it into '{}'?",
+                        DOTENV_FILE,
+                        vars.len(),
+                        first
+                    );
+                    if confirm(&question, true)? {
+                        imported = vars;
+                    }
+                }
+                Err(error) => println!(
+                    "{} {}",
+                    format!("⚠️  Skipping {}:", DOTENV_FILE).bright_yellow(),
+                    error
+                ),
+            }
+        }
+
+        let template = match template {
+            Some(template) => Some(template),
+            None => loop {
+                let answer = prompt_line(
+                    "Start from a template (node, rails, django, generic-web, none)",
+                    "none",
+                )?;
+                if answer == "none" {
+                    break None;
+                }
+                match ProjectTemplate::from_str(&answer, true) {
+                    Ok(template) => break Some(template),
+                    Err(_) => println!("{}", format!("Unknown template '{}'", answer).bright_red()),
+                }
+            },
+        };
+
+        let ignore = confirm(
+            "Keep .envMatch's unencrypted files out of git (.envMatch/.gitignore)?",
+            true,
+        )?;
+
+        self.config_manager.initialize_with_environment(&first)?;
+        for env_name in &environments[1..] {
+            self.config_manager
+                .save_environment(env_name, &EnvConfig::default())?;
+        }
+        self.record(
+            &format!("init {}", environments.join(" ")),
+            environments
+                .iter()
+                .map(|env_name| AuditEntry::new("init", env_name, None, None, None))
+                .collect(),
+        )?;
+        self.print_init_summary(&first);
+        if environments.len() > 1 {
+            println!(
+                "{} {}",
+                "📁 Other environments:".bright_yellow(),
+                environments[1..].join(", ").bright_green()
+            );
+        }
+
+        if let Some(template) = template {
+            for env_name in &environments {
+                self.apply_template(template, env_name)?;
+            }
+        }
+        if !imported.is_empty() {
+            let entries: Vec<_> = imported
+                .into_iter()
+                .map(|(key, value)| (key, value, VariableMetadata::default()))
+                .collect();
+            self.store_variables(&entries, &first)?;
+            println!(
+                "{} {}",
+                format!(
+                    "📥 Imported {} variable(s) from {} into",
+                    entries.len(),
+                    DOTENV_FILE
+                )
+                .bright_green(),
+                format!("'{}'", first).bright_green().bold()
+            );
+        }
+        if ignore && self.config_manager.update_gitignore()? {
+            println!("{}", "🙈 Updated .envMatch/.gitignore".bright_green());
+        }
+        Ok(())
+    }
+
+    /// Adds a template's starter variables to an environment, leaving the
+    /// ones it already sets alone. Returns how many were added.
+    pub fn apply_template(&self, template: ProjectTemplate, env_name: &str) -> Result<usize> {
+        let env_config = self.config_manager.load_environment(env_name)?;
+        let entries: Vec<_> = template
+            .variables(env_name)
+            .into_iter()
+            .filter(|(key, ..)| !env_config.variables.contains_key(key))
+            .map(|(key, value, secret)| {
+                let metadata = VariableMetadata {
+                    secret,
+                    ..Default::default()
+                };
+                (key, value, metadata)
+            })
+            .collect();
+
+        if !entries.is_empty() {
+            self.store_variables(&entries, env_name)?;
+        }
+        if !self.quiet {
+            let name = template
+                .to_possible_value()
+                .map(|value| value.get_name().to_string());
+            println!(
+                "{} {} {}",
+                format!("🧩 Added {} placeholder(s) from the", entries.len()).bright_green(),
+                name.unwrap_or_default().bright_cyan().bold(),
+                format!("template to '{}'", env_name).bright_green()
+            );
+        }
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigManager;
+
+    #[test]
+    fn test_apply_template_keeps_existing_values() {
+        let commands = EnvMatchCommands::with_config_manager(ConfigManager::in_memory());
+        commands.init_with_environment("staging").unwrap();
+        commands.set_variable("PORT", "4000", "staging").unwrap();
+
+        let added = commands
+            .apply_template(ProjectTemplate::Node, "staging")
+            .unwrap();
+        assert_eq!(added, 4);
+
+        let env_config = commands.config_manager.load_environment("staging").unwrap();
+        assert_eq!(env_config.variables["PORT"], "4000");
+        assert_eq!(env_config.variables["NODE_ENV"], "staging");
+        assert!(env_config.metadata["SESSION_SECRET"].secret);
+    }
+}
@@ -0,0 +1,155 @@
+use super::{ConfigManager, EnvConfig, StorageFormat};
+use crate::error::{EnvMatchError, Result};
+use std::path::{Path, PathBuf};
+
+/// Retired environments, kept out of the way under `.envMatch/archive/`.
+pub(super) const ARCHIVE_DIR: &str = "archive";
+
+impl ConfigManager {
+    /// Moves an environment into the archive. The current environment can't
+    /// be archived, and neither can one whose name is already archived.
+    pub fn archive_environment(&self, env_name: &str) -> Result<()> {
+        self.validate_environment_name(env_name)?;
+        if !self.environment_exists(env_name) {
+            return Err(self.environment_not_found(env_name));
+        }
+        if self.load_global_config()?.current_environment == env_name {
+            return Err(EnvMatchError::EnvironmentInUse {
+                name: env_name.to_string(),
+            });
+        }
+        if self.get_archived_path(env_name).is_some() {
+            return Err(EnvMatchError::EnvironmentExists {
+                name: env_name.to_string(),
+                archived: true,
+            });
+        }
+        let from = self.get_env_path(env_name);
+        self.move_file(&from, Path::new(ARCHIVE_DIR))
+    }
+
+    /// Moves an archived environment back next to the others.
+    pub fn unarchive_environment(&self, env_name: &str) -> Result<()> {
+        self.validate_environment_name(env_name)?;
+        let Some(from) = self.get_archived_path(env_name) else {
+            let archived = self.list_archived_environments()?;
+            return Err(EnvMatchError::environment_not_found(env_name, &archived));
+        };
+        if self.environment_exists(env_name) {
+            return Err(EnvMatchError::EnvironmentExists {
+                name: env_name.to_string(),
+                archived: false,
+            });
+        }
+        self.move_file(&from, &self.get_environments_dir())
+    }
+
+    /// Creates an empty environment, unless one by that name exists already,
+    /// archived or not.
+    pub fn create_environment(&self, env_name: &str) -> Result<()> {
+        if !self.is_initialized() {
+            return Err(EnvMatchError::NotInitialized);
+        }
+        self.validate_environment_name(env_name)?;
+        let archived = self.get_archived_path(env_name).is_some();
+        if archived || self.environment_exists(env_name) {
+            return Err(EnvMatchError::EnvironmentExists {
+                name: env_name.to_string(),
+                archived,
+            });
+        }
+        // Environments belong to the whole project, even from a scope
+        self.save_environment_in(
+            &self.get_environments_dir(),
+            env_name,
+            &EnvConfig::default(),
+        )
+    }
+
+    pub fn list_archived_environments(&self) -> Result<Vec<String>> {
+        let mut environments: Vec<String> = self
+            .storage
+            .list(&PathBuf::from(ARCHIVE_DIR))?
+            .iter()
+            .filter_map(|file_name| StorageFormat::strip_extension(file_name))
+            .map(|(env_name, _)| env_name.to_string())
+            .collect();
+        environments.sort();
+        environments.dedup();
+        Ok(environments)
+    }
+
+    /// Encrypts the values of archived environments, once encryption is on.
+    pub(super) fn encrypt_archive(&self) -> Result<()> {
+        for env_name in self.list_archived_environments()? {
+            let Some(path) = self.get_archived_path(&env_name) else {
+                continue;
+            };
+            let Some(content) = self.storage.read(&path)? else {
+                continue;
+            };
+            let format = StorageFormat::of_path(&path).unwrap_or_default();
+            let env_config: EnvConfig = format
+                .deserialize::<Option<EnvConfig>>(&path, &content)?
+                .unwrap_or_default();
+            let stored = self.encrypt_variables(&env_name, &env_config)?;
+            self.storage.write(&path, &format.serialize(&stored)?)?;
+        }
+        Ok(())
+    }
+
+    /// Moves a file into `dir` as is, so encrypted values, comments and
+    /// the file's format stay intact.
+    fn move_file(&self, from: &Path, dir: &Path) -> Result<()> {
+        let content = self.storage.read(from)?.unwrap_or_default();
+        self.storage
+            .write(&dir.join(from.file_name().unwrap_or_default()), &content)?;
+        self.storage.remove(from)
+    }
+
+    fn get_archived_path(&self, env_name: &str) -> Option<PathBuf> {
+        self.find_stored(Path::new(ARCHIVE_DIR), env_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_and_unarchive() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager
+            .initialize_with_environment("development")
+            .unwrap();
+        let mut env_config = EnvConfig::default();
+        env_config.set("OLD", "1");
+        config_manager
+            .save_environment("legacy", &env_config)
+            .unwrap();
+
+        assert!(matches!(
+            config_manager.archive_environment("development"),
+            Err(EnvMatchError::EnvironmentInUse { .. })
+        ));
+        config_manager.archive_environment("legacy").unwrap();
+        assert_eq!(
+            config_manager.list_environments().unwrap(),
+            vec!["development"]
+        );
+        assert_eq!(
+            config_manager.list_archived_environments().unwrap(),
+            vec!["legacy"]
+        );
+
+        config_manager.unarchive_environment("legacy").unwrap();
+        assert_eq!(
+            config_manager.load_environment("legacy").unwrap().variables["OLD"],
+            "1"
+        );
+        assert!(matches!(
+            config_manager.unarchive_environment("legacy"),
+            Err(EnvMatchError::EnvironmentNotFound { .. })
+        ));
+    }
+}
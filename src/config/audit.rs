@@ -0,0 +1,494 @@
+use super::history::current_user;
+use super::ConfigManager;
+use crate::crypto::{self, keyed_hash};
+use crate::error::{EnvMatchError, Result};
+use crate::storage::{FileBackend, StorageBackend};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const AUDIT_LOG: &str = "audit.log";
+/// The audit seal when there's no user config directory to keep it in, and
+/// the bare key earlier versions kept next to the log
+pub const AUDIT_KEY_FILE: &str = "audit.key";
+/// Directory of the user config holding each project's audit seal
+const AUDIT_SEALS_DIR: &str = "audit";
+
+type AuditKey = [u8; 32];
+
+/// One line of the audit log.
+///
+/// Entries are chained: `hash` covers the entry itself plus the hash of the
+/// previous entry, so editing or removing a line breaks every hash after it.
+/// Both `hash` and the value fingerprints are HMAC-SHA256 under the
+/// project's audit key, so without the key the chain can't be recomputed
+/// and guessed values can't be checked against the log.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub command: String,
+    pub env: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_hash: Option<String>,
+    pub prev_hash: String,
+    pub hash: String,
+    /// Old and new value, until `append_audit` fingerprints them
+    #[serde(skip)]
+    values: (Option<String>, Option<String>),
+}
+
+impl AuditEntry {
+    pub fn new(
+        command: &str,
+        env: &str,
+        key: Option<&str>,
+        old_value: Option<&str>,
+        new_value: Option<&str>,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            user: current_user(),
+            command: command.to_string(),
+            env: env.to_string(),
+            key: key.map(str::to_string),
+            old_hash: None,
+            new_hash: None,
+            prev_hash: String::new(),
+            hash: String::new(),
+            values: (old_value.map(str::to_string), new_value.map(str::to_string)),
+        }
+    }
+
+    fn compute_hash(&self, key: &AuditKey) -> Result<String> {
+        let unsealed = Self {
+            hash: String::new(),
+            ..self.clone()
+        };
+        Ok(keyed_hash(key, &serde_json::to_string(&unsealed)?))
+    }
+}
+
+/// What the audit log is checked against: its key, and how many entries it
+/// had and what the last one hashed to after the last append.
+///
+/// It's kept in the user config directory rather than next to the log, so
+/// someone able to rewrite `.envMatch` can neither re-seal a rewritten log
+/// without the key nor cut entries off its end unnoticed. A project without
+/// a user config directory (no home directory) keeps it in `.envMatch`,
+/// which only guards against whoever can't read that directory.
+#[derive(Serialize, Deserialize)]
+struct AuditSeal {
+    /// Project the seal belongs to, for whoever looks through the directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    project: Option<PathBuf>,
+    /// Base64 HMAC-SHA256 key
+    key: String,
+    /// Entries in the log after the last append
+    #[serde(default)]
+    entries: usize,
+    /// Hash of the last of them
+    #[serde(default)]
+    last_hash: String,
+}
+
+/// Hex-encoded SHA-256 of a value: what a keyed fingerprint must not match.
+#[cfg(test)]
+pub fn hash_value(value: &str) -> String {
+    use sha2::{Digest, Sha256};
+    hex(&Sha256::digest(value.as_bytes()))
+}
+
+pub(super) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl AuditSeal {
+    fn new(project: Option<PathBuf>) -> Self {
+        Self {
+            project,
+            key: STANDARD.encode(crypto::random_key()),
+            entries: 0,
+            last_hash: String::new(),
+        }
+    }
+
+    fn key(&self) -> Result<AuditKey> {
+        STANDARD
+            .decode(self.key.trim())
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(|| EnvMatchError::EncryptionError {
+                message: "the audit key is not valid".to_string(),
+            })
+    }
+}
+
+impl ConfigManager {
+    /// Chains and appends entries to the audit log, then records the new
+    /// length and last hash in the seal.
+    pub fn append_audit(&self, entries: Vec<AuditEntry>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let log = self.read_audit_log()?;
+        let mut prev_hash = log
+            .last()
+            .map(|entry| entry.hash.clone())
+            .unwrap_or_default();
+
+        let mut seal = self.audit_seal()?;
+        let key = seal.key()?;
+        let count = entries.len();
+        let mut lines = String::new();
+        for mut entry in entries {
+            let (old_value, new_value) = std::mem::take(&mut entry.values);
+            entry.old_hash = old_value.map(|value| keyed_hash(&key, &value));
+            entry.new_hash = new_value.map(|value| keyed_hash(&key, &value));
+            entry.prev_hash = prev_hash;
+            entry.hash = entry.compute_hash(&key)?;
+            prev_hash = entry.hash.clone();
+            lines.push_str(&serde_json::to_string(&entry)?);
+            lines.push('\n');
+        }
+
+        self.storage.append(&self.get_audit_log_path(), &lines)?;
+        seal.entries = log.len() + count;
+        seal.last_hash = prev_hash;
+        self.write_audit_seal(&seal)
+    }
+
+    /// A fingerprint of `value` for display, the same one the audit log
+    /// records: it shows when a value changed, but can't be checked against
+    /// guesses without the project's audit key.
+    pub fn fingerprint(&self, value: &str) -> Result<String> {
+        Ok(keyed_hash(&self.fingerprint_key()?, value))
+    }
+
+    /// The audit key, created if the project has none yet, for fingerprints
+    /// computed outside the config (remote versions).
+    pub fn fingerprint_key(&self) -> Result<[u8; 32]> {
+        if !self.is_initialized() {
+            return Err(EnvMatchError::NotInitialized);
+        }
+        self.audit_seal()?.key()
+    }
+
+    pub fn read_audit_log(&self) -> Result<Vec<AuditEntry>> {
+        let Some(content) = self.storage.read(&self.get_audit_log_path())? else {
+            return Ok(vec![]);
+        };
+
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    /// Checks the hash chain, failing on the first entry that doesn't match,
+    /// and that the log still has every entry the seal counted. A log whose
+    /// key is gone can't be checked at all, so it fails on its first entry.
+    pub fn verify_audit_log(&self) -> Result<usize> {
+        let entries = self.read_audit_log()?;
+        let seal = self.load_audit_seal()?;
+        if entries.is_empty() && seal.as_ref().is_none_or(|seal| seal.entries == 0) {
+            return Ok(0);
+        }
+        let seal = seal.ok_or(EnvMatchError::AuditLogTampered { line: 1 })?;
+        let key = seal.key()?;
+        let mut prev_hash = String::new();
+
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.prev_hash != prev_hash || entry.hash != entry.compute_hash(&key)? {
+                return Err(EnvMatchError::AuditLogTampered { line: index + 1 });
+            }
+            prev_hash = entry.hash.clone();
+        }
+        // Entries past the seal's count are fine: the chain covers them, and
+        // envMatch may have stopped between appending and sealing
+        if entries.len() < seal.entries {
+            return Err(EnvMatchError::AuditLogTruncated {
+                expected: seal.entries,
+                found: entries.len(),
+            });
+        }
+        if seal.entries > 0 && entries[seal.entries - 1].hash != seal.last_hash {
+            return Err(EnvMatchError::AuditLogTampered { line: seal.entries });
+        }
+
+        Ok(entries.len())
+    }
+
+    fn get_audit_log_path(&self) -> PathBuf {
+        PathBuf::from(AUDIT_LOG)
+    }
+
+    /// The project's seal, with a fresh random key if it has none yet.
+    fn audit_seal(&self) -> Result<AuditSeal> {
+        if let Some(seal) = self.load_audit_seal()? {
+            return Ok(seal);
+        }
+        let seal = AuditSeal::new(self.canonical_project_dir());
+        self.write_audit_seal(&seal)?;
+        Ok(seal)
+    }
+
+    fn load_audit_seal(&self) -> Result<Option<AuditSeal>> {
+        let Some((store, name)) = self.audit_seal_location() else {
+            return self.read_project_seal();
+        };
+        if let Some(content) = store.read(&name)? {
+            return Ok(Some(self.parse_yaml(&name, &content)?));
+        }
+        // Kept next to the log by earlier versions: move it out
+        let Some(seal) = self.read_project_seal()? else {
+            return Ok(None);
+        };
+        store.write(&name, &serde_yaml::to_string(&seal)?)?;
+        self.storage.remove(Path::new(AUDIT_KEY_FILE))?;
+        Ok(Some(seal))
+    }
+
+    /// The seal kept in the project when there's no user config directory,
+    /// or the bare key earlier versions kept there. The log is taken as it
+    /// is now for the latter, having had nothing to check it against.
+    fn read_project_seal(&self) -> Result<Option<AuditSeal>> {
+        let Some(content) = self.storage.read(Path::new(AUDIT_KEY_FILE))? else {
+            return Ok(None);
+        };
+        if let Ok(seal) = serde_yaml::from_str::<AuditSeal>(&content) {
+            return Ok(Some(seal));
+        }
+        let log = self.read_audit_log()?;
+        Ok(Some(AuditSeal {
+            project: self.canonical_project_dir(),
+            key: content.trim().to_string(),
+            entries: log.len(),
+            last_hash: log
+                .last()
+                .map(|entry| entry.hash.clone())
+                .unwrap_or_default(),
+        }))
+    }
+
+    fn write_audit_seal(&self, seal: &AuditSeal) -> Result<()> {
+        let content = serde_yaml::to_string(seal)?;
+        match self.audit_seal_location() {
+            Some((store, name)) => store.write(&name, &content),
+            None => self.storage.write(Path::new(AUDIT_KEY_FILE), &content),
+        }
+    }
+
+    /// `audit/<hash of the project path>.yaml` in the user config
+    /// directory, written owner-only like everything under `.envMatch`.
+    /// `None` without a user config directory or a project on disk.
+    fn audit_seal_location(&self) -> Option<(FileBackend, PathBuf)> {
+        use sha2::{Digest, Sha256};
+
+        let dir = self
+            .user_config_path
+            .as_ref()?
+            .parent()?
+            .join(AUDIT_SEALS_DIR);
+        let project = self.canonical_project_dir()?;
+        let digest = Sha256::digest(project.to_string_lossy().as_bytes());
+        let name = format!("{}.yaml", &hex(&digest)[..32]);
+        Some((FileBackend::new(dir), PathBuf::from(name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_chain_detects_tampering() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+
+        config_manager
+            .append_audit(vec![
+                AuditEntry::new("set", "development", Some("API_KEY"), None, Some("one")),
+                AuditEntry::new(
+                    "set",
+                    "development",
+                    Some("API_KEY"),
+                    Some("one"),
+                    Some("two"),
+                ),
+            ])
+            .unwrap();
+        config_manager
+            .append_audit(vec![AuditEntry::new(
+                "switch",
+                "production",
+                None,
+                None,
+                None,
+            )])
+            .unwrap();
+
+        let entries = config_manager.read_audit_log().unwrap();
+        assert_eq!(entries.len(), 3);
+        let key = config_manager.audit_seal().unwrap().key().unwrap();
+        assert_eq!(entries[1].old_hash, Some(keyed_hash(&key, "one")));
+        assert_ne!(entries[1].old_hash, Some(hash_value("one")));
+        assert_eq!(entries[2].prev_hash, entries[1].hash);
+        assert_eq!(config_manager.verify_audit_log().unwrap(), 3);
+
+        let path = config_manager.get_audit_log_path();
+        let tampered = config_manager
+            .storage
+            .read(&path)
+            .unwrap()
+            .unwrap()
+            .replace("production", "staging");
+        config_manager.storage.write(&path, &tampered).unwrap();
+        assert!(matches!(
+            config_manager.verify_audit_log(),
+            Err(EnvMatchError::AuditLogTampered { line: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_audit_chain_rejects_forged_plain_hashes() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        config_manager
+            .append_audit(vec![
+                AuditEntry::new("set", "production", Some("TOKEN"), None, Some("one")),
+                AuditEntry::new("switch", "production", None, None, None),
+            ])
+            .unwrap();
+
+        // Rewrite the first entry and recompute the whole chain the way an
+        // unkeyed log would be sealed
+        let mut prev_hash = String::new();
+        let mut forged = String::new();
+        for mut entry in config_manager.read_audit_log().unwrap() {
+            entry.env = "staging".to_string();
+            entry.new_hash = entry.new_hash.map(|_| hash_value("two"));
+            entry.prev_hash = prev_hash;
+            entry.hash = String::new();
+            entry.hash = hash_value(&serde_json::to_string(&entry).unwrap());
+            prev_hash = entry.hash.clone();
+            forged.push_str(&serde_json::to_string(&entry).unwrap());
+            forged.push('\n');
+        }
+        let path = config_manager.get_audit_log_path();
+        config_manager.storage.write(&path, &forged).unwrap();
+
+        assert!(matches!(
+            config_manager.verify_audit_log(),
+            Err(EnvMatchError::AuditLogTampered { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_audit_log_truncation_is_detected() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        for command in ["init", "set", "switch"] {
+            config_manager
+                .append_audit(vec![AuditEntry::new(
+                    command,
+                    "development",
+                    None,
+                    None,
+                    None,
+                )])
+                .unwrap();
+        }
+        assert_eq!(config_manager.verify_audit_log().unwrap(), 3);
+
+        // Dropping the last entry leaves a chain that checks out on its own
+        let path = config_manager.get_audit_log_path();
+        let content = config_manager.storage.read(&path).unwrap().unwrap();
+        let kept: Vec<_> = content.lines().take(2).collect();
+        config_manager
+            .storage
+            .write(&path, &format!("{}\n", kept.join("\n")))
+            .unwrap();
+        assert!(matches!(
+            config_manager.verify_audit_log(),
+            Err(EnvMatchError::AuditLogTruncated {
+                expected: 3,
+                found: 2
+            })
+        ));
+
+        config_manager.storage.remove(&path).unwrap();
+        assert!(matches!(
+            config_manager.verify_audit_log(),
+            Err(EnvMatchError::AuditLogTruncated { found: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_audit_seal_is_kept_outside_the_project() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("app");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        let config_manager = ConfigManager {
+            user_config_path: Some(temp_dir.path().join("envmatch").join("config.yaml")),
+            ..ConfigManager::with_project_dir(project_dir.clone())
+        };
+        config_manager.initialize().unwrap();
+
+        // A bare key next to the log, as earlier versions kept it
+        let key = crypto::random_key();
+        config_manager
+            .storage
+            .write(Path::new(AUDIT_KEY_FILE), &STANDARD.encode(key))
+            .unwrap();
+        config_manager
+            .append_audit(vec![AuditEntry::new(
+                "set",
+                "development",
+                None,
+                None,
+                None,
+            )])
+            .unwrap();
+
+        assert!(!config_manager.storage.exists(Path::new(AUDIT_KEY_FILE)));
+        let seals: Vec<_> = std::fs::read_dir(temp_dir.path().join("envmatch").join("audit"))
+            .unwrap()
+            .collect();
+        assert_eq!(seals.len(), 1);
+        assert_eq!(config_manager.audit_seal().unwrap().key().unwrap(), key);
+        assert_eq!(config_manager.verify_audit_log().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_audit_log_without_its_key_fails_verification() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        config_manager
+            .append_audit(vec![AuditEntry::new(
+                "init",
+                "development",
+                None,
+                None,
+                None,
+            )])
+            .unwrap();
+        assert_eq!(config_manager.verify_audit_log().unwrap(), 1);
+
+        config_manager
+            .storage
+            .remove(&PathBuf::from(AUDIT_KEY_FILE))
+            .unwrap();
+        assert!(matches!(
+            config_manager.verify_audit_log(),
+            Err(EnvMatchError::AuditLogTampered { line: 1 })
+        ));
+    }
+}
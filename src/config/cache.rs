@@ -0,0 +1,150 @@
+use super::ConfigManager;
+use crate::error::Result;
+use crate::storage::FileStamp;
+use std::any::Any;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// What was parsed from each file, along with the file's stamp at the time.
+/// An entry is only used while the stamp stays the same.
+#[derive(Debug, Default)]
+pub(super) struct FileCache {
+    entries: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    stamp: FileStamp,
+    value: Arc<dyn Any + Send + Sync>,
+}
+
+impl FileCache {
+    fn get<T: Clone + 'static>(&self, path: &Path, stamp: FileStamp) -> Option<T> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(path).filter(|entry| entry.stamp == stamp)?;
+        entry.value.downcast_ref::<T>().cloned()
+    }
+
+    fn insert<T: Send + Sync + 'static>(&self, path: &Path, stamp: FileStamp, value: T) {
+        self.entries.lock().unwrap().insert(
+            path.to_path_buf(),
+            CacheEntry {
+                stamp,
+                value: Arc::new(value),
+            },
+        );
+    }
+
+    pub(super) fn invalidate(&self, path: &Path) {
+        self.entries.lock().unwrap().remove(path);
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+impl ConfigManager {
+    /// Returns what `load` parses from `path`, reusing the last result while
+    /// the file hasn't changed. Failures aren't cached.
+    pub(super) fn cached<T>(&self, path: &Path, load: impl FnOnce() -> Result<T>) -> Result<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let Some(stamp) = self.storage.stamp(path) else {
+            return load();
+        };
+        if let Some(value) = self.cache.get(path, stamp) {
+            return Ok(value);
+        }
+        let value = load()?;
+        self.cache.insert(path, stamp, value.clone());
+        Ok(value)
+    }
+
+    /// Forgets everything parsed so far, e.g. when the file watcher reports
+    /// changes made by another process.
+    pub fn invalidate_cache(&self) {
+        self.cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EnvConfig;
+
+    #[test]
+    fn test_cache_follows_file_changes() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        let path = config_manager.get_env_path("development");
+        let loads = std::cell::Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            Ok(loads.get())
+        };
+
+        assert_eq!(config_manager.cached(&path, load).unwrap(), 1);
+        assert_eq!(config_manager.cached(&path, load).unwrap(), 1);
+
+        let mut env_config = EnvConfig::default();
+        env_config.set("PORT", "80");
+        config_manager
+            .save_environment("development", &env_config)
+            .unwrap();
+        assert_eq!(config_manager.cached(&path, load).unwrap(), 2);
+        assert_eq!(
+            config_manager.load_environment("development").unwrap(),
+            env_config
+        );
+
+        config_manager.invalidate_cache();
+        assert_eq!(config_manager.cached(&path, load).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_cache_sees_writes_within_one_clock_tick() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config_manager = ConfigManager::with_project_dir(temp_dir.path().to_path_buf());
+        config_manager.initialize().unwrap();
+        let path = config_manager.get_env_path("development");
+        let file = temp_dir.path().join(".envMatch").join(&path);
+        let contents = |value: &str| {
+            let mut env_config = EnvConfig::default();
+            env_config.set("PORT", value);
+            config_manager
+                .save_environment("development", &env_config)
+                .unwrap();
+            std::fs::read_to_string(&file).unwrap()
+        };
+        let (first, second) = (contents("8080"), contents("9090"));
+        assert_eq!(first.len(), second.len());
+
+        // Another process writes twice in place, both times within the same
+        // tick of a coarse clock, keeping the size
+        let tick =
+            std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let write = |contents: &str| {
+            std::fs::write(&file, contents).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&file)
+                .unwrap()
+                .set_modified(tick)
+                .unwrap();
+        };
+        write(&first);
+        let port = || {
+            config_manager
+                .load_environment("development")
+                .unwrap()
+                .variables["PORT"]
+                .clone()
+        };
+        assert_eq!(port(), "8080");
+        write(&second);
+        assert_eq!(port(), "9090");
+    }
+}
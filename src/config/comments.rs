@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+/// Comments written by hand in a YAML file, so they survive when the file is
+/// regenerated from its parsed contents.
+#[derive(Default)]
+struct Comments {
+    /// Comment and blank lines above a key, by the path of keys leading to it
+    leading: HashMap<Vec<String>, Vec<String>>,
+    /// Comments at the end of a key's line
+    trailing: HashMap<Vec<String>, String>,
+    /// Comment and blank lines after the last key
+    footer: Vec<String>,
+}
+
+/// Copies the comments in `old` onto the matching keys of `new`, a fresh
+/// serialization of the same document. Comments on keys that no longer
+/// exist are dropped.
+pub(super) fn carry_over(old: &str, new: &str) -> String {
+    let comments = Comments::parse(old);
+    if comments.leading.is_empty() && comments.trailing.is_empty() && comments.footer.is_empty() {
+        return new.to_string();
+    }
+
+    let mut output = String::new();
+    for line in Lines::new(new) {
+        match line {
+            Line::Key { path, text } => {
+                for comment in comments.leading.get(&path).into_iter().flatten() {
+                    output.push_str(comment);
+                    output.push('\n');
+                }
+                output.push_str(text);
+                if let Some(comment) = comments.trailing.get(&path) {
+                    output.push(' ');
+                    output.push_str(comment);
+                }
+            }
+            Line::Comment(text) | Line::Other(text) => output.push_str(text),
+        }
+        output.push('\n');
+    }
+    for comment in &comments.footer {
+        output.push_str(comment);
+        output.push('\n');
+    }
+    output
+}
+
+impl Comments {
+    fn parse(content: &str) -> Self {
+        let mut comments = Self::default();
+        let mut pending = Vec::new();
+        for line in Lines::new(content) {
+            match line {
+                Line::Comment(text) => pending.push(text.to_string()),
+                Line::Key { path, text } => {
+                    if let Some(comment) = trailing_comment(text) {
+                        comments.trailing.insert(path.clone(), comment.to_string());
+                    }
+                    let lines = std::mem::take(&mut pending);
+                    if lines.iter().any(|line| !line.trim().is_empty()) {
+                        comments.leading.insert(path, lines);
+                    }
+                }
+                Line::Other(_) => pending.clear(),
+            }
+        }
+        if pending.iter().any(|line| !line.trim().is_empty()) {
+            comments.footer = pending;
+        }
+        comments
+    }
+}
+
+enum Line<'a> {
+    /// A mapping key, with the keys of the mappings it's nested in
+    Key { path: Vec<String>, text: &'a str },
+    /// A comment or blank line outside of any value
+    Comment(&'a str),
+    /// Anything else: list items, block scalar contents...
+    Other(&'a str),
+}
+
+/// Walks a YAML document line by line, tracking which key each line is under.
+struct Lines<'a> {
+    lines: std::str::Lines<'a>,
+    /// Indentation and name of the keys enclosing the current line
+    stack: Vec<(usize, String)>,
+    /// Indentation of a key whose value is a block scalar (`|` or `>`)
+    block_scalar: Option<usize>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(content: &'a str) -> Self {
+        Self {
+            lines: content.lines(),
+            stack: Vec::new(),
+            block_scalar: None,
+        }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Line<'a>;
+
+    fn next(&mut self) -> Option<Line<'a>> {
+        let text = self.lines.next()?;
+        let trimmed = text.trim_start();
+        let indent = text.len() - trimmed.len();
+
+        if let Some(key_indent) = self.block_scalar {
+            if trimmed.is_empty() || indent > key_indent {
+                return Some(Line::Other(text));
+            }
+            self.block_scalar = None;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            return Some(Line::Comment(text));
+        }
+        let Some((key, value)) = split_key(trimmed) else {
+            return Some(Line::Other(text));
+        };
+
+        while self.stack.last().is_some_and(|(level, _)| *level >= indent) {
+            self.stack.pop();
+        }
+        self.stack.push((indent, key));
+        let value = value.trim_start();
+        if value.starts_with('|') || value.starts_with('>') {
+            self.block_scalar = Some(indent);
+        }
+        Some(Line::Key {
+            path: self.stack.iter().map(|(_, key)| key.clone()).collect(),
+            text,
+        })
+    }
+}
+
+/// Splits `KEY: value` into the unquoted key and the rest of the line.
+fn split_key(line: &str) -> Option<(String, &str)> {
+    if line.starts_with('-') {
+        return None;
+    }
+    let (key, rest) = match line.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = line[1..].find(quote)? + 1;
+            (line[1..end].to_string(), line[end + 1..].strip_prefix(':')?)
+        }
+        _ => {
+            let end = line
+                .find(": ")
+                .or_else(|| line.strip_suffix(':').map(str::len))?;
+            (line[..end].to_string(), &line[end + 1..])
+        }
+    };
+    Some((key, rest))
+}
+
+/// The ` # comment` at the end of a line, ignoring `#` inside quoted values.
+fn trailing_comment(line: &str) -> Option<&str> {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => return Some(&line[index..]),
+            None => {}
+        }
+        previous = c;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_carry_over_comments() {
+        let old = "\
+# Shared by the whole team
+variables:
+  # Where the API lives
+  API_URL: https://example.com  # staging uses its own
+  NOTE: 'not # a comment'
+  REMOVED: gone # dropped with its key
+  BODY: |-
+    # part of the value
+    second line
+metadata:
+  API_URL:
+    # Tags are free-form
+    tags:
+    - api
+# The end
+";
+        let new = "\
+variables:
+  API_URL: https://example.org
+  NOTE: 'not # a comment'
+  BODY: |-
+    # part of the value
+    second line
+  NEW: value
+metadata:
+  API_URL:
+    tags:
+    - api
+";
+        assert_eq!(
+            carry_over(old, new),
+            "\
+# Shared by the whole team
+variables:
+  # Where the API lives
+  API_URL: https://example.org # staging uses its own
+  NOTE: 'not # a comment'
+  BODY: |-
+    # part of the value
+    second line
+  NEW: value
+metadata:
+  API_URL:
+    # Tags are free-form
+    tags:
+    - api
+# The end
+"
+        );
+        assert_eq!(carry_over("variables: {}\n", new), new);
+    }
+}
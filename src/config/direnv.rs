@@ -0,0 +1,40 @@
+use super::{ConfigManager, ENV_MATCH_DIR};
+use crate::error::Result;
+use chrono::Utc;
+use std::path::PathBuf;
+
+/// File under `.envMatch` rewritten whenever variables or the current
+/// environment change, for direnv's `watch_file` to notice.
+pub(super) const WATCH_STAMP_FILE: &str = "direnv.stamp";
+
+impl ConfigManager {
+    /// Starts keeping the watch stamp up to date.
+    pub fn create_watch_stamp(&self) -> Result<()> {
+        self.write_watch_stamp()
+    }
+
+    /// The watch stamp, relative to the project root, as `.envrc` refers to it.
+    pub fn watch_stamp_path(&self) -> PathBuf {
+        PathBuf::from(ENV_MATCH_DIR).join(WATCH_STAMP_FILE)
+    }
+
+    /// Whether direnv is set up to watch the stamp.
+    pub fn uses_watch_stamp(&self) -> bool {
+        self.storage.exists(&PathBuf::from(WATCH_STAMP_FILE))
+    }
+
+    /// Rewrites the watch stamp if the project uses one.
+    pub(super) fn touch_watch_stamp(&self) -> Result<()> {
+        if self.uses_watch_stamp() {
+            self.write_watch_stamp()?;
+        }
+        Ok(())
+    }
+
+    fn write_watch_stamp(&self) -> Result<()> {
+        self.storage.write(
+            &PathBuf::from(WATCH_STAMP_FILE),
+            &format!("{}\n", Utc::now().to_rfc3339()),
+        )
+    }
+}
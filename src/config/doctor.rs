@@ -0,0 +1,319 @@
+use super::encryption::ENCRYPTION_FILE;
+use super::history::StoredSnapshot;
+use super::history::HISTORY_DIR;
+use super::schema::{Schema, SCHEMA_FILE};
+use super::sync_state::SYNC_DIR;
+use super::{ConfigManager, EnvConfig, GlobalConfig, StorageFormat, SyncState};
+use super::{CONFIG_FILE, ENVIRONMENTS_DIR};
+use crate::error::Result;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// One result of `envMatch doctor`, with how to fix it when it's a problem.
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    pub fix: Option<String>,
+}
+
+impl Finding {
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Ok,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    pub fn error(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+impl ConfigManager {
+    /// Checks the project's files and state for problems.
+    ///
+    /// Works on the stored files directly, so it never needs the passphrase
+    /// and keeps going past the problems it finds.
+    pub fn diagnose(&self) -> Vec<Finding> {
+        if !self.is_initialized() {
+            return vec![Finding::error(
+                "envMatch is not initialized here",
+                "Run 'envMatch init', or pass --dir to point at an existing project",
+            )];
+        }
+        let mut findings = vec![Finding::ok("Project initialized")];
+
+        let files = self.project_files();
+        let problems: Vec<Finding> = files
+            .iter()
+            .filter_map(|path| self.check_file(path))
+            .collect();
+        if problems.is_empty() {
+            findings.push(Finding::ok(format!("All {} files parse", files.len())));
+        }
+        findings.extend(problems);
+
+        let environments = self.list_environments().unwrap_or_default();
+        if let Ok(config) = self.load_global_config() {
+            if environments.contains(&config.current_environment) {
+                findings.push(Finding::ok(format!(
+                    "Current environment '{}' exists",
+                    config.current_environment
+                )));
+            } else {
+                findings.push(Finding::error(
+                    format!(
+                        "Current environment '{}' doesn't exist",
+                        config.current_environment
+                    ),
+                    format!(
+                        "Run 'envMatch switch <env>' with one of: {}",
+                        environments.join(", ")
+                    ),
+                ));
+            }
+        }
+
+        findings.extend(self.check_schema(&environments));
+
+        #[cfg(unix)]
+        findings.push(match self.loose_permissions().len() {
+            0 => Finding::ok("Only the owner can read .envMatch"),
+            count => Finding::warning(
+                format!(
+                    "{} path(s) under .envMatch can be read by other users",
+                    count
+                ),
+                "Run 'envMatch harden'",
+            ),
+        });
+
+        match self.verify_audit_log() {
+            Ok(_) => findings.push(Finding::ok("Audit log intact")),
+            Err(error) => findings.push(Finding::warning(
+                error.to_string(),
+                "Inspect .envMatch/audit.log; the entries from that line on can't be trusted",
+            )),
+        }
+
+        findings
+    }
+
+    /// Remotes that environments have been synced with, as `(env, remote)` pairs.
+    pub fn list_sync_remotes(&self) -> Result<Vec<(String, String)>> {
+        let mut remotes = Vec::new();
+        for env_name in self.list_environments()? {
+            if let Ok(states) = self.load_sync_states(&env_name) {
+                for remote in states.into_keys() {
+                    remotes.push((env_name.clone(), remote));
+                }
+            }
+        }
+        Ok(remotes)
+    }
+
+    fn project_files(&self) -> Vec<PathBuf> {
+        let mut files = vec![PathBuf::from(CONFIG_FILE)];
+        for file in [SCHEMA_FILE, ENCRYPTION_FILE] {
+            if self.storage.exists(Path::new(file)) {
+                files.push(PathBuf::from(file));
+            }
+        }
+        for dir in [ENVIRONMENTS_DIR, SYNC_DIR, HISTORY_DIR] {
+            for name in self.storage.list(Path::new(dir)).unwrap_or_default() {
+                // Only environments can be stored in other formats
+                let format = StorageFormat::strip_extension(&name).map(|(_, format)| format);
+                if format == Some(StorageFormat::Yaml)
+                    || (dir == ENVIRONMENTS_DIR && format.is_some())
+                {
+                    files.push(Path::new(dir).join(name));
+                }
+            }
+        }
+        files
+    }
+
+    /// Reads and parses one file, returning what's wrong with it, if anything.
+    fn check_file(&self, path: &Path) -> Option<Finding> {
+        let shown = Path::new(".envMatch").join(path).display().to_string();
+        let content = match self.storage.read(path) {
+            Ok(Some(content)) => content,
+            Ok(None) => return None,
+            Err(error) => {
+                return Some(Finding::error(
+                    format!("Can't read {}: {}", shown, error),
+                    format!("Check its permissions, e.g. 'chmod u+rw {}'", shown),
+                ))
+            }
+        };
+
+        let format = StorageFormat::of_path(path).unwrap_or_default();
+        if format != StorageFormat::Yaml {
+            return format
+                .deserialize::<Option<EnvConfig>>(path, &content)
+                .err()
+                .map(|error| {
+                    Finding::error(error.to_string(), format!("Fix the syntax in {}", shown))
+                });
+        }
+
+        // A generic parse catches syntax errors and duplicate keys, which
+        // typed parsing would silently resolve by keeping the last one
+        if let Err(error) = serde_yaml::from_str::<serde_yaml::Value>(&content) {
+            let fix = if error.to_string().contains("duplicate entry") {
+                "Remove one of the duplicate keys".to_string()
+            } else {
+                format!("Fix the YAML syntax in {}", shown)
+            };
+            return Some(Finding::error(format!("{}: {}", shown, error), fix));
+        }
+
+        let dir = path.parent().and_then(Path::to_str).unwrap_or_default();
+        let file = path.to_str().unwrap_or_default();
+        let typed = match (dir, file) {
+            (ENVIRONMENTS_DIR, _) => parses::<EnvConfig>(&content),
+            (SYNC_DIR, _) => parses::<BTreeMap<String, SyncState>>(&content),
+            (HISTORY_DIR, _) => parses::<StoredSnapshot>(&content),
+            (_, CONFIG_FILE) => parses::<GlobalConfig>(&content),
+            (_, SCHEMA_FILE) => parses::<Option<Schema>>(&content),
+            _ => Ok(()),
+        };
+        typed.err().map(|error| {
+            Finding::error(
+                format!("{} has an unexpected structure: {}", shown, error),
+                format!(
+                    "Fix {} by hand or restore it with 'envMatch rollback'",
+                    shown
+                ),
+            )
+        })
+    }
+
+    /// Compares each environment's keys with the schema, if there is one.
+    fn check_schema(&self, environments: &[String]) -> Vec<Finding> {
+        let Ok(schema) = self.load_schema() else {
+            return vec![];
+        };
+        if schema.variables.is_empty() {
+            return vec![];
+        }
+
+        let mut findings = Vec::new();
+        for env_name in environments {
+            // Keys are never encrypted, so the raw file is enough
+            let path = self.get_env_path(env_name);
+            let format = StorageFormat::of_path(&path).unwrap_or_default();
+            let Some(keys) = self
+                .storage
+                .read(&path)
+                .ok()
+                .flatten()
+                .and_then(|content| format.deserialize::<EnvConfig>(&path, &content).ok())
+                .map(|env_config| env_config.variables.into_keys().collect::<BTreeSet<_>>())
+            else {
+                continue;
+            };
+
+            let missing: Vec<&str> = schema
+                .variables
+                .iter()
+                .filter(|(key, variable)| variable.required && !keys.contains(*key))
+                .map(|(key, _)| key.as_str())
+                .collect();
+            let undeclared: Vec<&str> = keys
+                .iter()
+                .filter(|key| !schema.variables.contains_key(*key))
+                .map(String::as_str)
+                .collect();
+
+            if !missing.is_empty() {
+                findings.push(Finding::error(
+                    format!(
+                        "'{}' is missing required variables: {}",
+                        env_name,
+                        missing.join(", ")
+                    ),
+                    format!("Run 'envMatch set {}=<value> -e {}'", missing[0], env_name),
+                ));
+            }
+            if !undeclared.is_empty() {
+                findings.push(Finding::warning(
+                    format!(
+                        "'{}' has variables not in schema.yaml: {}",
+                        env_name,
+                        undeclared.join(", ")
+                    ),
+                    "Declare them in .envMatch/schema.yaml, or run 'envMatch git-init' to add them",
+                ));
+            }
+            if missing.is_empty() && undeclared.is_empty() {
+                findings.push(Finding::ok(format!("'{}' matches the schema", env_name)));
+            }
+        }
+        findings
+    }
+}
+
+fn parses<T: DeserializeOwned>(content: &str) -> std::result::Result<(), serde_yaml::Error> {
+    serde_yaml::from_str::<T>(content).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(config_manager: &ConfigManager) -> Vec<Finding> {
+        config_manager
+            .diagnose()
+            .into_iter()
+            .filter(|finding| finding.severity != Severity::Ok)
+            .collect()
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let config_manager = ConfigManager::in_memory();
+        assert_eq!(problems(&config_manager).len(), 1);
+
+        config_manager.initialize().unwrap();
+        assert!(problems(&config_manager).is_empty());
+
+        config_manager
+            .storage
+            .write(
+                Path::new("environments/staging.yaml"),
+                "variables:\n  A: '1'\n  A: '2'\n",
+            )
+            .unwrap();
+        let mut config = config_manager.load_global_config().unwrap();
+        config.current_environment = "production".to_string();
+        config_manager.save_global_config(&config).unwrap();
+
+        let found = problems(&config_manager);
+        assert_eq!(found.len(), 2);
+        assert!(found[0].message.contains("duplicate entry"));
+        assert!(found[1].message.contains("'production' doesn't exist"));
+    }
+}
@@ -0,0 +1,304 @@
+use super::{ConfigManager, EnvConfig, Profile};
+use crate::crypto::{self, keychain, Cipher, MasterKey};
+use crate::error::{EnvMatchError, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub(super) const ENCRYPTION_FILE: &str = "encryption.yaml";
+const CHECK_NAME: &str = "envmatch-check";
+const CHECK_VALUE: &str = "envmatch";
+
+/// Committed alongside the environments so every clone can derive the same key.
+#[derive(Serialize, Deserialize, Debug)]
+struct EncryptionConfig {
+    salt: String,
+    /// A known value encrypted with the key, to reject a wrong passphrase up front
+    check: String,
+}
+
+impl ConfigManager {
+    pub fn is_encrypted(&self) -> bool {
+        self.storage.exists(&self.get_encryption_path())
+    }
+
+    /// Encrypts every environment, archived ones and the overrides of every
+    /// scope included, with a key derived from `passphrase`. Returns how
+    /// many active ones there were.
+    pub fn enable_encryption(&self, passphrase: &str) -> Result<usize> {
+        if self.is_encrypted() {
+            return Err(EnvMatchError::EncryptionError {
+                message: "encryption is already enabled".to_string(),
+            });
+        }
+
+        // The project's own files, whatever scope this works on
+        let mut environments = Vec::new();
+        for env_name in self.list_environments()? {
+            let env_config = self.read_environment(&env_name, &self.get_env_path(&env_name))?;
+            environments.push((env_name, env_config));
+        }
+
+        let salt = crypto::random_salt();
+        let cipher = Cipher::derive(passphrase, &salt)?;
+        let config = EncryptionConfig {
+            salt: STANDARD.encode(salt),
+            check: cipher.encrypt(&[], CHECK_NAME, CHECK_VALUE),
+        };
+        self.storage.write(
+            &self.get_encryption_path(),
+            &serde_yaml::to_string(&config)?,
+        )?;
+        let _ = self.cipher.set(cipher);
+
+        for (env_name, env_config) in &environments {
+            self.save_environment_in(&self.get_environments_dir(), env_name, env_config)?;
+        }
+        self.encrypt_archive()?;
+        self.encrypt_history()?;
+        self.encrypt_sync_states()?;
+        self.encrypt_scopes()?;
+        Ok(environments.len())
+    }
+
+    /// Derives the key from `passphrase`, failing if it's the wrong one.
+    pub fn unlock(&self, passphrase: &str) -> Result<()> {
+        let master_key = self.derive_master_key(passphrase)?;
+        let _ = self.cipher.set(Cipher::from_master_key(&master_key));
+        Ok(())
+    }
+
+    /// Stores the key derived from `passphrase` in the OS keychain, so the
+    /// passphrase isn't needed again on this machine.
+    pub fn remember_key(&self, passphrase: &str) -> Result<()> {
+        let master_key = self.derive_master_key(passphrase)?;
+        keychain::store_key(&self.keychain_account()?, &master_key)
+    }
+
+    /// Removes this project's key from the OS keychain, returning whether it was there.
+    pub fn forget_key(&self) -> Result<bool> {
+        keychain::forget_key(&self.keychain_account()?)
+    }
+
+    fn load_encryption_config(&self) -> Result<EncryptionConfig> {
+        let path = self.get_encryption_path();
+        let content = self
+            .storage
+            .read(&path)?
+            .ok_or_else(|| EnvMatchError::EncryptionError {
+                message: "encryption is not enabled".to_string(),
+            })?;
+        self.parse_yaml(&path, &content)
+    }
+
+    fn derive_master_key(&self, passphrase: &str) -> Result<MasterKey> {
+        let config = self.load_encryption_config()?;
+        let salt =
+            STANDARD
+                .decode(&config.salt)
+                .map_err(|error| EnvMatchError::EncryptionError {
+                    message: format!("invalid salt: {}", error),
+                })?;
+
+        let master_key = crypto::derive_master_key(passphrase, &salt)?;
+        if !Self::key_matches(&master_key, &config) {
+            return Err(EnvMatchError::WrongPassphrase);
+        }
+        Ok(master_key)
+    }
+
+    fn key_matches(master_key: &MasterKey, config: &EncryptionConfig) -> bool {
+        Cipher::from_master_key(master_key)
+            .decrypt(&[], CHECK_NAME, &config.check)
+            .ok()
+            .as_deref()
+            == Some(CHECK_VALUE)
+    }
+
+    /// Keychain entries are per project: the salt is shared by every clone
+    /// of a project and differs between projects.
+    fn keychain_account(&self) -> Result<String> {
+        Ok(format!("project-{}", self.load_encryption_config()?.salt))
+    }
+
+    /// Decrypts the values of `env_name`. Each one is bound to the
+    /// environment, and profile overrides to their profile too, so a value
+    /// copied in from anywhere else fails to decrypt.
+    pub(super) fn decrypt_variables(
+        &self,
+        env_name: &str,
+        env_config: &mut EnvConfig,
+    ) -> Result<()> {
+        self.decrypt_values(&[env_name], &mut env_config.variables)?;
+        for (profile_name, profile) in env_config.profiles.iter_mut() {
+            self.decrypt_values(&[env_name, profile_name], &mut profile.overrides)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn encrypt_variables(
+        &self,
+        env_name: &str,
+        env_config: &EnvConfig,
+    ) -> Result<EnvConfig> {
+        Ok(EnvConfig {
+            variables: self.encrypt_values(&[env_name], &env_config.variables)?,
+            metadata: env_config.metadata.clone(),
+            profiles: env_config
+                .profiles
+                .iter()
+                .map(|(name, profile)| {
+                    let profile = Profile {
+                        include: profile.include.clone(),
+                        overrides: self.encrypt_values(&[env_name, name], &profile.overrides)?,
+                    };
+                    Ok((name.clone(), profile))
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Decrypts values sealed at `context` (see [`Cipher::encrypt`]) in place.
+    pub(super) fn decrypt_values(
+        &self,
+        context: &[&str],
+        variables: &mut IndexMap<String, String>,
+    ) -> Result<()> {
+        let Some(cipher) = self.cipher()? else {
+            return Ok(());
+        };
+        for (key, value) in variables.iter_mut() {
+            // Values added by hand stay readable until the next save encrypts them
+            if crypto::is_encrypted(value) {
+                *value = cipher.decrypt(context, key, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The values sealed at `context`, or as they are when the project
+    /// isn't encrypted.
+    pub(super) fn encrypt_values(
+        &self,
+        context: &[&str],
+        variables: &IndexMap<String, String>,
+    ) -> Result<IndexMap<String, String>> {
+        let Some(cipher) = self.cipher()? else {
+            return Ok(variables.clone());
+        };
+        Ok(variables
+            .iter()
+            .map(|(key, value)| (key.clone(), cipher.encrypt(context, key, value)))
+            .collect())
+    }
+
+    /// The cipher of an encrypted project, asking for the passphrase the first time.
+    fn cipher(&self) -> Result<Option<&Cipher>> {
+        if !self.is_encrypted() {
+            return Ok(None);
+        }
+        if self.cipher.get().is_none() {
+            let config = self.load_encryption_config()?;
+            match keychain::load_key(&self.keychain_account()?) {
+                Some(master_key) if Self::key_matches(&master_key, &config) => {
+                    let _ = self.cipher.set(Cipher::from_master_key(&master_key));
+                }
+                _ => self.unlock(&crypto::read_passphrase()?)?,
+            }
+        }
+        Ok(self.cipher.get())
+    }
+
+    fn get_encryption_path(&self) -> PathBuf {
+        PathBuf::from(ENCRYPTION_FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enable_encryption() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        let mut env_config = EnvConfig::default();
+        env_config
+            .variables
+            .insert("API_KEY".to_string(), "s3cret".to_string());
+        config_manager
+            .save_environment("development", &env_config)
+            .unwrap();
+
+        assert_eq!(config_manager.enable_encryption("passphrase").unwrap(), 1);
+        let stored = config_manager
+            .storage
+            .read(&config_manager.get_env_path("development"))
+            .unwrap()
+            .unwrap();
+        assert!(stored.contains("ENC[v2,"));
+        assert!(!stored.contains("s3cret"));
+        assert_eq!(
+            config_manager.load_environment("development").unwrap(),
+            env_config
+        );
+
+        // A fresh manager over the same files needs the right passphrase
+        let reopened =
+            ConfigManager::with_backend(Box::new(crate::storage::InMemoryBackend::new()));
+        for path in [
+            "config.yaml",
+            ENCRYPTION_FILE,
+            "environments/development.yaml",
+        ] {
+            let content = config_manager.storage.read(path.as_ref()).unwrap().unwrap();
+            reopened.storage.write(path.as_ref(), &content).unwrap();
+        }
+        assert!(matches!(
+            reopened.unlock("wrong"),
+            Err(EnvMatchError::WrongPassphrase)
+        ));
+        reopened.unlock("passphrase").unwrap();
+        assert_eq!(
+            reopened.load_environment("development").unwrap(),
+            env_config
+        );
+    }
+
+    #[test]
+    fn test_values_only_decrypt_in_their_environment() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        let mut env_config = EnvConfig::default();
+        env_config.set("API_KEY", "s3cret");
+        config_manager
+            .save_environment("production", &env_config)
+            .unwrap();
+        config_manager
+            .save_environment("staging", &EnvConfig::default())
+            .unwrap();
+        config_manager.enable_encryption("passphrase").unwrap();
+
+        // Copy the production file over staging's, ciphertexts and all
+        let production = config_manager
+            .storage
+            .read(&config_manager.get_env_path("production"))
+            .unwrap()
+            .unwrap();
+        config_manager
+            .storage
+            .write(&config_manager.get_env_path("staging"), &production)
+            .unwrap();
+        config_manager.invalidate_cache();
+
+        assert_eq!(
+            config_manager.load_environment("production").unwrap(),
+            env_config
+        );
+        assert!(matches!(
+            config_manager.load_environment("staging"),
+            Err(EnvMatchError::DecryptionFailed { key }) if key == "API_KEY"
+        ));
+    }
+}
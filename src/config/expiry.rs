@@ -0,0 +1,117 @@
+use super::VariableMetadata;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::fmt;
+
+/// How many days ahead an expiry counts as coming up.
+pub const ROTATION_WINDOW_DAYS: i64 = 14;
+
+/// A variable that has expired, or will within the rotation window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Expiry {
+    /// Expired this many whole days ago
+    Expired(i64),
+    /// Expires in this many whole days
+    Soon(i64),
+}
+
+impl Expiry {
+    pub fn is_expired(&self) -> bool {
+        matches!(self, Expiry::Expired(_))
+    }
+}
+
+impl fmt::Display for Expiry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (days, expired) = match *self {
+            Expiry::Expired(days) => (days, true),
+            Expiry::Soon(days) => (days, false),
+        };
+        let when = match days {
+            0 if expired => return write!(f, "expired today"),
+            0 => return write!(f, "expires today"),
+            1 => "1 day".to_string(),
+            _ => format!("{} days", days),
+        };
+        match expired {
+            true => write!(f, "expired {} ago", when),
+            false => write!(f, "expires in {}", when),
+        }
+    }
+}
+
+impl VariableMetadata {
+    /// Whether the variable has expired at `now` or will within `window_days`.
+    pub fn expiry(&self, now: DateTime<Utc>, window_days: i64) -> Option<Expiry> {
+        let expires_at = self.expires_at?;
+        if expires_at <= now {
+            Some(Expiry::Expired((now - expires_at).num_days()))
+        } else if expires_at - now <= Duration::days(window_days) {
+            Some(Expiry::Soon((expires_at - now).num_days()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads an expiry given as a lifetime from `now` (`12h`, `90d`, `6w`) or
+/// a date (`2025-06-30`, expiring at the start of that day, UTC).
+pub fn parse_expiry(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Ok(date
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight exists")
+            .and_utc());
+    }
+    let invalid = || {
+        format!(
+            "'{}' is not a lifetime like 90d, 6w or 12h, or a YYYY-MM-DD date",
+            input
+        )
+    };
+    let split = input.len().checked_sub(1).ok_or_else(invalid)?;
+    let (count, unit) = input.split_at_checked(split).ok_or_else(invalid)?;
+    let count: i64 = count.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?;
+    let lifetime = match unit {
+        "h" => Duration::try_hours(count),
+        "d" => Duration::try_days(count),
+        "w" => Duration::try_weeks(count),
+        _ => None,
+    };
+    lifetime
+        .and_then(|lifetime| now.checked_add_signed(lifetime))
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_expiry_and_status() {
+        let now = "2025-01-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(parse_expiry("90d", now).unwrap(), now + Duration::days(90));
+        assert_eq!(parse_expiry("12h", now).unwrap(), now + Duration::hours(12));
+        assert_eq!(
+            parse_expiry("2025-03-01", now).unwrap().to_rfc3339(),
+            "2025-03-01T00:00:00+00:00"
+        );
+        for bad in ["", "d", "0d", "-3d", "90", "90y", "2025-13-01"] {
+            assert!(parse_expiry(bad, now).is_err(), "{}", bad);
+        }
+
+        let expiring = |when: &str| VariableMetadata {
+            expires_at: Some(parse_expiry(when, now).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(VariableMetadata::default().expiry(now, 14), None);
+        assert_eq!(expiring("90d").expiry(now, 14), None);
+        assert_eq!(expiring("10d").expiry(now, 14), Some(Expiry::Soon(10)));
+        assert_eq!(
+            expiring("2024-12-30").expiry(now, 14),
+            Some(Expiry::Expired(2))
+        );
+        assert_eq!(Expiry::Soon(1).to_string(), "expires in 1 day");
+        assert_eq!(Expiry::Expired(2).to_string(), "expired 2 days ago");
+        assert_eq!(Expiry::Expired(0).to_string(), "expired today");
+    }
+}
@@ -0,0 +1,111 @@
+use super::ENV_MATCH_DIR;
+use crate::error::{EnvMatchError, Result};
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How environment files are written to disk. The project's own files
+/// (`config.yaml`, the schema, history) are always YAML.
+#[derive(Serialize, Deserialize, ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageFormat {
+    #[default]
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl StorageFormat {
+    pub const ALL: [StorageFormat; 3] = [Self::Yaml, Self::Toml, Self::Json];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+            Self::Json => "json",
+        }
+    }
+
+    /// The format a file is in, going by its extension.
+    pub fn of_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+
+    /// Splits `name.<ext>` into the name and its format, for listings.
+    pub fn strip_extension(file_name: &str) -> Option<(&str, Self)> {
+        let (name, extension) = file_name.rsplit_once('.')?;
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+            .map(|format| (name, format))
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String> {
+        Ok(match self {
+            Self::Yaml => serde_yaml::to_string(value)?,
+            Self::Toml => toml::to_string_pretty(value)?,
+            Self::Json => serde_json::to_string_pretty(value)? + "\n",
+        })
+    }
+
+    /// Parses a stored file, naming the file in the error.
+    pub fn deserialize<T: DeserializeOwned>(self, path: &Path, content: &str) -> Result<T> {
+        let path = Some(Path::new(ENV_MATCH_DIR).join(path));
+        match self {
+            Self::Yaml => {
+                serde_yaml::from_str(content).map_err(|source| EnvMatchError::YamlParseError {
+                    path,
+                    line: source.location().map(|location| location.line()),
+                    source,
+                })
+            }
+            Self::Toml => toml::from_str(content)
+                .map_err(|source| EnvMatchError::TomlParseError { path, source }),
+            Self::Json => Ok(serde_json::from_str(content)?),
+        }
+    }
+
+    /// Works out which format some content is in by parsing it as each one,
+    /// for files that arrive without a telling name (like git's merge temps).
+    pub fn detect<T: DeserializeOwned>(content: &str) -> Option<(Self, T)> {
+        [Self::Json, Self::Toml, Self::Yaml]
+            .into_iter()
+            .find_map(|format| {
+                let value = format.deserialize(Path::new(""), content).ok()?;
+                Some((format, value))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EnvConfig;
+
+    #[test]
+    fn test_formats_round_trip() {
+        let mut env_config = EnvConfig::default();
+        env_config.set("DATABASE_URL", "postgres://localhost/app");
+        env_config.set("MOTD", "line one\nline \"two\"");
+        env_config.metadata.get_mut("MOTD").unwrap().secret = true;
+
+        for format in StorageFormat::ALL {
+            let content = format.serialize(&env_config).unwrap();
+            let path = Path::new("environments/development").with_extension(format.extension());
+            assert_eq!(StorageFormat::of_path(&path), Some(format));
+            let parsed: EnvConfig = format.deserialize(&path, &content).unwrap();
+            assert_eq!(parsed, env_config);
+            let (detected, _) = StorageFormat::detect::<EnvConfig>(&content).unwrap();
+            assert_eq!(detected, format);
+        }
+        assert_eq!(
+            StorageFormat::strip_extension("staging.toml"),
+            Some(("staging", StorageFormat::Toml))
+        );
+        assert_eq!(StorageFormat::strip_extension("staging.yaml.bak"), None);
+    }
+}
@@ -0,0 +1,137 @@
+use super::archive::ARCHIVE_DIR;
+use super::audit::AUDIT_KEY_FILE;
+use super::direnv::WATCH_STAMP_FILE;
+use super::{ConfigManager, StorageFormat, ENVIRONMENTS_DIR, ENV_MATCH_DIR};
+use crate::error::Result;
+use std::path::{Path, PathBuf};
+
+const GITIGNORE_FILE: &str = ".gitignore";
+const GITATTRIBUTES_FILE: &str = ".gitattributes";
+const BLOCK_START: &str = "# >>> managed by envMatch >>>";
+const BLOCK_END: &str = "# <<< managed by envMatch <<<";
+
+/// Files under `.envMatch` that hold plaintext values and must stay local.
+const SENSITIVE_PATHS: &[&str] = &[
+    "history/",
+    "sync/",
+    "audit.log",
+    AUDIT_KEY_FILE,
+    "*.bak",
+    WATCH_STAMP_FILE,
+];
+
+/// Name of the git merge driver resolving environment files key by key.
+pub const MERGE_DRIVER: &str = "envmatch";
+
+impl ConfigManager {
+    pub fn project_dir(&self) -> Option<&Path> {
+        self.project_dir.as_deref()
+    }
+
+    /// The project's `.envMatch` directory on disk, if it has one.
+    pub fn env_match_dir(&self) -> Option<PathBuf> {
+        self.project_dir().map(|dir| dir.join(ENV_MATCH_DIR))
+    }
+
+    /// Keeps `.envMatch/.gitignore` listing everything that mustn't be committed,
+    /// including the environments themselves until they're encrypted.
+    /// Returns whether the file changed.
+    pub fn update_gitignore(&self) -> Result<bool> {
+        let environments = format!("{}/", ENVIRONMENTS_DIR);
+        let archive = format!("{}/", ARCHIVE_DIR);
+        let mut paths = SENSITIVE_PATHS.to_vec();
+        if !self.is_encrypted() {
+            paths.extend([environments.as_str(), archive.as_str()]);
+        }
+        self.update_managed_block(&PathBuf::from(GITIGNORE_FILE), &paths)
+    }
+
+    /// Routes environment files through the envMatch merge driver.
+    /// Returns whether the file changed.
+    pub fn update_gitattributes(&self) -> Result<bool> {
+        let rules: Vec<String> = StorageFormat::ALL
+            .iter()
+            .map(|format| {
+                format!(
+                    "{}/*.{} merge={}",
+                    ENVIRONMENTS_DIR,
+                    format.extension(),
+                    MERGE_DRIVER
+                )
+            })
+            .collect();
+        let rules: Vec<&str> = rules.iter().map(String::as_str).collect();
+        self.update_managed_block(&PathBuf::from(GITATTRIBUTES_FILE), &rules)
+    }
+
+    /// Replaces (or appends) the envMatch block of a file, keeping everything
+    /// the user wrote around it.
+    fn update_managed_block(&self, path: &Path, lines: &[&str]) -> Result<bool> {
+        let existing = self.storage.read(path)?.unwrap_or_default();
+        let content = with_managed_block(&existing, lines);
+        if content == existing {
+            return Ok(false);
+        }
+        self.storage.write(path, &content)?;
+        Ok(true)
+    }
+}
+
+/// `existing` with its envMatch block replaced by `lines`, or with the block
+/// appended if it has none.
+pub fn with_managed_block(existing: &str, lines: &[&str]) -> String {
+    let mut kept = Vec::new();
+    let mut in_block = false;
+    for line in existing.lines() {
+        match line {
+            BLOCK_START => in_block = true,
+            BLOCK_END => in_block = false,
+            _ if !in_block => kept.push(line),
+            _ => {}
+        }
+    }
+    while kept.last().is_some_and(|line| line.trim().is_empty()) {
+        kept.pop();
+    }
+
+    let mut content = String::new();
+    for line in kept {
+        content.push_str(line);
+        content.push('\n');
+    }
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    content.push_str(BLOCK_START);
+    content.push('\n');
+    for line in lines {
+        content.push_str(line);
+        content.push('\n');
+    }
+    content.push_str(BLOCK_END);
+    content.push('\n');
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_managed_block_keeps_user_lines() {
+        let config_manager = ConfigManager::in_memory();
+        let path = PathBuf::from(GITIGNORE_FILE);
+        config_manager
+            .storage
+            .write(&path, "my-notes.txt\n")
+            .unwrap();
+
+        assert!(config_manager.update_gitignore().unwrap());
+        assert!(!config_manager.update_gitignore().unwrap());
+
+        let content = config_manager.storage.read(&path).unwrap().unwrap();
+        assert!(content.starts_with("my-notes.txt\n\n# >>> managed by envMatch >>>\n"));
+        assert!(content.contains("history/\n"));
+        assert_eq!(content.matches(BLOCK_START).count(), 1);
+    }
+}
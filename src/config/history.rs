@@ -0,0 +1,367 @@
+use super::{ConfigManager, EnvConfig};
+use crate::error::{EnvMatchError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub(super) const HISTORY_DIR: &str = "history";
+/// How often a snapshot stores every environment rather than the ones
+/// that changed
+const FULL_SNAPSHOT_EVERY: u64 = 50;
+
+/// The full project state right after a mutating command, however much of
+/// it was stored.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub command: String,
+    pub current_environment: String,
+    pub environments: BTreeMap<String, EnvConfig>,
+}
+
+/// A snapshot as it's stored: only the environments that changed, with the
+/// names of all of them; or, without `names`, every environment.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(super) struct StoredSnapshot {
+    id: u64,
+    timestamp: DateTime<Utc>,
+    user: String,
+    command: String,
+    current_environment: String,
+    /// Every environment there was, when only the changed ones are stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    names: Option<Vec<String>>,
+    environments: BTreeMap<String, EnvConfig>,
+}
+
+impl StoredSnapshot {
+    fn names(&self) -> Vec<String> {
+        match &self.names {
+            Some(names) => names.clone(),
+            None => self.environments.keys().cloned().collect(),
+        }
+    }
+
+    /// The full snapshot, taking what this one doesn't store from
+    /// `previous`, the environments as of the snapshot before.
+    fn apply_to(self, previous: Option<&BTreeMap<String, EnvConfig>>) -> Snapshot {
+        let mut environments = self.environments;
+        if let (Some(names), Some(previous)) = (&self.names, previous) {
+            for name in names {
+                if let (false, Some(env_config)) =
+                    (environments.contains_key(name), previous.get(name))
+                {
+                    environments.insert(name.clone(), env_config.clone());
+                }
+            }
+        }
+        Snapshot {
+            id: self.id,
+            timestamp: self.timestamp,
+            user: self.user,
+            command: self.command,
+            current_environment: self.current_environment,
+            environments,
+        }
+    }
+}
+
+/// A variable's value as one command left it, taken from the snapshots.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyVersion {
+    /// Snapshot recorded right after the change
+    pub snapshot: u64,
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub command: String,
+    /// `None` once the variable was removed
+    pub value: Option<String>,
+}
+
+/// Name of the OS user running envMatch, for attributing changes.
+pub fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+impl ConfigManager {
+    /// Records a snapshot after a command that changed the environments in
+    /// `touched`, returning its id. Only those, and environments that
+    /// appeared since the last snapshot, are stored; the rest are carried
+    /// over from the snapshots before. Every [`FULL_SNAPSHOT_EVERY`]th
+    /// snapshot stores everything, so loading one never reads far back.
+    /// Values are encrypted on disk like the environments themselves.
+    pub fn record_snapshot(&self, command: &str, touched: &[String]) -> Result<u64> {
+        let id = self.last_snapshot_id()? + 1;
+        let names = self.list_environments()?;
+        let previous = match id {
+            1 => None,
+            _ => self.read_stored_snapshot(id - 1).ok(),
+        };
+        let full = id % FULL_SNAPSHOT_EVERY == 0 || previous.is_none();
+        let previous_names = previous
+            .map(|previous| previous.names())
+            .unwrap_or_default();
+
+        let mut environments = BTreeMap::new();
+        for env_name in &names {
+            if full || touched.contains(env_name) || !previous_names.contains(env_name) {
+                environments.insert(env_name.clone(), self.load_environment(env_name)?);
+            }
+        }
+        let stored = StoredSnapshot {
+            id,
+            timestamp: Utc::now(),
+            user: current_user(),
+            command: command.to_string(),
+            current_environment: self.load_global_config()?.current_environment,
+            names: (!full).then_some(names),
+            environments,
+        };
+        self.write_snapshot_to(&self.get_snapshot_path(id), &stored)?;
+        Ok(id)
+    }
+
+    fn write_snapshot_to(&self, path: &Path, snapshot: &StoredSnapshot) -> Result<()> {
+        let mut stored = snapshot.clone();
+        for (env_name, env_config) in stored.environments.iter_mut() {
+            *env_config = self.encrypt_variables(env_name, env_config)?;
+        }
+        self.storage.write(path, &serde_yaml::to_string(&stored)?)
+    }
+
+    /// Encrypts the project's snapshots recorded before encryption was
+    /// turned on.
+    pub(super) fn encrypt_history(&self) -> Result<()> {
+        for file_name in self.storage.list(Path::new(HISTORY_DIR))? {
+            self.encrypt_snapshot_file(&Path::new(HISTORY_DIR).join(file_name))?;
+        }
+        Ok(())
+    }
+
+    /// Encrypts one snapshot file in place.
+    pub(super) fn encrypt_snapshot_file(&self, path: &Path) -> Result<()> {
+        let Some(content) = self.storage.read(path)? else {
+            return Ok(());
+        };
+        let snapshot: StoredSnapshot = self.parse_yaml(path, &content)?;
+        self.write_snapshot_to(path, &snapshot)
+    }
+
+    /// All recorded snapshots, oldest first.
+    pub fn list_snapshots(&self) -> Result<Vec<Snapshot>> {
+        let mut snapshots: Vec<Snapshot> = Vec::new();
+        for id in self.snapshot_ids()? {
+            let stored = self.read_stored_snapshot(id)?;
+            let snapshot = stored.apply_to(snapshots.last().map(|last| &last.environments));
+            snapshots.push(snapshot);
+        }
+        Ok(snapshots)
+    }
+
+    /// A snapshot with every environment as it was, carried over from the
+    /// snapshots before it where it only stores what changed.
+    pub fn load_snapshot(&self, id: u64) -> Result<Snapshot> {
+        let mut chain = vec![self.read_stored_snapshot(id)?];
+        while chain.last().is_some_and(|stored| stored.names.is_some()) {
+            let earlier = chain.last().map(|stored| stored.id - 1).unwrap_or_default();
+            match self.read_stored_snapshot(earlier) {
+                Ok(stored) => chain.push(stored),
+                // History recorded before the first one kept is gone
+                Err(EnvMatchError::SnapshotNotFound { .. }) => break,
+                Err(error) => return Err(error),
+            }
+        }
+
+        let mut snapshot: Option<Snapshot> = None;
+        for stored in chain.into_iter().rev() {
+            snapshot = Some(stored.apply_to(snapshot.as_ref().map(|s| &s.environments)));
+        }
+        Ok(snapshot.expect("the chain holds at least the snapshot asked for"))
+    }
+
+    /// A snapshot as stored, values decrypted.
+    fn read_stored_snapshot(&self, id: u64) -> Result<StoredSnapshot> {
+        let path = self.get_snapshot_path(id);
+        let content = self
+            .storage
+            .read(&path)?
+            .ok_or_else(|| EnvMatchError::SnapshotNotFound { id: id.to_string() })?;
+        let mut snapshot: StoredSnapshot = self.parse_yaml(&path, &content)?;
+        for (env_name, env_config) in snapshot.environments.iter_mut() {
+            self.decrypt_variables(env_name, env_config)?;
+        }
+        Ok(snapshot)
+    }
+
+    /// The changes to one variable of an environment, oldest first: every
+    /// snapshot where its value differs from the snapshot before.
+    pub fn key_history(&self, env_name: &str, key: &str) -> Result<Vec<KeyVersion>> {
+        let mut versions = Vec::new();
+        let mut previous = None;
+        for snapshot in self.list_snapshots()? {
+            let value = snapshot
+                .environments
+                .get(env_name)
+                .and_then(|env_config| env_config.variables.get(key))
+                .cloned();
+            if value != previous {
+                previous = value.clone();
+                versions.push(KeyVersion {
+                    snapshot: snapshot.id,
+                    timestamp: snapshot.timestamp,
+                    user: snapshot.user,
+                    command: snapshot.command,
+                    value,
+                });
+            }
+        }
+        Ok(versions)
+    }
+
+    /// Makes the project match `snapshot`, removing environments it doesn't contain.
+    pub fn restore_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        for env_name in self.list_environments()? {
+            if !snapshot.environments.contains_key(&env_name) {
+                self.remove_scoped_environment(&env_name)?;
+            }
+        }
+        for (env_name, env_config) in &snapshot.environments {
+            self.save_environment(env_name, env_config)?;
+        }
+
+        let mut config = self.load_global_config()?;
+        config.current_environment = snapshot.current_environment.clone();
+        self.save_global_config(&config)
+    }
+
+    fn last_snapshot_id(&self) -> Result<u64> {
+        Ok(self.snapshot_ids()?.last().copied().unwrap_or(0))
+    }
+
+    fn snapshot_ids(&self) -> Result<Vec<u64>> {
+        let mut ids: Vec<u64> = self
+            .storage
+            .list(&self.get_history_dir())?
+            .iter()
+            .filter_map(|file_name| file_name.strip_suffix(".yaml")?.parse().ok())
+            .collect();
+
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    fn get_history_dir(&self) -> PathBuf {
+        self.get_scoped_dir(HISTORY_DIR)
+    }
+
+    fn get_snapshot_path(&self, id: u64) -> PathBuf {
+        self.get_history_dir().join(format!("{:06}.yaml", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+
+        let mut env_config = EnvConfig::default();
+        env_config
+            .variables
+            .insert("DATABASE_URL".to_string(), "postgres://old".to_string());
+        config_manager
+            .save_environment("development", &env_config)
+            .unwrap();
+        let first = config_manager
+            .record_snapshot("set DATABASE_URL", &["development".to_string()])
+            .unwrap();
+        assert_eq!(first, 1);
+        let first = config_manager.load_snapshot(first).unwrap();
+
+        env_config
+            .variables
+            .insert("DATABASE_URL".to_string(), "postgres://new".to_string());
+        config_manager
+            .save_environment("development", &env_config)
+            .unwrap();
+        config_manager
+            .save_environment("staging", &EnvConfig::default())
+            .unwrap();
+        config_manager
+            .record_snapshot("set DATABASE_URL", &["development".to_string()])
+            .unwrap();
+
+        let snapshots = config_manager.list_snapshots().unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[1].id, 2);
+
+        config_manager.restore_snapshot(&first).unwrap();
+        let restored = config_manager.load_environment("development").unwrap();
+        assert_eq!(
+            restored.variables.get("DATABASE_URL"),
+            Some(&"postgres://old".to_string())
+        );
+        assert_eq!(
+            config_manager.list_environments().unwrap(),
+            vec!["development"]
+        );
+
+        assert!(matches!(
+            config_manager.load_snapshot(42),
+            Err(EnvMatchError::SnapshotNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_stores_only_touched_environments() {
+        let config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        let mut staging = EnvConfig::default();
+        staging.set("API_URL", "https://staging");
+        config_manager
+            .save_environment("staging", &staging)
+            .unwrap();
+        config_manager
+            .record_snapshot("create staging", &[])
+            .unwrap();
+
+        let mut development = EnvConfig::default();
+        development.set("DEBUG", "true");
+        config_manager
+            .save_environment("development", &development)
+            .unwrap();
+        let id = config_manager
+            .record_snapshot("set DEBUG", &["development".to_string()])
+            .unwrap();
+
+        let stored = config_manager.read_stored_snapshot(id).unwrap();
+        assert_eq!(
+            stored.environments.keys().collect::<Vec<_>>(),
+            ["development"]
+        );
+        let snapshot = config_manager.load_snapshot(id).unwrap();
+        assert_eq!(snapshot.environments["staging"], staging);
+        assert_eq!(snapshot.environments["development"], development);
+
+        config_manager.remove_scoped_environment("staging").unwrap();
+        let id = config_manager
+            .record_snapshot("delete staging", &["staging".to_string()])
+            .unwrap();
+        let snapshots = config_manager.list_snapshots().unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(config_manager.load_snapshot(id).unwrap(), snapshots[2]);
+        assert_eq!(
+            snapshots[2].environments.keys().collect::<Vec<_>>(),
+            ["development"]
+        );
+        assert_eq!(snapshots[1].environments["staging"], staging);
+    }
+}
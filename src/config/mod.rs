@@ -1,82 +1,334 @@
+use crate::crypto::Cipher;
 use crate::error::{EnvMatchError, Result};
+use crate::storage::{
+    BackendKind, FileBackend, InMemoryBackend, SqliteBackend, StorageBackend, SQLITE_FILE,
+};
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::debug;
+
+mod archive;
+mod audit;
+mod cache;
+mod comments;
+mod direnv;
+mod doctor;
+mod encryption;
+mod expiry;
+mod format;
+mod git;
+mod history;
+mod permissions;
+mod policy;
+mod profiles;
+mod projects;
+mod references;
+mod rules;
+mod schema;
+mod scope;
+mod secret_scan;
+mod settings;
+mod sync_state;
+mod trust;
+
+#[cfg(test)]
+pub use audit::hash_value;
+pub use audit::AuditEntry;
+pub use doctor::{Finding, Severity};
+pub use expiry::{parse_expiry, Expiry, ROTATION_WINDOW_DAYS};
+pub use format::StorageFormat;
+pub use git::{with_managed_block, MERGE_DRIVER};
+pub use history::{KeyVersion, Snapshot};
+pub use policy::{check_policies, Policy, PolicyLevel, PolicyViolation};
+pub use profiles::Profile;
+pub use rules::EnvironmentRule;
+pub use schema::{Schema, VariableSchema, VariableType};
+pub use secret_scan::{SecretPattern, SecretScanner};
+pub use settings::{CommandValues, Settings, Theme, VariablesSort, VariablesView};
+pub use sync_state::SyncState;
 
 const ENV_MATCH_DIR: &str = ".envMatch";
 const CONFIG_FILE: &str = "config.yaml";
 const ENVIRONMENTS_DIR: &str = "environments";
 const DEFAULT_ENVIRONMENT: &str = "development";
+/// Separates a variable's namespace from the rest of its name, as in `DB__HOST`
+const GROUP_SEPARATOR: &str = "__";
 
 #[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
 pub struct EnvConfig {
-    pub variables: HashMap<String, String>,
+    /// Values in the order they were added, which is also how they're saved
+    pub variables: IndexMap<String, String>,
+    /// Optional details about the variables, by key. Kept apart from the
+    /// values so files without it still load and values stay plain strings.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, VariableMetadata>,
+    /// Subsets of the variables for different kinds of processes, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// What's known about a variable besides its value.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct VariableMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secret: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// When the value should have been rotated by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl EnvConfig {
+    /// Sets a variable, stamping when it was created and last changed.
+    pub fn set(&mut self, key: &str, value: &str) -> Option<String> {
+        let old_value = self.variables.insert(key.to_string(), value.to_string());
+        if old_value.as_deref() != Some(value) {
+            let now = Utc::now();
+            let metadata = self.metadata.entry(key.to_string()).or_default();
+            if old_value.is_none() {
+                metadata.created_at = Some(now);
+            }
+            metadata.updated_at = Some(now);
+        }
+        old_value
+    }
+
+    /// Removes a variable along with its metadata.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.metadata.remove(key);
+        self.variables.shift_remove(key)
+    }
+}
+
+/// The group a variable is listed under: its first tag if it has one,
+/// otherwise its namespace prefix (`DB` for `DB__HOST`).
+pub fn group_of<'a>(key: &'a str, metadata: Option<&'a VariableMetadata>) -> Option<&'a str> {
+    metadata
+        .and_then(|metadata| metadata.tags.first())
+        .map(String::as_str)
+        .or_else(|| {
+            key.split_once(GROUP_SEPARATOR)
+                .map(|(prefix, _)| prefix)
+                .filter(|prefix| !prefix.is_empty())
+        })
+}
+
+impl VariableMetadata {
+    /// Applies the details given in `update`: a description replaces the
+    /// current one (an empty one clears it), tags are added, the secret
+    /// flag can only be turned on and an expiry replaces the current one.
+    pub fn update(&mut self, update: &VariableMetadata) {
+        if let Some(description) = &update.description {
+            self.description = Some(description.clone()).filter(|d| !d.is_empty());
+        }
+        for tag in &update.tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+        self.secret |= update.secret;
+        if update.expires_at.is_some() {
+            self.expires_at = update.expires_at;
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
 pub struct GlobalConfig {
     pub current_environment: String,
+    /// Project-level overrides of the user settings
+    #[serde(default, flatten)]
+    pub settings: Settings,
+    /// Environments picked by git branch or directory, when `auto_switch` is on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub environment_rules: Vec<EnvironmentRule>,
 }
 
 #[derive(Debug)]
 pub struct ConfigManager {
-    base_dir: PathBuf,
+    storage: Box<dyn StorageBackend>,
+    user_config_path: Option<PathBuf>,
+    /// Root of the project on disk, for the files that live outside `.envMatch`
+    project_dir: Option<PathBuf>,
+    cipher: OnceLock<Cipher>,
+    /// Treat unparseable environment files as empty instead of failing
+    force_empty: bool,
+    /// Format to initialize the project with, instead of the settings'
+    storage_format: Option<StorageFormat>,
+    /// Parsed files, so repeated loads don't read and parse them again
+    cache: cache::FileCache,
+    /// Package of a monorepo whose environments extend the project's, as a
+    /// path relative to the project root
+    scope: Option<PathBuf>,
+    /// Environment this shell session works on, over the project's current one
+    session_environment: Option<String>,
 }
 
 impl ConfigManager {
+    /// Uses the nearest project found by walking up from the current
+    /// directory, falling back to the current directory itself.
     pub fn new() -> Self {
-        let base_dir = std::env::current_dir()
-            .expect("Failed to get current directory")
-            .join(ENV_MATCH_DIR);
+        let current_dir = std::env::current_dir().expect("Failed to get current directory");
+        let project_dir = Self::discover(&current_dir).unwrap_or(current_dir);
+        debug!(project_dir = %project_dir.display(), "using project");
+
+        Self::with_project_dir(project_dir)
+    }
+
+    /// Uses `project_dir` as the project root, without any discovery. Its
+    /// files are read from `.envMatch/store.db` if the project has one.
+    pub fn with_project_dir(project_dir: PathBuf) -> Self {
+        let env_match_dir = project_dir.join(ENV_MATCH_DIR);
+        let storage: Box<dyn StorageBackend> = match env_match_dir.join(SQLITE_FILE).is_file() {
+            true => Box::new(SqliteBackend::new(env_match_dir.join(SQLITE_FILE))),
+            false => Box::new(FileBackend::new(env_match_dir)),
+        };
+        Self {
+            user_config_path: Settings::user_config_path(),
+            project_dir: Some(project_dir),
+            ..Self::with_backend(storage)
+        }
+    }
+
+    /// A project that lives entirely in memory and ignores the user config.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn in_memory() -> Self {
+        Self::with_backend(Box::new(InMemoryBackend::new()))
+    }
+
+    /// Uses the given storage backend and no user config.
+    pub fn with_backend(storage: Box<dyn StorageBackend>) -> Self {
+        Self {
+            storage,
+            user_config_path: None,
+            project_dir: None,
+            cipher: OnceLock::new(),
+            force_empty: false,
+            storage_format: None,
+            cache: Default::default(),
+            scope: None,
+            session_environment: None,
+        }
+    }
+
+    /// Finds the closest ancestor of `start` (including itself) that contains
+    /// an initialized `.envMatch` directory, the same way git looks for `.git`.
+    pub fn discover(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .find(|dir| {
+                let env_match_dir = dir.join(ENV_MATCH_DIR);
+                env_match_dir.join(CONFIG_FILE).is_file()
+                    || env_match_dir.join(SQLITE_FILE).is_file()
+            })
+            .map(Path::to_path_buf)
+    }
 
-        Self { base_dir }
+    pub fn set_force_empty(&mut self, force_empty: bool) {
+        self.force_empty = force_empty;
+    }
+
+    /// Switches a project on disk to another kind of storage, before it's
+    /// initialized. Projects without a directory stay where they are.
+    pub fn set_backend(&mut self, backend: BackendKind) {
+        let Some(env_match_dir) = self.env_match_dir() else {
+            return;
+        };
+        self.storage = match backend {
+            BackendKind::Files => Box::new(FileBackend::new(env_match_dir)),
+            BackendKind::Sqlite => Box::new(SqliteBackend::new(env_match_dir.join(SQLITE_FILE))),
+        };
     }
 
-    #[cfg(test)]
-    pub fn with_base_dir(base_dir: PathBuf) -> Self {
-        Self { base_dir }
+    /// Chooses the format `initialize` records for the project.
+    pub fn set_storage_format(&mut self, storage_format: Option<StorageFormat>) {
+        self.storage_format = storage_format;
     }
 
     pub fn is_initialized(&self) -> bool {
-        self.base_dir.exists() && self.get_config_path().exists()
+        self.storage.exists(&self.get_config_path())
     }
 
     pub fn initialize(&self) -> Result<()> {
+        let settings = self.load_settings()?;
+        self.initialize_with_environment(settings.default_environment())
+    }
+
+    /// Initializes the project with `env_name` as its first and current environment.
+    pub fn initialize_with_environment(&self, env_name: &str) -> Result<()> {
         if self.is_initialized() {
             return Err(EnvMatchError::AlreadyInitialized);
         }
-
-        // Create directories
-        fs::create_dir_all(self.get_environments_dir())?;
+        self.validate_environment_name(env_name)?;
 
         // Create default config
-        let config = GlobalConfig {
-            current_environment: DEFAULT_ENVIRONMENT.to_string(),
+        let mut config = GlobalConfig {
+            current_environment: env_name.to_string(),
+            ..Default::default()
         };
+        config.settings.storage_format = self.storage_format;
         self.save_global_config(&config)?;
 
-        // Create default development environment
-        let dev_env = EnvConfig::default();
-        self.save_environment(DEFAULT_ENVIRONMENT, &dev_env)?;
+        // Create the initial environment
+        self.save_environment(env_name, &EnvConfig::default())?;
 
         Ok(())
     }
 
+    /// Effective settings: the user config, overridden by the project config.
+    pub fn load_settings(&self) -> Result<Settings> {
+        let user_settings = Settings::load_user(self.user_config_path.as_ref())?;
+        if !self.is_initialized() {
+            return Ok(user_settings);
+        }
+
+        let config = self.load_global_config()?;
+        Ok(user_settings.layered_with(&config.settings))
+    }
+
+    /// Changes the user config, leaving project configs alone. Does nothing
+    /// when there's no user config location.
+    pub fn update_user_settings(&self, update: impl FnOnce(&mut Settings)) -> Result<()> {
+        let Some(path) = &self.user_config_path else {
+            return Ok(());
+        };
+        let mut settings = Settings::load_user(Some(path))?;
+        update(&mut settings);
+        settings.save_user(path)
+    }
+
     pub fn load_global_config(&self) -> Result<GlobalConfig> {
         if !self.is_initialized() {
             return Err(EnvMatchError::NotInitialized);
         }
 
-        let content = fs::read_to_string(self.get_config_path())?;
-        let config = serde_yaml::from_str(&content)?;
-        Ok(config)
+        let path = self.get_config_path();
+        self.cached(&path, || {
+            let content = self
+                .storage
+                .read(&path)?
+                .ok_or(EnvMatchError::NotInitialized)?;
+            self.parse_yaml(&path, &content)
+        })
     }
 
     pub fn save_global_config(&self, config: &GlobalConfig) -> Result<()> {
         let config_yaml = serde_yaml::to_string(config)?;
-        fs::write(self.get_config_path(), config_yaml)?;
-        Ok(())
+        self.cache.invalidate(&self.get_config_path());
+        self.storage.write(&self.get_config_path(), &config_yaml)?;
+        self.touch_watch_stamp()
     }
 
     pub fn load_environment(&self, env_name: &str) -> Result<EnvConfig> {
@@ -85,69 +337,155 @@ impl ConfigManager {
         }
 
         let env_path = self.get_env_path(env_name);
-
-        if !env_path.exists() {
-            // Create new environment if it doesn't exist
+        if !self.storage.exists(&env_path) {
+            // A typo mustn't leave an empty environment behind, unless the
+            // project asks for the old behavior
+            if !self.load_settings()?.auto_create_environments() {
+                return Err(self.environment_not_found(env_name));
+            }
             let new_env = EnvConfig::default();
-            self.save_environment(env_name, &new_env)?;
+            self.save_environment_in(&self.get_environments_dir(), env_name, &new_env)?;
             return Ok(new_env);
         }
 
-        let content = fs::read_to_string(env_path)?;
-        let env_config = serde_yaml::from_str(&content).unwrap_or_default();
-        Ok(env_config)
+        // A scope only stores what it overrides, maybe nothing yet
+        match self.find_stored(&self.get_scoped_dir(ENVIRONMENTS_DIR), env_name) {
+            Some(path) => self.read_environment(env_name, &path),
+            None => Ok(EnvConfig::default()),
+        }
     }
 
+    fn read_environment(&self, env_name: &str, env_path: &Path) -> Result<EnvConfig> {
+        self.cached(env_path, || {
+            let content = self.storage.read(env_path)?.unwrap_or_default();
+            let format = StorageFormat::of_path(env_path).unwrap_or_default();
+            let mut env_config = match format.deserialize::<Option<EnvConfig>>(env_path, &content) {
+                Ok(env_config) => env_config.unwrap_or_default(),
+                Err(error) if self.force_empty => {
+                    eprintln!("⚠️  {} (treated as empty because of --force-empty)", error);
+                    EnvConfig::default()
+                }
+                Err(error) => return Err(error),
+            };
+            self.decrypt_variables(env_name, &mut env_config)?;
+            Ok(env_config)
+        })
+    }
+
+    /// Saves an environment in the project's storage format, keeping the
+    /// comments of the YAML file it replaces. A file in another format is
+    /// converted. If the old file doesn't parse, it's first copied to
+    /// `<env>.<ext>.bak` so its contents can still be recovered.
     pub fn save_environment(&self, env_name: &str, env_config: &EnvConfig) -> Result<()> {
+        self.save_environment_in(&self.get_scoped_dir(ENVIRONMENTS_DIR), env_name, env_config)
+    }
+
+    fn save_environment_in(
+        &self,
+        dir: &Path,
+        env_name: &str,
+        env_config: &EnvConfig,
+    ) -> Result<()> {
         self.validate_environment_name(env_name)?;
 
-        let env_yaml = serde_yaml::to_string(env_config)?;
+        let mut stored = self.encrypt_variables(env_name, env_config)?;
+        stored
+            .metadata
+            .retain(|key, _| stored.variables.contains_key(key));
+        let format = self.storage_format();
+        let mut content = format.serialize(&stored)?;
+
+        let env_path = dir.join(format!("{}.{}", env_name, format.extension()));
+        if let Some(existing_path) = self.find_stored(dir, env_name) {
+            self.cache.invalidate(&existing_path);
+            let existing_format = StorageFormat::of_path(&existing_path).unwrap_or_default();
+            let existing = self.storage.read(&existing_path)?.unwrap_or_default();
+            if existing_format
+                .deserialize::<Option<EnvConfig>>(&existing_path, &existing)
+                .is_err()
+            {
+                let backup = format!("{}.bak", existing_format.extension());
+                self.storage
+                    .write(&existing_path.with_extension(backup), &existing)?;
+            } else if existing_format == StorageFormat::Yaml && format == StorageFormat::Yaml {
+                content = comments::carry_over(&existing, &content);
+            }
+            if existing_path != env_path {
+                self.storage.remove(&existing_path)?;
+            }
+        }
+        self.storage.write(&env_path, &content)?;
+        self.touch_watch_stamp()
+    }
 
-        // Ensure environments directory exists
-        fs::create_dir_all(self.get_environments_dir())?;
+    pub fn environment_exists(&self, env_name: &str) -> bool {
+        self.storage.exists(&self.get_env_path(env_name))
+    }
 
-        fs::write(self.get_env_path(env_name), env_yaml)?;
-        Ok(())
+    /// The error for an environment that doesn't exist, suggesting an
+    /// existing one when the name looks like a typo of it.
+    pub fn environment_not_found(&self, env_name: &str) -> EnvMatchError {
+        let existing = self.list_environments().unwrap_or_default();
+        EnvMatchError::environment_not_found(env_name, &existing)
     }
 
     pub fn list_environments(&self) -> Result<Vec<String>> {
-        let env_dir = self.get_environments_dir();
+        let mut environments: Vec<String> = self
+            .storage
+            .list(&self.get_environments_dir())?
+            .iter()
+            .filter_map(|file_name| StorageFormat::strip_extension(file_name))
+            .map(|(env_name, _)| env_name.to_string())
+            .collect();
 
-        if !env_dir.exists() {
-            return Ok(vec![]);
-        }
-
-        let mut environments = Vec::new();
+        environments.sort();
+        environments.dedup();
+        Ok(environments)
+    }
 
-        for entry in fs::read_dir(&env_dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    /// The format environment files are saved in.
+    fn storage_format(&self) -> StorageFormat {
+        self.storage_format.unwrap_or_else(|| {
+            self.load_settings()
+                .map(|settings| settings.storage_format())
+                .unwrap_or_default()
+        })
+    }
 
-            if path.extension().is_some_and(|ext| ext == "yaml") {
-                if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
-                    environments.push(name.to_string());
-                }
-            }
-        }
+    /// Finds the file `name` is stored in under `dir`, whatever its format.
+    fn find_stored(&self, dir: &Path, name: &str) -> Option<PathBuf> {
+        StorageFormat::ALL
+            .into_iter()
+            .map(|format| dir.join(format!("{}.{}", name, format.extension())))
+            .find(|path| self.storage.exists(path))
+    }
 
-        environments.sort();
-        Ok(environments)
+    /// Parses a stored YAML file, naming the file in the error.
+    fn parse_yaml<T: DeserializeOwned>(&self, path: &Path, content: &str) -> Result<T> {
+        StorageFormat::Yaml.deserialize(path, content)
     }
 
     fn get_config_path(&self) -> PathBuf {
-        self.base_dir.join(CONFIG_FILE)
+        PathBuf::from(CONFIG_FILE)
     }
 
     fn get_environments_dir(&self) -> PathBuf {
-        self.base_dir.join(ENVIRONMENTS_DIR)
+        PathBuf::from(ENVIRONMENTS_DIR)
     }
 
+    /// The file an environment is stored in, or would be saved to.
     fn get_env_path(&self, env_name: &str) -> PathBuf {
-        self.get_environments_dir()
-            .join(format!("{}.yaml", env_name))
+        let dir = self.get_environments_dir();
+        self.find_stored(&dir, env_name).unwrap_or_else(|| {
+            dir.join(format!(
+                "{}.{}",
+                env_name,
+                self.storage_format().extension()
+            ))
+        })
     }
 
-    fn validate_environment_name(&self, name: &str) -> Result<()> {
+    pub fn validate_environment_name(&self, name: &str) -> Result<()> {
         if name
             .chars()
             .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
@@ -164,18 +502,16 @@ impl ConfigManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
     use tempfile::TempDir;
 
-    fn create_test_config_manager() -> (ConfigManager, TempDir) {
-        let temp_dir = TempDir::new().unwrap();
-        let base_dir = temp_dir.path().join(ENV_MATCH_DIR);
-        let config_manager = ConfigManager::with_base_dir(base_dir);
-        (config_manager, temp_dir)
+    fn create_test_config_manager() -> ConfigManager {
+        ConfigManager::in_memory()
     }
 
     #[test]
     fn test_initialization() {
-        let (config_manager, _temp_dir) = create_test_config_manager();
+        let config_manager = create_test_config_manager();
 
         assert!(!config_manager.is_initialized());
 
@@ -192,7 +528,7 @@ mod tests {
 
     #[test]
     fn test_global_config() {
-        let (config_manager, _temp_dir) = create_test_config_manager();
+        let config_manager = create_test_config_manager();
         config_manager.initialize().unwrap();
 
         let config = config_manager.load_global_config().unwrap();
@@ -200,6 +536,7 @@ mod tests {
 
         let new_config = GlobalConfig {
             current_environment: "production".to_string(),
+            ..Default::default()
         };
         config_manager.save_global_config(&new_config).unwrap();
 
@@ -209,7 +546,7 @@ mod tests {
 
     #[test]
     fn test_environment_management() {
-        let (config_manager, _temp_dir) = create_test_config_manager();
+        let config_manager = create_test_config_manager();
         config_manager.initialize().unwrap();
 
         let mut env_config = EnvConfig::default();
@@ -232,9 +569,48 @@ mod tests {
         assert!(environments.contains(&"development".to_string()));
     }
 
+    #[test]
+    fn test_multi_line_values_round_trip() {
+        let config_manager = create_test_config_manager();
+        config_manager.initialize().unwrap();
+
+        let values = [
+            "line1\nline2\n",
+            "  indented\n\tand tabbed",
+            "no trailing newline\nhere",
+            "crlf\r\nline",
+            "trailing spaces  \nkept  ",
+            "# not a comment\nkey: not yaml",
+            "\n\nleading blank lines",
+            "ends with blank lines\n\n\n",
+            "{\"type\": \"service_account\",\n  \"key\": \"-----BEGIN\\nKEY\"}",
+            "\u{7}bell and \u{0} nul",
+        ];
+        let mut env_config = EnvConfig::default();
+        for (i, value) in values.iter().enumerate() {
+            env_config.set(&format!("VALUE_{}", i), value);
+        }
+        config_manager
+            .save_environment("test", &env_config)
+            .unwrap();
+        // Saving again carries over comments around the block scalars
+        let path = config_manager.get_env_path("test");
+        let saved = config_manager.storage.read(&path).unwrap().unwrap();
+        let commented = saved.replace("  VALUE_1:", "  # about VALUE_1\n  VALUE_1:");
+        config_manager.storage.write(&path, &commented).unwrap();
+        config_manager
+            .save_environment("test", &env_config)
+            .unwrap();
+        let resaved = config_manager.storage.read(&path).unwrap().unwrap();
+        assert!(resaved.contains("# about VALUE_1\n  VALUE_1:"));
+
+        let loaded = config_manager.load_environment("test").unwrap();
+        assert_eq!(loaded.variables, env_config.variables);
+    }
+
     #[test]
     fn test_invalid_environment_name() {
-        let (config_manager, _temp_dir) = create_test_config_manager();
+        let config_manager = create_test_config_manager();
         config_manager.initialize().unwrap();
 
         let env_config = EnvConfig::default();
@@ -246,9 +622,92 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_project_settings_override_user_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let user_config_path = temp_dir.path().join("user.yaml");
+        fs::write(
+            &user_config_path,
+            "default_environment: local\nmask_values: true\n",
+        )
+        .unwrap();
+        let config_manager = ConfigManager {
+            user_config_path: Some(user_config_path),
+            ..ConfigManager::in_memory()
+        };
+
+        // The user default decides the first environment
+        config_manager.initialize().unwrap();
+        let mut config = config_manager.load_global_config().unwrap();
+        assert_eq!(config.current_environment, "local");
+        assert_eq!(config_manager.list_environments().unwrap(), vec!["local"]);
+
+        config.settings.mask_values = Some(false);
+        config_manager.save_global_config(&config).unwrap();
+        let settings = config_manager.load_settings().unwrap();
+        assert!(!settings.mask_values());
+        assert_eq!(settings.default_environment(), "local");
+    }
+
+    #[test]
+    fn test_discover_walks_up_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_manager = ConfigManager::with_project_dir(temp_dir.path().to_path_buf());
+
+        let nested = temp_dir.path().join("src").join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(ConfigManager::discover(&nested), None);
+
+        config_manager.initialize().unwrap();
+        assert_eq!(
+            ConfigManager::discover(&nested),
+            Some(temp_dir.path().to_path_buf())
+        );
+        assert_eq!(
+            ConfigManager::discover(temp_dir.path()),
+            Some(temp_dir.path().to_path_buf())
+        );
+    }
+
+    #[test]
+    fn test_corrupt_environment_is_not_silently_emptied() {
+        let mut config_manager = create_test_config_manager();
+        config_manager.initialize().unwrap();
+        let env_path = Path::new("environments/development.yaml");
+        let corrupt = "variables:\n  API_KEY: secret\n  - broken\n";
+        config_manager.storage.write(env_path, corrupt).unwrap();
+
+        match config_manager.load_environment("development") {
+            Err(EnvMatchError::YamlParseError { path, line, .. }) => {
+                assert_eq!(path, Some(Path::new(ENV_MATCH_DIR).join(env_path)));
+                assert_eq!(line, Some(3));
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+
+        config_manager.set_force_empty(true);
+        let env_config = config_manager.load_environment("development").unwrap();
+        assert!(env_config.variables.is_empty());
+        config_manager
+            .save_environment("development", &env_config)
+            .unwrap();
+        assert_eq!(
+            config_manager
+                .storage
+                .read(Path::new("environments/development.yaml.bak"))
+                .unwrap()
+                .as_deref(),
+            Some(corrupt)
+        );
+        assert_eq!(
+            config_manager.list_environments().unwrap(),
+            vec!["development"]
+        );
+    }
+
     #[test]
     fn test_not_initialized_error() {
-        let (config_manager, _temp_dir) = create_test_config_manager();
+        let config_manager = create_test_config_manager();
 
         let result = config_manager.load_global_config();
         assert!(matches!(result, Err(EnvMatchError::NotInitialized)));
@@ -0,0 +1,112 @@
+use super::ConfigManager;
+use crate::error::Result;
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use {
+    crate::error::EnvMatchError,
+    crate::storage::{DIR_MODE, FILE_MODE},
+    std::fs,
+    std::os::unix::fs::PermissionsExt,
+};
+
+impl ConfigManager {
+    /// Files and directories under `.envMatch` that users other than the
+    /// owner can access. Always empty where Unix permissions don't apply.
+    pub fn loose_permissions(&self) -> Vec<PathBuf> {
+        let mut loose = Vec::new();
+        if let Some(dir) = self.env_match_dir() {
+            visit(&dir, &mut |path, mode| {
+                if mode & 0o077 != 0 {
+                    loose.push(path.to_path_buf());
+                }
+                Ok(())
+            })
+            .ok();
+        }
+        loose
+    }
+
+    /// Makes everything under `.envMatch` private to its owner: 0700 for
+    /// directories and 0600 for files. Returns how many paths changed.
+    pub fn harden_permissions(&self) -> Result<usize> {
+        let mut changed = 0;
+        if let Some(dir) = self.env_match_dir() {
+            visit(&dir, &mut |path, mode| {
+                if restrict(path, mode)? {
+                    changed += 1;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(changed)
+    }
+}
+
+/// Calls `f` with `dir` and everything under it, along with their modes.
+#[cfg(unix)]
+fn visit(dir: &Path, f: &mut dyn FnMut(&Path, u32) -> Result<()>) -> Result<()> {
+    let metadata = fs::symlink_metadata(dir).map_err(|e| EnvMatchError::storage(dir, e))?;
+    f(dir, metadata.permissions().mode())?;
+    if metadata.is_dir() {
+        for entry in fs::read_dir(dir).map_err(|e| EnvMatchError::storage(dir, e))? {
+            let path = entry.map_err(|e| EnvMatchError::storage(dir, e))?.path();
+            let metadata =
+                fs::symlink_metadata(&path).map_err(|e| EnvMatchError::storage(&path, e))?;
+            if metadata.is_dir() {
+                visit(&path, f)?;
+            } else if metadata.is_file() {
+                f(&path, metadata.permissions().mode())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn visit(_dir: &Path, _f: &mut dyn FnMut(&Path, u32) -> Result<()>) -> Result<()> {
+    Ok(())
+}
+
+/// Takes away group and world access. Returns whether the mode changed.
+#[cfg(unix)]
+fn restrict(path: &Path, mode: u32) -> Result<bool> {
+    let wanted = if path.is_dir() { DIR_MODE } else { FILE_MODE };
+    let new_mode = mode & 0o700 | wanted;
+    if new_mode & 0o7777 == mode & 0o7777 {
+        return Ok(false);
+    }
+    fs::set_permissions(path, fs::Permissions::from_mode(new_mode))
+        .map_err(|e| EnvMatchError::storage(path, e))?;
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+fn restrict(_path: &Path, _mode: u32) -> Result<bool> {
+    Ok(false)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_harden_permissions() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_manager = ConfigManager::with_project_dir(temp_dir.path().to_path_buf());
+        config_manager.initialize().unwrap();
+        assert!(config_manager.loose_permissions().is_empty());
+
+        let config_path = temp_dir.path().join(".envMatch").join("config.yaml");
+        fs::set_permissions(&config_path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(
+            config_manager.loose_permissions(),
+            vec![config_path.clone()]
+        );
+
+        assert_eq!(config_manager.harden_permissions().unwrap(), 1);
+        assert!(config_manager.loose_permissions().is_empty());
+        let mode = fs::metadata(&config_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, FILE_MODE);
+    }
+}
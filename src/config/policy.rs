@@ -0,0 +1,188 @@
+use super::rules::glob_match;
+use crate::error::{EnvMatchError, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How much a broken policy matters.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyLevel {
+    /// Reported, but doesn't fail `validate` or block `set --enforce`
+    Warn,
+    #[default]
+    Error,
+}
+
+/// A severity for every environment, or one per environment with `*` for
+/// the rest, as in `{production: error, "*": warn}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum PolicySeverity {
+    All(PolicyLevel),
+    ByEnvironment(BTreeMap<String, PolicyLevel>),
+}
+
+impl Default for PolicySeverity {
+    fn default() -> Self {
+        PolicySeverity::All(PolicyLevel::default())
+    }
+}
+
+impl PolicySeverity {
+    pub fn for_environment(&self, env_name: &str) -> PolicyLevel {
+        match self {
+            PolicySeverity::All(severity) => *severity,
+            PolicySeverity::ByEnvironment(severities) => severities
+                .get(env_name)
+                .or_else(|| severities.get("*"))
+                .copied()
+                .unwrap_or(PolicyLevel::Warn),
+        }
+    }
+}
+
+/// A constraint on values, declared under `policies` in the schema, such as
+/// "production URLs must be https".
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Policy {
+    /// What the policy asks for, shown when a value breaks it
+    pub message: String,
+    /// Keys it applies to, `*` matching any run of characters; all when empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<String>,
+    /// Applies only to secrets
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub secrets: bool,
+    /// Environments it applies to; all when empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub environments: Vec<String>,
+    /// A regular expression values must match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// The only values allowed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub one_of: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    pub severity: PolicySeverity,
+}
+
+/// A value breaking a policy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyViolation {
+    pub key: String,
+    pub message: String,
+    pub severity: PolicyLevel,
+}
+
+impl Policy {
+    /// Whether the policy looks at `key` in `env_name`.
+    pub fn applies_to(&self, env_name: &str, key: &str, secret: bool) -> bool {
+        (self.environments.is_empty() || self.environments.iter().any(|env| env == env_name))
+            && (self.keys.is_empty() || self.keys.iter().any(|pattern| glob_match(pattern, key)))
+            && (!self.secrets || secret)
+    }
+
+    /// Whether `value` satisfies every constraint of the policy.
+    pub fn allows(&self, value: &str) -> Result<bool> {
+        if let Some(pattern) = &self.pattern {
+            let regex = Regex::new(pattern).map_err(|e| EnvMatchError::InvalidPolicy {
+                policy: self.message.clone(),
+                message: e.to_string(),
+            })?;
+            if !regex.is_match(value) {
+                return Ok(false);
+            }
+        }
+        Ok(
+            (self.one_of.is_empty() || self.one_of.iter().any(|allowed| allowed == value))
+                && self
+                    .min_length
+                    .is_none_or(|min_length| value.chars().count() >= min_length),
+        )
+    }
+}
+
+/// The policies `key=value` breaks in `env_name`, with their severity there.
+pub fn check_policies(
+    policies: &[Policy],
+    env_name: &str,
+    key: &str,
+    value: &str,
+    secret: bool,
+) -> Result<Vec<PolicyViolation>> {
+    let mut violations = Vec::new();
+    for policy in policies {
+        if policy.applies_to(env_name, key, secret) && !policy.allows(value)? {
+            violations.push(PolicyViolation {
+                key: key.to_string(),
+                message: policy.message.clone(),
+                severity: policy.severity.for_environment(env_name),
+            });
+        }
+    }
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_policies() {
+        let policies: Vec<Policy> = serde_yaml::from_str(
+            r#"
+- message: production URLs must be https
+  keys: ["*_URL"]
+  environments: [production]
+  pattern: "^https://"
+- message: DEBUG must be false outside development
+  keys: [DEBUG]
+  one_of: ["false"]
+  severity: { development: warn, "*": error }
+- message: secrets must be at least 32 characters
+  secrets: true
+  min_length: 32
+"#,
+        )
+        .unwrap();
+
+        let check = |env, key, value, secret| {
+            check_policies(&policies, env, key, value, secret)
+                .unwrap()
+                .into_iter()
+                .map(|violation| violation.severity)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            check("production", "API_URL", "http://x", false),
+            [PolicyLevel::Error]
+        );
+        assert!(check("production", "API_URL", "https://x", false).is_empty());
+        assert!(check("staging", "API_URL", "http://x", false).is_empty());
+        assert_eq!(
+            check("development", "DEBUG", "true", false),
+            [PolicyLevel::Warn]
+        );
+        assert_eq!(
+            check("production", "DEBUG", "true", false),
+            [PolicyLevel::Error]
+        );
+        assert_eq!(
+            check("staging", "TOKEN", "short", true),
+            [PolicyLevel::Error]
+        );
+        assert!(check("staging", "TOKEN", "short", false).is_empty());
+
+        let invalid = Policy {
+            pattern: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            check_policies(&[invalid], "production", "KEY", "value", false),
+            Err(EnvMatchError::InvalidPolicy { .. })
+        ));
+    }
+}
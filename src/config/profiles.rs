@@ -0,0 +1,62 @@
+use super::rules::glob_match;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// A named subset of an environment's variables for one kind of process,
+/// such as a `worker` running next to the `web` server.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct Profile {
+    /// Keys to pass on, `*` matching any run of characters; all when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Values that replace or add to the environment's
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub overrides: IndexMap<String, String>,
+}
+
+impl Profile {
+    pub fn includes(&self, key: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|pattern| glob_match(pattern, key))
+    }
+
+    /// The variables a process with this profile gets.
+    pub fn apply(&self, variables: IndexMap<String, String>) -> IndexMap<String, String> {
+        let mut variables: IndexMap<String, String> = variables
+            .into_iter()
+            .filter(|(key, _)| self.includes(key))
+            .collect();
+        variables.extend(self.overrides.clone());
+        variables
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_profile() {
+        let variables: IndexMap<String, String> = [
+            ("DATABASE_URL", "pg"),
+            ("QUEUE_URL", "redis"),
+            ("QUEUE_SIZE", "10"),
+            ("PORT", "80"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+        let worker = Profile {
+            include: vec!["QUEUE_*".to_string(), "DATABASE_URL".to_string()],
+            overrides: [("QUEUE_SIZE".to_string(), "50".to_string())].into(),
+        };
+        let applied = worker.apply(variables.clone());
+        assert_eq!(
+            applied.keys().collect::<Vec<_>>(),
+            ["DATABASE_URL", "QUEUE_URL", "QUEUE_SIZE"]
+        );
+        assert_eq!(applied["QUEUE_SIZE"], "50");
+
+        assert_eq!(Profile::default().apply(variables.clone()), variables);
+    }
+}
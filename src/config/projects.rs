@@ -0,0 +1,114 @@
+use super::ConfigManager;
+use crate::error::{EnvMatchError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PROJECTS_FILE: &str = "projects.yaml";
+
+/// Projects envMatch has been used in, kept next to the user config so the
+/// TUI and `projects` can jump between them.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct ProjectRegistry {
+    /// Project roots, most recently used first
+    #[serde(default)]
+    pub projects: Vec<PathBuf>,
+}
+
+impl ConfigManager {
+    /// The registered projects, most recently used first. Empty when there's
+    /// no user config location.
+    pub fn registered_projects(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.load_project_registry()?.projects)
+    }
+
+    /// Registers `project_dir`, or moves it to the front if it already is.
+    pub fn register_project(&self, project_dir: &Path) -> Result<()> {
+        let project_dir = project_dir
+            .canonicalize()
+            .unwrap_or_else(|_| project_dir.to_path_buf());
+        let mut registry = self.load_project_registry()?;
+        registry.projects.retain(|dir| dir != &project_dir);
+        registry.projects.insert(0, project_dir);
+        self.save_project_registry(&registry)
+    }
+
+    /// Forgets `project_dir`. Returns whether it was registered.
+    pub fn unregister_project(&self, project_dir: &Path) -> Result<bool> {
+        let canonical = project_dir.canonicalize().ok();
+        let mut registry = self.load_project_registry()?;
+        let count = registry.projects.len();
+        registry
+            .projects
+            .retain(|dir| dir != project_dir && Some(dir) != canonical.as_ref());
+        if registry.projects.len() == count {
+            return Ok(false);
+        }
+        self.save_project_registry(&registry)?;
+        Ok(true)
+    }
+
+    fn load_project_registry(&self) -> Result<ProjectRegistry> {
+        let Some(path) = self.project_registry_path() else {
+            return Ok(ProjectRegistry::default());
+        };
+        match fs::read_to_string(&path) {
+            // An empty file is an empty registry
+            Ok(content) => Ok(self
+                .parse_yaml::<Option<ProjectRegistry>>(&path, &content)?
+                .unwrap_or_default()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ProjectRegistry::default()),
+            Err(e) => Err(EnvMatchError::storage(&path, e)),
+        }
+    }
+
+    fn save_project_registry(&self, registry: &ProjectRegistry) -> Result<()> {
+        let Some(path) = self.project_registry_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| EnvMatchError::storage(dir, e))?;
+        }
+        fs::write(&path, serde_yaml::to_string(registry)?)
+            .map_err(|e| EnvMatchError::storage(&path, e))
+    }
+
+    fn project_registry_path(&self) -> Option<PathBuf> {
+        self.user_config_path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map(|dir| dir.join(PROJECTS_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_project_registry() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_manager = ConfigManager {
+            user_config_path: Some(temp_dir.path().join("envmatch").join("config.yaml")),
+            ..ConfigManager::in_memory()
+        };
+        let (api, web) = (temp_dir.path().join("api"), temp_dir.path().join("web"));
+        fs::create_dir_all(&api).unwrap();
+        fs::create_dir_all(&web).unwrap();
+        let (api, web) = (api.canonicalize().unwrap(), web.canonicalize().unwrap());
+
+        assert!(config_manager.registered_projects().unwrap().is_empty());
+        config_manager.register_project(&api).unwrap();
+        config_manager.register_project(&web).unwrap();
+        config_manager.register_project(&api).unwrap();
+        assert_eq!(
+            config_manager.registered_projects().unwrap(),
+            vec![api.clone(), web.clone()]
+        );
+
+        assert!(config_manager.unregister_project(&web).unwrap());
+        assert!(!config_manager.unregister_project(&web).unwrap());
+        assert_eq!(config_manager.registered_projects().unwrap(), vec![api]);
+    }
+}
@@ -0,0 +1,101 @@
+use super::ConfigManager;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+/// Directories of dependencies and build output, not the project's own code.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", "dist", "build"];
+/// Larger files are assumed to be data or bundles rather than source.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+impl ConfigManager {
+    /// Which of `keys` the project's files mention as a whole word, e.g.
+    /// `process.env.API_URL` or `${API_URL}`. Hidden files and directories
+    /// (`.envMatch`, `.git`, `.env`...) and dependency folders are skipped.
+    /// Finds nothing when there's no project directory.
+    pub fn find_references(&self, keys: &[String]) -> BTreeSet<String> {
+        let mut found = BTreeSet::new();
+        if let Some(dir) = self.project_dir() {
+            scan_dir(dir, keys, &mut found);
+        }
+        found
+    }
+}
+
+fn scan_dir(dir: &Path, keys: &[String], found: &mut BTreeSet<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if found.len() == keys.len() {
+            return;
+        }
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        if file_type.is_dir() && !SKIPPED_DIRS.contains(&name.as_ref()) {
+            scan_dir(&path, keys, found);
+        } else if file_type.is_file() {
+            let small = entry
+                .metadata()
+                .is_ok_and(|metadata| metadata.len() <= MAX_FILE_SIZE);
+            // Binary files aren't valid UTF-8 and are skipped too
+            if let Some(content) = small.then(|| fs::read_to_string(&path).ok()).flatten() {
+                for key in keys {
+                    if !found.contains(key) && mentions(&content, key) {
+                        debug!(%key, path = %path.display(), "variable referenced");
+                        found.insert(key.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether `key` appears in `content` not as part of a longer name.
+fn mentions(content: &str, key: &str) -> bool {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    content.match_indices(key).any(|(start, _)| {
+        let before = content[..start].chars().next_back();
+        let after = content[start + key.len()..].chars().next();
+        !before.is_some_and(is_name_char) && !after.is_some_and(is_name_char)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_references() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("node_modules/lib")).unwrap();
+        fs::write(
+            dir.join("src/app.js"),
+            "const url = process.env.API_URL;\nconst port = `${PORT}`;\nconst x = OLD_PORT_NUMBER;\n",
+        )
+        .unwrap();
+        fs::write(dir.join("node_modules/lib/index.js"), "process.env.SECRET").unwrap();
+        fs::write(dir.join(".env"), "OLD=1\n").unwrap();
+
+        let keys: Vec<String> = ["API_URL", "PORT", "OLD", "SECRET"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let found = ConfigManager::with_project_dir(dir.to_path_buf()).find_references(&keys);
+        assert_eq!(
+            found,
+            BTreeSet::from(["API_URL".to_string(), "PORT".to_string()])
+        );
+    }
+}
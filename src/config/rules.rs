@@ -0,0 +1,161 @@
+use super::ConfigManager;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use tracing::debug;
+
+/// Picks an environment automatically for a git branch or a subdirectory,
+/// e.g. `match: "branch:main"` or `match: "dir:services/api"`.
+///
+/// Patterns may use `*` for any run of characters. Directory patterns are
+/// relative to the project root and also match everything below them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EnvironmentRule {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    pub environment: String,
+}
+
+impl EnvironmentRule {
+    /// Whether the rule applies on `branch`, working in `dir` (relative to
+    /// the project root).
+    pub fn matches(&self, branch: Option<&str>, dir: Option<&Path>) -> bool {
+        if let Some(pattern) = self.pattern.strip_prefix("branch:") {
+            return branch.is_some_and(|branch| glob_match(pattern, branch));
+        }
+        if let Some(pattern) = self.pattern.strip_prefix("dir:") {
+            let pattern = pattern.trim_matches('/');
+            return dir.is_some_and(|dir| {
+                dir.ancestors()
+                    .filter_map(Path::to_str)
+                    .filter(|ancestor| !ancestor.is_empty())
+                    .any(|ancestor| glob_match(pattern, &ancestor.replace('\\', "/")))
+            });
+        }
+        false
+    }
+}
+
+impl ConfigManager {
+    /// Makes `env_name` the environment of this shell session, as
+    /// `ENVMATCH_ENV` does, so other terminals switching don't affect it.
+    pub fn set_session_environment(&mut self, env_name: Option<String>) {
+        self.session_environment = env_name.filter(|env_name| !env_name.is_empty());
+    }
+
+    pub fn session_environment(&self) -> Option<&str> {
+        self.session_environment.as_deref()
+    }
+
+    /// The environment commands use when none is given: the session's if
+    /// set, then the first matching rule's when automatic selection is on,
+    /// otherwise the current one.
+    pub fn active_environment(&self) -> Result<String> {
+        if let Some(environment) = &self.session_environment {
+            debug!(%environment, "environment selected by the session");
+            return Ok(environment.clone());
+        }
+        let environment = match self.matching_rule()? {
+            Some(rule) => {
+                debug!(rule = %rule.pattern, environment = %rule.environment, "environment selected by rule");
+                rule.environment
+            }
+            None => self.load_global_config()?.current_environment,
+        };
+        debug!(%environment, "active environment");
+        Ok(environment)
+    }
+
+    /// The rule selecting the environment here, if `auto_switch` is on.
+    pub fn matching_rule(&self) -> Result<Option<EnvironmentRule>> {
+        let config = self.load_global_config()?;
+        if config.environment_rules.is_empty() || !self.load_settings()?.auto_switch() {
+            return Ok(None);
+        }
+
+        let branch = self.git_branch();
+        let dir = self.project_dir().and_then(|project_dir| {
+            let current_dir = std::env::current_dir().ok()?;
+            Some(current_dir.strip_prefix(project_dir).ok()?.to_path_buf())
+        });
+        Ok(config
+            .environment_rules
+            .into_iter()
+            .find(|rule| rule.matches(branch.as_deref(), dir.as_deref())))
+    }
+
+    /// The checked-out git branch of the project, if it's in a git repository
+    /// and HEAD isn't detached.
+    fn git_branch(&self) -> Option<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(self.project_dir()?)
+            .args(["symbolic-ref", "--short", "HEAD"])
+            .output()
+            .ok()?;
+        let branch = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        (output.status.success() && !branch.is_empty()).then_some(branch)
+    }
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of characters.
+pub(super) fn glob_match(pattern: &str, text: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut remaining) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match remaining.find(part) {
+            Some(index) => remaining = &remaining[index + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str) -> EnvironmentRule {
+        EnvironmentRule {
+            pattern: pattern.to_string(),
+            environment: "env".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        assert!(rule("branch:main").matches(Some("main"), None));
+        assert!(!rule("branch:main").matches(Some("maint"), None));
+        assert!(rule("branch:feature/*").matches(Some("feature/login"), None));
+        assert!(rule("branch:*").matches(Some("anything"), None));
+        assert!(!rule("branch:*").matches(None, None));
+        assert!(rule("branch:release-*-rc*").matches(Some("release-2-rc1"), None));
+
+        let dir = Path::new("services/api/src");
+        assert!(rule("dir:services/api").matches(None, Some(dir)));
+        assert!(rule("dir:services/*").matches(None, Some(dir)));
+        assert!(!rule("dir:services/web").matches(None, Some(dir)));
+        assert!(!rule("dir:services/api").matches(None, Some(Path::new(""))));
+        assert!(!rule("main").matches(Some("main"), None));
+    }
+
+    #[test]
+    fn test_session_environment_comes_first() {
+        let mut config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        assert_eq!(config_manager.active_environment().unwrap(), "development");
+
+        config_manager.set_session_environment(Some("staging".to_string()));
+        assert_eq!(config_manager.active_environment().unwrap(), "staging");
+        config_manager.set_session_environment(Some(String::new()));
+        assert_eq!(config_manager.session_environment(), None);
+    }
+}
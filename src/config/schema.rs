@@ -0,0 +1,229 @@
+use super::{ConfigManager, Policy};
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+pub(super) const SCHEMA_FILE: &str = "schema.yaml";
+
+/// Plaintext description of the variables a project expects, safe to commit.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct Schema {
+    #[serde(default)]
+    pub variables: BTreeMap<String, VariableSchema>,
+    /// Constraints on values, checked by `validate` and `set --enforce`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policies: Vec<Policy>,
+    /// Groups of environments expected to set the same keys, checked by
+    /// `lint`; all environments form one group when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parity: Vec<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct VariableSchema {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub secret: bool,
+    /// Placeholder value for examples and templates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub example: Option<String>,
+    /// What the value holds, for exports that distinguish types
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<VariableType>,
+    /// Why the variable shouldn't be used anymore, naming its replacement
+    /// as in `use DATABASE_URL`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+    /// The value must differ between environments, as credentials and
+    /// hostnames should; `lint` reports environments sharing one
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub per_environment: bool,
+}
+
+impl VariableSchema {
+    /// The key a deprecated variable should be renamed to, from a note
+    /// starting with `use KEY`.
+    pub fn replacement(&self) -> Option<&str> {
+        let note = self.deprecated.as_deref()?.trim();
+        let key = note.strip_prefix("use ")?.split_whitespace().next()?;
+        let key = key.trim_end_matches(['.', ',', ';']);
+        Some(key).filter(|key| !key.is_empty())
+    }
+}
+
+/// Values are always stored as strings; a declared type says how to read them.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    String,
+    Number,
+    Bool,
+}
+
+impl VariableType {
+    /// Whether `value` reads as this type: a JSON number, or `true`/`false`.
+    pub fn accepts(self, value: &str) -> bool {
+        match self {
+            VariableType::String => true,
+            VariableType::Number => {
+                value.trim() == value && serde_json::from_str::<serde_json::Number>(value).is_ok()
+            }
+            VariableType::Bool => matches!(value, "true" | "false"),
+        }
+    }
+}
+
+impl Schema {
+    /// The deprecation note of every deprecated variable.
+    pub fn deprecations(&self) -> BTreeMap<String, String> {
+        self.variables
+            .iter()
+            .filter_map(|(key, variable)| Some((key.clone(), variable.deprecated.clone()?)))
+            .collect()
+    }
+
+    /// The environment groups `lint` checks for parity: the declared ones,
+    /// or all of `environments` as one group.
+    pub fn parity_groups(&self, environments: &[String]) -> Vec<Vec<String>> {
+        if self.parity.is_empty() {
+            vec![environments.to_vec()]
+        } else {
+            self.parity.clone()
+        }
+    }
+
+    /// The declared type of every variable that has one.
+    pub fn types(&self) -> BTreeMap<String, VariableType> {
+        self.variables
+            .iter()
+            .filter_map(|(key, variable)| Some((key.clone(), variable.value_type?)))
+            .collect()
+    }
+
+    /// The schema as a JSON Schema (draft 2020-12) for an object of
+    /// variables, for other validators to check environments with. Typed
+    /// values may be written as strings, the way they're stored, or as JSON
+    /// numbers and booleans. Secrets are marked `writeOnly` and get no examples.
+    pub fn to_json_schema(&self) -> Value {
+        let mut properties = Map::new();
+        for (key, variable) in &self.variables {
+            let mut property = match variable.value_type.unwrap_or(VariableType::String) {
+                VariableType::String => json!({ "type": "string" }),
+                VariableType::Number => json!({
+                    "type": ["number", "string"],
+                    "pattern": r"^-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?$",
+                }),
+                VariableType::Bool => json!({ "enum": [true, false, "true", "false"] }),
+            };
+            if let Some(description) = &variable.description {
+                property["description"] = json!(description);
+            }
+            if variable.deprecated.is_some() {
+                property["deprecated"] = json!(true);
+            }
+            match (&variable.example, variable.secret) {
+                (_, true) => property["writeOnly"] = json!(true),
+                (Some(example), false) => property["examples"] = json!([example]),
+                (None, false) => {}
+            }
+            properties.insert(key.clone(), property);
+        }
+        let required: Vec<&String> = self
+            .variables
+            .iter()
+            .filter(|(_, variable)| variable.required)
+            .map(|(key, _)| key)
+            .collect();
+
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "envMatch environment variables",
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": { "type": "string" },
+        })
+    }
+}
+
+impl ConfigManager {
+    /// The project schema, empty if there is none.
+    pub fn load_schema(&self) -> Result<Schema> {
+        let path = self.get_schema_path();
+        match self.storage.read(&path)? {
+            Some(content) => Ok(self
+                .parse_yaml::<Option<Schema>>(&path, &content)?
+                .unwrap_or_default()),
+            None => Ok(Schema::default()),
+        }
+    }
+
+    pub fn save_schema(&self, schema: &Schema) -> Result<()> {
+        self.storage
+            .write(&self.get_schema_path(), &serde_yaml::to_string(schema)?)
+    }
+
+    fn get_schema_path(&self) -> PathBuf {
+        PathBuf::from(SCHEMA_FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_schema() {
+        let mut schema = Schema::default();
+        schema.variables.insert(
+            "PORT".to_string(),
+            VariableSchema {
+                required: true,
+                example: Some("8080".to_string()),
+                value_type: Some(VariableType::Number),
+                ..Default::default()
+            },
+        );
+        schema.variables.insert(
+            "API_KEY".to_string(),
+            VariableSchema {
+                secret: true,
+                example: Some("sk_test".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let json_schema = schema.to_json_schema();
+        assert_eq!(json_schema["required"], json!(["PORT"]));
+        let port = &json_schema["properties"]["PORT"];
+        assert_eq!(port["type"], json!(["number", "string"]));
+        assert_eq!(port["examples"], json!(["8080"]));
+        let api_key = &json_schema["properties"]["API_KEY"];
+        assert_eq!(api_key["writeOnly"], json!(true));
+        assert!(api_key.get("examples").is_none());
+    }
+
+    #[test]
+    fn test_deprecation_replacement() {
+        let deprecated = |note: &str| VariableSchema {
+            deprecated: Some(note.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            deprecated("use DATABASE_URL").replacement(),
+            Some("DATABASE_URL")
+        );
+        assert_eq!(
+            deprecated("use DATABASE_URL, the old name goes away in 2.0").replacement(),
+            Some("DATABASE_URL")
+        );
+        assert_eq!(deprecated("no longer read").replacement(), None);
+        assert_eq!(VariableSchema::default().replacement(), None);
+    }
+}
@@ -0,0 +1,187 @@
+use super::history::HISTORY_DIR;
+use super::sync_state::SYNC_DIR;
+use super::{ConfigManager, EnvConfig, StorageFormat, ENVIRONMENTS_DIR};
+use crate::error::{EnvMatchError, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// Where the packages of a monorepo keep what they override, each under its
+/// path from the project root (e.g. `scopes/services/api/environments`)
+const SCOPES_DIR: &str = "scopes";
+
+impl ConfigManager {
+    /// Works on the package at `scope`, a path relative to the project root.
+    /// Its environments extend the project's ones with the same name: loads
+    /// and saves only touch what the package overrides, and
+    /// [`load_effective_environment`](Self::load_effective_environment) adds
+    /// the rest. `None` or `.` works on the project itself.
+    pub fn set_scope(&mut self, scope: Option<&Path>) -> Result<()> {
+        self.scope = scope.map(normalize_scope).transpose()?.flatten();
+        Ok(())
+    }
+
+    pub fn scope(&self) -> Option<&Path> {
+        self.scope.as_deref()
+    }
+
+    /// An environment as the scope sees it: the project's variables,
+    /// overridden by the scope's own. Just the environment outside a scope.
+    pub fn load_effective_environment(&self, env_name: &str) -> Result<EnvConfig> {
+        let own = self.load_environment(env_name)?;
+        if self.scope.is_none() {
+            return Ok(own);
+        }
+        let mut effective = self.read_environment(env_name, &self.get_env_path(env_name))?;
+        effective.variables.extend(own.variables);
+        effective.metadata.extend(own.metadata);
+        effective.profiles.extend(own.profiles);
+        Ok(effective)
+    }
+
+    /// Encrypts what every scope stores, saved before encryption was turned
+    /// on: its overrides, snapshots and sync bases.
+    pub(super) fn encrypt_scopes(&self) -> Result<()> {
+        for path in self.storage.list_recursive(Path::new(SCOPES_DIR))? {
+            let Some(dir) = path.parent() else {
+                continue;
+            };
+            match dir.file_name().and_then(|name| name.to_str()) {
+                Some(ENVIRONMENTS_DIR) => {
+                    let env_name = path.file_stem().and_then(|stem| stem.to_str());
+                    // Skips `.bak` copies of files that didn't parse
+                    if let (Some(env_name), Some(_)) = (env_name, StorageFormat::of_path(&path)) {
+                        let env_config = self.read_environment(env_name, &path)?;
+                        self.save_environment_in(dir, env_name, &env_config)?;
+                    }
+                }
+                Some(HISTORY_DIR) => self.encrypt_snapshot_file(&path)?,
+                Some(SYNC_DIR) => self.encrypt_sync_state_file(&path)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// `dir` of `.envMatch`, or of the scope's directory under it.
+    pub(super) fn get_scoped_dir(&self, dir: &str) -> PathBuf {
+        match &self.scope {
+            Some(scope) => Path::new(SCOPES_DIR).join(scope).join(dir),
+            None => PathBuf::from(dir),
+        }
+    }
+
+    /// Drops what the scope overrides in an environment, or the environment
+    /// itself outside a scope.
+    pub(super) fn remove_scoped_environment(&self, env_name: &str) -> Result<()> {
+        match self.find_stored(&self.get_scoped_dir(ENVIRONMENTS_DIR), env_name) {
+            Some(path) => self.storage.remove(&path),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `services/./api/` as `services/api`; `None` for the project root. Paths
+/// leaving the project are rejected.
+fn normalize_scope(scope: &Path) -> Result<Option<PathBuf>> {
+    let mut normalized = PathBuf::new();
+    for component in scope.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(EnvMatchError::InvalidScope {
+                    scope: scope.to_path_buf(),
+                })
+            }
+        }
+    }
+    Ok(Some(normalized).filter(|normalized| !normalized.as_os_str().is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_extends_project_environment() {
+        let mut config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        let mut root = EnvConfig::default();
+        root.set("HOST", "db");
+        root.set("PORT", "5432");
+        config_manager
+            .save_environment("development", &root)
+            .unwrap();
+
+        config_manager
+            .set_scope(Some(Path::new("./services/api/")))
+            .unwrap();
+        assert_eq!(config_manager.scope(), Some(Path::new("services/api")));
+        assert!(config_manager
+            .load_environment("development")
+            .unwrap()
+            .variables
+            .is_empty());
+        let mut api = EnvConfig::default();
+        api.set("PORT", "8080");
+        config_manager
+            .save_environment("development", &api)
+            .unwrap();
+
+        let effective = config_manager
+            .load_effective_environment("development")
+            .unwrap();
+        assert_eq!(effective.variables["HOST"], "db");
+        assert_eq!(effective.variables["PORT"], "8080");
+
+        config_manager.set_scope(None).unwrap();
+        let root = config_manager.load_environment("development").unwrap();
+        assert_eq!(root.variables["PORT"], "5432");
+
+        for invalid in ["../other", "/abs"] {
+            assert!(matches!(
+                config_manager.set_scope(Some(Path::new(invalid))),
+                Err(EnvMatchError::InvalidScope { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_enable_encryption_covers_scope_overrides() {
+        let mut config_manager = ConfigManager::in_memory();
+        config_manager.initialize().unwrap();
+        let mut root = EnvConfig::default();
+        root.set("DB_PASSWORD", "root-secret");
+        config_manager
+            .save_environment("development", &root)
+            .unwrap();
+        config_manager
+            .set_scope(Some(Path::new("services/api")))
+            .unwrap();
+        let mut api = EnvConfig::default();
+        api.set("API_KEY", "api-secret");
+        config_manager
+            .save_environment("development", &api)
+            .unwrap();
+        config_manager
+            .record_snapshot("set API_KEY", &["development".to_string()])
+            .unwrap();
+
+        // Turned on from inside the scope, it still covers the project's files
+        config_manager.enable_encryption("passphrase").unwrap();
+        for path in config_manager
+            .storage
+            .list_recursive(Path::new(""))
+            .unwrap()
+        {
+            let content = config_manager.storage.read(&path).unwrap().unwrap();
+            assert!(!content.contains("root-secret"), "{}", path.display());
+            assert!(!content.contains("api-secret"), "{}", path.display());
+        }
+
+        let effective = config_manager
+            .load_effective_environment("development")
+            .unwrap();
+        assert_eq!(effective.variables["DB_PASSWORD"], "root-secret");
+        assert_eq!(effective.variables["API_KEY"], "api-secret");
+    }
+}
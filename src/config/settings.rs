@@ -0,0 +1,438 @@
+use super::{SecretPattern, StorageFormat};
+use crate::error::{EnvMatchError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const USER_CONFIG_DIR: &str = "envmatch";
+const USER_CONFIG_FILE: &str = "config.yaml";
+const DEFAULT_MESSAGE_TIMEOUT: u64 = 4;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Whether output should be styled. `auto` only colors a terminal, and
+    /// not when `NO_COLOR` is set.
+    pub fn enabled(self, is_terminal: bool, no_color_env: bool) -> bool {
+        match self {
+            ColorMode::Auto => is_terminal && !no_color_env,
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+/// Which variable names `set` accepts.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyNames {
+    /// Letters, digits and underscores, not starting with a digit, as POSIX
+    /// shells and most tools expect
+    #[default]
+    Posix,
+    /// Anything a process environment can hold: no `=`, no NUL
+    Relaxed,
+}
+
+impl KeyNames {
+    pub fn check(self, key: &str) -> Result<()> {
+        let reason = if key.is_empty() {
+            Some("it is empty")
+        } else if key.contains(['=', '\0']) {
+            Some("environment variable names can't contain '=' or NUL")
+        } else if self == KeyNames::Posix {
+            if key.starts_with(|c: char| c.is_ascii_digit()) {
+                Some("it starts with a digit")
+            } else if !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                Some("only letters, digits and '_' are allowed")
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => Err(EnvMatchError::InvalidVariableKey {
+                key: key.to_string(),
+                reason: reason.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Whether `!cmd` values may run their command to produce a value.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandValues {
+    #[default]
+    Off,
+    /// Ask before running each one
+    Confirm,
+    Allow,
+}
+
+/// Order of the TUI's variables panel.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VariablesSort {
+    /// Under group headers, by group then key
+    #[default]
+    Group,
+    Alphabetical,
+    /// Most recently changed first
+    Recent,
+}
+
+impl VariablesSort {
+    /// The order `S` switches to next.
+    pub fn next(self) -> Self {
+        match self {
+            VariablesSort::Group => VariablesSort::Alphabetical,
+            VariablesSort::Alphabetical => VariablesSort::Recent,
+            VariablesSort::Recent => VariablesSort::Group,
+        }
+    }
+}
+
+/// Colors of the TUI.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Bright colors, for dark terminal backgrounds
+    #[default]
+    Dark,
+    /// Darker shades that stay readable on light backgrounds
+    Light,
+    /// No colors; highlights are shown reversed
+    Monochrome,
+}
+
+/// How the TUI's variables panel is laid out, changed with `S` and `o`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct VariablesView {
+    pub sort: VariablesSort,
+    /// Show each variable's description after its value
+    pub descriptions: bool,
+    /// Cut values longer than this many characters; 0 shows them whole
+    pub truncate: usize,
+    /// Show how long values are instead of the values
+    pub value_lengths: bool,
+}
+
+/// Preferences that can be set per user and overridden per project.
+///
+/// Every field is optional so that a project config only overrides what it
+/// actually sets, falling back to the user config and then to the defaults.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_environment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask_values: Option<bool>,
+    /// Follow the project's `environment_rules` instead of the current environment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_switch: Option<bool>,
+    /// Seconds TUI messages stay up; 0 keeps them until newer ones push them out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_names: Option<KeyNames>,
+    /// Secret formats to warn about besides the built-in ones
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret_patterns: Option<Vec<SecretPattern>>,
+    /// Format new and re-saved environment files are written in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_format: Option<StorageFormat>,
+    /// Ask before switching environments in the TUI, showing what changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirm_switch: Option<bool>,
+    /// Create environments on first use instead of failing, as before
+    /// `env create` existed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_create_environments: Option<bool>,
+    /// Sorting and display options of the TUI's variables panel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variables_view: Option<VariablesView>,
+    /// Whether `!cmd` values run their command; only read from the user config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command_values: Option<CommandValues>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    /// Editor for `edit`, with its arguments, over `$VISUAL` and `$EDITOR`;
+    /// only read from the user config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+}
+
+impl Settings {
+    /// Location of the user config: `$XDG_CONFIG_HOME/envmatch/config.yaml`,
+    /// or `~/.config/envmatch/config.yaml` when XDG_CONFIG_HOME is unset.
+    pub fn user_config_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+
+        Some(config_home.join(USER_CONFIG_DIR).join(USER_CONFIG_FILE))
+    }
+
+    pub fn load_user(path: Option<&PathBuf>) -> Result<Self> {
+        match path {
+            Some(path) if path.is_file() => {
+                let content =
+                    fs::read_to_string(path).map_err(|e| EnvMatchError::storage(path, e))?;
+                // An empty file is a valid (empty) config
+                Ok(serde_yaml::from_str::<Option<Self>>(&content)?.unwrap_or_default())
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Writes these settings as the user config at `path`.
+    pub fn save_user(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| EnvMatchError::storage(dir, e))?;
+        }
+        fs::write(path, serde_yaml::to_string(self)?).map_err(|e| EnvMatchError::storage(path, e))
+    }
+
+    /// Returns these settings with every value set in `overrides` taking precedence.
+    pub fn layered_with(self, overrides: &Settings) -> Self {
+        Self {
+            default_environment: overrides
+                .default_environment
+                .clone()
+                .or(self.default_environment),
+            color: overrides.color.or(self.color),
+            mask_values: overrides.mask_values.or(self.mask_values),
+            auto_switch: overrides.auto_switch.or(self.auto_switch),
+            message_timeout: overrides.message_timeout.or(self.message_timeout),
+            key_names: overrides.key_names.or(self.key_names),
+            secret_patterns: overrides.secret_patterns.clone().or(self.secret_patterns),
+            storage_format: overrides.storage_format.or(self.storage_format),
+            confirm_switch: overrides.confirm_switch.or(self.confirm_switch),
+            auto_create_environments: overrides
+                .auto_create_environments
+                .or(self.auto_create_environments),
+            variables_view: overrides.variables_view.or(self.variables_view),
+            theme: overrides.theme.or(self.theme),
+            // A project's files come from whoever commits them, so only the
+            // user decides whether its values may run commands, and which
+            // command edits them
+            command_values: self.command_values,
+            editor: self.editor,
+        }
+    }
+
+    pub fn default_environment(&self) -> &str {
+        self.default_environment
+            .as_deref()
+            .unwrap_or(super::DEFAULT_ENVIRONMENT)
+    }
+
+    pub fn mask_values(&self) -> bool {
+        self.mask_values.unwrap_or(false)
+    }
+
+    pub fn auto_switch(&self) -> bool {
+        self.auto_switch.unwrap_or(false)
+    }
+
+    pub fn key_names(&self) -> KeyNames {
+        self.key_names.unwrap_or_default()
+    }
+
+    pub fn secret_patterns(&self) -> &[SecretPattern] {
+        self.secret_patterns.as_deref().unwrap_or_default()
+    }
+
+    pub fn confirm_switch(&self) -> bool {
+        self.confirm_switch.unwrap_or(false)
+    }
+
+    pub fn auto_create_environments(&self) -> bool {
+        self.auto_create_environments.unwrap_or(false)
+    }
+
+    pub fn variables_view(&self) -> VariablesView {
+        self.variables_view.unwrap_or_default()
+    }
+
+    pub fn command_values(&self) -> CommandValues {
+        self.command_values.unwrap_or_default()
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.theme.unwrap_or_default()
+    }
+
+    pub fn editor(&self) -> Option<&str> {
+        self.editor
+            .as_deref()
+            .filter(|editor| !editor.trim().is_empty())
+    }
+
+    pub fn storage_format(&self) -> StorageFormat {
+        self.storage_format.unwrap_or_default()
+    }
+
+    pub fn message_timeout(&self) -> Option<Duration> {
+        match self.message_timeout.unwrap_or(DEFAULT_MESSAGE_TIMEOUT) {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        }
+    }
+
+    /// Turns `colored` output on or off for the whole process. `no_color`
+    /// (from `--no-color`) beats the setting, which beats `NO_COLOR`.
+    pub fn apply_color(&self, no_color: bool) {
+        let mode = if no_color {
+            ColorMode::Never
+        } else {
+            self.color.unwrap_or_default()
+        };
+        let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        colored::control::set_override(mode.enabled(io::stdout().is_terminal(), no_color_env));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_layering_prefers_overrides() {
+        let user = Settings {
+            default_environment: Some("local".to_string()),
+            color: Some(ColorMode::Never),
+            mask_values: None,
+            auto_switch: None,
+            message_timeout: Some(0),
+            key_names: None,
+            secret_patterns: None,
+            storage_format: None,
+            confirm_switch: None,
+            auto_create_environments: None,
+            variables_view: None,
+            command_values: Some(CommandValues::Confirm),
+            theme: Some(Theme::Light),
+            editor: Some("code --wait".to_string()),
+        };
+        let project = Settings {
+            default_environment: None,
+            color: Some(ColorMode::Always),
+            mask_values: Some(true),
+            auto_switch: None,
+            message_timeout: None,
+            key_names: Some(KeyNames::Relaxed),
+            secret_patterns: None,
+            storage_format: None,
+            confirm_switch: None,
+            auto_create_environments: None,
+            variables_view: None,
+            command_values: Some(CommandValues::Allow),
+            theme: Some(Theme::Monochrome),
+            editor: Some("./run-me.sh".to_string()),
+        };
+
+        let settings = user.layered_with(&project);
+        assert_eq!(settings.default_environment(), "local");
+        assert_eq!(settings.color, Some(ColorMode::Always));
+        assert!(settings.mask_values());
+        assert_eq!(settings.message_timeout(), None);
+        assert_eq!(settings.key_names(), KeyNames::Relaxed);
+        assert_eq!(settings.command_values(), CommandValues::Confirm);
+        assert_eq!(settings.theme(), Theme::Monochrome);
+        assert_eq!(settings.editor(), Some("code --wait"));
+        assert_eq!(Settings::default().theme(), Theme::Dark);
+        assert_eq!(Settings::default().editor(), None);
+        assert_eq!(Settings::default().default_environment(), "development");
+        assert_eq!(
+            Settings::default().message_timeout(),
+            Some(Duration::from_secs(4))
+        );
+    }
+
+    #[test]
+    fn test_color_mode_enabled() {
+        assert!(ColorMode::Auto.enabled(true, false));
+        assert!(!ColorMode::Auto.enabled(false, false));
+        assert!(!ColorMode::Auto.enabled(true, true));
+        assert!(ColorMode::Always.enabled(false, true));
+        assert!(!ColorMode::Never.enabled(true, false));
+    }
+
+    #[test]
+    fn test_key_names() {
+        assert!(KeyNames::Posix.check("DATABASE_URL").is_ok());
+        assert!(KeyNames::Posix.check("_private1").is_ok());
+        for key in ["", "1ST", "MY KEY", "A=B", "app.port"] {
+            assert!(matches!(
+                KeyNames::Posix.check(key),
+                Err(EnvMatchError::InvalidVariableKey { .. })
+            ));
+        }
+        assert!(KeyNames::Relaxed.check("app.port").is_ok());
+        assert!(KeyNames::Relaxed.check("1ST").is_ok());
+        assert!(KeyNames::Relaxed.check("A=B").is_err());
+    }
+
+    #[test]
+    fn test_load_user_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(USER_CONFIG_FILE);
+
+        assert_eq!(
+            Settings::load_user(Some(&path)).unwrap(),
+            Settings::default()
+        );
+
+        fs::write(&path, "").unwrap();
+        assert_eq!(
+            Settings::load_user(Some(&path)).unwrap(),
+            Settings::default()
+        );
+
+        fs::write(
+            &path,
+            "default_environment: local\ncolor: never\ntheme: light\neditor: nano -w\n",
+        )
+        .unwrap();
+        let settings = Settings::load_user(Some(&path)).unwrap();
+        assert_eq!(settings.default_environment(), "local");
+        assert_eq!(settings.color, Some(ColorMode::Never));
+        assert_eq!(settings.theme(), Theme::Light);
+        assert_eq!(settings.editor(), Some("nano -w"));
+
+        let view = VariablesView {
+            sort: VariablesSort::Recent,
+            truncate: 40,
+            ..Default::default()
+        };
+        let nested = temp_dir.path().join("envmatch").join(USER_CONFIG_FILE);
+        Settings {
+            variables_view: Some(view),
+            ..settings
+        }
+        .save_user(&nested)
+        .unwrap();
+        let saved = Settings::load_user(Some(&nested)).unwrap();
+        assert_eq!(saved.variables_view(), view);
+        assert_eq!(saved.default_environment(), "local");
+    }
+}
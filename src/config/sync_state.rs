@@ -0,0 +1,110 @@
+use super::ConfigManager;
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub(super) const SYNC_DIR: &str = "sync";
+
+/// What a remote looked like the last time an environment was synced with it.
+///
+/// It's the common ancestor for the next sync: anything that differs from it
+/// has changed since, locally or remotely.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SyncState {
+    pub synced_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub variables: IndexMap<String, String>,
+}
+
+impl ConfigManager {
+    pub fn load_sync_state(&self, env_name: &str, remote: &str) -> Result<Option<SyncState>> {
+        Ok(self.load_sync_states(env_name)?.remove(remote))
+    }
+
+    pub fn save_sync_state(&self, env_name: &str, remote: &str, state: SyncState) -> Result<()> {
+        let mut states = self.load_sync_states(env_name)?;
+        states.insert(remote.to_string(), state);
+        self.write_sync_states(env_name, &states)
+    }
+
+    /// Sync states of an environment, keyed by remote.
+    pub fn load_sync_states(&self, env_name: &str) -> Result<BTreeMap<String, SyncState>> {
+        let path = self.get_sync_state_path(env_name);
+        let Some(content) = self.storage.read(&path)? else {
+            return Ok(BTreeMap::new());
+        };
+        let mut states: BTreeMap<String, SyncState> = self.parse_yaml(&path, &content)?;
+        for (remote, state) in states.iter_mut() {
+            self.decrypt_values(&sync_context(env_name, remote), &mut state.variables)?;
+        }
+        Ok(states)
+    }
+
+    /// Writes the sync states of an environment, with their values
+    /// encrypted like the environment's.
+    fn write_sync_states(
+        &self,
+        env_name: &str,
+        states: &BTreeMap<String, SyncState>,
+    ) -> Result<()> {
+        self.write_sync_states_to(&self.get_sync_state_path(env_name), env_name, states)
+    }
+
+    fn write_sync_states_to(
+        &self,
+        path: &Path,
+        env_name: &str,
+        states: &BTreeMap<String, SyncState>,
+    ) -> Result<()> {
+        let stored = states
+            .iter()
+            .map(|(remote, state)| {
+                let state = SyncState {
+                    variables: self
+                        .encrypt_values(&sync_context(env_name, remote), &state.variables)?,
+                    ..state.clone()
+                };
+                Ok((remote.clone(), state))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        self.storage.write(path, &serde_yaml::to_string(&stored)?)
+    }
+
+    /// Encrypts the sync states saved before encryption was turned on.
+    pub(super) fn encrypt_sync_states(&self) -> Result<()> {
+        for file_name in self.storage.list(Path::new(SYNC_DIR))? {
+            self.encrypt_sync_state_file(&Path::new(SYNC_DIR).join(file_name))?;
+        }
+        Ok(())
+    }
+
+    /// Encrypts one file of sync states in place.
+    pub(super) fn encrypt_sync_state_file(&self, path: &Path) -> Result<()> {
+        let Some(env_name) = path
+            .file_name()
+            .and_then(|name| name.to_str()?.strip_suffix(".yaml"))
+        else {
+            return Ok(());
+        };
+        let Some(content) = self.storage.read(path)? else {
+            return Ok(());
+        };
+        let states: BTreeMap<String, SyncState> = self.parse_yaml(path, &content)?;
+        self.write_sync_states_to(path, env_name, &states)
+    }
+
+    fn get_sync_state_path(&self, env_name: &str) -> PathBuf {
+        self.get_scoped_dir(SYNC_DIR)
+            .join(format!("{}.yaml", env_name))
+    }
+}
+
+/// Where a sync base's values are sealed: apart from the environment's own
+/// values, so one can't stand in for the other.
+fn sync_context<'a>(env_name: &'a str, remote: &'a str) -> [&'a str; 3] {
+    [env_name, "sync", remote]
+}
@@ -0,0 +1,158 @@
+use super::audit::hex;
+use super::{ConfigManager, ENVIRONMENTS_DIR};
+use crate::error::{EnvMatchError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+const TRUSTED_FILE: &str = "trusted.yaml";
+
+/// Projects the shell hook may load, kept next to the user config where a
+/// cloned repository can't add itself. Each is allowed as its environment
+/// files were when `hook allow` ran; once they change, it has to be
+/// allowed again.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+struct TrustedProjects {
+    /// Project root to the hash of its environment files
+    #[serde(default)]
+    projects: BTreeMap<PathBuf, String>,
+}
+
+impl ConfigManager {
+    /// Whether the hook may load this project's environments as they are
+    /// now. Never, when there's no user config location to allow them in.
+    pub fn is_project_trusted(&self) -> Result<bool> {
+        let Some(project_dir) = self.canonical_project_dir() else {
+            return Ok(false);
+        };
+        let trusted = self.load_trusted_projects()?;
+        Ok(trusted.projects.get(&project_dir) == Some(&self.environments_hash()?))
+    }
+
+    /// Allows the hook to load this project's environments as they are now.
+    pub fn trust_project(&self) -> Result<PathBuf> {
+        let project_dir = self
+            .canonical_project_dir()
+            .ok_or(EnvMatchError::NotInitialized)?;
+        let mut trusted = self.load_trusted_projects()?;
+        trusted
+            .projects
+            .insert(project_dir.clone(), self.environments_hash()?);
+        self.save_trusted_projects(&trusted)?;
+        Ok(project_dir)
+    }
+
+    /// Stops the hook from loading this project. Returns whether it was
+    /// allowed.
+    pub fn distrust_project(&self) -> Result<bool> {
+        let Some(project_dir) = self.canonical_project_dir() else {
+            return Ok(false);
+        };
+        let mut trusted = self.load_trusted_projects()?;
+        if trusted.projects.remove(&project_dir).is_none() {
+            return Ok(false);
+        }
+        self.save_trusted_projects(&trusted)?;
+        Ok(true)
+    }
+
+    pub(super) fn canonical_project_dir(&self) -> Option<PathBuf> {
+        let dir = self.project_dir()?;
+        Some(dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()))
+    }
+
+    /// SHA-256 over the names and contents of the project's environment
+    /// files, and the scope's when there is one.
+    fn environments_hash(&self) -> Result<String> {
+        let mut hasher = Sha256::new();
+        let mut dirs = vec![PathBuf::from(ENVIRONMENTS_DIR)];
+        if self.scope().is_some() {
+            dirs.push(self.get_scoped_dir(ENVIRONMENTS_DIR));
+        }
+        for dir in dirs {
+            let mut names = self.storage.list(&dir)?;
+            names.sort();
+            for name in names {
+                let content = self.storage.read(&dir.join(&name))?.unwrap_or_default();
+                for part in [
+                    dir.join(&name).to_string_lossy().as_bytes(),
+                    content.as_bytes(),
+                ] {
+                    hasher.update((part.len() as u64).to_be_bytes());
+                    hasher.update(part);
+                }
+            }
+        }
+        Ok(hex(&hasher.finalize()))
+    }
+
+    fn load_trusted_projects(&self) -> Result<TrustedProjects> {
+        let Some(path) = self.trusted_projects_path() else {
+            return Ok(TrustedProjects::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => Ok(self
+                .parse_yaml::<Option<TrustedProjects>>(&path, &content)?
+                .unwrap_or_default()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(TrustedProjects::default()),
+            Err(e) => Err(EnvMatchError::storage(&path, e)),
+        }
+    }
+
+    fn save_trusted_projects(&self, trusted: &TrustedProjects) -> Result<()> {
+        let Some(path) = self.trusted_projects_path() else {
+            let message = "no user config directory to keep allowed projects in";
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, message).into());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| EnvMatchError::storage(dir, e))?;
+        }
+        fs::write(&path, serde_yaml::to_string(trusted)?)
+            .map_err(|e| EnvMatchError::storage(&path, e))
+    }
+
+    fn trusted_projects_path(&self) -> Option<PathBuf> {
+        self.user_config_path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map(|dir| dir.join(TRUSTED_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_trust_lapses_when_environments_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("app");
+        fs::create_dir_all(&project_dir).unwrap();
+        let config_manager = ConfigManager {
+            user_config_path: Some(temp_dir.path().join("envmatch").join("config.yaml")),
+            ..ConfigManager::with_project_dir(project_dir.clone())
+        };
+        config_manager.initialize().unwrap();
+
+        assert!(!config_manager.is_project_trusted().unwrap());
+        let allowed = config_manager.trust_project().unwrap();
+        assert_eq!(allowed, project_dir.canonicalize().unwrap());
+        assert!(config_manager.is_project_trusted().unwrap());
+
+        let mut env = config_manager.load_environment("development").unwrap();
+        env.variables
+            .insert("PATH".to_string(), "/tmp/evil".to_string());
+        config_manager
+            .save_environment("development", &env)
+            .unwrap();
+        assert!(!config_manager.is_project_trusted().unwrap());
+
+        config_manager.trust_project().unwrap();
+        assert!(config_manager.distrust_project().unwrap());
+        assert!(!config_manager.is_project_trusted().unwrap());
+        assert!(!config_manager.distrust_project().unwrap());
+    }
+}
@@ -0,0 +1,214 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use std::fmt;
+use std::str::FromStr;
+
+const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+/// Symbols that need no quoting or escaping in dotenv files, shells, URLs
+/// or cmd, as long as they don't come first: a leading `-` reads as an
+/// option and a leading `~` as a home directory
+const SAFE_SYMBOLS: &[u8] = b"-_.~";
+/// More variety for systems that ask for it, at the cost of quoting: `#`
+/// starts a comment, `!` expands history in bash, `@#%` break URLs and `%^`
+/// are special to cmd
+const WIDE_SYMBOLS: &[u8] = b"-_.~!@#%^*+=";
+const MAX_SIZE: usize = 1024;
+
+/// A kind of random value `set --generate` can create, e.g. `hex:32`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Generator {
+    /// `hex:<bytes>`: that many random bytes, hex-encoded
+    Hex(usize),
+    /// `base64:<bytes>`: that many random bytes, base64-encoded
+    Base64(usize),
+    /// `uuid`: a random (version 4) UUID
+    Uuid,
+    /// `password:<length>[:alnum|:wide]`: letters, digits and symbols, at
+    /// least one of each
+    Password { length: usize, symbols: Symbols },
+}
+
+/// Which symbols a generated password may use.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Symbols {
+    /// Only `-_.~`, which are safe unquoted almost anywhere
+    Safe,
+    /// `-_.~!@#%^*+=`, which have to be quoted in shells and escaped in URLs
+    Wide,
+    /// No symbols at all: `alnum`
+    None,
+}
+
+impl Generator {
+    pub fn generate(&self) -> String {
+        match *self {
+            Generator::Hex(bytes) => random_bytes(bytes)
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+            Generator::Base64(bytes) => STANDARD.encode(random_bytes(bytes)),
+            Generator::Uuid => {
+                let mut bytes = random_bytes(16);
+                bytes[6] = (bytes[6] & 0x0f) | 0x40;
+                bytes[8] = (bytes[8] & 0x3f) | 0x80;
+                let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                )
+            }
+            Generator::Password { length, symbols } => {
+                let mut classes = vec![LOWER, UPPER, DIGITS];
+                match symbols {
+                    Symbols::Safe => classes.push(SAFE_SYMBOLS),
+                    Symbols::Wide => classes.push(WIDE_SYMBOLS),
+                    Symbols::None => {}
+                }
+                let charset = classes.concat();
+                // Drawing again until every class shows up, and a letter or
+                // digit comes first, keeps every accepted password equally
+                // likely
+                loop {
+                    let password: Vec<u8> = (0..length).map(|_| pick(&charset)).collect();
+                    if password[0].is_ascii_alphanumeric()
+                        && classes
+                            .iter()
+                            .all(|class| password.iter().any(|c| class.contains(c)))
+                    {
+                        return String::from_utf8(password).expect("charset is ASCII");
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Generator {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = spec.split(':').collect();
+        match parts.as_slice() {
+            ["hex", bytes] => Ok(Generator::Hex(parse_size(bytes, 1)?)),
+            ["base64", bytes] => Ok(Generator::Base64(parse_size(bytes, 1)?)),
+            ["uuid"] => Ok(Generator::Uuid),
+            ["password", length] => Ok(Generator::Password {
+                length: parse_size(length, 4)?,
+                symbols: Symbols::Safe,
+            }),
+            ["password", length, "wide"] => Ok(Generator::Password {
+                length: parse_size(length, 4)?,
+                symbols: Symbols::Wide,
+            }),
+            ["password", length, "alnum"] => Ok(Generator::Password {
+                length: parse_size(length, 4)?,
+                symbols: Symbols::None,
+            }),
+            ["password", _, policy] => Err(format!("unknown password policy '{}'", policy)),
+            _ => Err(format!(
+                "'{}' is not a generator: expected hex:<bytes>, base64:<bytes>, uuid or password:<length>[:alnum|:wide]",
+                spec
+            )),
+        }
+    }
+}
+
+fn parse_size(size: &str, min: usize) -> Result<usize, String> {
+    match size.parse() {
+        Ok(size) if (min..=MAX_SIZE).contains(&size) => Ok(size),
+        _ => Err(format!(
+            "'{}' is not a size from {} to {}",
+            size, min, MAX_SIZE
+        )),
+    }
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Generator::Hex(bytes) => write!(f, "hex:{}", bytes),
+            Generator::Base64(bytes) => write!(f, "base64:{}", bytes),
+            Generator::Uuid => write!(f, "uuid"),
+            Generator::Password { length, symbols } => match symbols {
+                Symbols::Safe => write!(f, "password:{}", length),
+                Symbols::Wide => write!(f, "password:{}:wide", length),
+                Symbols::None => write!(f, "password:{}:alnum", length),
+            },
+        }
+    }
+}
+
+fn random_bytes(count: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; count];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// A uniformly random character of `charset`.
+fn pick(charset: &[u8]) -> u8 {
+    // Values past the last full multiple of the length would favour the first characters
+    let limit = u32::MAX - u32::MAX % charset.len() as u32;
+    loop {
+        let value = OsRng.next_u32();
+        if value < limit {
+            return charset[(value % charset.len() as u32) as usize];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generators() {
+        let hex = "hex:32".parse::<Generator>().unwrap().generate();
+        assert_eq!(hex.len(), 64);
+        assert!(hex.chars().all(|c| c.is_ascii_hexdigit()));
+
+        let uuid = "uuid".parse::<Generator>().unwrap().generate();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+
+        let password = "password:12:alnum".parse::<Generator>().unwrap();
+        let value = password.generate();
+        assert_eq!(value.len(), 12);
+        assert!(value.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert!(value.chars().any(|c| c.is_ascii_digit()));
+        assert_eq!(password.to_string(), "password:12:alnum");
+
+        for _ in 0..50 {
+            let value = "password:8".parse::<Generator>().unwrap().generate();
+            assert!(value.starts_with(|c: char| c.is_ascii_alphanumeric()));
+            assert!(value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.~".contains(c)));
+            assert!(value.chars().any(|c| "-_.~".contains(c)));
+        }
+        let wide = "password:16:wide".parse::<Generator>().unwrap();
+        assert_eq!(wide.to_string(), "password:16:wide");
+        assert!(wide
+            .generate()
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || WIDE_SYMBOLS.contains(&(c as u8))));
+
+        for spec in [
+            "hex",
+            "hex:0",
+            "hex:lots",
+            "password:3",
+            "md5:8",
+            "uuid:4",
+            "password:8:emoji",
+        ] {
+            assert!(spec.parse::<Generator>().is_err(), "{}", spec);
+        }
+    }
+}
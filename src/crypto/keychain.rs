@@ -0,0 +1,37 @@
+use super::MasterKey;
+use crate::error::{EnvMatchError, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use keyring::Entry;
+
+/// Service name the keys are filed under in the OS keychain.
+const SERVICE: &str = "envMatch";
+
+/// The key stored for `account`, if any.
+///
+/// An unavailable keychain (e.g. no Secret Service on a headless Linux box)
+/// counts as no key, so envMatch falls back to asking for the passphrase.
+pub fn load_key(account: &str) -> Option<MasterKey> {
+    let encoded = Entry::new(SERVICE, account).ok()?.get_password().ok()?;
+    STANDARD.decode(encoded).ok()?.try_into().ok()
+}
+
+pub fn store_key(account: &str, key: &MasterKey) -> Result<()> {
+    Entry::new(SERVICE, account)
+        .and_then(|entry| entry.set_password(&STANDARD.encode(key)))
+        .map_err(keychain_error)
+}
+
+/// Removes the key stored for `account`, returning whether there was one.
+pub fn forget_key(account: &str) -> Result<bool> {
+    match Entry::new(SERVICE, account).and_then(|entry| entry.delete_credential()) {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(error) => Err(keychain_error(error)),
+    }
+}
+
+fn keychain_error(error: keyring::Error) -> EnvMatchError {
+    EnvMatchError::KeychainError {
+        message: error.to_string(),
+    }
+}
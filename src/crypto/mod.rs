@@ -0,0 +1,271 @@
+use crate::error::{EnvMatchError, Result};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+use std::io::IsTerminal;
+
+pub mod generate;
+pub mod keychain;
+
+const PREFIX: &str = "ENC[v2,";
+const SUFFIX: &str = "]";
+const NONCE_LEN: usize = 24;
+pub const SALT_LEN: usize = 16;
+pub const PASSPHRASE_ENV: &str = "ENVMATCH_PASSPHRASE";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Encrypts variable values with a key derived from the project passphrase.
+///
+/// Encryption is deterministic: the nonce is a keyed hash of the variable
+/// name and value, so an unchanged value keeps the same ciphertext. That keeps
+/// git diffs down to the keys that really changed and lets the merge driver
+/// compare values without decrypting them. The name and where the value is
+/// stored (its environment, and profile for an override) are bound as
+/// associated data, so a ciphertext can't be moved to another key or into
+/// another environment.
+pub struct Cipher {
+    aead: XChaCha20Poly1305,
+    nonce_key: [u8; 32],
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Cipher { .. }")
+    }
+}
+
+pub type MasterKey = [u8; 32];
+
+/// Stretches a passphrase into the key every value is encrypted with.
+pub fn derive_master_key(passphrase: &str, salt: &[u8]) -> Result<MasterKey> {
+    let mut master_key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut master_key)
+        .map_err(|error| EnvMatchError::EncryptionError {
+            message: error.to_string(),
+        })?;
+    Ok(master_key)
+}
+
+impl Cipher {
+    pub fn derive(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        Ok(Self::from_master_key(&derive_master_key(passphrase, salt)?))
+    }
+
+    pub fn from_master_key(master_key: &MasterKey) -> Self {
+        Self {
+            aead: XChaCha20Poly1305::new(&subkey(master_key, "envmatch encryption").into()),
+            nonce_key: subkey(master_key, "envmatch nonce"),
+        }
+    }
+
+    /// Encrypts the value of `name` stored at `context`, e.g. `[env]` or
+    /// `[env, profile]`; it only decrypts with the same name and context.
+    pub fn encrypt(&self, context: &[&str], name: &str, value: &str) -> String {
+        let aad = associated_data(context, name);
+        let mut mac =
+            <HmacSha256 as Mac>::new_from_slice(&self.nonce_key).expect("HMAC accepts any key");
+        mac.update(&aad);
+        mac.update(&[0]);
+        mac.update(value.as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let nonce = XNonce::from_slice(&digest[..NONCE_LEN]);
+
+        let ciphertext = self
+            .aead
+            .encrypt(
+                nonce,
+                Payload {
+                    msg: value.as_bytes(),
+                    aad: &aad,
+                },
+            )
+            .expect("encryption with a valid key and nonce can't fail");
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        format!("{}{}{}", PREFIX, STANDARD.encode(sealed), SUFFIX)
+    }
+
+    pub fn decrypt(&self, context: &[&str], name: &str, value: &str) -> Result<String> {
+        let failed = || EnvMatchError::DecryptionFailed {
+            key: name.to_string(),
+        };
+
+        let encoded = value
+            .strip_prefix(PREFIX)
+            .and_then(|rest| rest.strip_suffix(SUFFIX))
+            .ok_or_else(failed)?;
+        let sealed = STANDARD.decode(encoded).map_err(|_| failed())?;
+        if sealed.len() < NONCE_LEN {
+            return Err(failed());
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .aead
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &associated_data(context, name),
+                },
+            )
+            .map_err(|_| failed())?;
+        String::from_utf8(plaintext).map_err(|_| failed())
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX) && value.ends_with(SUFFIX)
+}
+
+/// `context` and `name`, each prefixed with its length so no two
+/// different lists run together into the same bytes.
+fn associated_data(context: &[&str], name: &str) -> Vec<u8> {
+    let mut aad = Vec::new();
+    for part in context.iter().chain([&name]) {
+        aad.extend((part.len() as u64).to_be_bytes());
+        aad.extend(part.as_bytes());
+    }
+    aad
+}
+
+pub fn random_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+pub fn random_key() -> [u8; 32] {
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
+/// Hex-encoded HMAC-SHA256 of `value` under `key`: a fingerprint that tells
+/// values apart without letting anyone who lacks the key check a guess.
+pub fn keyed_hash(key: &[u8; 32], value: &str) -> String {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts any key");
+    mac.update(value.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Whether `a` and `b` are equal, taking as long whatever they are: both are
+/// MACed under a fresh key and the MACs compared in constant time, so the
+/// time taken gives away neither where they differ nor their lengths.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    let key = random_key();
+    let mac = |value: &str| {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&key).expect("HMAC accepts any key");
+        mac.update(value.as_bytes());
+        mac
+    };
+    mac(a).verify_slice(&mac(b).finalize().into_bytes()).is_ok()
+}
+
+/// Independent keys for independent jobs, all derived from the master key.
+fn subkey(master_key: &MasterKey, purpose: &str) -> [u8; 32] {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(master_key).expect("HMAC accepts any key");
+    mac.update(purpose.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// The passphrase from `ENVMATCH_PASSPHRASE`, or typed in when on a terminal.
+pub fn read_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(EnvMatchError::PassphraseRequired);
+    }
+    Ok(rpassword::prompt_password("🔑 Passphrase: ")?)
+}
+
+/// Like [`read_passphrase`], but asks twice when typed to catch typos.
+pub fn read_new_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(EnvMatchError::PassphraseRequired);
+    }
+
+    let passphrase = rpassword::prompt_password("🔑 New passphrase: ")?;
+    if rpassword::prompt_password("🔑 Repeat passphrase: ")? != passphrase {
+        return Err(EnvMatchError::EncryptionError {
+            message: "passphrases don't match".to_string(),
+        });
+    }
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let salt = random_salt();
+        let cipher = Cipher::derive("correct horse", &salt).unwrap();
+
+        let encrypted = cipher.encrypt(&["production"], "API_KEY", "s3cret");
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("s3cret"));
+        assert_eq!(
+            cipher
+                .decrypt(&["production"], "API_KEY", &encrypted)
+                .unwrap(),
+            "s3cret"
+        );
+
+        // Stable for unchanged values, different across keys
+        assert_eq!(
+            cipher.encrypt(&["production"], "API_KEY", "s3cret"),
+            encrypted
+        );
+        assert_ne!(
+            cipher.encrypt(&["production"], "OTHER_KEY", "s3cret"),
+            encrypted
+        );
+        assert!(cipher
+            .decrypt(&["production"], "OTHER_KEY", &encrypted)
+            .is_err());
+
+        let wrong = Cipher::derive("wrong horse", &salt).unwrap();
+        assert!(matches!(
+            wrong.decrypt(&["production"], "API_KEY", &encrypted),
+            Err(EnvMatchError::DecryptionFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ciphertexts_stay_in_their_environment() {
+        let cipher = Cipher::derive("correct horse", &random_salt()).unwrap();
+        let encrypted = cipher.encrypt(&["production"], "API_KEY", "s3cret");
+
+        for context in [&["staging"][..], &["production", "ci"], &[]] {
+            assert!(cipher.decrypt(context, "API_KEY", &encrypted).is_err());
+            assert_ne!(cipher.encrypt(context, "API_KEY", "s3cret"), encrypted);
+        }
+        // Names and contexts can't run into each other
+        assert_ne!(associated_data(&["ab"], "c"), associated_data(&["a"], "bc"));
+
+        // Only v2, which binds the context, is accepted
+        let relabeled = encrypted.replacen("ENC[v2,", "ENC[v1,", 1);
+        assert!(!is_encrypted(&relabeled));
+        assert!(cipher
+            .decrypt(&["production"], "API_KEY", &relabeled)
+            .is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use indexmap::IndexMap;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariableChange {
+    Added {
+        key: String,
+        value: String,
+    },
+    Removed {
+        key: String,
+        value: String,
+    },
+    Changed {
+        key: String,
+        old: String,
+        new: String,
+    },
+}
+
+/// Lists what it takes to go from `old` to `new`, sorted by key.
+pub fn diff_variables(
+    old: &IndexMap<String, String>,
+    new: &IndexMap<String, String>,
+) -> Vec<VariableChange> {
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+    keys.into_iter()
+        .filter_map(|key| match (old.get(key), new.get(key)) {
+            (None, Some(value)) => Some(VariableChange::Added {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+            (Some(value), None) => Some(VariableChange::Removed {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+            (Some(old), Some(new)) if old != new => Some(VariableChange::Changed {
+                key: key.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> IndexMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_variables() {
+        let old = vars(&[("KEEP", "1"), ("CHANGE", "old"), ("DROP", "x")]);
+        let new = vars(&[("KEEP", "1"), ("CHANGE", "new"), ("ADD", "y")]);
+
+        let changes = diff_variables(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                VariableChange::Added {
+                    key: "ADD".to_string(),
+                    value: "y".to_string()
+                },
+                VariableChange::Changed {
+                    key: "CHANGE".to_string(),
+                    old: "old".to_string(),
+                    new: "new".to_string()
+                },
+                VariableChange::Removed {
+                    key: "DROP".to_string(),
+                    value: "x".to_string()
+                },
+            ]
+        );
+        assert!(diff_variables(&old, &old).is_empty());
+    }
+}
@@ -1,5 +1,10 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
+mod suggest;
+
+pub use suggest::closest_match;
+
 #[derive(Error, Debug)]
 pub enum EnvMatchError {
     #[error("envMatch not initialized. Run 'envMatch init' first")]
@@ -8,26 +13,358 @@ pub enum EnvMatchError {
     #[error("envMatch already initialized in this directory")]
     AlreadyInitialized,
 
-    #[error("Variable '{key}' not found in environment '{env}'")]
-    VariableNotFound { key: String, env: String },
+    #[error("Variable '{key}' not found in environment '{env}'{}", .suggestion.as_deref().map_or(String::new(), |key| format!(". {}", did_you_mean(key))))]
+    VariableNotFound {
+        key: String,
+        env: String,
+        /// A key of the environment the name looks like a typo of
+        suggestion: Option<String>,
+    },
+
+    #[error("Environment '{name}' not found. {}", .suggestion.as_deref().map_or("Run 'envMatch envs' to list them".to_string(), did_you_mean))]
+    EnvironmentNotFound {
+        name: String,
+        /// An existing environment the name looks like a typo of
+        suggestion: Option<String>,
+    },
 
-    #[error("Failed to read config file: {source}")]
-    ConfigReadError {
+    #[error("Profile '{name}' not found in '{env}'. {}", .suggestion.as_deref().map_or("Run 'envMatch profile list' to list them".to_string(), did_you_mean))]
+    ProfileNotFound {
+        name: String,
+        env: String,
+        /// A profile the name looks like a typo of
+        suggestion: Option<String>,
+    },
+
+    #[error("Failed to access {}: {source}", .path.display())]
+    StorageError {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Database error in {path}: {source}")]
+    DatabaseError {
+        path: PathBuf,
+        #[source]
+        source: rusqlite::Error,
+    },
+
+    #[error("Terminal error: {source}")]
+    TerminalError {
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("I/O error: {source}")]
+    IoError {
         #[from]
         source: std::io::Error,
     },
 
-    #[error("Failed to parse YAML: {source}")]
+    #[error("Failed to parse {}: {source}", .path.as_deref().map_or("YAML".into(), |path| path.display().to_string()))]
     YamlParseError {
-        #[from]
+        /// File being parsed, when known
+        path: Option<PathBuf>,
+        line: Option<usize>,
+        #[source]
         source: serde_yaml::Error,
     },
 
+    #[error("Failed to parse {}: {source}", .path.as_deref().map_or("TOML".into(), |path| path.display().to_string()))]
+    TomlParseError {
+        /// File being parsed, when known
+        path: Option<PathBuf>,
+        #[source]
+        source: toml::de::Error,
+    },
+
+    #[error("Failed to write TOML: {source}")]
+    TomlWriteError {
+        #[from]
+        source: toml::ser::Error,
+    },
+
+    #[error("Failed to parse JSON: {source}")]
+    JsonError {
+        #[from]
+        source: serde_json::Error,
+    },
+
+    #[error("'{name}' is the current environment. Switch to another one first")]
+    EnvironmentInUse { name: String },
+
+    #[error("Environment '{name}' already exists{}", if *.archived { " in the archive" } else { "" })]
+    EnvironmentExists { name: String, archived: bool },
+
     #[error("Missing required variables in environment '{env}': {variables:?}")]
     MissingRequiredVariables { env: String, variables: Vec<String> },
 
+    #[error("Expired variables in environment '{env}': {variables:?}")]
+    ExpiredVariables { env: String, variables: Vec<String> },
+
+    #[error("Policy violations in environment '{env}': {violations:?}")]
+    PolicyViolations {
+        env: String,
+        violations: Vec<String>,
+    },
+
+    #[error("Edit of '{env}' rejected because of {problems} problem(s); nothing was saved")]
+    EditRejected { env: String, problems: usize },
+
     #[error("Invalid environment name: '{name}'. Environment names must be alphanumeric")]
     InvalidEnvironmentName { name: String },
+
+    #[error("Invalid scope '{}': it must be a path inside the project", .scope.display())]
+    InvalidScope { scope: PathBuf },
+
+    #[error(
+        "Invalid variable name '{key}': {reason}. Set key_names: relaxed in the config to allow it"
+    )]
+    InvalidVariableKey { key: String, reason: String },
+
+    #[error("'{key}' is not base64-encoded. Was it set with --base64?")]
+    NotBase64 { key: String },
+
+    #[error("Invalid secret pattern '{name}': {message}")]
+    InvalidSecretPattern { name: String, message: String },
+
+    #[error("Invalid filter '{pattern}': {message}")]
+    InvalidFilter { pattern: String, message: String },
+
+    #[error("Invalid pattern in policy '{policy}': {message}")]
+    InvalidPolicy { policy: String, message: String },
+
+    #[error("Invalid assignment: '{input}'. Expected KEY=VALUE")]
+    InvalidAssignment { input: String },
+
+    #[error("Cannot import {}: not a {format} file of variables", .path.display())]
+    NotAVariableMap { path: PathBuf, format: String },
+
+    #[error("Not running in a terminal, so there's no TUI. Pass a command instead, e.g. 'envMatch status'")]
+    NotATerminal,
+
+    #[error("Can't tell which variables are in use: add a schema, pass --keep or leave the codebase scan on")]
+    NoUsageInformation,
+
+    #[error("{} is not a registered project. Run 'envMatch projects' to list them", .path.display())]
+    ProjectNotRegistered { path: PathBuf },
+
+    #[error("{} isn't allowed to load in the shell hook. Review its environments, then run 'envMatch hook allow'", .path.display())]
+    ProjectNotAllowed { path: PathBuf },
+
+    #[error("Snapshot '{id}' not found. Run 'envMatch history' to list snapshots")]
+    SnapshotNotFound { id: String },
+
+    #[error("Audit log has been modified: hash chain breaks at line {line}")]
+    AuditLogTampered { line: usize },
+
+    #[error("Audit log has been truncated: {found} entries left of the {expected} written")]
+    AuditLogTruncated { expected: usize, found: usize },
+
+    #[error("Cannot export '{key}' as {format}: {reason}")]
+    UnexportableValue {
+        key: String,
+        format: String,
+        reason: String,
+    },
+
+    #[error("Sync with {remote} failed: {message}")]
+    RemoteError { remote: String, message: String },
+
+    #[error("Encryption error: {message}")]
+    EncryptionError { message: String },
+
+    #[error("Could not decrypt '{key}': the value is corrupt or was encrypted with another key")]
+    DecryptionFailed { key: String },
+
+    #[error("Environments are encrypted. Set ENVMATCH_PASSPHRASE or run in a terminal to enter the passphrase")]
+    PassphraseRequired,
+
+    #[error("Wrong passphrase for the encrypted environments")]
+    WrongPassphrase,
+
+    #[error("Doctor found {count} problem(s)")]
+    DoctorFoundProblems { count: usize },
+
+    #[error("Self-update failed: {message}")]
+    UpdateFailed { message: String },
+
+    #[error("The downloaded {asset} doesn't match its published checksum; nothing was replaced")]
+    ChecksumMismatch { asset: String },
+
+    #[error("envMatch was installed with {manager}; update it with `{command}`")]
+    PackageManaged { manager: String, command: String },
+
+    #[error("Lint found {count} problem(s)")]
+    LintFoundProblems { count: usize },
+
+    #[error("{format} export doesn't read back as exported: {}", .problems.join("; "))]
+    ExportCheckFailed {
+        format: String,
+        problems: Vec<String>,
+    },
+
+    #[error("OS keychain error: {message}")]
+    KeychainError { message: String },
+
+    #[error("Cannot resolve '{key}': {reason}")]
+    UnresolvedValue { key: String, reason: String },
+
+    #[error("Failed to run '{command}': {source}")]
+    RunError {
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Failed to watch for changes: {source}")]
+    WatchError {
+        #[from]
+        source: notify::Error,
+    },
+
+    #[error("{} path(s) under .envMatch can be read by other users, e.g. {}. Run 'envMatch harden'", .paths.len(), .paths[0].display())]
+    InsecurePermissions { paths: Vec<PathBuf> },
+
+    #[error("Sync conflict with {remote}: {keys:?} changed on both sides since the last sync. Use --force to resolve")]
+    SyncConflict { remote: String, keys: Vec<String> },
+
+    #[error("Sync with {remote} failed for {} key(s):{}", .failures.len(), .failures.iter().map(|(key, message)| format!("\n  {}: {}", key, message)).collect::<String>())]
+    RemoteKeysFailed {
+        remote: String,
+        /// Each key that failed, with why
+        failures: Vec<(String, String)>,
+    },
+}
+
+impl From<serde_yaml::Error> for EnvMatchError {
+    fn from(source: serde_yaml::Error) -> Self {
+        Self::YamlParseError {
+            path: None,
+            line: source.location().map(|location| location.line()),
+            source,
+        }
+    }
+}
+
+/// Process exit codes, so scripts can tell failures apart without parsing
+/// messages. Commands that run another program (`run`) exit with its code.
+pub mod exit_code {
+    /// Any failure without a more specific code
+    pub const FAILURE: i32 = 1;
+    /// The project has no `.envMatch` directory yet
+    pub const NOT_INITIALIZED: i32 = 2;
+    /// A variable, environment or snapshot doesn't exist
+    pub const NOT_FOUND: i32 = 3;
+    /// Validation failed: missing required variables, invalid names, doctor
+    /// problems, files other users can read with `--strict`
+    pub const VALIDATION_FAILED: i32 = 4;
+    /// A file or value couldn't be parsed
+    pub const PARSE_ERROR: i32 = 5;
+    /// Encryption, decryption or passphrase problems
+    pub const ENCRYPTION_ERROR: i32 = 6;
+    /// A remote secret store failed or conflicts with local changes
+    pub const REMOTE_ERROR: i32 = 7;
+    /// The command line itself is invalid (unknown flag, missing argument)
+    pub const USAGE: i32 = 64;
+}
+
+impl EnvMatchError {
+    /// The exit code for this error, one of [`exit_code`].
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NotInitialized => exit_code::NOT_INITIALIZED,
+            Self::VariableNotFound { .. }
+            | Self::EnvironmentNotFound { .. }
+            | Self::ProfileNotFound { .. }
+            | Self::SnapshotNotFound { .. }
+            | Self::ProjectNotRegistered { .. } => exit_code::NOT_FOUND,
+            Self::MissingRequiredVariables { .. }
+            | Self::ExpiredVariables { .. }
+            | Self::PolicyViolations { .. }
+            | Self::EnvironmentInUse { .. }
+            | Self::EnvironmentExists { .. }
+            | Self::InvalidEnvironmentName { .. }
+            | Self::InvalidScope { .. }
+            | Self::InvalidVariableKey { .. }
+            | Self::AlreadyInitialized
+            | Self::AuditLogTampered { .. }
+            | Self::AuditLogTruncated { .. }
+            | Self::DoctorFoundProblems { .. }
+            | Self::LintFoundProblems { .. }
+            | Self::ExportCheckFailed { .. }
+            | Self::ChecksumMismatch { .. }
+            | Self::InsecurePermissions { .. }
+            | Self::ProjectNotAllowed { .. }
+            | Self::EditRejected { .. } => exit_code::VALIDATION_FAILED,
+            Self::YamlParseError { .. }
+            | Self::TomlParseError { .. }
+            | Self::TomlWriteError { .. }
+            | Self::JsonError { .. }
+            | Self::InvalidAssignment { .. }
+            | Self::NotAVariableMap { .. }
+            | Self::NotBase64 { .. }
+            | Self::InvalidSecretPattern { .. }
+            | Self::InvalidFilter { .. }
+            | Self::InvalidPolicy { .. }
+            | Self::UnexportableValue { .. } => exit_code::PARSE_ERROR,
+            Self::EncryptionError { .. }
+            | Self::DecryptionFailed { .. }
+            | Self::PassphraseRequired
+            | Self::WrongPassphrase
+            | Self::KeychainError { .. } => exit_code::ENCRYPTION_ERROR,
+            Self::RemoteError { .. }
+            | Self::RemoteKeysFailed { .. }
+            | Self::SyncConflict { .. }
+            | Self::UpdateFailed { .. } => exit_code::REMOTE_ERROR,
+            Self::NoUsageInformation | Self::NotATerminal | Self::PackageManaged { .. } => {
+                exit_code::USAGE
+            }
+            Self::StorageError { .. }
+            | Self::DatabaseError { .. }
+            | Self::TerminalError { .. }
+            | Self::IoError { .. }
+            | Self::RunError { .. }
+            | Self::UnresolvedValue { .. }
+            | Self::WatchError { .. } => exit_code::FAILURE,
+        }
+    }
+
+    /// A variable missing from `env`, suggesting the closest of its `keys`.
+    pub fn variable_not_found<'a>(
+        key: &str,
+        env: &str,
+        keys: impl IntoIterator<Item = &'a String>,
+    ) -> Self {
+        Self::VariableNotFound {
+            key: key.to_string(),
+            env: env.to_string(),
+            suggestion: closest_match(key, keys.into_iter().map(String::as_str))
+                .map(str::to_string),
+        }
+    }
+
+    /// An unknown environment, suggesting the closest of `existing`.
+    pub fn environment_not_found(name: &str, existing: &[String]) -> Self {
+        Self::EnvironmentNotFound {
+            name: name.to_string(),
+            suggestion: closest_match(name, existing.iter().map(String::as_str))
+                .map(str::to_string),
+        }
+    }
+
+    /// An IO error on `path`, so the message says which file was involved.
+    pub fn storage(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Self::StorageError {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+fn did_you_mean(suggestion: &str) -> String {
+    format!("Did you mean '{}'?", suggestion)
 }
 
 pub type Result<T> = std::result::Result<T, EnvMatchError>;
@@ -0,0 +1,50 @@
+/// The candidate closest to `name`, if one is close enough to be a typo of
+/// it: at most a third of its characters off, ignoring case.
+pub fn closest_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance: the single-character insertions, deletions and
+/// substitutions turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != *b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closest_match() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        let envs = ["development", "production", "staging"];
+        assert_eq!(closest_match("prodcution", envs), Some("production"));
+        assert_eq!(closest_match("STAGING", envs), Some("staging"));
+        assert_eq!(closest_match("qa", envs), None);
+        assert_eq!(
+            closest_match("DATABSE_URL", ["DATABASE_URL", "API_URL"]),
+            Some("DATABASE_URL")
+        );
+    }
+}
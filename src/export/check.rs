@@ -0,0 +1,270 @@
+use super::{escape, ExportFormat};
+use crate::error::{EnvMatchError, Result};
+use crate::template;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Deserialize)]
+struct ManifestData {
+    #[serde(default)]
+    data: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct ComposeEnvironment {
+    #[serde(default)]
+    environment: BTreeMap<String, String>,
+}
+
+/// Reads `output` back the way a consumer of `format` would, for checking
+/// that [`super::render`] loses nothing. Typed values read back as their
+/// literal text.
+pub fn read_back(format: ExportFormat, output: &str) -> Result<Vec<(String, String)>> {
+    let vars = match format {
+        ExportFormat::Dotenv => template::parse_dotenv(output)?,
+        ExportFormat::Shell => read_shell(output).ok_or_else(|| unreadable("shell"))?,
+        ExportFormat::Powershell => output
+            .lines()
+            .map(|line| {
+                let (variable, value) = line.split_once(" = \"")?;
+                let key = match variable.strip_prefix("${env:") {
+                    Some(braced) => braced.strip_suffix('}')?,
+                    None => variable.strip_prefix("$env:")?,
+                };
+                let value = escape::unescape_powershell(value.strip_suffix('"')?);
+                Some((key.to_string(), value))
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| unreadable("powershell"))?,
+        ExportFormat::Cmd => output
+            .lines()
+            .map(|line| {
+                let assignment = escape::unescape_cmd(line.strip_prefix("set \"")?)?;
+                let (key, value) = assignment.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| unreadable("cmd"))?,
+        ExportFormat::Json => serde_json::from_str::<IndexMap<String, String>>(output)?
+            .into_iter()
+            .collect(),
+        ExportFormat::K8sSecret => {
+            let mut vars = Vec::new();
+            for (key, encoded) in serde_yaml::from_str::<ManifestData>(output)?.data {
+                let value = STANDARD
+                    .decode(encoded)
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .ok_or_else(|| unreadable("k8s-secret"))?;
+                vars.push((key, value));
+            }
+            vars
+        }
+        ExportFormat::K8sConfigmap => serde_yaml::from_str::<ManifestData>(output)?
+            .data
+            .into_iter()
+            .collect(),
+        ExportFormat::DockerEnv => output
+            .lines()
+            .map(|line| {
+                let (key, value) = line.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| unreadable("docker-env"))?,
+        ExportFormat::Compose => serde_yaml::from_str::<ComposeEnvironment>(output)?
+            .environment
+            .into_iter()
+            .map(|(key, value)| (key, escape::unescape_compose(&value)))
+            .collect(),
+        ExportFormat::Tfvars => output
+            .lines()
+            .map(|line| {
+                let (key, value) = line.split_once(" = ")?;
+                let value = match value.strip_prefix('"') {
+                    Some(quoted) => escape::unescape_hcl(quoted.strip_suffix('"')?),
+                    None => value.to_string(),
+                };
+                Some((key.to_string(), value))
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| unreadable("tfvars"))?,
+        ExportFormat::TfJson => serde_json::from_str::<IndexMap<String, Value>>(output)?
+            .into_iter()
+            .map(|(key, value)| match value {
+                Value::String(value) => (key, value),
+                value => (key, value.to_string()),
+            })
+            .collect(),
+        ExportFormat::Systemd => read_systemd(output).ok_or_else(|| unreadable("systemd"))?,
+        ExportFormat::Launchd => {
+            let entry =
+                Regex::new(r"<key>([^<]*)</key>\s*<string>([^<]*)</string>").expect("valid regex");
+            entry
+                .captures_iter(output)
+                .map(|entry| {
+                    (
+                        escape::unescape_xml(&entry[1]),
+                        escape::unescape_xml(&entry[2]),
+                    )
+                })
+                .collect()
+        }
+    };
+    Ok(vars)
+}
+
+/// Checks that `output`, rendered from `vars` in `format`, reads back as
+/// exactly `vars`. Problems name keys only, never values.
+pub fn check(format: ExportFormat, vars: &[(String, String)], output: &str) -> Result<()> {
+    let failed = |problems| EnvMatchError::ExportCheckFailed {
+        format: format_name(format),
+        problems,
+    };
+    let read = read_back(format, output).map_err(|e| match e {
+        EnvMatchError::ExportCheckFailed { .. } => e,
+        e => failed(vec![format!("the output doesn't parse: {}", e)]),
+    })?;
+    let mut read: IndexMap<String, String> = read.into_iter().collect();
+
+    let mut problems = Vec::new();
+    for (key, value) in vars {
+        match read.shift_remove(key) {
+            Some(read_value) if &read_value == value => {}
+            Some(_) => problems.push(format!("{} reads back with a different value", key)),
+            None => problems.push(format!("{} doesn't read back", key)),
+        }
+    }
+    for key in read.keys() {
+        problems.push(format!("{} reads back but wasn't exported", key));
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(failed(problems))
+    }
+}
+
+fn format_name(format: ExportFormat) -> String {
+    format
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn unreadable(format: &str) -> EnvMatchError {
+    EnvMatchError::ExportCheckFailed {
+        format: format.to_string(),
+        problems: vec!["the output doesn't parse".to_string()],
+    }
+}
+
+/// Reads `export KEY='value'` statements, whose values may span lines.
+fn read_shell(mut output: &str) -> Option<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    loop {
+        output = output.trim_start();
+        if output.is_empty() {
+            return Some(vars);
+        }
+        let statement = output.strip_prefix("export ")?;
+        let (key, value) = statement.split_once('=')?;
+        let (value, rest) = escape::read_shell_word(value)?;
+        vars.push((key.to_string(), value));
+        output = rest;
+    }
+}
+
+/// Reads `KEY="value"` lines, whose values may span lines.
+fn read_systemd(mut output: &str) -> Option<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    loop {
+        output = output.trim_start();
+        if output.is_empty() {
+            return Some(vars);
+        }
+        let (key, quoted) = output.split_once("=\"")?;
+        let mut escaped = false;
+        let end = quoted.char_indices().find_map(|(index, c)| {
+            let closing = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            closing.then_some(index)
+        })?;
+        vars.push((key.to_string(), escape::unescape_systemd(&quoted[..end])));
+        output = &quoted[end + 1..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VariableType;
+    use crate::export::{render, ExportOptions};
+
+    fn tricky_vars() -> Vec<(String, String)> {
+        [
+            ("QUOTES", "it's \"quoted\" `here`"),
+            ("DOLLARS", "$HOME ${PATH} $${x} %{if} $$ 50%"),
+            ("BACKSLASHES", "C:\\temp\\n \\ \\\\"),
+            ("MULTI_LINE", "-----BEGIN-----\nabc\r\n-----END-----\n"),
+            ("UNICODE", "héllo wörld ✓ 日本 🚀 \u{2028}"),
+            ("SPACES", "  padded\tvalue  # not a comment"),
+            ("MARKUP", "<a href=\"x\">&amp;</a> (a|b) ^c"),
+            ("SMART_QUOTES", "“quoted” „here“ 100%% `tick`"),
+            ("EMPTY", ""),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn test_every_format_round_trips() {
+        let options = ExportOptions {
+            name: "app".to_string(),
+            ..Default::default()
+        };
+        for format in ExportFormat::value_variants() {
+            // Formats that can't hold a value refuse it when rendering
+            let vars: Vec<_> = tricky_vars()
+                .into_iter()
+                .filter(|(key, _)| {
+                    !(key == "MULTI_LINE"
+                        && matches!(format, ExportFormat::DockerEnv | ExportFormat::Cmd))
+                })
+                .collect();
+            let output = render(*format, &vars, &options).unwrap();
+            if let Err(e) = check(*format, &vars, &output) {
+                panic!("{:?}: {}\n{}", format, e, output);
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_reports_lossy_values() {
+        let options = ExportOptions {
+            types: [("SCALE".to_string(), VariableType::Number)].into(),
+            ..Default::default()
+        };
+        let vars = vec![("SCALE".to_string(), "1e3".to_string())];
+        let output = render(ExportFormat::TfJson, &vars, &options).unwrap();
+        match check(ExportFormat::TfJson, &vars, &output) {
+            Err(EnvMatchError::ExportCheckFailed { format, problems }) => {
+                assert_eq!(format, "tf-json");
+                assert_eq!(problems, ["SCALE reads back with a different value"]);
+            }
+            other => panic!("expected a failed check, got {:?}", other),
+        }
+
+        assert!(matches!(
+            check(ExportFormat::Shell, &vars, "export SCALE='1e3"),
+            Err(EnvMatchError::ExportCheckFailed { .. })
+        ));
+    }
+}
@@ -0,0 +1,253 @@
+//! Quoting and escaping for the text formats, each with the inverse that
+//! `export --check` reads values back with.
+
+/// Escapes a value for a double-quoted dotenv value.
+pub fn double_quoted(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '$' => escaped.push_str("\\$"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reads the inside of a double-quoted dotenv value: `\n` and `\r` are line
+/// breaks and a backslash before anything else stands for that character.
+pub fn unescape_double_quoted(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Quotes a value as one POSIX shell word. Nothing is special inside single
+/// quotes, so a quote itself ends them, is escaped and starts them again.
+pub fn single_quoted(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Reads one POSIX shell word made of single-quoted, backslash-escaped and
+/// plain parts, as [`single_quoted`] writes them, returning it with the
+/// rest of `input`. `None` when a quote is left open.
+pub fn read_shell_word(input: &str) -> Option<(String, &str)> {
+    let mut word = String::new();
+    let mut chars = input.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\'' => loop {
+                match chars.next()? {
+                    (_, '\'') => break,
+                    (_, c) => word.push(c),
+                }
+            },
+            '\\' => match chars.next() {
+                // A backslash before a line break continues the line
+                Some((_, '\n')) => {}
+                Some((_, c)) => word.push(c),
+                None => word.push('\\'),
+            },
+            c if c.is_ascii_whitespace() || c == ';' => return Some((word, &input[index..])),
+            c => word.push(c),
+        }
+    }
+    Some((word, ""))
+}
+
+/// Escapes a value for a double-quoted PowerShell string, where a backtick
+/// escapes and `$` interpolates. PowerShell takes typographic double quotes
+/// for `"`, so those are escaped too.
+pub fn powershell(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '`' | '$' | '"' | '\u{201C}' | '\u{201D}' | '\u{201E}' => {
+                escaped.push('`');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("`n"),
+            '\r' => escaped.push_str("`r"),
+            '\0' => escaped.push_str("`0"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn unescape_powershell(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '`' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('0') => unescaped.push('\0'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('`'),
+        }
+    }
+    unescaped
+}
+
+/// Escapes a value for `set "KEY=value"` in a cmd batch file. Every `"`
+/// takes cmd in or out of quoted text, outside of which `^` has to escape
+/// the characters it treats specially; `%` is doubled everywhere.
+pub fn cmd(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    // The value starts inside the quote opening `"KEY=`
+    let mut quoted = true;
+    for c in value.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '%' => escaped.push('%'),
+            '^' | '&' | '|' | '<' | '>' | '(' | ')' if !quoted => escaped.push('^'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Reads back what follows `set "` on a line [`cmd`] escaped: `KEY=value`
+/// followed by the closing quote.
+pub fn unescape_cmd(line: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(line.len());
+    let mut quoted = true;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                unescaped.push(c);
+            }
+            '^' if !quoted => unescaped.push(chars.next()?),
+            '%' if chars.peek() == Some(&'%') => {
+                chars.next();
+                unescaped.push(c);
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    // `set` keeps what comes before the last quote
+    let end = unescaped.rfind('"')?;
+    unescaped.truncate(end);
+    Some(unescaped)
+}
+
+/// Escapes a value for docker-compose, which interpolates `$`.
+pub fn compose(value: &str) -> String {
+    value.replace('$', "$$")
+}
+
+pub fn unescape_compose(value: &str) -> String {
+    value.replace("$$", "$")
+}
+
+/// Escapes a value for an HCL quoted string, where `${` and `%{` start
+/// interpolations and directives.
+pub fn hcl(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '$' | '%' if chars.peek() == Some(&'{') => {
+                escaped.push(c);
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reads the inside of an HCL quoted string, where `$${` and `%%{` stand
+/// for a literal `${` and `%{`.
+pub fn unescape_hcl(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some('t') => unescaped.push('\t'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\'),
+            },
+            '$' | '%' => {
+                let mut ahead = chars.clone();
+                if ahead.next() == Some(c) && ahead.next() == Some('{') {
+                    chars.next();
+                }
+                unescaped.push(c);
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Escapes a value for a double-quoted systemd environment file value,
+/// which may span lines and only treats a backslash before `"`, `\`, `` ` ``
+/// or `$` as an escape.
+pub fn systemd(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '`' | '$') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+pub fn unescape_systemd(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.push(chars.next().unwrap_or('\\')),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+pub fn xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
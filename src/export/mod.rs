@@ -0,0 +1,465 @@
+use crate::config::VariableType;
+use crate::error::{EnvMatchError, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+mod check;
+pub mod escape;
+
+pub use check::check;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExportFormat {
+    /// KEY="value" lines, readable by dotenv loaders
+    #[default]
+    Dotenv,
+    /// `export KEY='value'` lines for POSIX shells
+    Shell,
+    /// `$env:KEY = "value"` lines for PowerShell
+    Powershell,
+    /// `set "KEY=value"` lines for a cmd batch file
+    Cmd,
+    /// A JSON object of string values
+    Json,
+    /// Kubernetes Secret manifest with base64-encoded data
+    K8sSecret,
+    /// Kubernetes ConfigMap manifest
+    K8sConfigmap,
+    /// KEY=value lines for `docker run --env-file`
+    DockerEnv,
+    /// `environment:` block for a docker-compose service
+    Compose,
+    /// Terraform `.tfvars` file, for `terraform plan -var-file`
+    Tfvars,
+    /// Terraform `.tfvars.json` file
+    TfJson,
+    /// File for a systemd unit's `EnvironmentFile=`
+    Systemd,
+    /// launchd property list with an `EnvironmentVariables` dictionary
+    Launchd,
+}
+
+/// Shells `envMatch shell` writes code for.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ShellDialect {
+    /// sh, bash, zsh and friends
+    Posix,
+    Powershell,
+    /// cmd.exe batch files
+    Cmd,
+}
+
+impl Default for ShellDialect {
+    fn default() -> Self {
+        if cfg!(windows) {
+            ShellDialect::Powershell
+        } else {
+            ShellDialect::Posix
+        }
+    }
+}
+
+impl From<ShellDialect> for ExportFormat {
+    fn from(dialect: ShellDialect) -> Self {
+        match dialect {
+            ShellDialect::Posix => ExportFormat::Shell,
+            ShellDialect::Powershell => ExportFormat::Powershell,
+            ShellDialect::Cmd => ExportFormat::Cmd,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExportOptions {
+    /// Resource name for manifest formats
+    pub name: String,
+    /// Optional namespace for manifest formats
+    pub namespace: Option<String>,
+    /// Declared variable types, so typed formats can leave numbers and
+    /// booleans unquoted
+    pub types: BTreeMap<String, VariableType>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest<'a> {
+    api_version: &'a str,
+    kind: &'a str,
+    metadata: Metadata<'a>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    secret_type: Option<&'a str>,
+    data: BTreeMap<&'a str, String>,
+}
+
+#[derive(Serialize)]
+struct Metadata<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<&'a str>,
+}
+
+/// Renders the given variables in the requested format.
+pub fn render(
+    format: ExportFormat,
+    vars: &[(String, String)],
+    options: &ExportOptions,
+) -> Result<String> {
+    let output = match format {
+        ExportFormat::Dotenv => vars
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"\n", key, escape::double_quoted(value)))
+            .collect(),
+        ExportFormat::Shell => vars
+            .iter()
+            .map(|(key, value)| format!("export {}={}\n", key, escape::single_quoted(value)))
+            .collect(),
+        ExportFormat::Powershell => vars
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "{} = \"{}\"\n",
+                    powershell_variable(key),
+                    escape::powershell(value)
+                )
+            })
+            .collect(),
+        ExportFormat::Cmd => {
+            let mut output = String::new();
+            for (key, value) in vars {
+                if value.contains(['\n', '\r']) {
+                    return Err(EnvMatchError::UnexportableValue {
+                        key: key.clone(),
+                        format: "cmd".to_string(),
+                        reason: "cmd cannot set multi-line values".to_string(),
+                    });
+                }
+                output.push_str(&format!("set \"{}={}\"\n", key, escape::cmd(value)));
+            }
+            output
+        }
+        ExportFormat::Json => {
+            let values: IndexMap<&str, &str> = vars
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect();
+            serde_json::to_string_pretty(&values)? + "\n"
+        }
+        ExportFormat::K8sSecret => {
+            let data = vars
+                .iter()
+                .map(|(key, value)| (key.as_str(), STANDARD.encode(value)))
+                .collect();
+            manifest("Secret", Some("Opaque"), data, options)?
+        }
+        ExportFormat::K8sConfigmap => {
+            let data = vars
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.clone()))
+                .collect();
+            manifest("ConfigMap", None, data, options)?
+        }
+        ExportFormat::DockerEnv => {
+            // Docker reads env-file values verbatim: no quoting, no escapes,
+            // and no way to continue a value onto the next line.
+            let mut output = String::new();
+            for (key, value) in vars {
+                if value.contains(['\n', '\r']) {
+                    return Err(EnvMatchError::UnexportableValue {
+                        key: key.clone(),
+                        format: "docker-env".to_string(),
+                        reason: "docker env files cannot hold multi-line values".to_string(),
+                    });
+                }
+                output.push_str(&format!("{}={}\n", key, value));
+            }
+            output
+        }
+        ExportFormat::Compose => {
+            // Compose interpolates `$` in values, so it has to be doubled
+            let environment: BTreeMap<&str, String> = vars
+                .iter()
+                .map(|(key, value)| (key.as_str(), escape::compose(value)))
+                .collect();
+            let mut block = BTreeMap::new();
+            block.insert("environment", environment);
+            serde_yaml::to_string(&block)?
+        }
+        ExportFormat::Tfvars => {
+            let mut output = String::new();
+            for (key, value) in vars {
+                if !is_hcl_identifier(key) {
+                    return Err(EnvMatchError::UnexportableValue {
+                        key: key.clone(),
+                        format: "tfvars".to_string(),
+                        reason: "not a valid Terraform variable name".to_string(),
+                    });
+                }
+                let value = match typed_value(value, options.types.get(key)) {
+                    Some(typed) => typed.to_string(),
+                    None => format!("\"{}\"", escape::hcl(value)),
+                };
+                output.push_str(&format!("{} = {}\n", key, value));
+            }
+            output
+        }
+        ExportFormat::TfJson => {
+            let values: IndexMap<&str, Value> = vars
+                .iter()
+                .map(|(key, value)| {
+                    let typed = typed_value(value, options.types.get(key))
+                        .unwrap_or_else(|| Value::String(value.clone()));
+                    (key.as_str(), typed)
+                })
+                .collect();
+            serde_json::to_string_pretty(&values)? + "\n"
+        }
+        ExportFormat::Systemd => vars
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"\n", key, escape::systemd(value)))
+            .collect(),
+        ExportFormat::Launchd => {
+            let mut output = String::from(PLIST_HEADER);
+            output.push_str("<dict>\n\t<key>EnvironmentVariables</key>\n\t<dict>\n");
+            for (key, value) in vars {
+                // XML 1.0 has no way to write other control characters
+                if value
+                    .chars()
+                    .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+                {
+                    return Err(EnvMatchError::UnexportableValue {
+                        key: key.clone(),
+                        format: "launchd".to_string(),
+                        reason: "property lists cannot hold control characters".to_string(),
+                    });
+                }
+                output.push_str(&format!(
+                    "\t\t<key>{}</key>\n\t\t<string>{}</string>\n",
+                    escape::xml(key),
+                    escape::xml(value)
+                ));
+            }
+            output.push_str("\t</dict>\n</dict>\n</plist>\n");
+            output
+        }
+    };
+    Ok(output)
+}
+
+/// The value as a JSON number or boolean when its declared type says so and
+/// it reads as one; anything else stays a string.
+fn typed_value(value: &str, value_type: Option<&VariableType>) -> Option<Value> {
+    match value_type? {
+        VariableType::Number if value.trim() == value => {
+            serde_json::from_str::<serde_json::Number>(value)
+                .ok()
+                .map(Value::Number)
+        }
+        VariableType::Bool => match value {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `$env:KEY`, or `${env:KEY}` for names PowerShell would otherwise end early.
+pub(crate) fn powershell_variable(key: &str) -> String {
+    if key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        format!("$env:{}", key)
+    } else {
+        format!("${{env:{}}}", key)
+    }
+}
+
+fn is_hcl_identifier(key: &str) -> bool {
+    key.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+const PLIST_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+"#;
+
+fn manifest(
+    kind: &str,
+    secret_type: Option<&str>,
+    data: BTreeMap<&str, String>,
+    options: &ExportOptions,
+) -> Result<String> {
+    let manifest = Manifest {
+        api_version: "v1",
+        kind,
+        metadata: Metadata {
+            name: &options.name,
+            namespace: options.namespace.as_deref(),
+        },
+        secret_type,
+        data,
+    };
+    Ok(serde_yaml::to_string(&manifest)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_vars() -> Vec<(String, String)> {
+        vec![
+            ("API_KEY".to_string(), "s3cr3t".to_string()),
+            ("GREETING".to_string(), "it's \"here\"".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_render_dotenv_and_shell() {
+        let options = ExportOptions::default();
+
+        let dotenv = render(ExportFormat::Dotenv, &sample_vars(), &options).unwrap();
+        assert_eq!(
+            dotenv,
+            "API_KEY=\"s3cr3t\"\nGREETING=\"it's \\\"here\\\"\"\n"
+        );
+
+        let shell = render(ExportFormat::Shell, &sample_vars(), &options).unwrap();
+        assert!(shell.contains("export GREETING='it'\\''s \"here\"'\n"));
+
+        let powershell = render(ExportFormat::Powershell, &sample_vars(), &options).unwrap();
+        assert!(powershell.contains("$env:GREETING = \"it's `\"here`\"\"\n"));
+
+        let vars = vec![("PIPE".to_string(), "a\"b & 100%".to_string())];
+        let cmd = render(ExportFormat::Cmd, &vars, &options).unwrap();
+        assert_eq!(cmd, "set \"PIPE=a\"b ^& 100%%\"\n");
+
+        let json = render(ExportFormat::Json, &sample_vars(), &options).unwrap();
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["GREETING"], "it's \"here\"");
+    }
+
+    #[test]
+    fn test_render_k8s_manifests() {
+        let options = ExportOptions {
+            name: "my-app-secrets".to_string(),
+            namespace: Some("apps".to_string()),
+            ..Default::default()
+        };
+
+        let secret = render(ExportFormat::K8sSecret, &sample_vars(), &options).unwrap();
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&secret).unwrap();
+        assert_eq!(parsed["kind"], "Secret");
+        assert_eq!(parsed["type"], "Opaque");
+        assert_eq!(parsed["metadata"]["name"], "my-app-secrets");
+        assert_eq!(parsed["metadata"]["namespace"], "apps");
+        assert_eq!(parsed["data"]["API_KEY"], "czNjcjN0");
+
+        let config_map = render(ExportFormat::K8sConfigmap, &sample_vars(), &options).unwrap();
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&config_map).unwrap();
+        assert_eq!(parsed["kind"], "ConfigMap");
+        assert!(parsed.get("type").is_none());
+        assert_eq!(parsed["data"]["GREETING"], "it's \"here\"");
+    }
+
+    #[test]
+    fn test_render_docker_formats() {
+        let options = ExportOptions::default();
+        let vars = vec![
+            ("PRICE".to_string(), "$5 \"flat\"".to_string()),
+            ("URL".to_string(), "http://db:5432".to_string()),
+        ];
+
+        let env_file = render(ExportFormat::DockerEnv, &vars, &options).unwrap();
+        assert_eq!(env_file, "PRICE=$5 \"flat\"\nURL=http://db:5432\n");
+
+        let compose = render(ExportFormat::Compose, &vars, &options).unwrap();
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&compose).unwrap();
+        assert_eq!(parsed["environment"]["PRICE"], "$$5 \"flat\"");
+        assert_eq!(parsed["environment"]["URL"], "http://db:5432");
+
+        let multi_line = vec![("CERT".to_string(), "line1\nline2".to_string())];
+        assert!(matches!(
+            render(ExportFormat::DockerEnv, &multi_line, &options),
+            Err(EnvMatchError::UnexportableValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_render_terraform_formats() {
+        let options = ExportOptions {
+            types: [
+                ("PORT".to_string(), VariableType::Number),
+                ("DEBUG".to_string(), VariableType::Bool),
+                ("ZIP".to_string(), VariableType::String),
+                ("REPLICAS".to_string(), VariableType::Number),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let vars: Vec<(String, String)> = [
+            ("PORT", "8080"),
+            ("DEBUG", "true"),
+            ("ZIP", "02134"),
+            ("REPLICAS", "many"),
+            ("GREETING", "hi \"${name}\"\n"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+        let tfvars = render(ExportFormat::Tfvars, &vars, &options).unwrap();
+        assert_eq!(
+            tfvars,
+            "PORT = 8080\nDEBUG = true\nZIP = \"02134\"\nREPLICAS = \"many\"\n\
+             GREETING = \"hi \\\"$${name}\\\"\\n\"\n"
+        );
+
+        let tf_json = render(ExportFormat::TfJson, &vars, &options).unwrap();
+        let parsed: Value = serde_json::from_str(&tf_json).unwrap();
+        assert_eq!(parsed["PORT"], 8080);
+        assert_eq!(parsed["DEBUG"], true);
+        assert_eq!(parsed["ZIP"], "02134");
+        assert_eq!(parsed["GREETING"], "hi \"${name}\"\n");
+
+        let invalid = vec![("1ST".to_string(), "x".to_string())];
+        assert!(matches!(
+            render(ExportFormat::Tfvars, &invalid, &options),
+            Err(EnvMatchError::UnexportableValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_render_service_formats() {
+        let options = ExportOptions::default();
+        let vars = vec![
+            ("GREETING".to_string(), "say \"hi\" & <$USER>".to_string()),
+            ("CERT".to_string(), "line1\nline2".to_string()),
+        ];
+
+        let systemd = render(ExportFormat::Systemd, &vars, &options).unwrap();
+        assert_eq!(
+            systemd,
+            "GREETING=\"say \\\"hi\\\" & <\\$USER>\"\nCERT=\"line1\nline2\"\n"
+        );
+
+        let plist = render(ExportFormat::Launchd, &vars, &options).unwrap();
+        assert!(plist.starts_with("<?xml"));
+        assert!(plist.contains(
+            "\t\t<key>GREETING</key>\n\t\t<string>say \"hi\" &amp; &lt;$USER&gt;</string>\n"
+        ));
+        assert!(plist.ends_with("\t</dict>\n</dict>\n</plist>\n"));
+
+        let bell = vec![("BELL".to_string(), "\u{7}".to_string())];
+        assert!(matches!(
+            render(ExportFormat::Launchd, &bell, &options),
+            Err(EnvMatchError::UnexportableValue { .. })
+        ));
+    }
+}
@@ -1,19 +1,45 @@
+mod codegen;
 mod commands;
 mod config;
+mod crypto;
+mod diff;
 mod error;
+mod export;
+mod storage;
+mod sync;
+mod template;
 mod tui;
+mod update;
 
-use clap::{Parser, Subcommand};
-use commands::EnvMatchCommands;
+use chrono::{DateTime, Utc};
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand};
+use codegen::Language;
+use commands::{
+    EnvMatchCommands, HookShell, ImportFormat, Inheritance, KeyFilter, ListFormat, ListOptions,
+    ListSort, MergeStrategy, PruneOptions, SchemaFormat,
+};
+use config::{Settings, StorageFormat, VariableMetadata};
 use crossterm::{
+    cursor::Show,
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use error::EnvMatchError;
+use crypto::generate::Generator;
+use error::{exit_code, EnvMatchError};
+use export::{ExportFormat, ShellDialect};
 use ratatui::{backend::CrosstermBackend, Terminal};
-use std::io;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
+use storage::BackendKind;
+use sync::{
+    AwsSecretsProvider, AzureKeyVaultProvider, HerokuProvider, HttpProvider, S3Location,
+    S3Provider, SyncAction, Transfer, VaultAuth, VaultProvider,
+};
+use template::ProjectTemplate;
+use tracing_subscriber::EnvFilter;
 use tui::{App, EventHandler};
 
 #[derive(Parser)]
@@ -23,6 +49,44 @@ use tui::{App, EventHandler};
 )]
 #[command(version = "0.1.0")]
 struct Cli {
+    /// Project root to use instead of searching upwards from the current directory
+    #[arg(long, global = true, env = "ENVMATCH_DIR")]
+    dir: Option<PathBuf>,
+
+    /// Package of a monorepo to work on, as a path from the project root
+    /// (e.g. services/api); its environments extend the project's
+    #[arg(long, global = true, env = "ENVMATCH_SCOPE")]
+    scope: Option<PathBuf>,
+
+    /// Treat environment files that fail to parse as empty instead of failing;
+    /// the broken file is backed up to <env>.yaml.bak before it's overwritten
+    #[arg(long, global = true)]
+    force_empty: bool,
+
+    /// Don't print confirmations from commands that change things; errors
+    /// and requested output (get, list, export...) are still shown
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Log what envMatch does to stderr: -v for debug, -vv for trace
+    /// (ENVMATCH_LOG, e.g. "envMatch=debug", takes precedence)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Fail instead of warning when other users can read files under .envMatch
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Print without colors; also the default when NO_COLOR is set or
+    /// output isn't a terminal
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Don't warn when a value that looks like a secret (an AWS key, a
+    /// private key, a JWT, a random-looking string...) is stored in plain text
+    #[arg(long, global = true)]
+    no_secret_scan: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -31,76 +95,1148 @@ struct Cli {
 enum Commands {
     /// Initialize envMatch in current directory
     Init {
-        /// Initial environment to create (default: development)
-        #[arg(default_value = "development")]
-        environment: String,
+        /// Initial environment to create (default: from user config, else development)
+        environment: Option<String>,
+        /// Ask which environments to create, whether to import .env, a template and .gitignore
+        #[arg(short, long)]
+        interactive: bool,
+        /// Pre-populate the environment with a project template's common keys
+        #[arg(short, long, value_enum)]
+        template: Option<ProjectTemplate>,
+        /// Format to store environment files in (default: from user config, else yaml)
+        #[arg(long, value_enum)]
+        format: Option<StorageFormat>,
+        /// Where to keep the project's files
+        #[arg(long, value_enum, default_value_t)]
+        backend: BackendKind,
     },
     /// Launch interactive TUI (default mode)
     Tui,
-    /// Set an environment variable
+    /// Set one or more environment variables (KEY VALUE or KEY=VALUE...,
+    /// or KEY... with --generate)
     Set {
-        key: String,
-        value: String,
-        #[arg(short, long, default_value = "development")]
-        env: String,
+        #[arg(required = true, num_args = 1..)]
+        assignments: Vec<String>,
+        /// Environment to use (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+        /// Describe the variable(s); an empty description clears it
+        #[arg(long = "desc", value_name = "TEXT")]
+        description: Option<String>,
+        /// Tag the variable(s); repeat to add several tags
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Flag the variable(s) as secret
+        #[arg(long)]
+        secret: bool,
+        /// Store the value(s) base64-encoded, for binary payloads; a value of
+        /// "-" is read from stdin
+        #[arg(long)]
+        base64: bool,
+        /// Set each KEY to a new random secret: hex:<bytes>, base64:<bytes>,
+        /// uuid or password:<length>[:alnum|:wide]
+        #[arg(long, value_name = "KIND", conflicts_with = "base64")]
+        generate: Option<Generator>,
+        /// Print the generated value(s) once instead of keeping them hidden
+        #[arg(long, requires = "generate")]
+        show: bool,
+        /// Record when the value(s) should be rotated by: a lifetime such as
+        /// 90d, 6w or 12h, or a YYYY-MM-DD date
+        #[arg(long, value_name = "WHEN", value_parser = parse_expiry)]
+        expires: Option<DateTime<Utc>>,
+        /// Create the environment if it doesn't exist yet
+        #[arg(long)]
+        create: bool,
+        /// Don't echo what was set
+        #[arg(long)]
+        silent: bool,
+        /// Refuse values breaking a schema policy of error severity
+        #[arg(long)]
+        enforce: bool,
     },
     /// Get an environment variable
     Get {
         key: String,
-        #[arg(short, long, default_value = "development")]
-        env: String,
+        /// Environment to use (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+        /// Print only the value, without a trailing newline
+        #[arg(long)]
+        raw: bool,
+        /// Decode a value stored with `set --base64` and print the raw bytes
+        #[arg(long, conflicts_with = "raw")]
+        decode: bool,
+        /// Show the value in every environment, or "(unset)"
+        #[arg(long, conflicts_with_all = ["env", "raw", "decode"])]
+        all_envs: bool,
+        /// Show secret values on a terminal instead of masking them
+        #[arg(long)]
+        reveal: bool,
     },
-    /// Remove an environment variable
+    /// Remove one or more environment variables
     Unset {
-        key: String,
-        #[arg(short, long, default_value = "development")]
-        env: String,
+        #[arg(required = true, num_args = 1..)]
+        keys: Vec<String>,
+        /// Environment to use (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+    },
+    /// Edit an environment's variables in $EDITOR, validated before saving
+    Edit {
+        /// Environment to use (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
     },
     /// Switch to a different environment
-    Switch { environment: String },
+    Switch {
+        environment: String,
+        /// Create the environment if it doesn't exist yet
+        #[arg(long)]
+        create: bool,
+    },
     /// List all variables in current environment
     List {
         #[arg(short, long)]
         env: Option<String>,
+        /// Also show descriptions, tags, the secret flag and timestamps
+        #[arg(short, long)]
+        long: bool,
+        /// Show variables in sections by their first tag or `PREFIX__` namespace
+        #[arg(short, long)]
+        group: bool,
+        /// Only list keys matching a glob (`AWS_*`) or a /regex/
+        #[arg(short, long)]
+        filter: Option<String>,
+        /// Print just the keys, one per line
+        #[arg(long, conflicts_with_all = ["values_only", "long"])]
+        keys_only: bool,
+        /// Print just the values, one per line
+        #[arg(long, conflicts_with = "long")]
+        values_only: bool,
+        /// Output format; anything but table prints raw values for scripts
+        #[arg(long, value_enum, default_value_t)]
+        #[arg(conflicts_with_all = ["keys_only", "values_only", "long", "group"])]
+        format: ListFormat,
+        /// Order to list variables in (within groups when grouping)
+        #[arg(long, value_enum, default_value_t)]
+        sort: ListSort,
     },
     /// Show current active environment
     Current,
+    /// Describe environments, keys and the schema in one JSON document for
+    /// editor plugins and other tools; values are left out
+    Dump,
+    /// Summarize the environment's health: variables, schema problems,
+    /// expiring secrets, unsynced changes and when it last changed
+    Status {
+        /// Environment to use (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+    },
     /// Validate environment setup
     Validate {
+        /// Comma-separated variables that must be set (default: the schema's required ones)
         #[arg(short, long)]
         required: Option<String>,
+        /// Prompt for each missing variable and save the values entered
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Check environments against each other: keys missing from some
+    /// environments of a parity group, `per_environment` values shared
+    /// between environments, and empty values
+    Lint {
+        /// Print the problems as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Show available environments
-    Envs,
+    Envs {
+        /// Also show archived environments
+        #[arg(short, long)]
+        all: bool,
+    },
+    /// Manage environments
+    Env {
+        #[command(subcommand)]
+        action: EnvAction,
+    },
+    /// Manage profiles: subsets of an environment's variables for one kind
+    /// of process, used with `run --profile`
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+    /// List the projects envMatch knows about, or register and forget them
+    Projects {
+        #[command(subcommand)]
+        action: Option<ProjectsAction>,
+    },
+    /// Show the history of changes recorded as snapshots
+    History,
+    /// Show how an environment changed since a snapshot, or how it differs from the process environment
+    Diff {
+        /// Snapshot to compare against (see `envMatch history`)
+        #[arg(long, required_unless_present = "os", conflicts_with = "os")]
+        at: Option<String>,
+        /// Compare against the variables set in the current process environment
+        #[arg(long)]
+        os: bool,
+        #[arg(short, long)]
+        env: Option<String>,
+    },
+    /// Show how one variable changed over time, or restore an earlier value
+    Log {
+        key: String,
+        /// Environment to use (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+        /// Set the variable back to its value as of this snapshot (see the log)
+        #[arg(long, value_name = "SNAPSHOT")]
+        revert: Option<String>,
+    },
+    /// Restore every environment to the state recorded in a snapshot
+    Rollback { snapshot: String },
+    /// Rename deprecated variables to the replacement their schema note
+    /// names (`deprecated: use KEY`), in every environment at once
+    MigrateKeys {
+        /// Only show what would be renamed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove variables that aren't in the schema or used in the project's files
+    Prune {
+        /// Environment to use (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+        /// Show what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+        /// Keep these variables even if nothing uses them; repeat to keep several
+        #[arg(long, value_name = "KEY")]
+        keep: Vec<String>,
+        /// Don't search the project's files, only go by the schema and --keep
+        #[arg(long)]
+        no_scan: bool,
+        /// Remove without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// List variables that have expired or expire soon
+    Rotate {
+        /// Only check this environment (default: all environments)
+        #[arg(short, long)]
+        env: Option<String>,
+        /// Include variables expiring within this many days
+        #[arg(long, value_name = "DAYS", default_value_t = config::ROTATION_WINDOW_DAYS)]
+        within: i64,
+    },
+    /// Show the audit log of changes
+    Audit {
+        #[arg(short, long)]
+        env: Option<String>,
+        #[arg(short, long)]
+        key: Option<String>,
+        /// Check that the log's hash chain hasn't been tampered with
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Summarize the audit log: environment switches, the most changed
+    /// variables and command usage (read locally, never sent anywhere)
+    Stats {
+        /// Only count the last DAYS days
+        #[arg(long, value_name = "DAYS")]
+        days: Option<u32>,
+        /// How many of the most changed variables to show
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+    /// Export an environment in a format other tools can consume
+    Export {
+        /// Environment to export; repeat to merge several in order, later ones winning
+        #[arg(short, long)]
+        env: Vec<String>,
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Dotenv)]
+        format: ExportFormat,
+        /// Resource name for Kubernetes manifests (default: environment name)
+        #[arg(long)]
+        name: Option<String>,
+        /// Namespace for Kubernetes manifests
+        #[arg(long)]
+        namespace: Option<String>,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Replace `!cmd` values with their command's output and `!file` values
+        /// with the file's contents
+        #[arg(long)]
+        resolved: bool,
+        /// Read the output back and fail, writing nothing, unless every
+        /// value survives unchanged
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print code that loads an environment into the shell: eval "$(envMatch shell)",
+    /// or envMatch shell --dialect powershell | Out-String | Invoke-Expression
+    Shell {
+        /// Environments to load, later ones overriding earlier ones (default:
+        /// the active environment, whichever it is when this runs)
+        #[arg(short, long)]
+        env: Vec<String>,
+        /// Shell to write code for (default: powershell on Windows, posix elsewhere)
+        #[arg(long, value_enum, default_value_t, hide_default_value = true)]
+        dialect: ShellDialect,
+    },
+    /// Print a shell hook that loads the current environment on entering the
+    /// project and unloads it on leaving: eval "$(envMatch hook bash)". It
+    /// only loads projects allowed with 'envMatch hook allow'
+    #[command(args_conflicts_with_subcommands = true)]
+    Hook {
+        #[arg(required = true)]
+        shell: Option<HookShell>,
+        #[command(subcommand)]
+        action: Option<HookAction>,
+    },
+    /// Shell code the hook evaluates before each prompt
+    #[command(hide = true)]
+    HookEnv { shell: HookShell },
+    /// Update envMatch to the latest GitHub release, verifying its checksum
+    SelfUpdate {
+        /// Only say whether a newer version is out, e.g. for installs made by
+        /// Homebrew or WinGet
+        #[arg(long)]
+        check: bool,
+    },
+    /// Write man pages for envMatch and every subcommand, e.g. for packaging
+    Man {
+        /// Directory to write the pages to
+        #[arg(short, long, default_value = "man")]
+        output: PathBuf,
+    },
+    /// Set up direnv to load envMatch environments
+    Direnv {
+        #[command(subcommand)]
+        action: DirenvAction,
+    },
+    /// Work with the variable schema in .envMatch/schema.yaml
+    Schema {
+        #[command(subcommand)]
+        action: SchemaAction,
+    },
+    /// Write a .env.example-style template: keys, descriptions and placeholders, secrets blanked
+    Template {
+        /// Environment to use (default: current environment)
+        #[arg(short, long, conflicts_with = "schema")]
+        env: Option<String>,
+        /// Use the keys declared in schema.yaml instead of an environment
+        #[arg(long)]
+        schema: bool,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Generate a typed config module from schema.yaml: field names, types
+    /// and which variables are required
+    Codegen {
+        #[arg(long, value_enum)]
+        lang: Language,
+        /// Write to this file, adding the language's extension if it has
+        /// none, instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Check that an environment sets every key listed in a template file
+    Check {
+        template: PathBuf,
+        /// Environment to check (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+    },
+    /// Set variables from a dotenv, JSON, TOML or YAML file
+    Import {
+        file: PathBuf,
+        /// Format of the file (default: from its extension, else dotenv)
+        #[arg(short, long)]
+        format: Option<ImportFormat>,
+        /// Environment to import into (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+        /// Joins nested keys into variable names, e.g. DB__HOST
+        #[arg(long, default_value = "__")]
+        separator: String,
+    },
+    /// Run a command with an environment's variables set
+    Run {
+        /// Environment to use; repeat to merge several in order, later ones winning
+        /// (default: current environment)
+        #[arg(short, long)]
+        env: Vec<String>,
+        /// Only pass on the variables of this profile, with its overrides
+        #[arg(short, long)]
+        profile: Option<String>,
+        /// Print to stderr which environment each variable comes from and which
+        /// inherited values it replaces; without a command, only explain
+        #[arg(long)]
+        explain: bool,
+        /// Pass on only envMatch's variables, none of the calling shell's (by
+        /// default the command gets all of them)
+        #[arg(long)]
+        isolated: bool,
+        /// Pass on only these of the calling shell's variables, e.g. PATH,HOME
+        #[arg(long, value_delimiter = ',')]
+        allow: Vec<String>,
+        /// Command to run, with its arguments
+        #[arg(
+            required_unless_present = "explain",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        command: Vec<String>,
+    },
+    /// Run a command, restarting it whenever its environment changes
+    Watch {
+        /// Environment to use; repeat to merge several in order, later ones winning
+        /// (default: current environment)
+        #[arg(short, long)]
+        env: Vec<String>,
+        /// Command to run, with its arguments
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Diagnose setup problems and suggest fixes
+    Doctor,
+    /// Serve the environments read-only over HTTP, for `sync http` and other tools
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        addr: String,
+        /// Only answer requests with "Authorization: Bearer <token>" (default:
+        /// a random token, printed at start)
+        #[arg(long, env = "ENVMATCH_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// Make .envMatch readable by its owner only (0700 directories, 0600 files)
+    Harden,
+    /// Set up .envMatch for committing: encryption, schema, .gitignore and merge driver
+    GitInit,
+    /// Git merge driver for environment files (configured by git-init)
+    #[command(hide = true)]
+    MergeDriver {
+        base: PathBuf,
+        ours: PathBuf,
+        theirs: PathBuf,
+    },
+    /// Manage the encryption key stored in the OS keychain
+    Key {
+        #[command(subcommand)]
+        action: KeyAction,
+    },
+    /// Synchronize an environment with a remote secret store
+    Sync {
+        #[command(subcommand)]
+        remote: SyncRemote,
+    },
+    /// Push an environment to a CI/CD platform
+    Push {
+        #[command(subcommand)]
+        target: PushTarget,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// List an environment's profiles
+    List {
+        /// Environment to use (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+    },
+    /// Define a profile, replacing any by that name
+    Set {
+        name: String,
+        /// Environment to use (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+        /// Keys to include, `*` matching anything; repeat for more (default: all)
+        #[arg(short, long)]
+        include: Vec<String>,
+        /// Value to use instead of the environment's; repeat for more
+        #[arg(short = 'o', long = "override", value_name = "KEY=VALUE")]
+        overrides: Vec<String>,
+    },
+    /// Remove a profile
+    Remove {
+        name: String,
+        /// Environment to use (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum EnvAction {
+    /// Create an empty environment, or a copy of another with --from
+    Create {
+        name: String,
+        /// Copy the variables of this environment
+        #[arg(long, value_name = "ENV")]
+        from: Option<String>,
+        /// Put a placeholder in place of secret values when copying
+        #[arg(long, requires = "from")]
+        strip_secrets: bool,
+    },
+    /// Fold one environment's variables into another, e.g. `env merge feature-x into development`
+    Merge {
+        /// Environment to merge from
+        source: String,
+        /// The word "into"
+        #[arg(value_name = "into", value_parser = ["into"])]
+        into: String,
+        /// Environment to merge into
+        target: String,
+        /// How to settle keys set differently in both
+        #[arg(long, value_enum, default_value_t = MergeStrategy::Interactive)]
+        strategy: MergeStrategy,
+    },
+    /// Move an environment to .envMatch/archive/, out of `envs` and the TUI
+    Archive { name: String },
+    /// Bring an archived environment back
+    Unarchive { name: String },
+}
+
+#[derive(Subcommand)]
+enum SchemaAction {
+    /// Write the schema for other validators, such as CI policies and editors
+    Export {
+        #[arg(long, value_enum, default_value_t)]
+        format: SchemaFormat,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookAction {
+    /// Let the hook load this project's environments as they are now; run
+    /// again after they change
+    Allow,
+    /// Stop the hook from loading this project
+    Deny,
+}
+
+#[derive(Subcommand)]
+enum DirenvAction {
+    /// Write a .envrc loading the current environment and allow it
+    Setup {
+        /// Also reload whenever variables or the current environment change
+        #[arg(long)]
+        watch_stamp: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProjectsAction {
+    /// Register the project a directory is in, so the TUI can switch to it
+    Add {
+        /// A directory of the project
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Forget a registered project; its files are left alone
+    Remove {
+        /// Root of the project, as `projects` lists it
+        dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeyAction {
+    /// Store the key in the OS keychain so the passphrase isn't asked again
+    Set,
+    /// Remove the key from the OS keychain
+    Forget,
+}
+
+#[derive(Subcommand)]
+enum SyncRemote {
+    /// HashiCorp Vault (KV v2), authenticating with a token or AppRole
+    Vault {
+        #[command(flatten)]
+        sync: SyncArgs,
+        /// Secret path, starting with the KV mount (e.g. secret/myapp/staging)
+        #[arg(long)]
+        path: String,
+        #[arg(long, env = "VAULT_ADDR")]
+        addr: String,
+        #[arg(long, env = "VAULT_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+        #[arg(long, env = "VAULT_ROLE_ID")]
+        role_id: Option<String>,
+        #[arg(long, env = "VAULT_SECRET_ID", hide_env_values = true)]
+        secret_id: Option<String>,
+    },
+    /// AWS Secrets Manager, storing the environment as one JSON secret (uses the aws CLI)
+    AwsSecrets {
+        #[command(flatten)]
+        sync: SyncArgs,
+        /// Name or ARN of the secret
+        #[arg(long)]
+        secret_id: String,
+        /// AWS region (default: from the aws CLI configuration)
+        #[arg(long)]
+        region: Option<String>,
+        /// Named profile from the aws CLI configuration
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    /// Azure Key Vault, one secret per variable (uses the az CLI)
+    AzureKeyvault {
+        #[command(flatten)]
+        sync: SyncArgs,
+        /// Name of the key vault
+        #[arg(long)]
+        vault_name: String,
+    },
+    /// The config vars of a Heroku app, through the Heroku platform API
+    Heroku {
+        #[command(flatten)]
+        sync: SyncArgs,
+        /// Name of the app
+        #[arg(long)]
+        app: String,
+        /// API key, e.g. from `heroku auth:token`
+        #[arg(long, env = "HEROKU_API_KEY", hide_env_values = true)]
+        api_key: String,
+    },
+    /// An envMatch HTTP server, such as `envMatch serve`
+    Http {
+        #[command(flatten)]
+        sync: SyncArgs,
+        /// Base URL of the server, e.g. http://config.internal:7878
+        #[arg(long)]
+        url: String,
+        /// Sent as "Authorization: Bearer <token>"
+        #[arg(long, env = "ENVMATCH_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// S3 or an S3-compatible store, one encrypted object per environment (uses the aws CLI)
+    S3 {
+        #[command(flatten)]
+        sync: SyncArgs,
+        #[arg(long)]
+        bucket: String,
+        /// Folder in the bucket; environments are stored as <prefix>/<env>.json
+        #[arg(long, default_value = "")]
+        prefix: String,
+        /// AWS region (default: from the aws CLI configuration)
+        #[arg(long)]
+        region: Option<String>,
+        /// Named profile from the aws CLI configuration
+        #[arg(long)]
+        profile: Option<String>,
+        /// Endpoint of an S3-compatible service, e.g. MinIO or Cloudflare R2
+        #[arg(long)]
+        endpoint_url: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PushTarget {
+    /// GitHub Actions environment: secret variables become encrypted secrets, the rest variables
+    Github {
+        /// Repository, as owner/name
+        #[arg(long)]
+        repo: String,
+        /// Environment to push (default: current environment)
+        #[arg(short, long)]
+        env: Option<String>,
+        /// GitHub environment to push to (default: same name as --env)
+        #[arg(long)]
+        environment: Option<String>,
+        /// Only show what would change
+        #[arg(long)]
+        dry_run: bool,
+        /// Token allowed to manage the repository's environments
+        #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+        token: String,
+    },
+}
+
+/// Options shared by every sync remote.
+#[derive(Args)]
+struct SyncArgs {
+    #[arg(value_enum, default_value_t = SyncAction::Pull)]
+    action: SyncAction,
+    /// Environment to sync (default: current environment)
+    #[arg(short, long)]
+    env: Option<String>,
+    /// On conflict, keep the values of the side being synced from
+    #[arg(long)]
+    force: bool,
+    /// Requests to run at once, for remotes with one secret per variable
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..=64))]
+    jobs: u16,
+    /// Times to retry a failed request, waiting 0.5s, 1s, 2s... in between
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retry: u32,
+}
+
+impl SyncArgs {
+    fn transfer(&self) -> Transfer {
+        Transfer {
+            jobs: self.jobs.into(),
+            retries: self.retry,
+            ..Transfer::default()
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
-    let commands = EnvMatchCommands::new();
-
-    // If no command is specified, check if initialized and launch TUI
-    let command = cli.command.unwrap_or_else(|| {
-        if commands.is_initialized() {
-            Commands::Tui
+    let cli = Cli::try_parse().unwrap_or_else(|error| {
+        // clap would exit with 2, which means "not initialized" here
+        let code = if error.use_stderr() {
+            exit_code::USAGE
         } else {
-            Commands::Init {
-                environment: "development".to_string(),
+            0
+        };
+        let _ = error.print();
+        std::process::exit(code);
+    });
+    init_logging(cli.verbose);
+    let mut commands = match (&cli.dir, &cli.command) {
+        (Some(dir), _) => EnvMatchCommands::with_project_dir(dir.clone()),
+        // `init` always targets the current directory, even inside another project
+        (None, Some(Commands::Init { .. })) => {
+            EnvMatchCommands::with_project_dir(std::env::current_dir()?)
+        }
+        (None, _) => EnvMatchCommands::new(),
+    };
+    commands.set_force_empty(cli.force_empty);
+    if let Err(error) = commands.set_scope(cli.scope.as_deref()) {
+        handle_error(error);
+    }
+    commands.set_session_environment(commands::session_environment());
+    commands
+        .set_quiet(cli.quiet || matches!(&cli.command, Some(Commands::Set { silent: true, .. })));
+    commands.set_secret_scan(!cli.no_secret_scan);
+    commands.set_enforce_policies(matches!(
+        &cli.command,
+        Some(Commands::Set { enforce: true, .. })
+    ));
+    commands.set_check_exports(matches!(
+        &cli.command,
+        Some(Commands::Export { check: true, .. })
+    ));
+    if let Some(Commands::Init {
+        format, backend, ..
+    }) = &cli.command
+    {
+        commands.set_storage_format(*format);
+        if !commands.is_initialized() {
+            commands.set_backend(*backend);
+        }
+    }
+
+    match commands.settings() {
+        Ok(settings) => settings.apply_color(cli.no_color),
+        Err(error) => {
+            Settings::default().apply_color(cli.no_color);
+            handle_error(error)
+        }
+    }
+
+    // Without a command, launch the TUI, or initialize a new directory. Both
+    // need someone at a terminal: in CI they would hang in raw mode or create
+    // a project nobody asked for, so show the status or the help and fail
+    let command = match cli.command {
+        Some(command) => command,
+        None if !is_interactive() => {
+            if commands.is_initialized() {
+                let _ = commands.show_status(None);
+            } else {
+                let _ = Cli::command().write_help(&mut io::stderr());
             }
+            handle_error(EnvMatchError::NotATerminal)
         }
-    });
+        None if commands.is_initialized() => Commands::Tui,
+        None => Commands::Init {
+            environment: None,
+            interactive: false,
+            template: None,
+            format: None,
+            backend: BackendKind::Files,
+        },
+    };
+
+    // `harden` and `doctor` report loose permissions themselves, and the
+    // shell hook runs before every prompt
+    if commands.is_initialized()
+        && !matches!(
+            command,
+            Commands::Harden | Commands::Doctor | Commands::HookEnv { .. }
+        )
+    {
+        if let Err(error) = commands.check_permissions(cli.strict) {
+            handle_error(error);
+        }
+    }
 
     let result = match command {
-        Commands::Init { environment } => commands.init_with_environment(&environment),
-        Commands::Tui => run_tui().await,
-        Commands::Set { key, value, env } => commands.set_variable(&key, &value, &env),
-        Commands::Get { key, env } => commands.get_variable(&key, &env).map(|_| ()),
-        Commands::Unset { key, env } => commands.unset_variable(&key, &env),
-        Commands::Switch { environment } => commands.switch_environment(&environment),
-        Commands::List { env } => commands.list_variables(env.as_deref()).map(|_| ()),
+        Commands::Init {
+            environment,
+            interactive: true,
+            template,
+            ..
+        } => commands
+            .init_interactive(environment.as_deref(), template)
+            .inspect(|_| register_project(&commands)),
+        Commands::Init {
+            environment,
+            template,
+            ..
+        } => match environment {
+            Some(environment) => commands.init_with_environment(&environment),
+            None => commands.init(),
+        }
+        .and_then(|_| match template {
+            Some(template) => {
+                let env_name = commands.resolve_environment(None)?;
+                commands.apply_template(template, &env_name).map(|_| ())
+            }
+            None => Ok(()),
+        })
+        .inspect(|_| register_project(&commands)),
+        Commands::Tui => run_tui(commands).await,
+        Commands::Set {
+            assignments,
+            env,
+            description,
+            tags,
+            secret,
+            base64,
+            generate,
+            show,
+            expires,
+            create,
+            ..
+        } => commands
+            .resolve_environment(env.as_deref())
+            .and_then(|env| {
+                if create && !commands.environment_exists(&env) {
+                    commands.create_environment(&env)?;
+                }
+                let metadata = VariableMetadata {
+                    description,
+                    tags,
+                    secret,
+                    expires_at: expires,
+                    ..Default::default()
+                };
+                if let Some(generator) = generate {
+                    return commands
+                        .generate_variables(&assignments, generator, &metadata, &env, show)
+                        .map(|_| ());
+                }
+                let mut vars = commands::parse_assignments(&assignments)?;
+                if base64 {
+                    commands::encode_base64_values(&mut vars)?;
+                }
+                commands.set_variables_with_metadata(&vars, &metadata, &env)
+            }),
+        Commands::Get {
+            key,
+            all_envs: true,
+            reveal,
+            ..
+        } => commands
+            .get_variable_across_environments(&key, reveal)
+            .map(|_| ()),
+        Commands::Get {
+            key,
+            env,
+            raw,
+            decode,
+            all_envs: false,
+            reveal,
+        } => commands
+            .resolve_environment(env.as_deref())
+            .and_then(|env| {
+                let bytes = if decode {
+                    commands.decode_variable(&key, &env)?
+                } else if raw {
+                    commands.lookup_variable(&key, &env)?.into_bytes()
+                } else {
+                    return commands.get_variable(&key, &env, reveal).map(|_| ());
+                };
+                let mut stdout = io::stdout();
+                stdout.write_all(&bytes)?;
+                stdout.flush()?;
+                Ok(())
+            }),
+        Commands::Unset { keys, env } => commands
+            .resolve_environment(env.as_deref())
+            .and_then(|env| commands.unset_variables(&keys, &env)),
+        Commands::Edit { env } => commands.edit_environment(env.as_deref()).map(|_| ()),
+        Commands::Switch {
+            environment,
+            create,
+        } => {
+            if create && !commands.environment_exists(&environment) {
+                commands
+                    .create_environment(&environment)
+                    .and_then(|_| commands.switch_environment(&environment))
+            } else {
+                commands.switch_environment(&environment)
+            }
+        }
+        Commands::List {
+            env,
+            long,
+            group,
+            filter,
+            keys_only,
+            values_only,
+            format,
+            sort,
+        } => filter
+            .as_deref()
+            .map(KeyFilter::parse)
+            .transpose()
+            .and_then(|filter| {
+                let options = ListOptions {
+                    long,
+                    group,
+                    filter,
+                    keys_only,
+                    values_only,
+                    format,
+                    sort,
+                };
+                commands.list_variables(env.as_deref(), options)
+            })
+            .map(|_| ()),
         Commands::Current => commands.show_current_environment().map(|_| ()),
-        Commands::Validate { required } => commands.validate_environment(required.as_deref()),
-        Commands::Envs => commands.list_environments().map(|_| ()),
+        Commands::Dump => commands.dump().map(|_| ()),
+        Commands::Status { env } => commands.show_status(env.as_deref()).map(|_| ()),
+        Commands::Validate { required, fix } => {
+            commands.validate_environment(required.as_deref(), fix)
+        }
+        Commands::Lint { json } => commands.lint(json).map(|_| ()),
+        Commands::Envs { all } => commands.list_environments(all).map(|_| ()),
+        Commands::Profile { action } => match action {
+            ProfileAction::List { env } => commands.list_profiles(env.as_deref()).map(|_| ()),
+            ProfileAction::Set {
+                name,
+                env,
+                include,
+                overrides,
+            } => commands::parse_assignments(&overrides).and_then(|overrides| {
+                let env = commands.resolve_environment(env.as_deref())?;
+                commands.set_profile(&env, &name, &include, &overrides)
+            }),
+            ProfileAction::Remove { name, env } => commands
+                .resolve_environment(env.as_deref())
+                .and_then(|env| commands.remove_profile(&env, &name)),
+        },
+        Commands::Env { action } => match action {
+            EnvAction::Create {
+                name,
+                from,
+                strip_secrets,
+            } => match from {
+                Some(source) => commands.create_environment_from(&name, &source, strip_secrets),
+                None => commands.create_environment(&name),
+            },
+            EnvAction::Merge {
+                source,
+                target,
+                strategy,
+                ..
+            } => commands
+                .merge_environments(&source, &target, strategy)
+                .map(|_| ()),
+            EnvAction::Archive { name } => commands.archive_environment(&name),
+            EnvAction::Unarchive { name } => commands.unarchive_environment(&name),
+        },
+        Commands::Projects { action } => match action {
+            None => commands.list_projects().map(|_| ()),
+            Some(ProjectsAction::Add { dir }) => commands.add_project(&dir).map(|_| ()),
+            Some(ProjectsAction::Remove { dir }) => commands.remove_project(&dir),
+        },
+        Commands::History => commands.show_history().map(|_| ()),
+        Commands::Diff { at, env, .. } => match at {
+            Some(at) => commands.diff_with_snapshot(&at, env.as_deref()),
+            None => commands.diff_with_os(env.as_deref()),
+        }
+        .map(|_| ()),
+        Commands::Log { key, env, revert } => match revert {
+            Some(snapshot) => commands.revert_key(&key, env.as_deref(), &snapshot),
+            None => commands.key_log(&key, env.as_deref()).map(|_| ()),
+        },
+        Commands::Rollback { snapshot } => commands.rollback(&snapshot),
+        Commands::MigrateKeys { dry_run } => commands.migrate_keys(dry_run).map(|_| ()),
+        Commands::Prune {
+            env,
+            dry_run,
+            keep,
+            no_scan,
+            yes,
+        } => {
+            let options = PruneOptions {
+                keep,
+                scan: !no_scan,
+                dry_run,
+                yes,
+            };
+            commands.prune(env.as_deref(), &options).map(|_| ())
+        }
+        Commands::Rotate { env, within } => {
+            commands.rotation_due(env.as_deref(), within).map(|_| ())
+        }
+        Commands::Audit { env, key, verify } => commands
+            .show_audit_log(env.as_deref(), key.as_deref(), verify)
+            .map(|_| ()),
+        Commands::Stats { days, top } => commands.stats(days, top).map(|_| ()),
+        Commands::Export {
+            env,
+            format,
+            name,
+            namespace,
+            output,
+            resolved,
+            ..
+        } => commands
+            .export_environment(
+                &env,
+                format,
+                name.as_deref(),
+                namespace.as_deref(),
+                output.as_deref(),
+                resolved,
+            )
+            .map(|_| ()),
+        Commands::Sync { remote } => sync_remote(&commands, remote),
+        Commands::Push { target } => match target {
+            PushTarget::Github {
+                repo,
+                env,
+                environment,
+                dry_run,
+                token,
+            } => commands
+                .push_github(
+                    &repo,
+                    env.as_deref(),
+                    environment.as_deref(),
+                    &token,
+                    dry_run,
+                )
+                .map(|_| ()),
+        },
+        Commands::Shell { env, dialect } => commands
+            .export_environment(&env, dialect.into(), None, None, None, false)
+            .map(|_| ()),
+        Commands::Hook {
+            action: Some(HookAction::Allow),
+            ..
+        } => commands.hook_allow(),
+        Commands::Hook {
+            action: Some(HookAction::Deny),
+            ..
+        } => commands.hook_deny().map(|_| ()),
+        Commands::Hook { shell, .. } => {
+            commands.hook(shell.expect("clap requires a shell without an action"));
+            Ok(())
+        }
+        Commands::HookEnv { shell } => {
+            commands.hook_env(shell);
+            Ok(())
+        }
+        Commands::SelfUpdate { check } => update::self_update(check),
+        Commands::Man { output } => write_man_pages(&output).map(|count| {
+            if !cli.quiet {
+                println!("✅ Wrote {} man page(s) to {}", count, output.display());
+            }
+        }),
+        Commands::Direnv { action } => match action {
+            DirenvAction::Setup { watch_stamp } => commands.direnv_setup(watch_stamp).map(|_| ()),
+        },
+        Commands::Schema { action } => match action {
+            SchemaAction::Export { format, output } => commands
+                .export_schema(format, output.as_deref())
+                .map(|_| ()),
+        },
+        Commands::Template {
+            env,
+            schema,
+            output,
+        } => commands
+            .template(env.as_deref(), schema, output.as_deref())
+            .map(|_| ()),
+        Commands::Codegen { lang, output } => commands.codegen(lang, output.as_deref()).map(|_| ()),
+        Commands::Check { template, env } => commands.check_template(&template, env.as_deref()),
+        Commands::Import {
+            file,
+            format,
+            env,
+            separator,
+        } => commands
+            .import_file(&file, format, &separator, env.as_deref())
+            .map(|_| ()),
+        Commands::Run {
+            env,
+            profile,
+            explain,
+            isolated,
+            allow,
+            command,
+        } => {
+            let inheritance = match isolated || !allow.is_empty() {
+                true => Inheritance::Only(allow),
+                false => Inheritance::All,
+            };
+            let explained = match explain {
+                true => commands
+                    .explain_run(&env, profile.as_deref(), &inheritance)
+                    .map(|_| ()),
+                false => Ok(()),
+            };
+            explained.and_then(|_| match command.split_first() {
+                Some((program, args)) => {
+                    let profile = profile.as_deref();
+                    commands
+                        .run(&env, profile, &inheritance, program, args)
+                        .map(|code| {
+                            if code != 0 {
+                                std::process::exit(code);
+                            }
+                        })
+                }
+                None => Ok(()),
+            })
+        }
+        Commands::Watch { env, command } => commands.watch(&env, &command[0], &command[1..]),
+        Commands::Doctor => commands.doctor().map(|_| ()),
+        Commands::Harden => commands.harden().map(|_| ()),
+        Commands::Serve { addr, token } => commands.serve(&addr, token.as_deref()),
+        Commands::GitInit => commands.git_init(),
+        Commands::Key { action } => match action {
+            KeyAction::Set => commands.key_set(),
+            KeyAction::Forget => commands.key_forget(),
+        },
+        Commands::MergeDriver { base, ours, theirs } => {
+            commands::merge_environment_files(&base, &ours, &theirs).map(|conflicts| {
+                if !conflicts.is_empty() {
+                    eprintln!(
+                        "⚠️  Changed on both sides, kept our value: {}",
+                        conflicts.join(", ")
+                    );
+                    std::process::exit(1);
+                }
+            })
+        }
     };
 
     if let Err(error) = result {
@@ -110,62 +1246,286 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn run_tui() -> Result<(), EnvMatchError> {
+/// Sends envMatch's logs to stderr: only warnings by default, debug with
+/// `-v` and trace with `-vv`. `ENVMATCH_LOG` takes a full filter instead.
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => "warn",
+        1 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_env("ENVMATCH_LOG")
+        .unwrap_or_else(|_| EnvFilter::new(format!("warn,{}={}", env!("CARGO_CRATE_NAME"), level)));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .without_time()
+        .init();
+}
+
+/// Remembers the project for `projects` and the TUI's project switcher. A
+/// user config that can't be written mustn't stop the command itself.
+fn register_project(commands: &EnvMatchCommands) {
+    if let Err(error) = commands.register_project() {
+        tracing::warn!(%error, "could not register the project");
+    }
+}
+
+/// Whether someone is at a terminal: both stdin and stdout are one.
+fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+async fn run_tui(mut commands: EnvMatchCommands) -> Result<(), EnvMatchError> {
+    if !is_interactive() {
+        return Err(EnvMatchError::NotATerminal);
+    }
+    register_project(&commands);
+    // Warnings printed to stderr would land on top of the screen
+    commands.set_secret_scan(false);
+    // Put the terminal back however the TUI ends: a panic mid-draw or a
+    // SIGINT from outside (Ctrl+C itself arrives as a key in raw mode)
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            let _ = restore_terminal();
+            std::process::exit(130);
+        }
+    });
+
     // Setup terminal
-    enable_raw_mode().map_err(|e| EnvMatchError::ConfigReadError { source: e })?;
+    enable_raw_mode().map_err(|e| EnvMatchError::TerminalError { source: e })?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)
-        .map_err(|e| EnvMatchError::ConfigReadError { source: e })?;
+        .map_err(|e| EnvMatchError::TerminalError { source: e })?;
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal =
-        Terminal::new(backend).map_err(|e| EnvMatchError::ConfigReadError { source: e })?;
 
+    let result = match Terminal::new(backend) {
+        Ok(mut terminal) => run_app(&mut terminal, commands).await,
+        Err(e) => Err(EnvMatchError::TerminalError { source: e }),
+    };
+
+    restore_terminal().map_err(|e| EnvMatchError::TerminalError { source: e })?;
+    result
+}
+
+async fn run_app(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    commands: EnvMatchCommands,
+) -> Result<(), EnvMatchError> {
     // Create app and event handler
-    let mut app = App::new()?;
+    let mut app = App::new(commands)?;
     let mut event_handler = EventHandler::new(Duration::from_millis(100));
 
     // Run the main loop
+    let result = event_loop(terminal, &mut app, &mut event_handler).await;
+    event_handler.shutdown().await;
+    result
+}
+
+async fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+    event_handler: &mut EventHandler,
+) -> Result<(), EnvMatchError> {
+    let mut watched = None;
     while !app.should_quit {
+        // Watch the project again whenever the TUI switches to another one.
+        // Without a watcher the TUI still works; F5 reloads by hand
+        let env_match_dir = app.commands.env_match_dir();
+        if env_match_dir != watched {
+            if let Some(dir) = &env_match_dir {
+                if let Err(e) = event_handler.watch(dir) {
+                    app.set_error(e.to_string());
+                }
+            }
+            watched = env_match_dir;
+        }
+
         terminal
-            .draw(|f| tui::ui::draw(f, &mut app))
-            .map_err(|e| EnvMatchError::ConfigReadError { source: e })?;
+            .draw(|f| tui::ui::draw(f, app))
+            .map_err(|e| EnvMatchError::TerminalError { source: e })?;
 
         if let Some(event) = event_handler.next().await {
             match event {
                 tui::Event::Key(key_event) => {
-                    if let Err(e) = app.handle_key(key_event.code) {
-                        app.error_message = e.to_string();
-                    }
-                }
-                tui::Event::Tick => {
-                    // Clear old messages after some time
-                    if !app.status_message.is_empty() || !app.error_message.is_empty() {
-                        // You could add a timer here to clear messages after a delay
+                    if let Err(e) = app.handle_key_event(key_event) {
+                        app.set_error(e.to_string());
                     }
                 }
+                tui::Event::Tick => app.expire_messages(),
                 tui::Event::Resize => {
                     // Terminal was resized, redraw on next iteration
                 }
+                tui::Event::FilesChanged => {
+                    if let Err(e) = app.reload_from_disk() {
+                        app.set_error(e.to_string());
+                    }
+                }
+                tui::Event::Error(e) => return Err(EnvMatchError::TerminalError { source: e }),
             }
         }
     }
+    Ok(())
+}
 
-    // Restore terminal
-    disable_raw_mode().map_err(|e| EnvMatchError::ConfigReadError { source: e })?;
+/// Leaves raw mode and the alternate screen and shows the cursor again.
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
     execute!(
-        terminal.backend_mut(),
+        io::stdout(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        Show
     )
-    .map_err(|e| EnvMatchError::ConfigReadError { source: e })?;
-    terminal
-        .show_cursor()
-        .map_err(|e| EnvMatchError::ConfigReadError { source: e })?;
+}
 
-    Ok(())
+fn sync_remote(commands: &EnvMatchCommands, remote: SyncRemote) -> Result<(), EnvMatchError> {
+    match remote {
+        SyncRemote::Vault {
+            sync,
+            path,
+            addr,
+            token,
+            role_id,
+            secret_id,
+        } => {
+            let auth = match (role_id, secret_id, token) {
+                (Some(role_id), Some(secret_id), _) => VaultAuth::AppRole { role_id, secret_id },
+                (_, _, Some(token)) => VaultAuth::Token(token),
+                _ => {
+                    return Err(EnvMatchError::RemoteError {
+                        remote: "vault".to_string(),
+                        message:
+                            "no credentials, set VAULT_TOKEN or VAULT_ROLE_ID and VAULT_SECRET_ID"
+                                .to_string(),
+                    })
+                }
+            };
+            let provider = VaultProvider::connect(&addr, &path, auth)?;
+            commands.sync(
+                &provider,
+                sync.env.as_deref(),
+                sync.action,
+                sync.force,
+                &sync.transfer(),
+            )
+        }
+        SyncRemote::AwsSecrets {
+            sync,
+            secret_id,
+            region,
+            profile,
+        } => {
+            let provider =
+                AwsSecretsProvider::new(&secret_id, region.as_deref(), profile.as_deref());
+            commands.sync(
+                &provider,
+                sync.env.as_deref(),
+                sync.action,
+                sync.force,
+                &sync.transfer(),
+            )
+        }
+        SyncRemote::AzureKeyvault { sync, vault_name } => {
+            let provider = AzureKeyVaultProvider::new(&vault_name, commands.fingerprint_key()?);
+            commands.sync(
+                &provider,
+                sync.env.as_deref(),
+                sync.action,
+                sync.force,
+                &sync.transfer(),
+            )
+        }
+        SyncRemote::Heroku { sync, app, api_key } => {
+            let provider = HerokuProvider::new(&app, &api_key);
+            commands.sync(
+                &provider,
+                sync.env.as_deref(),
+                sync.action,
+                sync.force,
+                &sync.transfer(),
+            )
+        }
+        SyncRemote::Http { sync, url, token } => {
+            let env_name = commands.resolve_environment(sync.env.as_deref())?;
+            let provider = HttpProvider::new(&url, &env_name, token.as_deref());
+            commands.sync(
+                &provider,
+                Some(&env_name),
+                sync.action,
+                sync.force,
+                &sync.transfer(),
+            )
+        }
+        SyncRemote::S3 {
+            sync,
+            bucket,
+            prefix,
+            region,
+            profile,
+            endpoint_url,
+        } => {
+            let location = S3Location {
+                bucket: &bucket,
+                prefix: &prefix,
+                region: region.as_deref(),
+                profile: profile.as_deref(),
+                endpoint_url: endpoint_url.as_deref(),
+            };
+            let env_name = commands.resolve_environment(sync.env.as_deref())?;
+            let provider = S3Provider::new(&location, &env_name, crypto::read_passphrase()?);
+            commands.sync(
+                &provider,
+                Some(&env_name),
+                sync.action,
+                sync.force,
+                &sync.transfer(),
+            )
+        }
+    }
+}
+
+/// Writes `envMatch.1`, plus `envMatch-<subcommand>.1` for every visible
+/// subcommand, nested ones included. Returns how many pages were written.
+fn write_man_pages(dir: &Path) -> Result<usize, EnvMatchError> {
+    fn write(dir: &Path, command: clap::Command) -> Result<usize, EnvMatchError> {
+        let path = dir.join(format!("{}.1", command.get_name()));
+        let mut page = Vec::new();
+        clap_mangen::Man::new(command.clone())
+            .render(&mut page)
+            .and_then(|()| fs::write(&path, page))
+            .map_err(|e| EnvMatchError::storage(&path, e))?;
+
+        let mut count = 1;
+        // clap adds a `help` subcommand everywhere; `--help` covers it
+        let visible = command
+            .get_subcommands()
+            .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help");
+        for subcommand in visible {
+            let name = format!("{}-{}", command.get_name(), subcommand.get_name());
+            count += write(dir, subcommand.clone().name(name))?;
+        }
+        Ok(count)
+    }
+
+    fs::create_dir_all(dir).map_err(|e| EnvMatchError::storage(dir, e))?;
+    let mut command = Cli::command();
+    command.build();
+    write(dir, command)
+}
+
+/// Reads `--expires` relative to now.
+fn parse_expiry(input: &str) -> Result<DateTime<Utc>, String> {
+    config::parse_expiry(input, Utc::now())
 }
 
-fn handle_error(error: EnvMatchError) {
+fn handle_error(error: EnvMatchError) -> ! {
     match &error {
         EnvMatchError::MissingRequiredVariables { env, variables } => {
             eprintln!("❌ Missing required variables in environment '{}':", env);
@@ -173,9 +1533,18 @@ fn handle_error(error: EnvMatchError) {
                 eprintln!("  - {}", var);
             }
         }
+        EnvMatchError::ExpiredVariables { env, variables } => {
+            eprintln!(
+                "❌ Expired variables in environment '{}', rotate them:",
+                env
+            );
+            for var in variables {
+                eprintln!("  - {}", var);
+            }
+        }
         _ => {
             eprintln!("❌ {}", error);
         }
     }
-    std::process::exit(1);
+    std::process::exit(error.exit_code());
 }
@@ -0,0 +1,209 @@
+use super::{FileStamp, StorageBackend};
+use crate::error::{EnvMatchError, Result};
+use sha2::{Digest, Sha256};
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{ErrorKind, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Mode of the directories the backend creates: only the owner gets in
+#[cfg(unix)]
+pub const DIR_MODE: u32 = 0o700;
+/// Mode of the files the backend creates: only the owner can read them
+#[cfg(unix)]
+pub const FILE_MODE: u32 = 0o600;
+
+/// Stores files on disk under a root directory. On Unix, what it creates is
+/// private to the owner since it holds secrets.
+#[derive(Debug)]
+pub struct FileBackend {
+    root: PathBuf,
+}
+
+impl FileBackend {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+
+    fn create_parent(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            let mut builder = DirBuilder::new();
+            builder.recursive(true);
+            #[cfg(unix)]
+            builder.mode(DIR_MODE);
+            builder
+                .create(parent)
+                .map_err(|e| EnvMatchError::storage(parent, e))?;
+        }
+        Ok(())
+    }
+
+    /// Opens a file for writing, creating it private to the owner if needed.
+    fn open(path: &Path, append: bool) -> std::io::Result<fs::File> {
+        let mut options = OpenOptions::new();
+        options
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append);
+        #[cfg(unix)]
+        options.mode(FILE_MODE);
+        options.open(path)
+    }
+}
+
+impl StorageBackend for FileBackend {
+    fn read(&self, path: &Path) -> Result<Option<String>> {
+        let path = self.resolve(path);
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                debug!(path = %path.display(), "read file");
+                Ok(Some(content))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                debug!(path = %path.display(), "file not found");
+                Ok(None)
+            }
+            Err(e) => Err(EnvMatchError::storage(path, e)),
+        }
+    }
+
+    /// Writes to a temporary file next to `path` and renames it over `path`,
+    /// so readers see either the old contents or the new, never a mix.
+    fn write(&self, path: &Path, contents: &str) -> Result<()> {
+        let path = self.resolve(path);
+        self.create_parent(&path)?;
+        debug!(path = %path.display(), "write file");
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+        Self::open(&temp_path, false)
+            .and_then(|mut file| {
+                file.write_all(contents.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp_path, &path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&temp_path);
+                EnvMatchError::storage(path, e)
+            })
+    }
+
+    fn append(&self, path: &Path, contents: &str) -> Result<()> {
+        let path = self.resolve(path);
+        self.create_parent(&path)?;
+        debug!(path = %path.display(), "append to file");
+        Self::open(&path, true)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|e| EnvMatchError::storage(path, e))
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        let path = self.resolve(path);
+        debug!(path = %path.display(), "remove file");
+        fs::remove_file(&path).map_err(|e| EnvMatchError::storage(path, e))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.resolve(path).is_file()
+    }
+
+    fn list(&self, dir: &Path) -> Result<Vec<String>> {
+        let dir = self.resolve(dir);
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+
+        let error = |e| EnvMatchError::storage(&dir, e);
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&dir).map_err(error)? {
+            let entry = entry.map_err(error)?;
+            if entry.file_type().map_err(error)?.is_file() {
+                if let Some(name) = entry.file_name().to_str() {
+                    names.push(name.to_string());
+                }
+            }
+        }
+
+        names.sort();
+        Ok(names)
+    }
+
+    fn list_recursive(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let resolved = self.resolve(&dir);
+            if !resolved.is_dir() {
+                continue;
+            }
+            let error = |e| EnvMatchError::storage(&resolved, e);
+            for entry in fs::read_dir(&resolved).map_err(error)? {
+                let entry = entry.map_err(error)?;
+                let path = dir.join(entry.file_name());
+                match entry.file_type().map_err(error)? {
+                    kind if kind.is_dir() => pending.push(path),
+                    kind if kind.is_file() => paths.push(path),
+                    _ => {}
+                }
+            }
+        }
+
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn stamp(&self, path: &Path) -> Option<FileStamp> {
+        let path = self.resolve(path);
+        let metadata = fs::metadata(&path).ok()?;
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Some(FileStamp {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+            inode,
+            hash: Sha256::digest(fs::read(&path).ok()?).into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_file_backend_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = FileBackend::new(temp_dir.path().join(".envMatch"));
+        let path = Path::new("environments/development.yaml");
+
+        assert_eq!(backend.read(path).unwrap(), None);
+        assert!(backend.list(Path::new("environments")).unwrap().is_empty());
+
+        backend.write(path, "variables: {}\n").unwrap();
+        backend.append(Path::new("audit.log"), "one\n").unwrap();
+        backend.append(Path::new("audit.log"), "two\n").unwrap();
+
+        assert!(backend.exists(path));
+        assert_eq!(backend.read(path).unwrap().unwrap(), "variables: {}\n");
+        assert_eq!(
+            backend.read(Path::new("audit.log")).unwrap().unwrap(),
+            "one\ntwo\n"
+        );
+        assert_eq!(
+            backend.list(Path::new("environments")).unwrap(),
+            vec!["development.yaml"]
+        );
+
+        backend.remove(path).unwrap();
+        assert!(!backend.exists(path));
+    }
+}
@@ -0,0 +1,126 @@
+use super::{FileStamp, StorageBackend};
+use crate::error::Result;
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+/// Keeps every file in memory. Nothing touches the disk, which makes it a
+/// good fit for tests and for throwaway environments.
+#[derive(Debug, Default)]
+#[cfg_attr(not(test), allow(dead_code))]
+pub struct InMemoryBackend {
+    /// Contents and last change of each file
+    files: RwLock<BTreeMap<PathBuf, (String, SystemTime)>>,
+}
+
+impl InMemoryBackend {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StorageBackend for InMemoryBackend {
+    fn read(&self, path: &Path) -> Result<Option<String>> {
+        Ok(self
+            .files
+            .read()
+            .unwrap()
+            .get(path)
+            .map(|(contents, _)| contents.clone()))
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> Result<()> {
+        self.files.write().unwrap().insert(
+            path.to_path_buf(),
+            (contents.to_string(), SystemTime::now()),
+        );
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, contents: &str) -> Result<()> {
+        let mut files = self.files.write().unwrap();
+        let (existing, modified) = files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| (String::new(), SystemTime::now()));
+        existing.push_str(contents);
+        *modified = SystemTime::now();
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        match self.files.write().unwrap().remove(path) {
+            Some(_) => Ok(()),
+            None => Err(Error::new(ErrorKind::NotFound, path.display().to_string()).into()),
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.read().unwrap().contains_key(path)
+    }
+
+    fn list(&self, dir: &Path) -> Result<Vec<String>> {
+        // BTreeMap keys are already sorted
+        Ok(self
+            .files
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|path| path.parent() == Some(dir))
+            .filter_map(|path| path.file_name()?.to_str().map(str::to_string))
+            .collect())
+    }
+
+    fn list_recursive(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .files
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|path| path.starts_with(dir) && path.as_path() != dir)
+            .cloned()
+            .collect())
+    }
+
+    fn stamp(&self, path: &Path) -> Option<FileStamp> {
+        self.files
+            .read()
+            .unwrap()
+            .get(path)
+            .map(|(contents, modified)| FileStamp {
+                modified: *modified,
+                len: contents.len() as u64,
+                inode: 0,
+                hash: [0; 32],
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_memory_backend() {
+        let backend = InMemoryBackend::new();
+        let path = Path::new("environments/development.yaml");
+
+        backend.write(path, "variables: {}\n").unwrap();
+        backend
+            .write(Path::new("environments/nested/other.yaml"), "")
+            .unwrap();
+        backend.append(Path::new("audit.log"), "one\n").unwrap();
+
+        assert_eq!(backend.read(path).unwrap().unwrap(), "variables: {}\n");
+        assert_eq!(
+            backend.list(Path::new("environments")).unwrap(),
+            vec!["development.yaml"]
+        );
+
+        backend.remove(path).unwrap();
+        assert_eq!(backend.read(path).unwrap(), None);
+        assert!(backend.remove(path).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::error::Result;
+use clap::ValueEnum;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+mod file;
+mod memory;
+mod sqlite;
+
+pub use file::FileBackend;
+#[cfg(unix)]
+pub use file::{DIR_MODE, FILE_MODE};
+pub use memory::InMemoryBackend;
+pub use sqlite::{SqliteBackend, SQLITE_FILE};
+
+/// Where a project on disk keeps its files, chosen at `init`.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum BackendKind {
+    /// One file per environment under `.envMatch`
+    #[default]
+    Files,
+    /// A single `.envMatch/store.db`, for projects with many environments
+    Sqlite,
+}
+
+/// Where `ConfigManager` keeps its files.
+///
+/// Paths are relative to the `.envMatch` directory (e.g.
+/// `environments/development.yaml`), so the same layout works on disk and in
+/// memory.
+pub trait StorageBackend: Debug + Send + Sync {
+    /// Reads a file, returning `None` if it doesn't exist.
+    fn read(&self, path: &Path) -> Result<Option<String>>;
+
+    /// Creates or replaces a file, creating parent directories as needed.
+    fn write(&self, path: &Path, contents: &str) -> Result<()>;
+
+    /// Appends to a file, creating it if needed.
+    fn append(&self, path: &Path, contents: &str) -> Result<()>;
+
+    fn remove(&self, path: &Path) -> Result<()>;
+
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Names of the files directly inside `dir`, empty if it doesn't exist.
+    fn list(&self, dir: &Path) -> Result<Vec<String>>;
+
+    /// Paths of the files anywhere under `dir`, sorted, empty if it doesn't
+    /// exist.
+    fn list_recursive(&self, dir: &Path) -> Result<Vec<PathBuf>>;
+
+    /// What tells this version of a file from the next, if it exists, so
+    /// what was parsed from it can be reused until it changes again.
+    fn stamp(&self, path: &Path) -> Option<FileStamp>;
+}
+
+/// When a file last changed, with its size, its inode on Unix and, for
+/// files other processes write too, a hash of its contents. Clocks as
+/// coarse as 1-2 s (HFS+, FAT, some network mounts) give two writes in a
+/// row the same time, and an edit can keep the size and write in place;
+/// only the contents tell those apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileStamp {
+    pub modified: SystemTime,
+    pub len: u64,
+    /// 0 where there's no inode to go by
+    pub inode: u64,
+    /// SHA-256 of the contents; zeroes where every write goes through the
+    /// backend, which moves `modified` on each one
+    pub hash: [u8; 32],
+}
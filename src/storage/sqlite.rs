@@ -0,0 +1,275 @@
+use super::{FileStamp, StorageBackend};
+use crate::error::{EnvMatchError, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs::DirBuilder;
+use std::io::{Error, ErrorKind};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Name of the database inside `.envMatch`.
+pub const SQLITE_FILE: &str = "store.db";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS files (
+        path TEXT PRIMARY KEY,
+        dir TEXT NOT NULL,
+        name TEXT NOT NULL,
+        contents TEXT NOT NULL,
+        modified INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS files_by_dir ON files (dir, name);
+";
+
+/// Keeps every file as a row of a single SQLite database, for projects with
+/// so many environments that a directory of files gets slow. Each write is
+/// its own transaction, so a crash never leaves half a file behind.
+///
+/// The database is opened on first use and, on Unix, created private to
+/// the owner like the files of [`super::FileBackend`].
+#[derive(Debug)]
+pub struct SqliteBackend {
+    path: PathBuf,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl SqliteBackend {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            connection: Mutex::new(None),
+        }
+    }
+
+    fn connect(&self) -> Result<MutexGuard<'_, Option<Connection>>> {
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            debug!(path = %self.path.display(), "open database");
+            self.create_file()?;
+            let opened = Connection::open(&self.path).map_err(|e| self.error(e))?;
+            opened.execute_batch(SCHEMA).map_err(|e| self.error(e))?;
+            *connection = Some(opened);
+        }
+        Ok(connection)
+    }
+
+    /// Creates the database file and its directory with private modes, so
+    /// SQLite doesn't create them with the default ones.
+    fn create_file(&self) -> Result<()> {
+        if self.path.is_file() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            let mut builder = DirBuilder::new();
+            builder.recursive(true);
+            #[cfg(unix)]
+            builder.mode(super::DIR_MODE);
+            builder
+                .create(parent)
+                .map_err(|e| EnvMatchError::storage(parent, e))?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        options.mode(super::FILE_MODE);
+        options
+            .open(&self.path)
+            .map(drop)
+            .map_err(|e| EnvMatchError::storage(&self.path, e))
+    }
+
+    fn error(&self, source: rusqlite::Error) -> EnvMatchError {
+        EnvMatchError::DatabaseError {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    /// Runs `f` with the open connection.
+    fn with<T>(&self, f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T> {
+        let connection = self.connect()?;
+        f(connection.as_ref().expect("connected")).map_err(|e| self.error(e))
+    }
+}
+
+/// Nanoseconds since the epoch, as rows record their last change.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as i64)
+}
+
+/// Rows are keyed by `/`-separated paths, whatever the platform.
+fn key(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn dir_and_name(path: &Path) -> (String, String) {
+    let dir = path.parent().map(key).unwrap_or_default();
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    (dir, name)
+}
+
+impl StorageBackend for SqliteBackend {
+    fn read(&self, path: &Path) -> Result<Option<String>> {
+        self.with(|connection| {
+            connection
+                .query_row(
+                    "SELECT contents FROM files WHERE path = ?1",
+                    params![key(path)],
+                    |row| row.get(0),
+                )
+                .optional()
+        })
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> Result<()> {
+        debug!(path = %path.display(), "write row");
+        let (dir, name) = dir_and_name(path);
+        self.with(|connection| {
+            connection.execute(
+                "INSERT INTO files (path, dir, name, contents, modified)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (path) DO UPDATE
+                 SET contents = excluded.contents, modified = excluded.modified",
+                params![key(path), dir, name, contents, now()],
+            )
+        })
+        .map(drop)
+    }
+
+    fn append(&self, path: &Path, contents: &str) -> Result<()> {
+        debug!(path = %path.display(), "append to row");
+        let (dir, name) = dir_and_name(path);
+        self.with(|connection| {
+            connection.execute(
+                "INSERT INTO files (path, dir, name, contents, modified)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (path) DO UPDATE
+                 SET contents = contents || excluded.contents, modified = excluded.modified",
+                params![key(path), dir, name, contents, now()],
+            )
+        })
+        .map(drop)
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        debug!(path = %path.display(), "remove row");
+        let removed = self.with(|connection| {
+            connection.execute("DELETE FROM files WHERE path = ?1", params![key(path)])
+        })?;
+        match removed {
+            0 => Err(Error::new(ErrorKind::NotFound, path.display().to_string()).into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.with(|connection| {
+            connection
+                .query_row(
+                    "SELECT 1 FROM files WHERE path = ?1",
+                    params![key(path)],
+                    |_| Ok(()),
+                )
+                .optional()
+        })
+        .is_ok_and(|row| row.is_some())
+    }
+
+    fn list(&self, dir: &Path) -> Result<Vec<String>> {
+        self.with(|connection| {
+            let mut statement =
+                connection.prepare("SELECT name FROM files WHERE dir = ?1 ORDER BY name")?;
+            let names = statement.query_map(params![key(dir)], |row| row.get(0))?;
+            names.collect()
+        })
+    }
+
+    fn list_recursive(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let paths: Vec<String> = self.with(|connection| {
+            let mut statement = connection.prepare("SELECT path FROM files ORDER BY path")?;
+            let paths = statement.query_map([], |row| row.get(0))?;
+            paths.collect()
+        })?;
+        Ok(paths
+            .into_iter()
+            .map(PathBuf::from)
+            .filter(|path| path.starts_with(dir) && path.as_path() != dir)
+            .collect())
+    }
+
+    fn stamp(&self, path: &Path) -> Option<FileStamp> {
+        let (nanos, len): (i64, i64) = self
+            .with(|connection| {
+                connection
+                    .query_row(
+                        "SELECT modified, length(CAST(contents AS BLOB)) FROM files WHERE path = ?1",
+                        params![key(path)],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .optional()
+            })
+            .ok()??;
+        Some(FileStamp {
+            modified: UNIX_EPOCH + Duration::from_nanos(nanos as u64),
+            len: len as u64,
+            inode: 0,
+            hash: [0; 32],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sqlite_backend() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join(".envMatch").join(SQLITE_FILE);
+        let backend = SqliteBackend::new(db_path.clone());
+        let path = Path::new("environments/development.yaml");
+
+        backend.write(path, "variables: {}\n").unwrap();
+        backend
+            .write(Path::new("environments/nested/other.yaml"), "")
+            .unwrap();
+        backend.append(Path::new("audit.log"), "one\n").unwrap();
+        backend.append(Path::new("audit.log"), "two\n").unwrap();
+
+        assert_eq!(backend.read(path).unwrap().unwrap(), "variables: {}\n");
+        assert_eq!(
+            backend.read(Path::new("audit.log")).unwrap().unwrap(),
+            "one\ntwo\n"
+        );
+        assert_eq!(
+            backend.list(Path::new("environments")).unwrap(),
+            vec!["development.yaml"]
+        );
+        assert!(backend.exists(path));
+
+        // A second connection sees what the first one wrote
+        let reopened = SqliteBackend::new(db_path.clone());
+        reopened.remove(path).unwrap();
+        assert_eq!(reopened.read(path).unwrap(), None);
+        assert!(reopened.remove(path).is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&db_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, super::super::FILE_MODE);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use super::{json_to_variables, run_cli, RemoteProvider, RemoteSnapshot, Transfer};
+use crate::error::{EnvMatchError, Result};
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::io::Write;
+use std::process::Command;
+
+const REMOTE: &str = "aws-secrets";
+
+/// An AWS Secrets Manager secret holding a whole environment as one JSON object.
+///
+/// Goes through the `aws` CLI so that profiles, SSO sessions and regions
+/// resolve exactly as they do for the rest of the AWS tooling.
+pub struct AwsSecretsProvider {
+    secret_id: String,
+    region: Option<String>,
+    profile: Option<String>,
+}
+
+impl AwsSecretsProvider {
+    pub fn new(secret_id: &str, region: Option<&str>, profile: Option<&str>) -> Self {
+        Self {
+            secret_id: secret_id.to_string(),
+            region: region.map(str::to_string),
+            profile: profile.map(str::to_string),
+        }
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new("aws");
+        command.arg("secretsmanager").args(args);
+        if let Some(region) = &self.region {
+            command.args(["--region", region]);
+        }
+        if let Some(profile) = &self.profile {
+            command.args(["--profile", profile]);
+        }
+        command.args(["--output", "json"]);
+        command
+    }
+
+    /// Runs an `aws secretsmanager` command, with `None` meaning the secret doesn't exist.
+    fn run(&self, command: Command) -> Result<Option<Value>> {
+        match run_cli(REMOTE, command)? {
+            Ok(stdout) => Ok(Some(serde_json::from_str(&stdout)?)),
+            Err(stderr) if stderr.contains("ResourceNotFoundException") => Ok(None),
+            Err(stderr) => Err(remote_error(&stderr)),
+        }
+    }
+}
+
+impl RemoteProvider for AwsSecretsProvider {
+    fn id(&self) -> String {
+        format!("{}:{}", REMOTE, self.secret_id)
+    }
+
+    fn fetch(&self, _transfer: &Transfer) -> Result<RemoteSnapshot> {
+        let Some(secret) =
+            self.run(self.command(&["get-secret-value", "--secret-id", &self.secret_id]))?
+        else {
+            return Ok(RemoteSnapshot::default());
+        };
+
+        let secret_string = secret["SecretString"]
+            .as_str()
+            .ok_or_else(|| remote_error("secret has no SecretString"))?;
+        let value: Value = serde_json::from_str(secret_string)
+            .map_err(|_| remote_error("secret is not a JSON object of variables"))?;
+
+        Ok(RemoteSnapshot {
+            variables: json_to_variables(&value),
+            version: secret["VersionId"].as_str().map(str::to_string),
+        })
+    }
+
+    fn store(
+        &self,
+        variables: &IndexMap<String, String>,
+        expected_version: Option<&str>,
+        transfer: &Transfer,
+    ) -> Result<Option<String>> {
+        // Secrets Manager has no check-and-set, so compare versions right before writing
+        if self.fetch(transfer)?.version.as_deref() != expected_version {
+            return Err(remote_error("secret changed during sync, try again"));
+        }
+
+        // Passed as a file so the values never appear in the process list
+        let mut secret_file = tempfile::NamedTempFile::new()?;
+        secret_file.write_all(serde_json::to_string(variables)?.as_bytes())?;
+        let secret_string = format!("file://{}", secret_file.path().display());
+
+        let command = match expected_version {
+            Some(_) => self.command(&[
+                "put-secret-value",
+                "--secret-id",
+                &self.secret_id,
+                "--secret-string",
+                &secret_string,
+            ]),
+            None => self.command(&[
+                "create-secret",
+                "--name",
+                &self.secret_id,
+                "--secret-string",
+                &secret_string,
+            ]),
+        };
+        let response = self
+            .run(command)?
+            .ok_or_else(|| remote_error("secret was deleted during sync"))?;
+
+        Ok(response["VersionId"].as_str().map(str::to_string))
+    }
+}
+
+fn remote_error(message: &str) -> EnvMatchError {
+    EnvMatchError::RemoteError {
+        remote: REMOTE.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_passes_region_and_profile() {
+        let provider = AwsSecretsProvider::new("myapp/prod", Some("eu-west-1"), Some("ops"));
+        let command = provider.command(&["get-secret-value", "--secret-id", "myapp/prod"]);
+        let args: Vec<_> = command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect();
+
+        assert_eq!(command.get_program(), "aws");
+        assert_eq!(
+            args,
+            vec![
+                "secretsmanager",
+                "get-secret-value",
+                "--secret-id",
+                "myapp/prod",
+                "--region",
+                "eu-west-1",
+                "--profile",
+                "ops",
+                "--output",
+                "json"
+            ]
+        );
+        assert_eq!(provider.id(), "aws-secrets:myapp/prod");
+    }
+}
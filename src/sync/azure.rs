@@ -0,0 +1,283 @@
+use super::{run_cli, RemoteProvider, RemoteSnapshot, Transfer};
+use crate::crypto::keyed_hash;
+use crate::diff::{self, VariableChange};
+use crate::error::{EnvMatchError, Result};
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+const REMOTE: &str = "azure-keyvault";
+/// Tag recording the variable a secret came from, since the name mapping is lossy.
+const KEY_TAG: &str = "envmatch-key";
+/// Times to try setting a secret again while a deleted one is being recovered
+const RECOVER_ATTEMPTS: u32 = 10;
+const RECOVER_WAIT: Duration = Duration::from_secs(2);
+
+/// An Azure Key Vault, with one secret per variable (uses the `az` CLI).
+pub struct AzureKeyVaultProvider {
+    vault_name: String,
+    version_key: [u8; 32],
+}
+
+struct SecretItem {
+    name: String,
+    key: String,
+    updated: String,
+}
+
+impl AzureKeyVaultProvider {
+    /// `version_key` keys the version derived from the secrets' names and
+    /// update times, so it reveals nothing about them.
+    pub fn new(vault_name: &str, version_key: [u8; 32]) -> Self {
+        Self {
+            vault_name: vault_name.to_string(),
+            version_key,
+        }
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new("az");
+        command.args(["keyvault", "secret"]).args(args).args([
+            "--vault-name",
+            &self.vault_name,
+            "--output",
+            "json",
+        ]);
+        command
+    }
+
+    fn run(&self, command: Command) -> Result<Value> {
+        match run_cli(REMOTE, command)? {
+            Ok(stdout) if stdout.trim().is_empty() => Ok(Value::Null),
+            Ok(stdout) => Ok(serde_json::from_str(&stdout)?),
+            Err(stderr) => Err(remote_error(&stderr)),
+        }
+    }
+
+    /// Enabled secrets in the vault, without their values.
+    fn list(&self) -> Result<Vec<SecretItem>> {
+        let items = self.run(self.command(&["list"]))?;
+        Ok(items
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|item| item["attributes"]["enabled"].as_bool() != Some(false))
+            .filter_map(|item| {
+                let name = item["name"].as_str()?.to_string();
+                Some(SecretItem {
+                    key: item["tags"][KEY_TAG]
+                        .as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| name.replace('-', "_")),
+                    updated: item["attributes"]["updated"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    name,
+                })
+            })
+            .collect())
+    }
+
+    /// A vault has no overall version, so derive one from every secret's update time.
+    fn version(&self, items: &[SecretItem]) -> String {
+        let mut stamps: Vec<String> = items
+            .iter()
+            .map(|item| format!("{}@{}", item.name, item.updated))
+            .collect();
+        stamps.sort();
+        keyed_hash(&self.version_key, &stamps.join("\n"))
+    }
+
+    /// Sets a secret, recovering it first if an earlier push deleted it.
+    ///
+    /// Deleting a secret only soft-deletes it in vaults with soft delete on
+    /// (the default), and its name can't be reused until it's recovered or
+    /// purged. Purging is refused under purge protection, so the secret is
+    /// recovered and then overwritten, keeping its old versions around.
+    fn set_secret(&self, key: &str, value: &str) -> Result<()> {
+        // Passed as a file so the value never appears in the process list
+        let mut value_file = tempfile::NamedTempFile::new()?;
+        value_file.write_all(value.as_bytes())?;
+        let path = value_file.path().to_string_lossy().into_owned();
+        let name = secret_name(key)?;
+        let tag = format!("{}={}", KEY_TAG, key);
+        let set = || {
+            self.command(&[
+                "set",
+                "--name",
+                &name,
+                "--file",
+                &path,
+                "--encoding",
+                "utf-8",
+                "--tags",
+                &tag,
+            ])
+        };
+
+        let mut attempts = 0;
+        loop {
+            let stderr = match run_cli(REMOTE, set())? {
+                Ok(_) => return Ok(()),
+                Err(stderr) => stderr,
+            };
+            if !is_deleted(&stderr) || attempts == RECOVER_ATTEMPTS {
+                return Err(remote_error(&stderr));
+            }
+            if attempts == 0 {
+                self.run(self.command(&["recover", "--name", &name]))?;
+            }
+            attempts += 1;
+            // Recovery finishes in the background, usually within seconds
+            thread::sleep(RECOVER_WAIT);
+        }
+    }
+}
+
+impl RemoteProvider for AzureKeyVaultProvider {
+    fn id(&self) -> String {
+        format!("{}:{}", REMOTE, self.vault_name)
+    }
+
+    fn fetch(&self, transfer: &Transfer) -> Result<RemoteSnapshot> {
+        let items = self.list()?;
+        let names: Vec<(String, &str)> = items
+            .iter()
+            .map(|item| (item.key.clone(), item.name.as_str()))
+            .collect();
+        let values = transfer.run(&self.id(), "Reading secrets", &names, |_, name| {
+            let secret = self.run(self.command(&["show", "--name", name]))?;
+            Ok(secret["value"].as_str().map(str::to_string))
+        })?;
+
+        Ok(RemoteSnapshot {
+            variables: values
+                .into_iter()
+                .filter_map(|(key, value)| Some((key, value?)))
+                .collect(),
+            version: Some(self.version(&items)),
+        })
+    }
+
+    fn store(
+        &self,
+        variables: &IndexMap<String, String>,
+        expected_version: Option<&str>,
+        transfer: &Transfer,
+    ) -> Result<Option<String>> {
+        // Check every name up front so a bad key doesn't leave a half-written vault
+        check_names(variables.keys())?;
+
+        let current = transfer.retry(|| self.fetch(transfer))?;
+        if current.version.as_deref() != expected_version {
+            return Err(remote_error("vault changed during sync, try again"));
+        }
+
+        // The new value of each changed key, `None` to delete it
+        let changes: Vec<(String, Option<String>)> =
+            diff::diff_variables(&current.variables, variables)
+                .into_iter()
+                .map(|change| match change {
+                    VariableChange::Added { key, value } => (key, Some(value)),
+                    VariableChange::Changed { key, new, .. } => (key, Some(new)),
+                    VariableChange::Removed { key, .. } => (key, None),
+                })
+                .collect();
+        transfer.run(&self.id(), "Writing secrets", &changes, |key, value| {
+            match value {
+                Some(value) => self.set_secret(key, value),
+                // Soft-deleted where soft delete is on; `set_secret`
+                // recovers it if the key comes back
+                None => self
+                    .run(self.command(&["delete", "--name", &secret_name(key)?]))
+                    .map(drop),
+            }
+        })?;
+
+        Ok(Some(self.version(&self.list()?)))
+    }
+}
+
+/// Key Vault secret names only allow letters, digits and dashes, so
+/// `DATABASE_URL` is stored as `DATABASE-URL`.
+fn secret_name(key: &str) -> Result<String> {
+    let name = key.replace('_', "-");
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        Ok(name)
+    } else {
+        Err(remote_error(&format!(
+            "'{}' can't be stored as a Key Vault secret name",
+            key
+        )))
+    }
+}
+
+/// Fails if two keys map to the same secret. Key Vault compares names
+/// without regard to case, so `api_key` and `API_KEY` collide too.
+fn check_names<'a>(keys: impl IntoIterator<Item = &'a String>) -> Result<()> {
+    let mut names: HashMap<String, &str> = HashMap::new();
+    for key in keys {
+        if let Some(other) = names.insert(secret_name(key)?.to_ascii_lowercase(), key) {
+            return Err(remote_error(&format!(
+                "'{}' and '{}' map to the same secret name",
+                other, key
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a failed `set` hit a secret that was deleted but can still be
+/// recovered, or is being recovered right now.
+fn is_deleted(stderr: &str) -> bool {
+    ["ObjectIsDeletedButRecoverable", "ObjectIsBeingRecovered"]
+        .iter()
+        .any(|code| stderr.contains(code))
+        || stderr.contains("deleted but recoverable state")
+        || stderr.contains("currently being recovered")
+}
+
+fn remote_error(message: &str) -> EnvMatchError {
+    EnvMatchError::RemoteError {
+        remote: REMOTE.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_name() {
+        assert_eq!(secret_name("DATABASE_URL").unwrap(), "DATABASE-URL");
+        assert_eq!(secret_name("api-key").unwrap(), "api-key");
+        assert!(secret_name("app.port").is_err());
+        assert!(secret_name("").is_err());
+    }
+
+    #[test]
+    fn test_check_names_ignores_case() {
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        assert!(check_names(&keys(&["DATABASE_URL", "API_KEY"])).is_ok());
+        assert!(check_names(&keys(&["DATABASE_URL", "DATABASE-URL"])).is_err());
+        assert!(check_names(&keys(&["api_key", "API_KEY"])).is_err());
+    }
+
+    #[test]
+    fn test_is_deleted() {
+        assert!(is_deleted(
+            "ERROR: (Conflict) Secret API-KEY is currently in a deleted but recoverable state, \
+             and its name cannot be reused; in this state, the secret can only be recovered or \
+             purged.\nCode: Conflict\nInner error: {\"code\": \"ObjectIsDeletedButRecoverable\"}"
+        ));
+        assert!(!is_deleted(
+            "ERROR: (Forbidden) The user does not have secrets set permission"
+        ));
+    }
+}
@@ -0,0 +1,178 @@
+use crate::error::{EnvMatchError, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crypto_box::aead::OsRng;
+use crypto_box::PublicKey;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::time::Duration;
+use tracing::debug;
+
+const REMOTE: &str = "github";
+const API_URL: &str = "https://api.github.com";
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// GitHub lists at most this many secrets or variables per page
+const PAGE_SIZE: usize = 100;
+
+/// The secrets and variables of one GitHub Actions environment, over the
+/// GitHub REST API.
+///
+/// Secret values can't be read back, only their names; they are sealed with
+/// the repository environment's public key (a libsodium sealed box) before
+/// being sent.
+pub struct GithubEnvironment {
+    agent: ureq::Agent,
+    /// `https://api.github.com/repos/<owner>/<repo>/environments/<env>`
+    base_url: String,
+    token: String,
+}
+
+/// What the environment holds on GitHub.
+#[derive(Debug, Default, PartialEq)]
+pub struct GithubState {
+    pub secrets: BTreeSet<String>,
+    pub variables: IndexMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct PublicKeyResponse {
+    key_id: String,
+    key: String,
+}
+
+impl GithubEnvironment {
+    pub fn new(repo: &str, environment: &str, token: &str) -> Result<Self> {
+        if repo.split('/').filter(|part| !part.is_empty()).count() != 2 {
+            return Err(remote_error(&format!(
+                "'{}' is not a repository, expected owner/name",
+                repo
+            )));
+        }
+        Ok(Self {
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+            base_url: format!("{}/repos/{}/environments/{}", API_URL, repo, environment),
+            token: token.to_string(),
+        })
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let url = format!("{}/{}", self.base_url, path);
+        debug!(method, %url, "calling the GitHub API");
+        self.agent
+            .request(method, &url)
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .set("Accept", "application/vnd.github+json")
+            .set("X-GitHub-Api-Version", "2022-11-28")
+    }
+
+    /// Reads every page of a list of secrets or variables.
+    fn list(&self, kind: &str) -> Result<Vec<Value>> {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let response = self
+                .request("GET", kind)
+                .query("per_page", &PAGE_SIZE.to_string())
+                .query("page", &page.to_string())
+                .call();
+            let body: Value = match response {
+                Ok(response) => response.into_json()?,
+                // The environment doesn't exist yet or has none
+                Err(ureq::Error::Status(404, _)) => break,
+                Err(error) => return Err(request_error(error)),
+            };
+            let page_items = body[kind].as_array().cloned().unwrap_or_default();
+            let done = page_items.len() < PAGE_SIZE;
+            items.extend(page_items);
+            if done {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    pub fn fetch(&self) -> Result<GithubState> {
+        let name = |item: &Value| item["name"].as_str().map(str::to_string);
+        Ok(GithubState {
+            secrets: self.list("secrets")?.iter().filter_map(name).collect(),
+            variables: self
+                .list("variables")?
+                .iter()
+                .filter_map(|item| Some((name(item)?, item["value"].as_str()?.to_string())))
+                .collect(),
+        })
+    }
+
+    /// Creates or updates secrets, sealing each value for the environment.
+    pub fn put_secrets(&self, secrets: &[(&String, &String)]) -> Result<()> {
+        if secrets.is_empty() {
+            return Ok(());
+        }
+        let public_key: PublicKeyResponse = self
+            .request("GET", "secrets/public-key")
+            .call()
+            .map_err(request_error)?
+            .into_json()?;
+        let key_bytes: [u8; 32] = STANDARD
+            .decode(&public_key.key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| remote_error("GitHub returned an invalid public key"))?;
+        let public_key_id = public_key.key_id;
+        let public_key = PublicKey::from(key_bytes);
+
+        for (name, value) in secrets {
+            let sealed = public_key
+                .seal(&mut OsRng, value.as_bytes())
+                .map_err(|_| remote_error("could not encrypt a secret"))?;
+            self.request("PUT", &format!("secrets/{}", name))
+                .send_json(json!({
+                    "encrypted_value": STANDARD.encode(sealed),
+                    "key_id": public_key_id,
+                }))
+                .map_err(request_error)?;
+        }
+        Ok(())
+    }
+
+    /// Creates or updates plain variables, given which already exist.
+    pub fn put_variables(
+        &self,
+        variables: &[(&String, &String)],
+        existing: &IndexMap<String, String>,
+    ) -> Result<()> {
+        for (name, value) in variables {
+            let body = json!({ "name": name, "value": value });
+            let response = if existing.contains_key(*name) {
+                self.request("PATCH", &format!("variables/{}", name))
+                    .send_json(body)
+            } else {
+                self.request("POST", "variables").send_json(body)
+            };
+            response.map_err(request_error)?;
+        }
+        Ok(())
+    }
+}
+
+fn remote_error(message: &str) -> EnvMatchError {
+    EnvMatchError::RemoteError {
+        remote: REMOTE.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Turns an HTTP failure into a readable error, using GitHub's message when present.
+fn request_error(error: ureq::Error) -> EnvMatchError {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let message = response
+                .into_json::<Value>()
+                .ok()
+                .and_then(|body| body["message"].as_str().map(str::to_string))
+                .unwrap_or_default();
+            remote_error(&format!("HTTP {}: {}", status, message))
+        }
+        ureq::Error::Transport(transport) => remote_error(&transport.to_string()),
+    }
+}
@@ -0,0 +1,155 @@
+use super::{json_to_variables, RemoteProvider, RemoteSnapshot, Transfer};
+use crate::error::{EnvMatchError, Result};
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+use std::time::Duration;
+use tracing::debug;
+
+const REMOTE: &str = "heroku";
+const API_URL: &str = "https://api.heroku.com";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The config vars of a Heroku app, accessed over the Heroku platform API.
+///
+/// Heroku keeps no version of config vars, so a push can't detect a
+/// concurrent change; the three-way merge against the last sync still
+/// catches changes made on the Heroku side before it.
+pub struct HerokuProvider {
+    agent: ureq::Agent,
+    app: String,
+    api_key: String,
+}
+
+impl HerokuProvider {
+    pub fn new(app: &str, api_key: &str) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+            app: app.to_string(),
+            api_key: api_key.to_string(),
+        }
+    }
+
+    fn request(&self, method: &str) -> ureq::Request {
+        let url = format!("{}/apps/{}/config-vars", API_URL, self.app);
+        debug!(method, %url, "calling Heroku API");
+        self.agent
+            .request(method, &url)
+            .set("Accept", "application/vnd.heroku+json; version=3")
+            .set("Authorization", &format!("Bearer {}", self.api_key))
+    }
+
+    fn config_vars(&self) -> Result<IndexMap<String, String>> {
+        let body: Value = self
+            .request("GET")
+            .call()
+            .map_err(request_error)?
+            .into_json()?;
+        Ok(json_to_variables(&body))
+    }
+}
+
+impl RemoteProvider for HerokuProvider {
+    fn id(&self) -> String {
+        format!("{}:{}", REMOTE, self.app)
+    }
+
+    fn fetch(&self, _transfer: &Transfer) -> Result<RemoteSnapshot> {
+        Ok(RemoteSnapshot {
+            variables: self.config_vars()?,
+            version: None,
+        })
+    }
+
+    fn store(
+        &self,
+        variables: &IndexMap<String, String>,
+        _expected_version: Option<&str>,
+        _transfer: &Transfer,
+    ) -> Result<Option<String>> {
+        let current = self.config_vars()?;
+        let body = config_vars_patch(&current, variables);
+        if !body.is_empty() {
+            self.request("PATCH")
+                .send_json(Value::Object(body))
+                .map_err(request_error)?;
+        }
+        Ok(None)
+    }
+}
+
+/// The body of a PATCH turning `current` into `wanted`: changed values, and
+/// `null` for each var to remove, as Heroku only updates the vars it's sent.
+fn config_vars_patch(
+    current: &IndexMap<String, String>,
+    wanted: &IndexMap<String, String>,
+) -> Map<String, Value> {
+    let changed = wanted
+        .iter()
+        .filter(|(key, value)| current.get(*key) != Some(value))
+        .map(|(key, value)| (key.clone(), Value::String(value.clone())));
+    let removed = current
+        .keys()
+        .filter(|key| !wanted.contains_key(*key))
+        .map(|key| (key.clone(), Value::Null));
+    changed.chain(removed).collect()
+}
+
+/// Checks that the Heroku API answers.
+pub(super) fn check_reachable() -> std::result::Result<(), String> {
+    match ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .get(API_URL)
+        .call()
+    {
+        Ok(_) | Err(ureq::Error::Status(..)) => Ok(()),
+        Err(ureq::Error::Transport(transport)) => Err(transport.to_string()),
+    }
+}
+
+fn remote_error(message: &str) -> EnvMatchError {
+    EnvMatchError::RemoteError {
+        remote: REMOTE.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Turns an HTTP failure into a readable error, using Heroku's message when present.
+fn request_error(error: ureq::Error) -> EnvMatchError {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let message = response
+                .into_json::<Value>()
+                .ok()
+                .and_then(|body| body["message"].as_str().map(str::to_string))
+                .unwrap_or_default();
+            remote_error(&format!("HTTP {}: {}", status, message))
+        }
+        ureq::Error::Transport(transport) => remote_error(&transport.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_config_vars_patch() {
+        let vars = |pairs: &[(&str, &str)]| -> IndexMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let current = vars(&[("SAME", "1"), ("CHANGED", "a"), ("REMOVED", "x")]);
+        let wanted = vars(&[("SAME", "1"), ("CHANGED", "b"), ("ADDED", "y")]);
+
+        let patch = config_vars_patch(&current, &wanted);
+        assert_eq!(
+            Value::Object(patch),
+            json!({ "CHANGED": "b", "ADDED": "y", "REMOVED": null })
+        );
+        assert!(config_vars_patch(&current, &current).is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+use super::{RemoteProvider, RemoteSnapshot, Transfer};
+use crate::error::{EnvMatchError, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tracing::debug;
+
+const REMOTE: &str = "http";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where the environments live on an envMatch HTTP server.
+///
+/// - `GET  /v1/environments` lists them as an [`EnvironmentList`]
+/// - `GET  /v1/environments/<name>` returns one as an [`EnvironmentBody`], or 404
+/// - `PUT  /v1/environments/<name>` replaces one; the body's `version` must be
+///   the current one (`null` to create it) or the server answers 409
+///
+/// Requests carry `Authorization: Bearer <token>` when the server wants one.
+/// Errors come back as `{"error": "<message>"}`.
+pub const ENVIRONMENTS_PATH: &str = "/v1/environments";
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EnvironmentList {
+    pub environments: Vec<EnvironmentSummary>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EnvironmentSummary {
+    pub name: String,
+    pub version: String,
+}
+
+/// An environment as sent and received. Versions are opaque to clients: they
+/// only compare them and send them back.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EnvironmentBody {
+    pub version: Option<String>,
+    pub variables: IndexMap<String, String>,
+}
+
+/// An environment on an envMatch HTTP server, e.g. one run with `envMatch serve`.
+pub struct HttpProvider {
+    agent: ureq::Agent,
+    url: String,
+    env_name: String,
+    token: Option<String>,
+}
+
+impl HttpProvider {
+    pub fn new(url: &str, env_name: &str, token: Option<&str>) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+            url: url.trim_end_matches('/').to_string(),
+            env_name: env_name.to_string(),
+            token: token.map(str::to_string),
+        }
+    }
+
+    fn request(&self, method: &str) -> ureq::Request {
+        let url = format!("{}{}/{}", self.url, ENVIRONMENTS_PATH, self.env_name);
+        debug!(method, %url, "calling envMatch server");
+        let request = self.agent.request(method, &url);
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+}
+
+impl RemoteProvider for HttpProvider {
+    fn id(&self) -> String {
+        format!("{}:{}/{}", REMOTE, self.url, self.env_name)
+    }
+
+    fn fetch(&self, _transfer: &Transfer) -> Result<RemoteSnapshot> {
+        let body: EnvironmentBody = match self.request("GET").call() {
+            Ok(response) => response.into_json()?,
+            // Nobody has pushed this environment yet
+            Err(ureq::Error::Status(404, _)) => return Ok(RemoteSnapshot::default()),
+            Err(error) => return Err(request_error(error)),
+        };
+        Ok(RemoteSnapshot {
+            variables: body.variables,
+            version: body.version,
+        })
+    }
+
+    fn store(
+        &self,
+        variables: &IndexMap<String, String>,
+        expected_version: Option<&str>,
+        _transfer: &Transfer,
+    ) -> Result<Option<String>> {
+        let body = EnvironmentBody {
+            version: expected_version.map(str::to_string),
+            variables: variables.clone(),
+        };
+        let response: EnvironmentBody = match self.request("PUT").send_json(&body) {
+            Ok(response) => response.into_json()?,
+            Err(ureq::Error::Status(409, _)) => {
+                return Err(remote_error("environment changed during sync, try again"))
+            }
+            Err(error) => return Err(request_error(error)),
+        };
+        Ok(response.version)
+    }
+}
+
+/// Checks that an envMatch server answers at `url`.
+pub(super) fn check_reachable(url: &str) -> std::result::Result<(), String> {
+    match ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .get(&format!("{}{}", url, ENVIRONMENTS_PATH))
+        .call()
+    {
+        Ok(_) | Err(ureq::Error::Status(..)) => Ok(()),
+        Err(ureq::Error::Transport(transport)) => Err(transport.to_string()),
+    }
+}
+
+fn remote_error(message: &str) -> EnvMatchError {
+    EnvMatchError::RemoteError {
+        remote: REMOTE.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Turns an HTTP failure into a readable error, using the server's message when present.
+fn request_error(error: ureq::Error) -> EnvMatchError {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let message = response
+                .into_json::<Value>()
+                .ok()
+                .and_then(|body| body["error"].as_str().map(str::to_string))
+                .unwrap_or_default();
+            remote_error(&format!("HTTP {}: {}", status, message))
+        }
+        ureq::Error::Transport(transport) => remote_error(&transport.to_string()),
+    }
+}
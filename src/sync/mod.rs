@@ -0,0 +1,223 @@
+use crate::error::{EnvMatchError, Result};
+use clap::ValueEnum;
+use indexmap::{IndexMap, IndexSet};
+use serde_json::Value;
+use std::process::Command;
+use tracing::debug;
+
+mod aws;
+mod azure;
+mod github;
+mod heroku;
+mod http;
+mod s3;
+mod transfer;
+mod vault;
+
+pub use aws::AwsSecretsProvider;
+pub use azure::AzureKeyVaultProvider;
+pub use github::{GithubEnvironment, GithubState};
+pub use heroku::HerokuProvider;
+pub use http::{
+    EnvironmentBody, EnvironmentList, EnvironmentSummary, HttpProvider, ENVIRONMENTS_PATH,
+};
+pub use s3::{S3Location, S3Provider};
+pub use transfer::Transfer;
+pub use vault::{VaultAuth, VaultProvider};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SyncAction {
+    /// Bring remote changes into the local environment
+    Pull,
+    /// Send local changes to the remote
+    Push,
+    /// Show what changed on each side since the last sync
+    #[value(alias = "diff")]
+    Status,
+}
+
+/// The variables stored remotely, with whatever version marker the remote uses.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RemoteSnapshot {
+    pub variables: IndexMap<String, String>,
+    pub version: Option<String>,
+}
+
+/// A remote secret store that an environment can be synchronized with.
+///
+/// Remotes that make a request per key run them through `transfer`, so
+/// they go concurrently and one key failing doesn't stop the rest.
+pub trait RemoteProvider: Sync {
+    /// Identifies the remote location, e.g. `vault:secret/myapp/staging`.
+    fn id(&self) -> String;
+
+    fn fetch(&self, transfer: &Transfer) -> Result<RemoteSnapshot>;
+
+    /// Replaces the remote variables and returns the new version.
+    ///
+    /// Fails instead of overwriting when the remote is no longer at
+    /// `expected_version`, so concurrent writers can't clobber each other.
+    fn store(
+        &self,
+        variables: &IndexMap<String, String>,
+        expected_version: Option<&str>,
+        transfer: &Transfer,
+    ) -> Result<Option<String>>;
+}
+
+/// Result of reconciling local and remote changes against the last synced state.
+#[derive(Debug, PartialEq)]
+pub struct Merge {
+    pub merged: IndexMap<String, String>,
+    /// Keys changed differently on both sides; `merged` keeps the local value.
+    pub conflicts: Vec<String>,
+}
+
+/// Three-way merge of `local` and `remote`, both descended from `base`.
+///
+/// A key changed on only one side takes that side's value; a key changed on
+/// both sides to different values is a conflict.
+pub fn merge(
+    base: &IndexMap<String, String>,
+    local: &IndexMap<String, String>,
+    remote: &IndexMap<String, String>,
+) -> Merge {
+    // Local keys keep their place, new remote keys are added after them
+    let keys: IndexSet<&String> = local
+        .keys()
+        .chain(remote.keys())
+        .chain(base.keys())
+        .collect();
+    let mut merged = IndexMap::new();
+    let mut conflicts = Vec::new();
+
+    for key in keys {
+        let (ours, theirs) = (local.get(key), remote.get(key));
+        let value = if ours == theirs || theirs == base.get(key) {
+            ours
+        } else if ours == base.get(key) {
+            theirs
+        } else {
+            conflicts.push(key.clone());
+            ours
+        };
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+
+    Merge { merged, conflicts }
+}
+
+/// Reads a JSON object of secrets, keeping non-string values as their JSON text.
+fn json_to_variables(value: &Value) -> IndexMap<String, String> {
+    value
+        .as_object()
+        .map(|object| {
+            object
+                .iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(value) => value.clone(),
+                        other => other.to_string(),
+                    };
+                    (key.clone(), value)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Checks that the remote behind a sync id (e.g. `vault:secret/app`) can be
+/// reached from here, without touching any secrets.
+pub fn check_remote(remote_id: &str) -> std::result::Result<(), String> {
+    let (kind, location) = remote_id.split_once(':').unwrap_or((remote_id, ""));
+    let cli = match kind {
+        "vault" => return vault::check_reachable(),
+        // The location ends with the environment's name
+        "http" => {
+            let url = location.rsplit_once('/').map_or(location, |(url, _)| url);
+            return http::check_reachable(url);
+        }
+        "heroku" => return heroku::check_reachable(),
+        "aws-secrets" | "s3" => "aws",
+        "azure-keyvault" => "az",
+        _ => return Err(format!("unknown remote type '{}'", kind)),
+    };
+
+    match Command::new(cli).arg("--version").output() {
+        Ok(output) if output.status.success() => Ok(()),
+        _ => Err(format!("the '{}' CLI is not installed or not working", cli)),
+    }
+}
+
+/// Runs a provider's own CLI, returning its stdout, or its stderr if it failed.
+fn run_cli(remote: &str, mut command: Command) -> Result<std::result::Result<String, String>> {
+    // Values are passed in temporary files, so the command line is safe to log
+    debug!(remote, ?command, "running provider CLI");
+    let output = command
+        .output()
+        .map_err(|error| EnvMatchError::RemoteError {
+            remote: remote.to_string(),
+            message: format!(
+                "could not run '{}': {}",
+                command.get_program().to_string_lossy(),
+                error
+            ),
+        })?;
+
+    if output.status.success() {
+        Ok(Ok(String::from_utf8_lossy(&output.stdout).into_owned()))
+    } else {
+        Ok(Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> IndexMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = vars(&[
+            ("SAME", "1"),
+            ("LOCAL", "a"),
+            ("REMOTE", "b"),
+            ("BOTH", "c"),
+        ]);
+        let local = vars(&[
+            ("SAME", "1"),
+            ("LOCAL", "a2"),
+            ("REMOTE", "b"),
+            ("BOTH", "local"),
+            ("NEW", "x"),
+        ]);
+        let remote = vars(&[("SAME", "1"), ("LOCAL", "a"), ("BOTH", "remote")]);
+
+        let result = merge(&base, &local, &remote);
+        assert_eq!(result.conflicts, vec!["BOTH"]);
+        assert_eq!(
+            result.merged,
+            vars(&[
+                ("SAME", "1"),
+                ("LOCAL", "a2"),
+                ("BOTH", "local"),
+                ("NEW", "x")
+            ])
+        );
+
+        // Both sides making the same change is not a conflict
+        let result = merge(&base, &remote, &remote);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.merged, remote);
+    }
+}
@@ -0,0 +1,332 @@
+use super::{run_cli, RemoteProvider, RemoteSnapshot, Transfer};
+use crate::crypto::{self, Cipher};
+use crate::error::{EnvMatchError, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+const REMOTE: &str = "s3";
+
+/// An environment stored as one object in an S3 bucket, or any storage that
+/// speaks the S3 API (MinIO, Cloudflare R2...).
+///
+/// Values are encrypted before they leave the machine, with a key derived
+/// from the passphrase and a salt kept in the object, so the bucket never
+/// sees plaintext. Each value is bound to its key and environment, so it
+/// can't be moved into another environment's object. Writes are conditional
+/// on the ETag read last, so two teammates pushing at once can't overwrite
+/// each other.
+pub struct S3Provider {
+    bucket: String,
+    key: String,
+    env_name: String,
+    region: Option<String>,
+    profile: Option<String>,
+    endpoint_url: Option<String>,
+    passphrase: String,
+    /// Salt of the object as last read and the cipher derived from it, so a
+    /// push doesn't read the object and stretch the passphrase again
+    keys: Mutex<Option<(Vec<u8>, Arc<Cipher>)>>,
+}
+
+/// What's stored in the object.
+#[derive(Serialize, Deserialize)]
+struct StoredEnvironment {
+    /// Base64 salt the passphrase is stretched with
+    salt: String,
+    /// Encrypted values, by key
+    variables: IndexMap<String, String>,
+}
+
+/// Where and how to reach the bucket.
+pub struct S3Location<'a> {
+    pub bucket: &'a str,
+    /// Folder in the bucket; each environment is `<prefix>/<env>.json`
+    pub prefix: &'a str,
+    pub region: Option<&'a str>,
+    pub profile: Option<&'a str>,
+    /// Endpoint of an S3-compatible service other than AWS
+    pub endpoint_url: Option<&'a str>,
+}
+
+impl S3Provider {
+    pub fn new(location: &S3Location, env_name: &str, passphrase: String) -> Self {
+        let prefix = location.prefix.trim_matches('/');
+        let key = match prefix {
+            "" => format!("{}.json", env_name),
+            _ => format!("{}/{}.json", prefix, env_name),
+        };
+        Self {
+            bucket: location.bucket.to_string(),
+            key,
+            env_name: env_name.to_string(),
+            region: location.region.map(str::to_string),
+            profile: location.profile.map(str::to_string),
+            endpoint_url: location.endpoint_url.map(str::to_string),
+            passphrase,
+            keys: Mutex::new(None),
+        }
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new("aws");
+        command
+            .arg("s3api")
+            .args(args)
+            .args(["--bucket", &self.bucket, "--key", &self.key]);
+        if let Some(region) = &self.region {
+            command.args(["--region", region]);
+        }
+        if let Some(profile) = &self.profile {
+            command.args(["--profile", profile]);
+        }
+        if let Some(endpoint_url) = &self.endpoint_url {
+            command.args(["--endpoint-url", endpoint_url]);
+        }
+        command.args(["--output", "json"]);
+        command
+    }
+
+    /// The cipher for `salt`, derived only once for the salt last used.
+    fn cipher(&self, salt: &[u8]) -> Result<Arc<Cipher>> {
+        let mut keys = self.keys.lock().expect("deriving a key doesn't panic");
+        if let Some((cached, cipher)) = &*keys {
+            if cached == salt {
+                return Ok(cipher.clone());
+            }
+        }
+        let cipher = Arc::new(Cipher::derive(&self.passphrase, salt)?);
+        *keys = Some((salt.to_vec(), cipher.clone()));
+        Ok(cipher)
+    }
+
+    fn cached_salt(&self) -> Option<Vec<u8>> {
+        let keys = self.keys.lock().expect("deriving a key doesn't panic");
+        keys.as_ref().map(|(salt, _)| salt.clone())
+    }
+
+    /// Encrypts `variables` for this environment's object.
+    fn seal(&self, salt: &[u8], variables: &IndexMap<String, String>) -> Result<StoredEnvironment> {
+        let cipher = self.cipher(salt)?;
+        Ok(StoredEnvironment {
+            salt: STANDARD.encode(salt),
+            variables: variables
+                .iter()
+                .map(|(key, value)| {
+                    let value = cipher.encrypt(&[&self.env_name], key, value);
+                    (key.clone(), value)
+                })
+                .collect(),
+        })
+    }
+
+    /// Decrypts what's stored. When nothing decrypts the passphrase is
+    /// wrong; when only some values don't, they were sealed for another
+    /// environment or tampered with.
+    fn open(&self, stored: &StoredEnvironment) -> Result<IndexMap<String, String>> {
+        let salt = STANDARD
+            .decode(&stored.salt)
+            .map_err(|_| remote_error("object has an invalid salt"))?;
+        let cipher = self.cipher(&salt)?;
+        let decrypted: Vec<_> = stored
+            .variables
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), cipher.decrypt(&[&self.env_name], key, value)?)))
+            .collect();
+        if !decrypted.is_empty() && decrypted.iter().all(Result::is_err) {
+            return Err(EnvMatchError::WrongPassphrase);
+        }
+        decrypted.into_iter().collect()
+    }
+
+    /// Reads the stored object, with `None` meaning it doesn't exist yet.
+    fn read(&self) -> Result<Option<(StoredEnvironment, String)>> {
+        let body = tempfile::NamedTempFile::new()?;
+        let body_path = body.path().to_string_lossy().into_owned();
+        let response: Value = match run_cli(REMOTE, self.command(&["get-object", &body_path]))? {
+            Ok(stdout) => serde_json::from_str(&stdout)?,
+            Err(stderr) if stderr.contains("NoSuchKey") => return Ok(None),
+            Err(stderr) => return Err(remote_error(&stderr)),
+        };
+
+        let etag = response["ETag"]
+            .as_str()
+            .ok_or_else(|| remote_error("object has no ETag"))?
+            .to_string();
+        let content = std::fs::read_to_string(body.path())?;
+        let stored = serde_json::from_str(&content)
+            .map_err(|_| remote_error("object is not an envMatch environment"))?;
+        Ok(Some((stored, etag)))
+    }
+}
+
+impl RemoteProvider for S3Provider {
+    fn id(&self) -> String {
+        format!("{}:{}/{}", REMOTE, self.bucket, self.key)
+    }
+
+    fn fetch(&self, _transfer: &Transfer) -> Result<RemoteSnapshot> {
+        let Some((stored, etag)) = self.read()? else {
+            return Ok(RemoteSnapshot::default());
+        };
+        Ok(RemoteSnapshot {
+            variables: self.open(&stored)?,
+            version: Some(etag),
+        })
+    }
+
+    fn store(
+        &self,
+        variables: &IndexMap<String, String>,
+        expected_version: Option<&str>,
+        _transfer: &Transfer,
+    ) -> Result<Option<String>> {
+        // Keep the salt teammates already derive their key from, as read by
+        // the fetch before this; a new object gets a salt of its own
+        let salt = match (self.cached_salt(), expected_version) {
+            (Some(salt), Some(_)) => salt,
+            _ => crypto::random_salt().to_vec(),
+        };
+        let stored = self.seal(&salt, variables)?;
+
+        let mut body = tempfile::NamedTempFile::new()?;
+        body.write_all(serde_json::to_string_pretty(&stored)?.as_bytes())?;
+        let body_path = body.path().to_string_lossy().into_owned();
+        let mut args = vec!["put-object", "--body", &body_path];
+        match expected_version {
+            Some(etag) => args.extend(["--if-match", etag]),
+            None => args.extend(["--if-none-match", "*"]),
+        }
+
+        let response: Value = match run_cli(REMOTE, self.command(&args))? {
+            Ok(stdout) => serde_json::from_str(&stdout)?,
+            Err(stderr) if stderr.contains("PreconditionFailed") => {
+                return Err(remote_error("object changed during sync, try again"))
+            }
+            Err(stderr) => return Err(remote_error(&stderr)),
+        };
+        Ok(response["ETag"].as_str().map(str::to_string))
+    }
+}
+
+fn remote_error(message: &str) -> EnvMatchError {
+    EnvMatchError::RemoteError {
+        remote: REMOTE.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_key_and_command() {
+        let location = S3Location {
+            bucket: "team-config",
+            prefix: "/myapp/",
+            region: Some("eu-west-1"),
+            profile: None,
+            endpoint_url: Some("https://minio.internal"),
+        };
+        let provider = S3Provider::new(&location, "staging", "secret".to_string());
+        let command = provider.command(&["get-object", "/tmp/body"]);
+        let args: Vec<_> = command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect();
+
+        assert_eq!(provider.id(), "s3:team-config/myapp/staging.json");
+        assert_eq!(
+            args,
+            vec![
+                "s3api",
+                "get-object",
+                "/tmp/body",
+                "--bucket",
+                "team-config",
+                "--key",
+                "myapp/staging.json",
+                "--region",
+                "eu-west-1",
+                "--endpoint-url",
+                "https://minio.internal",
+                "--output",
+                "json"
+            ]
+        );
+    }
+
+    fn provider(env_name: &str) -> S3Provider {
+        let location = S3Location {
+            bucket: "team-config",
+            prefix: "myapp",
+            region: None,
+            profile: None,
+            endpoint_url: None,
+        };
+        S3Provider::new(&location, env_name, "secret".to_string())
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> IndexMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_seal_and_open_round_trip() {
+        let staging = provider("staging");
+        let variables = vars(&[("API_KEY", "s3cret"), ("PORT", "8080")]);
+        let salt = crypto::random_salt();
+
+        let stored = staging.seal(&salt, &variables).unwrap();
+        assert!(!serde_json::to_string(&stored).unwrap().contains("s3cret"));
+        // Through JSON, as the object goes
+        let stored: StoredEnvironment =
+            serde_json::from_str(&serde_json::to_string(&stored).unwrap()).unwrap();
+        assert_eq!(staging.open(&stored).unwrap(), variables);
+        assert_eq!(staging.cached_salt(), Some(salt.to_vec()));
+
+        let wrong = S3Provider::new(
+            &S3Location {
+                bucket: "team-config",
+                prefix: "myapp",
+                region: None,
+                profile: None,
+                endpoint_url: None,
+            },
+            "staging",
+            "wrong".to_string(),
+        );
+        assert!(matches!(
+            wrong.open(&stored),
+            Err(EnvMatchError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_values_swapped_between_environments_are_rejected() {
+        let salt = crypto::random_salt();
+        let staging = provider("staging")
+            .seal(&salt, &vars(&[("API_KEY", "staging-key")]))
+            .unwrap();
+        let production = provider("production");
+        let mut stored = production
+            .seal(&salt, &vars(&[("API_KEY", "live-key"), ("PORT", "443")]))
+            .unwrap();
+
+        stored
+            .variables
+            .insert("API_KEY".to_string(), staging.variables["API_KEY"].clone());
+        assert!(matches!(
+            production.open(&stored),
+            Err(EnvMatchError::DecryptionFailed { key }) if key == "API_KEY"
+        ));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::error::{EnvMatchError, Result};
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Width of the progress bar on stderr
+const BAR_WIDTH: usize = 30;
+
+/// How requests to a remote are made: how many at once, and how often a
+/// failed one is tried again.
+///
+/// Remotes with one secret per variable (Azure Key Vault) read and write
+/// each key in a request of its own, so a slow API is only bearable with
+/// several of them in flight.
+#[derive(Clone, Debug)]
+pub struct Transfer {
+    /// Requests in flight at once
+    pub jobs: usize,
+    /// Times a failed request is tried again
+    pub retries: u32,
+    /// Wait before the first retry, doubled before each one after that
+    pub backoff: Duration,
+    /// Show a progress bar on stderr
+    pub progress: bool,
+}
+
+impl Default for Transfer {
+    fn default() -> Self {
+        Self {
+            jobs: 8,
+            retries: 0,
+            backoff: Duration::from_millis(500),
+            progress: std::io::stderr().is_terminal(),
+        }
+    }
+}
+
+impl Transfer {
+    /// Runs `request` for every item, at most [`jobs`](Self::jobs) at a
+    /// time, retrying each one on its own. A key that still fails doesn't
+    /// stop the others: once they're all done, every failure is reported
+    /// together as [`EnvMatchError::RemoteKeysFailed`].
+    ///
+    /// Results come back in the order of `items`.
+    pub fn run<T, R>(
+        &self,
+        remote: &str,
+        what: &str,
+        items: &[(String, T)],
+        request: impl Fn(&str, &T) -> Result<R> + Sync,
+    ) -> Result<Vec<(String, R)>>
+    where
+        T: Sync,
+        R: Send,
+    {
+        let next = AtomicUsize::new(0);
+        let done = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<Result<R>>>> =
+            Mutex::new((0..items.len()).map(|_| None).collect());
+        let progress = self.progress && items.len() > 1;
+
+        thread::scope(|scope| {
+            for _ in 0..self.jobs.clamp(1, items.len().max(1)) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some((key, item)) = items.get(index) else {
+                        break;
+                    };
+                    let result = self.retry(|| request(key, item));
+                    results.lock().expect("no request panics holding the lock")[index] =
+                        Some(result);
+                    let done = done.fetch_add(1, Ordering::SeqCst) + 1;
+                    if progress {
+                        draw_progress(what, done, items.len());
+                    }
+                });
+            }
+        });
+        if progress {
+            eprint!("\r{}\r", " ".repeat(BAR_WIDTH + what.len() + 24));
+        }
+
+        let mut succeeded = Vec::new();
+        let mut failures = Vec::new();
+        let results = results.into_inner().expect("every worker has finished");
+        for ((key, _), result) in items.iter().zip(results) {
+            match result.expect("every item was run") {
+                Ok(value) => succeeded.push((key.clone(), value)),
+                Err(error) => failures.push((key.clone(), error.to_string())),
+            }
+        }
+        match failures.is_empty() {
+            true => Ok(succeeded),
+            false => Err(EnvMatchError::RemoteKeysFailed {
+                remote: remote.to_string(),
+                failures,
+            }),
+        }
+    }
+
+    /// Runs `request`, trying again up to [`retries`](Self::retries) times
+    /// while it fails with a [`EnvMatchError::RemoteError`], which is what
+    /// network trouble and throttling come back as. Other errors (a wrong
+    /// passphrase, keys that already failed their own retries) are returned
+    /// right away.
+    pub fn retry<R>(&self, mut request: impl FnMut() -> Result<R>) -> Result<R> {
+        let mut attempt = 0;
+        loop {
+            match request() {
+                Err(EnvMatchError::RemoteError { .. }) if attempt < self.retries => {
+                    thread::sleep(self.delay(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Wait before retry number `attempt` (from 0).
+    fn delay(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(attempt))
+    }
+}
+
+fn draw_progress(what: &str, done: usize, total: usize) {
+    let filled = done * BAR_WIDTH / total;
+    let mut stderr = std::io::stderr().lock();
+    let _ = write!(
+        stderr,
+        "\r{} [{}{}] {}/{}",
+        what,
+        "█".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        done,
+        total
+    );
+    let _ = stderr.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles() {
+        let transfer = Transfer {
+            backoff: Duration::from_millis(500),
+            ..Transfer::default()
+        };
+        let delays: Vec<_> = (0..4).map(|attempt| transfer.delay(attempt)).collect();
+        assert_eq!(delays, [500, 1000, 2000, 4000].map(Duration::from_millis));
+    }
+}
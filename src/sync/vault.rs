@@ -0,0 +1,190 @@
+use super::{json_to_variables, RemoteProvider, RemoteSnapshot, Transfer};
+use crate::error::{EnvMatchError, Result};
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::debug;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+pub enum VaultAuth {
+    Token(String),
+    AppRole { role_id: String, secret_id: String },
+}
+
+/// A secret in a Vault KV v2 engine, accessed over Vault's HTTP API.
+pub struct VaultProvider {
+    agent: ureq::Agent,
+    addr: String,
+    mount: String,
+    path: String,
+    token: String,
+}
+
+impl VaultProvider {
+    /// Connects to the secret at `path`, whose first segment is the KV mount
+    /// (`secret/myapp/staging` is `myapp/staging` in the `secret` engine).
+    pub fn connect(addr: &str, path: &str, auth: VaultAuth) -> Result<Self> {
+        let (mount, path) = split_path(path)?;
+        let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+        let addr = addr.trim_end_matches('/').to_string();
+
+        let token = match auth {
+            VaultAuth::Token(token) => token,
+            VaultAuth::AppRole { role_id, secret_id } => {
+                debug!(%addr, "logging in to Vault with AppRole");
+                let response: Value = agent
+                    .post(&format!("{}/v1/auth/approle/login", addr))
+                    .send_json(json!({ "role_id": role_id, "secret_id": secret_id }))
+                    .map_err(request_error)?
+                    .into_json()?;
+                response["auth"]["client_token"]
+                    .as_str()
+                    .ok_or_else(|| remote_error("AppRole login returned no token"))?
+                    .to_string()
+            }
+        };
+
+        Ok(Self {
+            agent,
+            addr,
+            mount,
+            path,
+            token,
+        })
+    }
+
+    fn data_url(&self) -> String {
+        format!("{}/v1/{}/data/{}", self.addr, self.mount, self.path)
+    }
+}
+
+impl RemoteProvider for VaultProvider {
+    fn id(&self) -> String {
+        format!("vault:{}/{}", self.mount, self.path)
+    }
+
+    fn fetch(&self, _transfer: &Transfer) -> Result<RemoteSnapshot> {
+        debug!(url = %self.data_url(), "reading secret from Vault");
+        let response = match self
+            .agent
+            .get(&self.data_url())
+            .set("X-Vault-Token", &self.token)
+            .call()
+        {
+            Ok(response) => response,
+            // Nothing has been written to this path yet
+            Err(ureq::Error::Status(404, _)) => return Ok(RemoteSnapshot::default()),
+            Err(error) => return Err(request_error(error)),
+        };
+
+        let body: Value = response.into_json()?;
+        Ok(RemoteSnapshot {
+            variables: json_to_variables(&body["data"]["data"]),
+            version: body["data"]["metadata"]["version"]
+                .as_u64()
+                .map(|version| version.to_string()),
+        })
+    }
+
+    fn store(
+        &self,
+        variables: &IndexMap<String, String>,
+        expected_version: Option<&str>,
+        _transfer: &Transfer,
+    ) -> Result<Option<String>> {
+        // Check-and-set: version 0 means the secret must not exist yet
+        let cas: u64 = expected_version
+            .and_then(|version| version.parse().ok())
+            .unwrap_or(0);
+        debug!(url = %self.data_url(), cas, "writing secret to Vault");
+
+        let body: Value = self
+            .agent
+            .post(&self.data_url())
+            .set("X-Vault-Token", &self.token)
+            .send_json(json!({ "options": { "cas": cas }, "data": variables }))
+            .map_err(request_error)?
+            .into_json()?;
+
+        Ok(body["data"]["version"]
+            .as_u64()
+            .map(|version| version.to_string()))
+    }
+}
+
+/// Checks that the server in `VAULT_ADDR` answers its health endpoint.
+pub(super) fn check_reachable() -> std::result::Result<(), String> {
+    let addr = std::env::var("VAULT_ADDR").map_err(|_| "VAULT_ADDR is not set".to_string())?;
+    let url = format!("{}/v1/sys/health", addr.trim_end_matches('/'));
+
+    match ureq::AgentBuilder::new()
+        .timeout(TIMEOUT)
+        .build()
+        .get(&url)
+        .call()
+    {
+        // Sealed or standby servers answer with an error status, but they answer
+        Ok(_) | Err(ureq::Error::Status(..)) => Ok(()),
+        Err(ureq::Error::Transport(transport)) => Err(transport.to_string()),
+    }
+}
+
+fn split_path(path: &str) -> Result<(String, String)> {
+    match path.trim_matches('/').split_once('/') {
+        Some((mount, secret)) if !mount.is_empty() && !secret.is_empty() => {
+            Ok((mount.to_string(), secret.to_string()))
+        }
+        _ => Err(remote_error(&format!(
+            "invalid path '{}', expected <mount>/<secret>",
+            path
+        ))),
+    }
+}
+
+fn remote_error(message: &str) -> EnvMatchError {
+    EnvMatchError::RemoteError {
+        remote: "vault".to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Turns an HTTP failure into a readable error, using Vault's own messages when present.
+fn request_error(error: ureq::Error) -> EnvMatchError {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let errors = response
+                .into_json::<Value>()
+                .ok()
+                .and_then(|body| {
+                    let errors: Vec<String> = body["errors"]
+                        .as_array()?
+                        .iter()
+                        .filter_map(|error| error.as_str().map(str::to_string))
+                        .collect();
+                    Some(errors.join("; "))
+                })
+                .unwrap_or_default();
+            remote_error(&format!("HTTP {}: {}", status, errors))
+        }
+        ureq::Error::Transport(transport) => remote_error(&transport.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(
+            split_path("secret/myapp/staging").unwrap(),
+            ("secret".to_string(), "myapp/staging".to_string())
+        );
+        assert_eq!(
+            split_path("/kv/app/").unwrap(),
+            ("kv".to_string(), "app".to_string())
+        );
+        assert!(split_path("secret").is_err());
+    }
+}
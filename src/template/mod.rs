@@ -0,0 +1,127 @@
+use crate::error::{EnvMatchError, Result};
+use crate::export::escape;
+
+mod project;
+
+pub use project::ProjectTemplate;
+
+/// One variable of a `.env.example` template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateEntry {
+    pub key: String,
+    /// Written as a comment above the variable
+    pub description: Option<String>,
+    /// Placeholder value; empty for secrets
+    pub value: String,
+}
+
+/// Renders a dotenv template: a comment naming the source, then one
+/// `KEY="placeholder"` line per variable, preceded by its description.
+pub fn render(source: &str, entries: &[TemplateEntry]) -> String {
+    let mut output = format!("# Generated by envMatch from {}\n", source);
+    for entry in entries {
+        output.push('\n');
+        if let Some(description) = &entry.description {
+            for line in description.lines() {
+                output.push_str(&format!("# {}\n", line));
+            }
+        }
+        if entry.value.is_empty() {
+            output.push_str(&format!("{}=\n", entry.key));
+        } else {
+            output.push_str(&format!(
+                "{}=\"{}\"\n",
+                entry.key,
+                escape::double_quoted(&entry.value)
+            ));
+        }
+    }
+    output
+}
+
+/// The keys declared in a dotenv file, in order.
+pub fn parse_keys(content: &str) -> Result<Vec<String>> {
+    Ok(parse_dotenv(content)?
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect())
+}
+
+/// Reads the variables of a dotenv file, in order.
+///
+/// Comments, blank lines and `export ` prefixes are skipped. Double-quoted
+/// values understand `\n`, `\"`, `\\` and `\$` escapes, single-quoted ones are
+/// taken literally, and unquoted ones end at a ` #` comment.
+pub fn parse_dotenv(content: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let invalid = || EnvMatchError::InvalidAssignment {
+            input: line.to_string(),
+        };
+        let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(invalid());
+        }
+
+        let value = value.trim();
+        let value = if let Some(quoted) = value.strip_prefix('"') {
+            escape::unescape_double_quoted(quoted.strip_suffix('"').ok_or_else(invalid)?)
+        } else if let Some(quoted) = value.strip_prefix('\'') {
+            quoted.strip_suffix('\'').ok_or_else(invalid)?.to_string()
+        } else {
+            match value.find(" #") {
+                Some(comment) => value[..comment].trim_end().to_string(),
+                None => value.to_string(),
+            }
+        };
+        vars.push((key.to_string(), value));
+    }
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_and_parse_keys() {
+        let entries = vec![
+            TemplateEntry {
+                key: "API_KEY".to_string(),
+                description: Some("Payments API key".to_string()),
+                value: String::new(),
+            },
+            TemplateEntry {
+                key: "PORT".to_string(),
+                description: None,
+                value: "8080".to_string(),
+            },
+        ];
+
+        let rendered = render("'production'", &entries);
+        assert_eq!(
+            rendered,
+            "# Generated by envMatch from 'production'\n\n# Payments API key\nAPI_KEY=\n\nPORT=\"8080\"\n"
+        );
+        assert_eq!(parse_keys(&rendered).unwrap(), vec!["API_KEY", "PORT"]);
+        assert_eq!(
+            parse_keys("export DEBUG=1\n").unwrap(),
+            vec!["DEBUG".to_string()]
+        );
+        assert!(parse_keys("NOT AN ASSIGNMENT").is_err());
+        assert_eq!(
+            parse_dotenv("A=\"x\\\"y\\n\"\nB='$z' \nC=plain # note\n").unwrap(),
+            vec![
+                ("A".to_string(), "x\"y\n".to_string()),
+                ("B".to_string(), "$z".to_string()),
+                ("C".to_string(), "plain".to_string()),
+            ]
+        );
+    }
+}
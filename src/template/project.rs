@@ -0,0 +1,70 @@
+use clap::ValueEnum;
+
+/// Starter variables for common kinds of projects.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ProjectTemplate {
+    /// Node.js service
+    Node,
+    /// Ruby on Rails application
+    Rails,
+    /// Django application
+    Django,
+    /// Any web application with a database
+    GenericWeb,
+}
+
+/// A starter variable: key, placeholder value and whether it's a secret.
+/// `{env}` in the value stands for the environment's name.
+type Preset = (&'static str, &'static str, bool);
+
+const NODE: &[Preset] = &[
+    ("NODE_ENV", "{env}", false),
+    ("PORT", "3000", false),
+    ("DATABASE_URL", "postgres://localhost:5432/app_{env}", false),
+    ("LOG_LEVEL", "info", false),
+    ("SESSION_SECRET", "change-me", true),
+];
+
+const RAILS: &[Preset] = &[
+    ("RAILS_ENV", "{env}", false),
+    ("DATABASE_URL", "postgres://localhost:5432/app_{env}", false),
+    ("REDIS_URL", "redis://localhost:6379/0", false),
+    ("RAILS_MAX_THREADS", "5", false),
+    ("RAILS_LOG_LEVEL", "info", false),
+    ("SECRET_KEY_BASE", "change-me", true),
+];
+
+const DJANGO: &[Preset] = &[
+    ("DJANGO_SETTINGS_MODULE", "config.settings", false),
+    ("DJANGO_DEBUG", "True", false),
+    ("DJANGO_ALLOWED_HOSTS", "localhost,127.0.0.1", false),
+    ("DATABASE_URL", "postgres://localhost:5432/app_{env}", false),
+    ("DJANGO_SECRET_KEY", "change-me", true),
+];
+
+const GENERIC_WEB: &[Preset] = &[
+    ("APP_ENV", "{env}", false),
+    ("PORT", "8080", false),
+    ("BASE_URL", "http://localhost:8080", false),
+    ("DATABASE_URL", "postgres://localhost:5432/app_{env}", false),
+    ("LOG_LEVEL", "info", false),
+    ("API_KEY", "change-me", true),
+];
+
+impl ProjectTemplate {
+    /// The template's variables for an environment, as `(key, value, secret)`.
+    pub fn variables(self, env_name: &str) -> Vec<(String, String, bool)> {
+        let presets = match self {
+            ProjectTemplate::Node => NODE,
+            ProjectTemplate::Rails => RAILS,
+            ProjectTemplate::Django => DJANGO,
+            ProjectTemplate::GenericWeb => GENERIC_WEB,
+        };
+        presets
+            .iter()
+            .map(|(key, value, secret)| {
+                (key.to_string(), value.replace("{env}", env_name), *secret)
+            })
+            .collect()
+    }
+}
@@ -1,6 +1,20 @@
-use crate::commands::EnvMatchCommands;
+use crate::commands::{
+    self, EnvMatchCommands, ImportEntry, IssueKind, ListOptions, MergeStrategy, ProjectStatus,
+    SwitchPreview, ValidationIssue,
+};
+use crate::config::{
+    self, PolicyLevel, PolicyViolation, Theme, VariableMetadata, VariablesSort, VariablesView,
+};
+use crate::diff::{self, VariableChange};
 use crate::error::{EnvMatchError, Result};
-use crossterm::event::KeyCode;
+use crate::export::{self, ExportFormat, ExportOptions};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub enum AppState {
@@ -10,12 +24,186 @@ pub enum AppState {
     AddVariable,
     EditVariable,
     ConfirmDelete,
+    DiffView,
+    CopyTo,
+    VariableDetail,
+    Transfer,
+    Validation,
+    ConfirmSwitch,
+    DisplayOptions,
+    Projects,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Variable {
     pub key: String,
     pub value: String,
+    pub metadata: VariableMetadata,
+    pub group: Option<String>,
+    /// The schema's deprecation note, if the variable shouldn't be used anymore
+    pub deprecated: Option<String>,
+}
+
+/// A line of the variables panel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Row {
+    Group {
+        name: String,
+        count: usize,
+        collapsed: bool,
+    },
+    /// Index into `App::variables`
+    Variable(usize),
+}
+
+/// Two environments compared side by side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffView {
+    pub left: String,
+    pub right: String,
+    /// What differs going from `left` to `right`, sorted by key
+    pub changes: Vec<VariableChange>,
+    pub selected: usize,
+}
+
+impl DiffView {
+    fn load(commands: &EnvMatchCommands, left: &str, right: &str) -> Result<Self> {
+        let left_vars = commands.resolve_layers(&[left.to_string()])?;
+        let right_vars = commands.resolve_layers(&[right.to_string()])?;
+        Ok(Self {
+            left: left.to_string(),
+            right: right.to_string(),
+            changes: diff::diff_variables(&left_vars, &right_vars),
+            selected: 0,
+        })
+    }
+}
+
+/// The validation results of an environment, opened with `V`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationView {
+    pub env: String,
+    pub issues: Vec<ValidationIssue>,
+    pub selected: usize,
+}
+
+/// The registered projects to switch to, opened with `p`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectsView {
+    pub projects: Vec<PathBuf>,
+    pub selected: usize,
+}
+
+/// Formats offered by the export panel
+pub const EXPORT_FORMATS: [ExportFormat; 6] = [
+    ExportFormat::Dotenv,
+    ExportFormat::Json,
+    ExportFormat::Shell,
+    ExportFormat::Powershell,
+    ExportFormat::K8sSecret,
+    ExportFormat::K8sConfigmap,
+];
+
+/// How an import settles keys the environment already sets; asking for
+/// each one isn't possible inside the TUI
+pub const IMPORT_STRATEGIES: [MergeStrategy; 2] = [MergeStrategy::Theirs, MergeStrategy::Ours];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferMode {
+    Export,
+    Import,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferStep {
+    /// Choosing an export format, or how to merge what's being imported
+    Choose,
+    /// Typing a file path into `App::input_buffer`
+    Path,
+}
+
+/// The export/import panel opened with `x`. Exporting picks a format, then
+/// copies to the clipboard or asks for a file; importing asks for a file,
+/// then how to merge it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferPanel {
+    pub mode: TransferMode,
+    pub step: TransferStep,
+    /// Index into `EXPORT_FORMATS` or `IMPORT_STRATEGIES`
+    pub selected: usize,
+    /// Variables read from the file being imported, with its path
+    pub pending: Option<(String, Vec<ImportEntry>)>,
+    /// The list the panel was opened from
+    return_to: AppState,
+}
+
+impl TransferPanel {
+    fn new(mode: TransferMode, return_to: AppState) -> Self {
+        Self {
+            mode,
+            step: match mode {
+                TransferMode::Export => TransferStep::Choose,
+                TransferMode::Import => TransferStep::Path,
+            },
+            selected: 0,
+            pending: None,
+            return_to,
+        }
+    }
+
+    /// How many choices the `Choose` step offers.
+    pub fn choices(&self) -> usize {
+        match self.mode {
+            TransferMode::Export => EXPORT_FORMATS.len(),
+            TransferMode::Import => IMPORT_STRATEGIES.len(),
+        }
+    }
+}
+
+/// How many messages the footer shows at once
+const MAX_MESSAGES: usize = 3;
+/// Lines PageUp/PageDown scroll the help popup by
+const HELP_PAGE: u16 = 10;
+
+/// Lengths `o` cycles values through, 0 showing them whole
+const TRUNCATE_LENGTHS: [usize; 4] = [0, 20, 40, 80];
+
+/// Rows of the display options popup opened with `o`
+pub const DISPLAY_OPTIONS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageKind {
+    Status,
+    Error,
+}
+
+/// A line of feedback in the footer.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub kind: MessageKind,
+    pub text: String,
+    pub shown_at: Instant,
+}
+
+/// A variable's value and metadata, or `None` where it isn't set.
+type VariableState = (String, Option<(String, VariableMetadata)>);
+
+/// A change made from the TUI that `u` can undo and `Ctrl+R` redo.
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub description: String,
+    pub env: String,
+    pub before: Vec<VariableState>,
+    pub after: Vec<VariableState>,
+}
+
+/// How `S` announces a sort order.
+pub fn sort_name(sort: VariablesSort) -> &'static str {
+    match sort {
+        VariablesSort::Group => "by group",
+        VariablesSort::Alphabetical => "alphabetically",
+        VariablesSort::Recent => "by most recently changed",
+    }
 }
 
 #[derive(Debug)]
@@ -26,27 +214,72 @@ pub struct App {
     pub environments: Vec<String>,
     pub variables: Vec<Variable>,
     pub selected_env_index: usize,
+    /// Index into `rows()`, which may be a group header
     pub selected_var_index: usize,
+    pub collapsed_groups: HashSet<String>,
+    /// Environment marked with `m`, to compare against with `d`
+    pub marked_env: Option<String>,
+    pub diff: Option<DiffView>,
+    pub transfer: Option<TransferPanel>,
+    pub validation: Option<ValidationView>,
+    pub projects: Option<ProjectsView>,
+    /// What the switch being confirmed would change
+    pub switch_preview: Option<SwitchPreview>,
+    /// Keys of the variables marked for a bulk operation
+    pub marked_vars: BTreeSet<String>,
+    /// Row where a `V` range selection started, while one is in progress
+    pub visual_anchor: Option<usize>,
+    /// Index into `copy_targets()` while choosing where to copy to
+    pub selected_copy_index: usize,
+    /// Bulk actions apply to the selected variable only, not the marked ones,
+    /// while started from its detail popup
+    pub focus_single: bool,
+    /// Show the detail popup's value even when values are masked
+    pub reveal_value: bool,
+    pub undo_stack: Vec<Operation>,
+    pub redo_stack: Vec<Operation>,
     pub input_buffer: String,
     pub input_key: String,
+    /// What the schema says about the value being added or edited, checked
+    /// as it's typed; `None` while nothing in the schema applies to it
+    pub input_problems: Option<Vec<PolicyViolation>>,
     pub should_quit: bool,
-    pub status_message: String,
-    pub error_message: String,
+    /// Recent feedback, oldest first
+    pub messages: Vec<Message>,
+    /// How long messages stay up; `None` keeps them until pushed out
+    pub message_timeout: Option<Duration>,
     pub show_help: bool,
+    /// First line of the help popup on screen
+    pub help_scroll: u16,
+    pub mask_values: bool,
+    /// Ask before switching environments (`confirm_switch`)
+    pub confirm_switch: bool,
+    /// Sorting and display options of the variables panel
+    pub view: VariablesView,
+    pub theme: Theme,
+    /// Row of the display options popup
+    pub selected_option: usize,
+    /// Health summary of the current environment, for the header
+    pub status: Option<ProjectStatus>,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let commands = EnvMatchCommands::new();
-
+    pub fn new(commands: EnvMatchCommands) -> Result<Self> {
         // Check if initialized
         if !commands.is_initialized() {
             return Err(EnvMatchError::NotInitialized);
         }
 
+        let settings = commands.settings()?;
+        let mask_values = settings.mask_values();
+        let message_timeout = settings.message_timeout();
+        let confirm_switch = settings.confirm_switch();
+        let view = settings.variables_view();
+        let theme = settings.theme();
         let current_environment = commands.show_current_environment()?;
-        let environments = commands.list_environments()?;
+        let environments = commands.list_environments(false)?;
         let variables = Self::load_variables(&commands, &current_environment)?;
+        let status = commands.project_status(Some(&current_environment)).ok();
 
         let selected_env_index = environments
             .iter()
@@ -61,90 +294,703 @@ impl App {
             variables,
             selected_env_index,
             selected_var_index: 0,
+            collapsed_groups: HashSet::new(),
+            marked_env: None,
+            diff: None,
+            transfer: None,
+            validation: None,
+            projects: None,
+            switch_preview: None,
+            marked_vars: BTreeSet::new(),
+            visual_anchor: None,
+            selected_copy_index: 0,
+            focus_single: false,
+            reveal_value: false,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
             input_buffer: String::new(),
             input_key: String::new(),
+            input_problems: None,
             should_quit: false,
-            status_message: String::new(),
-            error_message: String::new(),
+            messages: Vec::new(),
+            message_timeout,
             show_help: false,
+            help_scroll: 0,
+            mask_values,
+            confirm_switch,
+            view,
+            theme,
+            selected_option: 0,
+            status,
         })
     }
 
     fn load_variables(commands: &EnvMatchCommands, env_name: &str) -> Result<Vec<Variable>> {
-        let vars = commands.list_variables(Some(env_name))?;
+        let options = ListOptions {
+            group: true,
+            ..Default::default()
+        };
+        let vars = commands.list_variables(Some(env_name), options)?;
+        let mut metadata = commands.variable_metadata(env_name)?;
+        let mut deprecations = commands.deprecations()?;
         Ok(vars
             .into_iter()
-            .map(|(key, value)| Variable { key, value })
+            .map(|(key, value)| {
+                let metadata = metadata.remove(&key).unwrap_or_default();
+                Variable {
+                    group: config::group_of(&key, Some(&metadata)).map(str::to_string),
+                    deprecated: deprecations.remove(&key),
+                    metadata,
+                    key,
+                    value,
+                }
+            })
             .collect())
     }
 
+    /// Whether the variables panel shows group headers: when sorted by group
+    /// and some variable has one.
+    pub fn grouped(&self) -> bool {
+        self.view.sort == VariablesSort::Group
+            && self.variables.iter().any(|var| var.group.is_some())
+    }
+
+    /// The lines of the variables panel, in the order of `view.sort`. When
+    /// grouped, the variables of collapsed groups are hidden.
+    pub fn rows(&self) -> Vec<Row> {
+        if !self.grouped() {
+            let mut indices: Vec<usize> = (0..self.variables.len()).collect();
+            match self.view.sort {
+                VariablesSort::Group => {}
+                VariablesSort::Alphabetical => {
+                    indices.sort_by(|&a, &b| self.variables[a].key.cmp(&self.variables[b].key));
+                }
+                VariablesSort::Recent => indices.sort_by(|&a, &b| {
+                    let changed = |i: usize| self.variables[i].metadata.updated_at;
+                    changed(b).cmp(&changed(a))
+                }),
+            }
+            return indices.into_iter().map(Row::Variable).collect();
+        }
+
+        let mut rows = Vec::new();
+        let mut current_group = None;
+        for (index, var) in self.variables.iter().enumerate() {
+            let name = var.group.as_deref().unwrap_or("Other");
+            let collapsed = self.collapsed_groups.contains(name);
+            if index == 0 || current_group != Some(name) {
+                rows.push(Row::Group {
+                    name: name.to_string(),
+                    count: self
+                        .variables
+                        .iter()
+                        .filter(|other| other.group == var.group)
+                        .count(),
+                    collapsed,
+                });
+                current_group = Some(name);
+            }
+            if !collapsed {
+                rows.push(Row::Variable(index));
+            }
+        }
+        rows
+    }
+
+    /// The variable on the selected row, unless it's a group header.
+    pub fn selected_variable(&self) -> Option<&Variable> {
+        match self.rows().get(self.selected_var_index) {
+            Some(Row::Variable(index)) => self.variables.get(*index),
+            _ => None,
+        }
+    }
+
+    /// Keys of the variables a bulk operation applies to: the marked ones
+    /// plus any `V` range in progress, in display order.
+    pub fn selection(&self) -> Vec<String> {
+        let rows = self.rows();
+        let range = self.visual_anchor.map(|anchor| {
+            anchor.min(self.selected_var_index)..=anchor.max(self.selected_var_index)
+        });
+        rows.iter()
+            .enumerate()
+            .filter_map(|(i, row)| match row {
+                Row::Variable(index) => Some((i, &self.variables[*index].key)),
+                Row::Group { .. } => None,
+            })
+            .filter(|(i, key)| {
+                range.as_ref().is_some_and(|range| range.contains(i))
+                    || self.marked_vars.contains(*key)
+            })
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+
+    /// The selection, or the variable under the cursor when nothing is marked.
+    pub fn targets(&self) -> Vec<String> {
+        let selection = self.selection();
+        if !selection.is_empty() && !self.focus_single {
+            return selection;
+        }
+        self.selected_variable()
+            .map(|var| vec![var.key.clone()])
+            .unwrap_or_default()
+    }
+
+    fn clear_selection(&mut self) {
+        self.marked_vars.clear();
+        self.visual_anchor = None;
+    }
+
+    /// Unmarks everything after a bulk action, unless it only applied to the
+    /// variable in the detail popup.
+    fn consume_selection(&mut self) {
+        if !self.focus_single {
+            self.clear_selection();
+        }
+    }
+
+    /// Environments variables can be copied to: all but the current one.
+    pub fn copy_targets(&self) -> Vec<&String> {
+        self.environments
+            .iter()
+            .filter(|env| **env != self.current_environment)
+            .collect()
+    }
+
+    fn toggle_selected_group(&mut self) {
+        if let Some(Row::Group {
+            name, collapsed, ..
+        }) = self.rows().get(self.selected_var_index).cloned()
+        {
+            if collapsed {
+                self.collapsed_groups.remove(&name);
+            } else {
+                self.collapsed_groups.insert(name);
+            }
+        }
+    }
+
+    pub fn set_status(&mut self, text: impl Into<String>) {
+        self.push_message(MessageKind::Status, text.into());
+    }
+
+    pub fn set_error(&mut self, text: impl Into<String>) {
+        self.push_message(MessageKind::Error, text.into());
+    }
+
+    /// Adds a message below the others, dropping the oldest past
+    /// `MAX_MESSAGES`. Repeating the latest message just shows it for longer.
+    fn push_message(&mut self, kind: MessageKind, text: String) {
+        let now = Instant::now();
+        if let Some(last) = self.messages.last_mut() {
+            if last.kind == kind && last.text == text {
+                last.shown_at = now;
+                return;
+            }
+        }
+        self.messages.push(Message {
+            kind,
+            text,
+            shown_at: now,
+        });
+        let excess = self.messages.len().saturating_sub(MAX_MESSAGES);
+        self.messages.drain(..excess);
+    }
+
+    /// Drops the messages that have been up longer than `message_timeout`.
+    pub fn expire_messages(&mut self) {
+        if let Some(timeout) = self.message_timeout {
+            self.messages
+                .retain(|message| message.shown_at.elapsed() < timeout);
+        }
+    }
+
+    pub fn handle_key_event(&mut self, event: KeyEvent) -> Result<()> {
+        // Ctrl+C quits from anywhere, as it would outside raw mode
+        if event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL) {
+            self.should_quit = true;
+            return Ok(());
+        }
+        let browsing = matches!(
+            self.state,
+            AppState::EnvironmentList | AppState::VariableList
+        );
+        if browsing && event.modifiers.contains(KeyModifiers::CONTROL) {
+            if event.code == KeyCode::Char('r') {
+                self.redo()?;
+            }
+            return Ok(());
+        }
+        self.handle_key(event.code)
+    }
+
     pub fn handle_key(&mut self, key: KeyCode) -> Result<()> {
+        if self.show_help {
+            self.handle_help_key(key);
+            return Ok(());
+        }
         match self.state {
             AppState::EnvironmentList => self.handle_env_list_key(key)?,
             AppState::VariableList => self.handle_var_list_key(key)?,
             AppState::AddVariable => self.handle_add_var_key(key)?,
             AppState::EditVariable => self.handle_edit_var_key(key)?,
             AppState::ConfirmDelete => self.handle_confirm_delete_key(key)?,
+            AppState::DiffView => self.handle_diff_key(key)?,
+            AppState::CopyTo => self.handle_copy_to_key(key)?,
+            AppState::VariableDetail => self.handle_detail_key(key)?,
+            AppState::Transfer => self.handle_transfer_key(key)?,
+            AppState::Validation => self.handle_validation_key(key)?,
+            AppState::ConfirmSwitch => self.handle_confirm_switch_key(key)?,
+            AppState::DisplayOptions => self.handle_display_options_key(key)?,
+            AppState::Projects => self.handle_projects_key(key)?,
+        }
+        if self.state == AppState::VariableList {
+            self.focus_single = false;
         }
+        self.check_input()?;
+        Ok(())
+    }
+
+    /// Checks the value being added or edited against the schema.
+    fn check_input(&mut self) -> Result<()> {
+        let typing_value = self.state == AppState::EditVariable
+            || (self.state == AppState::AddVariable && !self.input_key.is_empty());
+        self.input_problems = match typing_value {
+            true => self.commands.value_problems(
+                &self.input_key,
+                &self.input_buffer,
+                &self.current_environment,
+            )?,
+            false => None,
+        };
         Ok(())
     }
 
+    /// Refuses to save a value the schema rejects, saying why.
+    fn input_rejected(&mut self) -> bool {
+        let problem = self
+            .input_problems
+            .iter()
+            .flatten()
+            .find(|problem| problem.severity == PolicyLevel::Error)
+            .map(|problem| problem.message.clone());
+        match problem {
+            Some(message) => {
+                self.set_error(format!("{} {}", self.input_key, message));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Scrolls the help popup; the drawing code keeps it within the text.
+    fn handle_help_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Esc | KeyCode::Char('h') | KeyCode::Char('q') | KeyCode::F(1) => {
+                self.show_help = false;
+                self.help_scroll = 0;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.help_scroll = self.help_scroll.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.help_scroll = self.help_scroll.saturating_add(1);
+            }
+            KeyCode::PageUp => self.help_scroll = self.help_scroll.saturating_sub(HELP_PAGE),
+            KeyCode::PageDown => self.help_scroll = self.help_scroll.saturating_add(HELP_PAGE),
+            KeyCode::Home => self.help_scroll = 0,
+            KeyCode::End => self.help_scroll = u16::MAX,
+            _ => {}
+        }
+    }
+
     fn handle_env_list_key(&mut self, key: KeyCode) -> Result<()> {
         match key {
             KeyCode::Char('q') => self.should_quit = true,
             KeyCode::Char('h') | KeyCode::F(1) => self.show_help = !self.show_help,
-            KeyCode::Up | KeyCode::Char('k') => {
-                if self.selected_env_index > 0 {
-                    self.selected_env_index -= 1;
-                }
+            KeyCode::Up | KeyCode::Char('k') if self.selected_env_index > 0 => {
+                self.selected_env_index -= 1;
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if self.selected_env_index < self.environments.len().saturating_sub(1) {
-                    self.selected_env_index += 1;
-                }
+            KeyCode::Down | KeyCode::Char('j')
+                if self.selected_env_index < self.environments.len().saturating_sub(1) =>
+            {
+                self.selected_env_index += 1;
             }
             KeyCode::Enter => {
-                if let Some(env) = self.environments.get(self.selected_env_index) {
-                    self.switch_environment(env.clone())?;
+                if let Some(env) = self.environments.get(self.selected_env_index).cloned() {
+                    if self.confirm_switch && env != self.current_environment {
+                        self.switch_preview = Some(self.commands.switch_preview(&env)?);
+                        self.state = AppState::ConfirmSwitch;
+                        return Ok(());
+                    }
+                    self.switch_environment(env)?;
                 }
                 self.state = AppState::VariableList;
             }
             KeyCode::Tab => self.state = AppState::VariableList,
+            KeyCode::Char('m') => {
+                let env = self.environments.get(self.selected_env_index).cloned();
+                if self.marked_env == env {
+                    self.marked_env = None;
+                } else {
+                    self.marked_env = env;
+                }
+            }
+            KeyCode::Char('d') => self.open_diff()?,
+            KeyCode::Char('u') => self.undo()?,
+            KeyCode::Char('x') => self.open_transfer(),
+            KeyCode::Char('V') => self.open_validation()?,
+            KeyCode::Char('p') => self.open_projects()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_confirm_switch_key(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                if let Some(preview) = self.switch_preview.take() {
+                    self.switch_environment(preview.to)?;
+                }
+                self.state = AppState::VariableList;
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.switch_preview = None;
+                self.state = AppState::EnvironmentList;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn open_validation(&mut self) -> Result<()> {
+        let Some(env) = self.environments.get(self.selected_env_index).cloned() else {
+            return Ok(());
+        };
+        let issues = self.commands.validation_issues(&env)?;
+        if issues.is_empty() {
+            self.set_status(format!("'{}' is valid", env));
+            return Ok(());
+        }
+        self.validation = Some(ValidationView {
+            env,
+            issues,
+            selected: 0,
+        });
+        self.state = AppState::Validation;
+        Ok(())
+    }
+
+    fn open_projects(&mut self) -> Result<()> {
+        let projects = self.commands.registered_projects()?;
+        if projects.is_empty() {
+            self.set_status("No projects registered. Run 'envMatch projects add' in one");
+            return Ok(());
+        }
+        self.projects = Some(ProjectsView {
+            projects,
+            selected: 0,
+        });
+        self.state = AppState::Projects;
+        Ok(())
+    }
+
+    fn handle_projects_key(&mut self, key: KeyCode) -> Result<()> {
+        let Some(view) = self.projects.as_mut() else {
+            self.state = AppState::EnvironmentList;
+            return Ok(());
+        };
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.projects = None;
+                self.state = AppState::EnvironmentList;
+            }
+            KeyCode::Up | KeyCode::Char('k') => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                view.selected = (view.selected + 1).min(view.projects.len() - 1);
+            }
+            KeyCode::Enter => {
+                let project_dir = view.projects[view.selected].clone();
+                self.switch_project(project_dir)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Reopens the TUI on another project, keeping only the messages.
+    fn switch_project(&mut self, project_dir: PathBuf) -> Result<()> {
+        let mut commands = EnvMatchCommands::with_project_dir(project_dir.clone());
+        if !commands.is_initialized() {
+            self.set_error(format!(
+                "{} is no longer an envMatch project",
+                project_dir.display()
+            ));
+            return Ok(());
+        }
+        commands.set_secret_scan(false);
+        let mut app = App::new(commands)?;
+        app.messages = std::mem::take(&mut self.messages);
+        *self = app;
+        self.commands.register_project()?;
+        self.set_status(format!("Opened {}", project_dir.display()));
+        Ok(())
+    }
+
+    fn handle_validation_key(&mut self, key: KeyCode) -> Result<()> {
+        let Some(view) = self.validation.as_mut() else {
+            self.state = AppState::EnvironmentList;
+            return Ok(());
+        };
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.validation = None;
+                self.state = AppState::EnvironmentList;
+            }
+            KeyCode::Up | KeyCode::Char('k') => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                view.selected = (view.selected + 1).min(view.issues.len() - 1);
+            }
+            KeyCode::Enter => {
+                let (env, issue) = (view.env.clone(), view.issues[view.selected].clone());
+                self.validation = None;
+                self.jump_to_issue(&env, &issue)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_display_options_key(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('o') => {
+                self.state = AppState::VariableList;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected_option = self.selected_option.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected_option = (self.selected_option + 1).min(DISPLAY_OPTIONS - 1);
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                match self.selected_option {
+                    0 => self.view.descriptions = !self.view.descriptions,
+                    1 => {
+                        let next = TRUNCATE_LENGTHS
+                            .iter()
+                            .position(|&length| length == self.view.truncate)
+                            .map_or(0, |i| (i + 1) % TRUNCATE_LENGTHS.len());
+                        self.view.truncate = TRUNCATE_LENGTHS[next];
+                    }
+                    _ => self.view.value_lengths = !self.view.value_lengths,
+                }
+                self.save_view()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Remembers the variables panel layout for the next session.
+    fn save_view(&mut self) -> Result<()> {
+        self.commands.save_variables_view(self.view)
+    }
+
+    /// Moves the cursor to the row of `key`, or the first row without one,
+    /// after the rows were reordered.
+    fn select_key(&mut self, key: Option<&str>) {
+        self.selected_var_index = self
+            .rows()
+            .iter()
+            .position(|row| match row {
+                Row::Variable(index) => Some(self.variables[*index].key.as_str()) == key,
+                Row::Group { .. } => false,
+            })
+            .unwrap_or(0);
+    }
+
+    /// Switches to the issue's environment and selects its variable, or
+    /// starts adding it when it's missing.
+    fn jump_to_issue(&mut self, env: &str, issue: &ValidationIssue) -> Result<()> {
+        if env != self.current_environment {
+            self.switch_environment(env.to_string())?;
+        }
+        if issue.kind == IssueKind::Missing {
+            self.input_key = issue.key.clone();
+            self.input_buffer.clear();
+            self.state = AppState::AddVariable;
+            return Ok(());
+        }
+
+        if let Some(group) = self
+            .variables
+            .iter()
+            .find(|var| var.key == issue.key)
+            .map(|var| var.group.clone().unwrap_or_else(|| "Other".to_string()))
+        {
+            self.collapsed_groups.remove(&group);
+        }
+        if let Some(row) = self.rows().iter().position(
+            |row| matches!(row, Row::Variable(index) if self.variables[*index].key == issue.key),
+        ) {
+            self.selected_var_index = row;
+        }
+        self.state = AppState::VariableList;
+        Ok(())
+    }
+
+    fn open_diff(&mut self) -> Result<()> {
+        let Some(right) = self.environments.get(self.selected_env_index).cloned() else {
+            return Ok(());
+        };
+        match self.marked_env.clone() {
+            Some(left) if left != right => {
+                self.diff = Some(DiffView::load(&self.commands, &left, &right)?);
+                self.state = AppState::DiffView;
+            }
+            _ => {
+                self.set_error("Mark an environment with 'm', then press 'd' on another");
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_diff_key(&mut self, key: KeyCode) -> Result<()> {
+        let Some(view) = self.diff.as_mut() else {
+            self.state = AppState::EnvironmentList;
+            return Ok(());
+        };
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.diff = None;
+                self.state = AppState::EnvironmentList;
+            }
+            KeyCode::Char('h') | KeyCode::F(1) => self.show_help = !self.show_help,
+            KeyCode::Up | KeyCode::Char('k') if view.selected > 0 => view.selected -= 1,
+            KeyCode::Down | KeyCode::Char('j')
+                if view.selected < view.changes.len().saturating_sub(1) =>
+            {
+                view.selected += 1;
+            }
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('>') => {
+                self.copy_diff_value(true)?
+            }
+            KeyCode::Left | KeyCode::Char('<') => self.copy_diff_value(false)?,
             _ => {}
         }
         Ok(())
     }
 
+    /// Copies the selected key's value across the diff: left to right when
+    /// `to_right`, otherwise right to left.
+    fn copy_diff_value(&mut self, to_right: bool) -> Result<()> {
+        let Some(view) = self.diff.as_ref() else {
+            return Ok(());
+        };
+        let Some(change) = view.changes.get(view.selected) else {
+            return Ok(());
+        };
+        let (key, left_value, right_value) = match change {
+            VariableChange::Added { key, value } => (key, None, Some(value)),
+            VariableChange::Removed { key, value } => (key, Some(value), None),
+            VariableChange::Changed { key, old, new } => (key, Some(old), Some(new)),
+        };
+        let (value, target) = if to_right {
+            (left_value, &view.right)
+        } else {
+            (right_value, &view.left)
+        };
+        let Some(value) = value else {
+            self.set_error(format!("'{}' has no value on that side to copy", key));
+            return Ok(());
+        };
+
+        let (key, target) = (key.clone(), target.clone());
+        self.commands.set_variable(&key, value, &target)?;
+        let selected = view.selected;
+        let mut fresh = DiffView::load(&self.commands, &view.left, &view.right)?;
+        fresh.selected = selected.min(fresh.changes.len().saturating_sub(1));
+        self.diff = Some(fresh);
+        if target == self.current_environment {
+            self.refresh_variables()?;
+        }
+        self.set_status(format!("Copied {} to '{}'", key, target));
+        Ok(())
+    }
+
     fn handle_var_list_key(&mut self, key: KeyCode) -> Result<()> {
         match key {
             KeyCode::Char('q') => self.should_quit = true,
             KeyCode::Char('h') | KeyCode::F(1) => self.show_help = !self.show_help,
-            KeyCode::Up | KeyCode::Char('k') => {
-                if self.selected_var_index > 0 {
-                    self.selected_var_index -= 1;
+            KeyCode::Up | KeyCode::Char('k') if self.selected_var_index > 0 => {
+                self.selected_var_index -= 1;
+            }
+            KeyCode::Down | KeyCode::Char('j')
+                if self.selected_var_index < self.rows().len().saturating_sub(1) =>
+            {
+                self.selected_var_index += 1;
+            }
+            KeyCode::Enter => {
+                if self.selected_variable().is_some() {
+                    self.reveal_value = false;
+                    self.state = AppState::VariableDetail;
+                } else {
+                    self.toggle_selected_group();
                 }
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if self.selected_var_index < self.variables.len().saturating_sub(1) {
-                    self.selected_var_index += 1;
+            KeyCode::Char(' ') => match self.selected_variable().map(|var| var.key.clone()) {
+                Some(key) => {
+                    if !self.marked_vars.remove(&key) {
+                        self.marked_vars.insert(key);
+                    }
+                }
+                None => self.toggle_selected_group(),
+            },
+            KeyCode::Char('V') => match self.visual_anchor {
+                Some(_) => {
+                    self.marked_vars = self.selection().into_iter().collect();
+                    self.visual_anchor = None;
                 }
+                None => self.visual_anchor = Some(self.selected_var_index),
+            },
+            KeyCode::Esc => self.clear_selection(),
+            KeyCode::Char('c') if !self.targets().is_empty() => {
+                if self.copy_targets().is_empty() {
+                    self.set_error("No other environment to copy to");
+                } else {
+                    self.selected_copy_index = 0;
+                    self.state = AppState::CopyTo;
+                }
+            }
+            KeyCode::Char('s') => self.mark_secret()?,
+            KeyCode::Char('S') => {
+                let key = self.selected_variable().map(|var| var.key.clone());
+                self.view.sort = self.view.sort.next();
+                self.save_view()?;
+                self.select_key(key.as_deref());
+                self.set_status(format!("Sorted {}", sort_name(self.view.sort)));
             }
+            KeyCode::Char('o') => {
+                self.selected_option = 0;
+                self.state = AppState::DisplayOptions;
+            }
+            KeyCode::Char('x') => self.open_transfer(),
+            KeyCode::Char('u') => self.undo()?,
             KeyCode::Char('a') => {
                 self.input_key.clear();
                 self.input_buffer.clear();
                 self.state = AppState::AddVariable;
             }
             KeyCode::Char('e') => {
-                if let Some(var) = self.variables.get(self.selected_var_index) {
+                if let Some(var) = self.selected_variable().cloned() {
                     self.input_key = var.key.clone();
                     self.input_buffer = var.value.clone();
                     self.state = AppState::EditVariable;
                 }
             }
-            KeyCode::Char('d') | KeyCode::Delete => {
-                if !self.variables.is_empty() {
-                    self.state = AppState::ConfirmDelete;
-                }
+            KeyCode::Char('d') | KeyCode::Delete if !self.targets().is_empty() => {
+                self.state = AppState::ConfirmDelete;
             }
             KeyCode::Tab => self.state = AppState::EnvironmentList,
             KeyCode::F(5) => self.refresh_variables()?,
@@ -204,6 +1050,229 @@ impl App {
         Ok(())
     }
 
+    fn handle_detail_key(&mut self, key: KeyCode) -> Result<()> {
+        let Some(var) = self.selected_variable().cloned() else {
+            self.state = AppState::VariableList;
+            return Ok(());
+        };
+        self.focus_single = true;
+        match key {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+                self.state = AppState::VariableList;
+            }
+            KeyCode::Char('r') => self.reveal_value = !self.reveal_value,
+            KeyCode::Char('e') => {
+                self.input_key = var.key;
+                self.input_buffer = var.value;
+                self.state = AppState::EditVariable;
+            }
+            KeyCode::Char('d') | KeyCode::Delete => self.state = AppState::ConfirmDelete,
+            KeyCode::Char('c') => {
+                if self.copy_targets().is_empty() {
+                    self.set_error("No other environment to copy to");
+                } else {
+                    self.selected_copy_index = 0;
+                    self.state = AppState::CopyTo;
+                }
+            }
+            KeyCode::Char('s') => self.mark_secret()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_copy_to_key(&mut self, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Esc => self.state = AppState::VariableList,
+            KeyCode::Up | KeyCode::Char('k') if self.selected_copy_index > 0 => {
+                self.selected_copy_index -= 1;
+            }
+            KeyCode::Down | KeyCode::Char('j')
+                if self.selected_copy_index < self.copy_targets().len().saturating_sub(1) =>
+            {
+                self.selected_copy_index += 1;
+            }
+            KeyCode::Enter => {
+                if let Some(target) = self.copy_targets().get(self.selected_copy_index) {
+                    let target = target.to_string();
+                    let copied = self.commands.copy_variables(
+                        &self.targets(),
+                        &self.current_environment,
+                        &target,
+                    )?;
+                    self.consume_selection();
+                    self.set_status(format!("Copied {} variable(s) to '{}'", copied, target));
+                }
+                self.state = AppState::VariableList;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn mark_secret(&mut self) -> Result<()> {
+        let targets = self.targets();
+        let vars: Vec<_> = self
+            .variables
+            .iter()
+            .filter(|var| targets.contains(&var.key))
+            .map(|var| (var.key.clone(), var.value.clone()))
+            .collect();
+        if vars.is_empty() {
+            return Ok(());
+        }
+
+        let secret = VariableMetadata {
+            secret: true,
+            ..Default::default()
+        };
+        let before = self.variable_states(&targets);
+        self.commands
+            .set_variables_with_metadata(&vars, &secret, &self.current_environment)?;
+        self.refresh_variables()?;
+        self.consume_selection();
+        let description = format!("mark {} variable(s) as secret", vars.len());
+        self.set_status(format!("Marked {} variable(s) as secret", vars.len()));
+        self.push_operation(description, before);
+        Ok(())
+    }
+
+    fn open_transfer(&mut self) {
+        let panel = TransferPanel::new(TransferMode::Export, self.state.clone());
+        self.transfer = Some(panel);
+        self.input_buffer.clear();
+        self.state = AppState::Transfer;
+    }
+
+    /// Closes the export/import panel, back to the list it was opened from.
+    fn close_transfer(&mut self) {
+        let panel = self.transfer.take();
+        self.state = panel.map_or(AppState::VariableList, |panel| panel.return_to);
+        self.input_buffer.clear();
+    }
+
+    fn handle_transfer_key(&mut self, key: KeyCode) -> Result<()> {
+        let Some(panel) = self.transfer.as_mut() else {
+            self.state = AppState::VariableList;
+            return Ok(());
+        };
+        match (panel.step, key) {
+            (_, KeyCode::Esc) => self.close_transfer(),
+            (_, KeyCode::Tab) => {
+                let mode = match panel.mode {
+                    TransferMode::Export => TransferMode::Import,
+                    TransferMode::Import => TransferMode::Export,
+                };
+                *panel = TransferPanel::new(mode, panel.return_to.clone());
+                self.input_buffer.clear();
+            }
+            (TransferStep::Choose, KeyCode::Up | KeyCode::Char('k')) => {
+                panel.selected = panel.selected.saturating_sub(1);
+            }
+            (TransferStep::Choose, KeyCode::Down | KeyCode::Char('j')) => {
+                panel.selected = (panel.selected + 1).min(panel.choices() - 1);
+            }
+            (TransferStep::Choose, KeyCode::Char('c')) if panel.mode == TransferMode::Export => {
+                let format = EXPORT_FORMATS[panel.selected];
+                let (count, rendered) = self.render_export(format)?;
+                copy_to_clipboard(&rendered)
+                    .map_err(|e| EnvMatchError::TerminalError { source: e })?;
+                self.set_status(format!("Copied {} variable(s) to the clipboard", count));
+                self.close_transfer();
+            }
+            (TransferStep::Choose, KeyCode::Enter) => match panel.mode {
+                TransferMode::Export => {
+                    let format = EXPORT_FORMATS[panel.selected];
+                    panel.step = TransferStep::Path;
+                    self.input_buffer = default_export_path(format, &self.current_environment);
+                }
+                TransferMode::Import => self.apply_import()?,
+            },
+            (TransferStep::Path, KeyCode::Backspace) => {
+                self.input_buffer.pop();
+            }
+            (TransferStep::Path, KeyCode::Char(c)) => self.input_buffer.push(c),
+            (TransferStep::Path, KeyCode::Enter) if self.input_buffer.is_empty() => {
+                self.set_error("Enter a file path");
+            }
+            (TransferStep::Path, KeyCode::Enter) => match panel.mode {
+                TransferMode::Export => {
+                    let format = EXPORT_FORMATS[panel.selected];
+                    let (count, rendered) = self.render_export(format)?;
+                    let path = self.input_buffer.clone();
+                    fs::write(&path, rendered).map_err(|e| EnvMatchError::storage(&path, e))?;
+                    self.set_status(format!("Exported {} variable(s) to {}", count, path));
+                    self.close_transfer();
+                }
+                TransferMode::Import => {
+                    let path = self.input_buffer.clone();
+                    let entries = commands::read_import(Path::new(&path), None, "__")?;
+                    if entries.is_empty() {
+                        self.set_error(format!("No variables found in {}", path));
+                    } else {
+                        panel.pending = Some((path, entries));
+                        panel.step = TransferStep::Choose;
+                    }
+                }
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Renders the marked variables, or the whole environment when none
+    /// are marked, returning how many there are.
+    fn render_export(&mut self, format: ExportFormat) -> Result<(usize, String)> {
+        let selection = self.selection();
+        let vars: Vec<_> = self
+            .variables
+            .iter()
+            .filter(|var| selection.is_empty() || selection.contains(&var.key))
+            .map(|var| (var.key.clone(), var.value.clone()))
+            .collect();
+        let options = ExportOptions {
+            name: self.current_environment.clone(),
+            namespace: None,
+            ..Default::default()
+        };
+        let rendered = export::render(format, &vars, &options)?;
+        self.clear_selection();
+        Ok((vars.len(), rendered))
+    }
+
+    /// Sets the variables read for import, as the chosen strategy says.
+    fn apply_import(&mut self) -> Result<()> {
+        let Some(TransferPanel {
+            pending: Some((path, entries)),
+            selected,
+            ..
+        }) = self.transfer.clone()
+        else {
+            return Ok(());
+        };
+        let keep_existing = IMPORT_STRATEGIES[selected] == MergeStrategy::Ours;
+        let keys: Vec<String> = entries.iter().map(|(key, ..)| key.clone()).collect();
+        let before = self.variable_states(&keys);
+        let imported =
+            self.commands
+                .import_entries(entries, &self.current_environment, keep_existing)?;
+        self.refresh_variables()?;
+        if !imported.is_empty() {
+            let before = before
+                .into_iter()
+                .filter(|(key, _)| imported.contains(key))
+                .collect();
+            self.push_operation(format!("import {}", path), before);
+        }
+        self.set_status(format!(
+            "Imported {} variable(s) from {}",
+            imported.len(),
+            path
+        ));
+        self.close_transfer();
+        Ok(())
+    }
+
     fn handle_confirm_delete_key(&mut self, key: KeyCode) -> Result<()> {
         match key {
             KeyCode::Char('y') | KeyCode::Char('Y') => {
@@ -219,73 +1288,270 @@ impl App {
     }
 
     fn switch_environment(&mut self, env_name: String) -> Result<()> {
-        self.commands.switch_environment(&env_name)?;
+        // A session started with ENVMATCH_ENV switches on its own, leaving
+        // the project's current environment to other terminals
+        let session = self.commands.session_environment().is_some();
+        if session {
+            self.commands.variable_metadata(&env_name)?;
+            self.commands
+                .set_session_environment(Some(env_name.clone()));
+        } else {
+            self.commands.switch_environment(&env_name)?;
+        }
         self.current_environment = env_name.clone();
         self.variables = Self::load_variables(&self.commands, &env_name)?;
+        self.refresh_status();
         self.selected_var_index = 0;
-        self.status_message = format!("Switched to environment: {}", env_name);
-        self.error_message.clear();
+        self.clear_selection();
+        match session {
+            true => self.set_status(format!("Switched this session to: {}", env_name)),
+            false => self.set_status(format!("Switched to environment: {}", env_name)),
+        }
         Ok(())
     }
 
     fn add_variable(&mut self) -> Result<()> {
         if self.input_key.is_empty() || self.input_buffer.is_empty() {
-            self.error_message = "Both key and value are required".to_string();
+            self.set_error("Both key and value are required");
+            return Ok(());
+        }
+        self.check_input()?;
+        if self.input_rejected() {
             return Ok(());
         }
 
+        let before = self.variable_states(std::slice::from_ref(&self.input_key));
         self.commands.set_variable(
             &self.input_key,
             &self.input_buffer,
             &self.current_environment,
         )?;
         self.refresh_variables()?;
+        self.push_operation(format!("add {}", self.input_key), before);
         self.state = AppState::VariableList;
-        self.status_message = format!("Added variable: {}={}", self.input_key, self.input_buffer);
+        self.set_status(format!(
+            "Added variable: {}={}",
+            self.input_key, self.input_buffer
+        ));
         self.input_key.clear();
         self.input_buffer.clear();
-        self.error_message.clear();
         Ok(())
     }
 
     fn edit_variable(&mut self) -> Result<()> {
         if self.input_buffer.is_empty() {
-            self.error_message = "Value cannot be empty".to_string();
+            self.set_error("Value cannot be empty");
+            return Ok(());
+        }
+        self.check_input()?;
+        if self.input_rejected() {
             return Ok(());
         }
 
+        let before = self.variable_states(std::slice::from_ref(&self.input_key));
         self.commands.set_variable(
             &self.input_key,
             &self.input_buffer,
             &self.current_environment,
         )?;
         self.refresh_variables()?;
+        self.push_operation(format!("edit {}", self.input_key), before);
         self.state = AppState::VariableList;
-        self.status_message = format!("Updated variable: {}={}", self.input_key, self.input_buffer);
+        self.set_status(format!(
+            "Updated variable: {}={}",
+            self.input_key, self.input_buffer
+        ));
         self.input_key.clear();
         self.input_buffer.clear();
-        self.error_message.clear();
         Ok(())
     }
 
     fn delete_variable(&mut self) -> Result<()> {
-        if let Some(var) = self.variables.get(self.selected_var_index).cloned() {
+        let keys = self.targets();
+        if !keys.is_empty() {
+            let before = self.variable_states(&keys);
             self.commands
-                .unset_variable(&var.key, &self.current_environment)?;
+                .unset_variables(&keys, &self.current_environment)?;
             self.refresh_variables()?;
-            self.status_message = format!("Deleted variable: {}", var.key);
-            self.error_message.clear();
+            self.consume_selection();
+            self.push_operation(format!("delete {}", keys.join(", ")), before);
+            self.set_status(match keys.as_slice() {
+                [key] => format!("Deleted variable: {}", key),
+                _ => format!("Deleted {} variables", keys.len()),
+            });
 
             // Adjust selected index if necessary
-            if self.selected_var_index >= self.variables.len() && !self.variables.is_empty() {
-                self.selected_var_index = self.variables.len() - 1;
+            let rows = self.rows().len();
+            if self.selected_var_index >= rows && rows > 0 {
+                self.selected_var_index = rows - 1;
             }
         }
         Ok(())
     }
 
+    /// Picks up changes other processes made on disk: the environments, the
+    /// current environment and its variables. Keeps the selection on the same
+    /// environment and variable where they still exist.
+    pub fn reload_from_disk(&mut self) -> Result<()> {
+        self.commands.invalidate_cache();
+        if let Some(view) = &self.diff {
+            let mut fresh = DiffView::load(&self.commands, &view.left, &view.right)?;
+            fresh.selected = view.selected.min(fresh.changes.len().saturating_sub(1));
+            self.diff = Some(fresh);
+        }
+
+        let current_environment = self.commands.resolve_environment(None)?;
+        let environments = self.commands.list_environments(false)?;
+        let variables = Self::load_variables(&self.commands, &current_environment)?;
+        if current_environment == self.current_environment
+            && environments == self.environments
+            && variables == self.variables
+        {
+            return Ok(());
+        }
+
+        let selected_env = self.environments.get(self.selected_env_index).cloned();
+        let selected_key = self.selected_variable().map(|var| var.key.clone());
+        let switched = current_environment != self.current_environment;
+
+        self.current_environment = current_environment;
+        self.environments = environments;
+        self.variables = variables;
+        self.refresh_status();
+        self.visual_anchor = None;
+        if switched {
+            self.marked_vars.clear();
+        } else {
+            let variables = &self.variables;
+            self.marked_vars
+                .retain(|key| variables.iter().any(|var| var.key == *key));
+        }
+        self.selected_env_index = selected_env
+            .and_then(|env| self.environments.iter().position(|other| *other == env))
+            .unwrap_or(0);
+        let rows = self.rows();
+        self.selected_var_index = if switched {
+            0
+        } else {
+            selected_key
+                .and_then(|key| {
+                    rows.iter().position(|row| {
+                        matches!(row, Row::Variable(index) if self.variables[*index].key == key)
+                    })
+                })
+                .unwrap_or(self.selected_var_index.min(rows.len().saturating_sub(1)))
+        };
+        self.set_status("Reloaded: files changed on disk");
+        Ok(())
+    }
+
+    /// The current values and metadata of `keys` in the current environment.
+    fn variable_states(&self, keys: &[String]) -> Vec<VariableState> {
+        keys.iter()
+            .map(|key| {
+                let state = self
+                    .variables
+                    .iter()
+                    .find(|var| var.key == *key)
+                    .map(|var| (var.value.clone(), var.metadata.clone()));
+                (key.clone(), state)
+            })
+            .collect()
+    }
+
+    /// Records a change to the current environment for undo, given the
+    /// variables' states before it. Call after `refresh_variables`.
+    fn push_operation(&mut self, description: String, before: Vec<VariableState>) {
+        let keys: Vec<String> = before.iter().map(|(key, _)| key.clone()).collect();
+        self.undo_stack.push(Operation {
+            description,
+            env: self.current_environment.clone(),
+            after: self.variable_states(&keys),
+            before,
+        });
+        self.redo_stack.clear();
+    }
+
+    fn undo(&mut self) -> Result<()> {
+        let Some(operation) = self.undo_stack.pop() else {
+            self.set_error("Nothing to undo");
+            return Ok(());
+        };
+        self.commands.restore_variables(
+            &operation.before,
+            &operation.env,
+            &format!("undo {}", operation.description),
+        )?;
+        self.set_status(format!(
+            "Undid: {} in '{}' (Ctrl+R to redo)",
+            operation.description, operation.env
+        ));
+        self.after_undo_or_redo(&operation.env)?;
+        self.redo_stack.push(operation);
+        Ok(())
+    }
+
+    fn redo(&mut self) -> Result<()> {
+        let Some(operation) = self.redo_stack.pop() else {
+            self.set_error("Nothing to redo");
+            return Ok(());
+        };
+        self.commands.restore_variables(
+            &operation.after,
+            &operation.env,
+            &format!("redo {}", operation.description),
+        )?;
+        self.set_status(format!(
+            "Redid: {} in '{}'",
+            operation.description, operation.env
+        ));
+        self.after_undo_or_redo(&operation.env)?;
+        self.undo_stack.push(operation);
+        Ok(())
+    }
+
+    fn after_undo_or_redo(&mut self, env: &str) -> Result<()> {
+        if env == self.current_environment {
+            self.refresh_variables()?;
+            let rows = self.rows().len();
+            self.selected_var_index = self.selected_var_index.min(rows.saturating_sub(1));
+        }
+        Ok(())
+    }
+
     fn refresh_variables(&mut self) -> Result<()> {
         self.variables = Self::load_variables(&self.commands, &self.current_environment)?;
+        self.refresh_status();
         Ok(())
     }
+
+    /// The header summary is informative only, so failing to work it out
+    /// just hides it.
+    fn refresh_status(&mut self) {
+        self.status = self
+            .commands
+            .project_status(Some(&self.current_environment))
+            .ok();
+    }
+}
+
+/// Where the export panel suggests writing an environment in `format`.
+fn default_export_path(format: ExportFormat, env_name: &str) -> String {
+    match format {
+        ExportFormat::Json => format!("{}.json", env_name),
+        ExportFormat::Shell => format!("{}.sh", env_name),
+        ExportFormat::Powershell => format!("{}.ps1", env_name),
+        ExportFormat::Cmd => format!("{}.cmd", env_name),
+        ExportFormat::K8sSecret => format!("{}-secret.yaml", env_name),
+        ExportFormat::K8sConfigmap => format!("{}-configmap.yaml", env_name),
+        _ => format!("{}.env", env_name),
+    }
+}
+
+/// Puts text on the system clipboard through the terminal (OSC 52), which
+/// also works over SSH and needs no clipboard tool installed.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
 }
@@ -1,45 +1,114 @@
-use crossterm::event::{self, Event as CrosstermEvent, KeyEvent};
+use crate::error::Result;
+use crossterm::event::{self, Event as CrosstermEvent, KeyEvent, KeyEventKind};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Debug)]
 pub enum Event {
     Tick,
+    /// A key press, with its modifiers (Ctrl, Alt, Shift)
     Key(KeyEvent),
     Resize,
+    /// Files under a watched directory were created, changed or removed
+    FilesChanged,
+    /// Reading the terminal failed; no more input events will follow
+    Error(io::Error),
 }
 
 pub struct EventHandler {
-    _tx: mpsc::UnboundedSender<Event>,
+    tx: mpsc::UnboundedSender<Event>,
     rx: mpsc::UnboundedReceiver<Event>,
+    /// Tells the input thread to stop at its next tick
+    stop: Arc<AtomicBool>,
+    input: Option<JoinHandle<()>>,
+    _watcher: Option<RecommendedWatcher>,
 }
 
 impl EventHandler {
     pub fn new(tick_rate: Duration) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
-        let _tx = tx.clone();
-
-        tokio::spawn(async move {
-            loop {
-                if event::poll(tick_rate).unwrap() {
-                    match event::read().unwrap() {
-                        CrosstermEvent::Key(key_event) => {
-                            tx.send(Event::Key(key_event)).unwrap();
-                        }
-                        CrosstermEvent::Resize(_, _) => {
-                            tx.send(Event::Resize).unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        // Polling the terminal blocks, so it gets a thread of its own
+        let input_tx = tx.clone();
+        let input_stop = Arc::clone(&stop);
+        let input = tokio::task::spawn_blocking(move || {
+            while !input_stop.load(Ordering::Relaxed) {
+                let event = match event::poll(tick_rate) {
+                    Ok(true) => match event::read() {
+                        // Some platforms also report releases; act on presses only
+                        Ok(CrosstermEvent::Key(key_event))
+                            if key_event.kind != KeyEventKind::Release =>
+                        {
+                            Some(Event::Key(key_event))
                         }
-                        _ => {}
-                    }
+                        Ok(CrosstermEvent::Resize(_, _)) => Some(Event::Resize),
+                        Ok(_) => None,
+                        Err(e) => Some(Event::Error(e)),
+                    },
+                    Ok(false) => None,
+                    Err(e) => Some(Event::Error(e)),
+                };
+
+                let failed = matches!(event, Some(Event::Error(_)));
+                let sent = event.is_none_or(|event| input_tx.send(event).is_ok());
+                // Stop once the terminal fails or nobody is listening anymore
+                if failed || !sent || input_tx.send(Event::Tick).is_err() {
+                    break;
                 }
-                tx.send(Event::Tick).unwrap();
             }
         });
 
-        Self { _tx, rx }
+        Self {
+            tx,
+            rx,
+            stop,
+            input: Some(input),
+            _watcher: None,
+        }
+    }
+
+    /// Also sends `Event::FilesChanged` whenever something under `path`
+    /// changes, e.g. when the CLI, git or a sync job rewrites an environment.
+    pub fn watch(&mut self, path: &Path) -> Result<()> {
+        let tx = self.tx.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<_>| {
+            if let Ok(notify::Event {
+                kind: EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_),
+                ..
+            }) = event
+            {
+                tx.send(Event::FilesChanged).ok();
+            }
+        })?;
+        watcher.watch(path, RecursiveMode::Recursive)?;
+        self._watcher = Some(watcher);
+        Ok(())
     }
 
     pub async fn next(&mut self) -> Option<Event> {
         self.rx.recv().await
     }
+
+    /// Stops the input thread and waits for it, so nothing reads the
+    /// terminal once the TUI has given it back.
+    pub async fn shutdown(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self._watcher = None;
+        if let Some(input) = self.input.take() {
+            let _ = input.await;
+        }
+    }
+}
+
+impl Drop for EventHandler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
 }
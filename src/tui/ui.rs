@@ -1,22 +1,38 @@
-use crate::tui::app::{App, AppState};
+use crate::commands::{mask, IssueKind, MergeStrategy, ProjectStatus, SwitchPreview};
+use crate::config::{self, PolicyLevel, Theme, VariableMetadata};
+use crate::diff::VariableChange;
+use crate::tui::app::{
+    sort_name, App, AppState, DiffView, MessageKind, ProjectsView, Row, TransferMode, TransferStep,
+    ValidationView, EXPORT_FORMATS, IMPORT_STRATEGIES,
+};
+use clap::ValueEnum;
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
     Frame,
 };
+use std::path::Path;
 
 pub fn draw(f: &mut Frame, app: &mut App) {
     let size = f.size();
 
+    // The footer grows with the messages and the selection summary
+    let selection_line = app.state == AppState::VariableList && !app.selection().is_empty();
+    let footer_height = 3 + app.messages.len() as u16 + u16::from(selection_line);
+
     // Create main layout
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // Header
-            Constraint::Min(0),    // Main content
-            Constraint::Length(3), // Footer
+            Constraint::Length(3),             // Header
+            Constraint::Min(0),                // Main content
+            Constraint::Length(footer_height), // Footer
         ])
         .split(size);
 
@@ -24,7 +40,12 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     draw_header(f, chunks[0], app);
 
     // Draw main content
-    draw_main_content(f, chunks[1], app);
+    match &app.diff {
+        Some(view) if app.state == AppState::DiffView => {
+            draw_diff_view(f, chunks[1], view, app.mask_values)
+        }
+        _ => draw_main_content(f, chunks[1], app),
+    }
 
     // Draw footer
     draw_footer(f, chunks[2], app);
@@ -38,13 +59,68 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         AppState::AddVariable => draw_add_variable_popup(f, app),
         AppState::EditVariable => draw_edit_variable_popup(f, app),
         AppState::ConfirmDelete => draw_confirm_delete_popup(f, app),
+        AppState::CopyTo => draw_copy_to_popup(f, app),
+        AppState::VariableDetail => draw_variable_detail_popup(f, app),
+        AppState::Transfer => draw_transfer_popup(f, app),
+        AppState::Validation => {
+            if let Some(view) = &app.validation {
+                draw_validation_popup(f, view);
+            }
+        }
+        AppState::ConfirmSwitch => {
+            if let Some(preview) = &app.switch_preview {
+                draw_confirm_switch_popup(f, preview);
+            }
+        }
+        AppState::DisplayOptions => draw_display_options_popup(f, app),
+        AppState::Projects => {
+            if let Some(view) = &app.projects {
+                draw_projects_popup(f, view, app.commands.project_dir());
+            }
+        }
         _ => {}
     }
+
+    apply_theme(f.buffer_mut(), app.theme);
+}
+
+/// Recolors the drawn screen for the theme. Everything is drawn in the dark
+/// theme's colors first.
+fn apply_theme(buffer: &mut Buffer, theme: Theme) {
+    let recolor: fn(Color) -> Color = match theme {
+        Theme::Dark => return,
+        Theme::Light => light_color,
+        Theme::Monochrome => |_| Color::Reset,
+    };
+    for cell in &mut buffer.content {
+        if theme == Theme::Monochrome && cell.bg != Color::Reset {
+            cell.modifier |= Modifier::REVERSED;
+        }
+        cell.fg = recolor(cell.fg);
+        cell.bg = recolor(cell.bg);
+    }
+}
+
+/// The shade of a dark theme color that reads on a light background.
+fn light_color(color: Color) -> Color {
+    match color {
+        Color::White => Color::Black,
+        Color::Black => Color::White,
+        Color::Gray => Color::DarkGray,
+        Color::DarkGray => Color::Gray,
+        Color::Yellow | Color::LightYellow => Color::Rgb(150, 100, 0),
+        Color::LightRed => Color::Red,
+        Color::LightGreen => Color::Green,
+        Color::LightBlue => Color::Blue,
+        Color::LightMagenta => Color::Magenta,
+        Color::LightCyan => Color::Cyan,
+        other => other,
+    }
 }
 
 fn draw_header(f: &mut Frame, area: Rect, app: &App) {
     // Create a colorful header with gradient-like effect
-    let header_spans = vec![
+    let mut header_spans = vec![
         Span::styled("🔧 ", Style::default().fg(Color::Yellow)),
         Span::styled(
             "env",
@@ -69,6 +145,9 @@ fn draw_header(f: &mut Frame, area: Rect, app: &App) {
                 .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
         ),
     ];
+    if let Some(status) = &app.status {
+        header_spans.extend(status_spans(status));
+    }
 
     let header = Paragraph::new(Line::from(header_spans)).block(
         Block::default()
@@ -93,8 +172,207 @@ fn draw_main_content(f: &mut Frame, area: Rect, app: &mut App) {
     // Draw environments list
     draw_environments_list(f, chunks[0], app);
 
+    let variable_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(6)])
+        .split(chunks[1]);
+
     // Draw variables list
-    draw_variables_list(f, chunks[1], app);
+    draw_variables_list(f, variable_chunks[0], app);
+
+    // Draw the selected variable's details
+    draw_variable_details(f, variable_chunks[1], app);
+}
+
+/// The `status` summary in brief: only what needs attention is shown.
+fn status_spans(status: &ProjectStatus) -> Vec<Span<'static>> {
+    let separator = || Span::styled(" · ", Style::default().fg(Color::DarkGray));
+    let mut spans = vec![
+        separator(),
+        Span::styled(
+            format!("{} vars", status.variables),
+            Style::default().fg(Color::Gray),
+        ),
+    ];
+    let mut flag = |text: String, color: Color| {
+        spans.push(separator());
+        spans.push(Span::styled(
+            text,
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ));
+    };
+    if status.schema_violations() > 0 {
+        flag(
+            format!("⚠ {} schema issue(s)", status.schema_violations()),
+            Color::LightRed,
+        );
+    }
+    if !status.expiring.is_empty() {
+        flag(
+            format!("⏰ {} expiring", status.expiring.len()),
+            Color::Yellow,
+        );
+    }
+    let unsynced: usize = status.unsynced.iter().map(|(_, changes)| changes).sum();
+    if unsynced > 0 {
+        flag(format!("⇅ {} unsynced", unsynced), Color::LightBlue);
+    }
+    spans
+}
+
+/// A warning for a variable that has expired or expires soon.
+fn expiry_span(metadata: &VariableMetadata) -> Option<Span<'static>> {
+    let expiry = metadata.expiry(chrono::Utc::now(), config::ROTATION_WINDOW_DAYS)?;
+    let color = if expiry.is_expired() {
+        Color::LightRed
+    } else {
+        Color::Yellow
+    };
+    Some(Span::styled(
+        format!("⏰ {}", expiry),
+        Style::default().fg(color).add_modifier(Modifier::BOLD),
+    ))
+}
+
+fn draw_variable_details(f: &mut Frame, area: Rect, app: &App) {
+    let label_style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let none_style = Style::default().fg(Color::DarkGray);
+
+    let lines = match app.selected_variable() {
+        Some(var) => {
+            let metadata = &var.metadata;
+            let description = match &metadata.description {
+                Some(description) => Span::styled(description, Style::default().fg(Color::White)),
+                None => Span::styled("(no description)", none_style),
+            };
+            let tags = if metadata.tags.is_empty() {
+                Span::styled("(none)", none_style)
+            } else {
+                Span::styled(
+                    metadata.tags.join(", "),
+                    Style::default().fg(Color::LightMagenta),
+                )
+            };
+            let updated = match metadata.updated_at {
+                Some(updated_at) => Span::styled(
+                    updated_at
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string(),
+                    Style::default().fg(Color::LightBlue),
+                ),
+                None => Span::styled("(unknown)", none_style),
+            };
+            let mut tag_line = vec![Span::styled("Tags: ", label_style), tags];
+            if metadata.secret {
+                tag_line.push(Span::styled(
+                    "  🔒 secret",
+                    Style::default()
+                        .fg(Color::LightRed)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+            if let Some(expiry) = expiry_span(metadata) {
+                tag_line.extend([Span::raw("  "), expiry]);
+            }
+
+            vec![
+                Line::from(description),
+                Line::from(tag_line),
+                Line::from(vec![Span::styled("Modified: ", label_style), updated]),
+            ]
+        }
+        None => vec![Line::from(Span::styled(
+            "(no variable selected)",
+            none_style,
+        ))],
+    };
+
+    let details = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(
+                    " ℹ️ Details ",
+                    Style::default().fg(Color::LightCyan),
+                ))
+                .border_style(Style::default().fg(Color::DarkGray)),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(details, area);
+}
+
+fn draw_diff_view(f: &mut Frame, area: Rect, view: &DiffView, mask_values: bool) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+
+    let shown = |value: &str| {
+        if mask_values {
+            mask(value)
+        } else {
+            value.to_string()
+        }
+    };
+    let missing_style = Style::default().fg(Color::DarkGray);
+    let mut left_items = Vec::new();
+    let mut right_items = Vec::new();
+    for change in &view.changes {
+        let (key, left, right, color) = match change {
+            VariableChange::Added { key, value } => (key, None, Some(value), Color::LightGreen),
+            VariableChange::Removed { key, value } => (key, Some(value), None, Color::LightRed),
+            VariableChange::Changed { key, old, new } => {
+                (key, Some(old), Some(new), Color::LightYellow)
+            }
+        };
+        let side = |value: Option<&String>| match value {
+            Some(value) => ListItem::new(Line::from(vec![
+                Span::styled(
+                    key.clone(),
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled("=", Style::default().fg(Color::White)),
+                Span::styled(shown(value), Style::default().fg(color)),
+            ])),
+            None => ListItem::new(Line::from(Span::styled(
+                format!("{} (not set)", key),
+                missing_style,
+            ))),
+        };
+        left_items.push(side(left));
+        right_items.push(side(right));
+    }
+    if view.changes.is_empty() {
+        let same = ListItem::new(Line::from(Span::styled("(no differences)", missing_style)));
+        left_items.push(same.clone());
+        right_items.push(same);
+    }
+
+    for (items, env, area) in [
+        (left_items, &view.left, chunks[0]),
+        (right_items, &view.right, chunks[1]),
+    ] {
+        let mut state = ListState::default();
+        state.select((!view.changes.is_empty()).then_some(view.selected));
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(Span::styled(
+                        format!(" 🔀 {} ", env),
+                        Style::default()
+                            .fg(Color::Yellow)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                    .border_style(Style::default().fg(Color::Yellow)),
+            )
+            .highlight_style(Style::default().bg(Color::DarkGray))
+            .highlight_symbol("❯ ");
+        f.render_stateful_widget(list, area, &mut state);
+    }
 }
 
 fn draw_environments_list(f: &mut Frame, area: Rect, app: &mut App) {
@@ -127,10 +405,14 @@ fn draw_environments_list(f: &mut Frame, area: Rect, app: &mut App) {
                 ("  ", Style::default())
             };
 
-            ListItem::new(Line::from(vec![
-                Span::styled(prefix, prefix_style),
-                Span::styled(env, style),
-            ]))
+            let mut spans = vec![Span::styled(prefix, prefix_style), Span::styled(env, style)];
+            if app.marked_env.as_ref() == Some(env) {
+                spans.push(Span::styled(
+                    " ◆ marked",
+                    Style::default().fg(Color::LightMagenta),
+                ));
+            }
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
@@ -172,16 +454,46 @@ fn draw_environments_list(f: &mut Frame, area: Rect, app: &mut App) {
         .highlight_symbol("❯ ");
 
     f.render_stateful_widget(list, area, &mut state);
+    draw_list_position(f, area, app.environments.len(), app.selected_env_index);
 }
 
 fn draw_variables_list(f: &mut Frame, area: Rect, app: &mut App) {
+    let grouped = app.grouped();
+    let selection = app.selection();
     let items: Vec<ListItem> = app
-        .variables
-        .iter()
+        .rows()
+        .into_iter()
         .enumerate()
-        .map(|(i, var)| {
+        .map(|(i, row)| {
+            let selected = i == app.selected_var_index && app.state == AppState::VariableList;
+            let var = match row {
+                Row::Group {
+                    name,
+                    count,
+                    collapsed,
+                } => {
+                    let style = if selected {
+                        Style::default()
+                            .fg(Color::Black)
+                            .bg(Color::Yellow)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default()
+                            .fg(Color::LightMagenta)
+                            .add_modifier(Modifier::BOLD)
+                    };
+                    let marker = if collapsed { "▸ " } else { "▾ " };
+                    return ListItem::new(Line::from(vec![
+                        Span::styled(marker, style),
+                        Span::styled(name, style),
+                        Span::styled(format!(" ({})", count), Style::default().fg(Color::Gray)),
+                    ]));
+                }
+                Row::Variable(index) => &app.variables[index],
+            };
+
             // Color the key and value differently
-            let key_style = if i == app.selected_var_index && app.state == AppState::VariableList {
+            let key_style = if selected {
                 Style::default()
                     .fg(Color::Black)
                     .bg(Color::Yellow)
@@ -192,28 +504,74 @@ fn draw_variables_list(f: &mut Frame, area: Rect, app: &mut App) {
                     .add_modifier(Modifier::BOLD)
             };
 
-            let equals_style = if i == app.selected_var_index && app.state == AppState::VariableList
-            {
+            let equals_style = if selected {
                 Style::default().fg(Color::Black).bg(Color::Yellow)
             } else {
                 Style::default().fg(Color::White)
             };
 
-            let value_style = if i == app.selected_var_index && app.state == AppState::VariableList
-            {
+            let value_style = if selected {
                 Style::default().fg(Color::Black).bg(Color::Yellow)
             } else {
                 Style::default().fg(Color::LightGreen)
             };
 
-            ListItem::new(Line::from(vec![
+            // Variables under a group header are indented
+            let indent = if grouped { "  " } else { "" };
+
+            let value = if app.view.value_lengths {
+                format!("({} chars)", var.value.chars().count())
+            } else if app.mask_values {
+                mask(&var.value)
+            } else {
+                truncate(&var.value, app.view.truncate)
+            };
+
+            let mark = if selection.contains(&var.key) {
+                Span::styled(
+                    "● ",
+                    Style::default()
+                        .fg(Color::LightMagenta)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else if selection.is_empty() {
+                Span::raw("")
+            } else {
+                Span::raw("  ")
+            };
+
+            let mut line = vec![
+                Span::raw(indent),
+                mark,
                 Span::styled(&var.key, key_style),
                 Span::styled("=", equals_style),
-                Span::styled(&var.value, value_style),
-            ]))
+                Span::styled(value, value_style),
+            ];
+            if let Some(expiry) = expiry_span(&var.metadata) {
+                line.extend([Span::raw("  "), expiry]);
+            }
+            if let Some(note) = &var.deprecated {
+                line.push(Span::styled(
+                    format!("  ⚠ deprecated: {}", note),
+                    Style::default().fg(Color::LightYellow),
+                ));
+            }
+            if let Some(description) = var
+                .metadata
+                .description
+                .as_ref()
+                .filter(|_| app.view.descriptions)
+            {
+                line.push(Span::styled(
+                    format!("  # {}", description),
+                    Style::default().fg(Color::Gray),
+                ));
+            }
+            ListItem::new(Line::from(line))
         })
         .collect();
 
+    let row_count = items.len();
     let mut state = ListState::default();
     state.select(Some(app.selected_var_index));
 
@@ -252,41 +610,96 @@ fn draw_variables_list(f: &mut Frame, area: Rect, app: &mut App) {
         .highlight_symbol("❯ ");
 
     f.render_stateful_widget(list, area, &mut state);
+    draw_list_position(f, area, row_count, app.selected_var_index);
+}
+
+/// `value` cut to `length` characters with an ellipsis; 0 leaves it whole.
+fn truncate(value: &str, length: usize) -> String {
+    match value.char_indices().nth(length) {
+        Some((end, _)) if length > 0 => format!("{}…", &value[..end]),
+        _ => value.to_string(),
+    }
+}
+
+/// For a bordered list too long for its area, draws a scrollbar on the right
+/// border and an "x–y of N" counter on the bottom one.
+fn draw_list_position(f: &mut Frame, area: Rect, len: usize, selected: usize) {
+    let visible = area.height.saturating_sub(2) as usize;
+    if len <= visible || visible == 0 {
+        return;
+    }
+    // Lists are drawn from the top each frame, scrolling just enough to show
+    // the selected item
+    let offset = selected.saturating_sub(visible - 1);
+
+    let mut scrollbar_state = ScrollbarState::new(len.saturating_sub(visible) + 1).position(offset);
+    f.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None),
+        area.inner(&Margin {
+            vertical: 1,
+            horizontal: 0,
+        }),
+        &mut scrollbar_state,
+    );
+
+    let counter = format!(
+        " {}–{} of {} ",
+        offset + 1,
+        (offset + visible).min(len),
+        len
+    );
+    let width = (counter.chars().count() as u16).min(area.width.saturating_sub(2));
+    let counter_area = Rect {
+        x: area.right().saturating_sub(width + 1),
+        y: area.bottom().saturating_sub(1),
+        width,
+        height: 1,
+    };
+    f.render_widget(
+        Paragraph::new(Span::styled(counter, Style::default().fg(Color::Gray))),
+        counter_area,
+    );
 }
 
 fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
     let mut lines = vec![];
 
-    // Status messages with enhanced colors
-    if !app.status_message.is_empty() {
+    // Status and error messages, oldest first
+    for message in &app.messages {
+        let (icon, icon_color, text_color) = match message.kind {
+            MessageKind::Status => ("✅ ", Color::Green, Color::LightGreen),
+            MessageKind::Error => ("❌ ", Color::Red, Color::LightRed),
+        };
         lines.push(Line::from(vec![
             Span::styled(
-                "✅ ",
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD),
+                icon,
+                Style::default().fg(icon_color).add_modifier(Modifier::BOLD),
             ),
             Span::styled(
-                &app.status_message,
-                Style::default()
-                    .fg(Color::LightGreen)
-                    .add_modifier(Modifier::BOLD),
+                &message.text,
+                Style::default().fg(text_color).add_modifier(Modifier::BOLD),
             ),
         ]));
     }
 
-    if !app.error_message.is_empty() {
+    let selection = app.selection().len();
+    if selection > 0 && app.state == AppState::VariableList {
+        let mode = if app.visual_anchor.is_some() {
+            " (V to finish the range)"
+        } else {
+            ""
+        };
         lines.push(Line::from(vec![
             Span::styled(
-                "❌ ",
-                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-            ),
-            Span::styled(
-                &app.error_message,
+                format!("● {} selected", selection),
                 Style::default()
-                    .fg(Color::LightRed)
+                    .fg(Color::LightMagenta)
                     .add_modifier(Modifier::BOLD),
             ),
+            Span::raw(mode),
+            Span::raw(" | d: Delete | c: Copy to… | s: Secret | x: Export | Esc: Clear"),
         ]));
     }
 
@@ -314,6 +727,27 @@ fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
                     .add_modifier(Modifier::BOLD),
             ),
             Span::raw(": Switch | "),
+            Span::styled(
+                "m",
+                Style::default()
+                    .fg(Color::LightMagenta)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Mark | "),
+            Span::styled(
+                "d",
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Diff with marked | "),
+            Span::styled(
+                "V",
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Validate | "),
             Span::styled(
                 "q",
                 Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
@@ -361,6 +795,13 @@ fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
                 Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
             ),
             Span::raw(": Delete | "),
+            Span::styled(
+                "u",
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Undo | "),
             Span::styled(
                 "F5",
                 Style::default()
@@ -409,7 +850,167 @@ fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
             ),
             Span::raw(": Cancel"),
         ],
-        AppState::ConfirmDelete => vec![
+        AppState::VariableDetail => vec![
+            Span::styled(
+                "Esc",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Close details"),
+        ],
+        AppState::CopyTo => vec![
+            Span::styled(
+                "↑↓",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Choose environment | "),
+            Span::styled(
+                "Enter",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Copy | "),
+            Span::styled(
+                "Esc",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Cancel"),
+        ],
+        AppState::Validation => vec![
+            Span::styled(
+                "↑↓",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Navigate | "),
+            Span::styled(
+                "Enter",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Go to variable | "),
+            Span::styled(
+                "Esc",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Close"),
+        ],
+        AppState::Projects => vec![
+            Span::styled(
+                "↑↓",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Navigate | "),
+            Span::styled(
+                "Enter",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Open project | "),
+            Span::styled(
+                "Esc",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Close"),
+        ],
+        AppState::DisplayOptions => vec![
+            Span::styled(
+                "↑↓",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Navigate | "),
+            Span::styled(
+                "Enter",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Change | "),
+            Span::styled(
+                "Esc",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Close"),
+        ],
+        AppState::Transfer => {
+            let choosing = app
+                .transfer
+                .as_ref()
+                .is_some_and(|panel| panel.step == TransferStep::Choose);
+            let mut spans = vec![
+                Span::styled(
+                    "Tab",
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(": Export/Import | "),
+            ];
+            if choosing {
+                spans.extend([
+                    Span::styled(
+                        "↑↓",
+                        Style::default()
+                            .fg(Color::Cyan)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(": Choose | "),
+                ]);
+            }
+            spans.extend([
+                Span::styled(
+                    "Enter",
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(": Confirm | "),
+                Span::styled(
+                    "Esc",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(": Cancel"),
+            ]);
+            spans
+        }
+        AppState::DiffView => vec![
+            Span::styled(
+                "↑↓",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Navigate | "),
+            Span::styled(
+                "→",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Copy to right | "),
+            Span::styled(
+                "←",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Copy to left | "),
+            Span::styled(
+                "Esc",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Back"),
+        ],
+        AppState::ConfirmDelete | AppState::ConfirmSwitch => vec![
             Span::styled(
                 "y",
                 Style::default()
@@ -496,31 +1097,18 @@ fn draw_add_variable_popup(f: &mut Frame, app: &App) {
             )),
         ]
     } else {
-        vec![
-            Line::from(vec![
-                Span::styled(
-                    "Key: ",
-                    Style::default()
-                        .fg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(&app.input_key, Style::default().fg(Color::LightGreen)),
-            ]),
-            Line::from(vec![
-                Span::styled(
-                    "Value: ",
-                    Style::default()
-                        .fg(Color::Magenta)
-                        .add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(
-                    &app.input_buffer,
-                    Style::default()
-                        .fg(Color::Yellow)
-                        .add_modifier(Modifier::UNDERLINED),
-                ),
-                Span::styled("█", Style::default().fg(Color::White)), // Cursor
-            ]),
+        vec![Line::from(vec![
+            Span::styled(
+                "Key: ",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(&app.input_key, Style::default().fg(Color::LightGreen)),
+        ])]
+        .into_iter()
+        .chain(value_input_lines(app))
+        .chain([
             Line::from(""),
             Line::from(Span::styled(
                 "Press Enter to save variable",
@@ -528,7 +1116,8 @@ fn draw_add_variable_popup(f: &mut Frame, app: &App) {
                     .fg(Color::Gray)
                     .add_modifier(Modifier::ITALIC),
             )),
-        ]
+        ])
+        .collect()
     };
 
     let popup = Paragraph::new(content_lines)
@@ -543,45 +1132,73 @@ fn draw_add_variable_popup(f: &mut Frame, app: &App) {
     f.render_widget(popup, popup_area);
 }
 
+/// The value being typed, red while the schema rejects it and green once it
+/// accepts it, followed by what's wrong with it.
+fn value_input_lines(app: &App) -> Vec<Line<'_>> {
+    let problems = app.input_problems.as_deref();
+    let color = match problems {
+        None => Color::Yellow,
+        Some(problems) if problems.iter().any(|p| p.severity == PolicyLevel::Error) => Color::Red,
+        Some([]) => Color::LightGreen,
+        Some(_) => Color::Yellow,
+    };
+    let mut lines = vec![Line::from(vec![
+        Span::styled(
+            "Value: ",
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            &app.input_buffer,
+            Style::default()
+                .fg(color)
+                .add_modifier(Modifier::UNDERLINED),
+        ),
+        Span::styled("█", Style::default().fg(Color::White)), // Cursor
+    ])];
+    for problem in problems.unwrap_or_default() {
+        let (icon, color) = match problem.severity {
+            PolicyLevel::Error => ("✗", Color::Red),
+            PolicyLevel::Warn => ("⚠", Color::Yellow),
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{} {}", icon, problem.message),
+            Style::default().fg(color),
+        )));
+    }
+    if problems.is_some_and(|problems| problems.is_empty()) {
+        lines.push(Line::from(Span::styled(
+            "✓ Matches the schema",
+            Style::default().fg(Color::LightGreen),
+        )));
+    }
+    lines
+}
+
 fn draw_edit_variable_popup(f: &mut Frame, app: &App) {
     let size = f.size();
     let popup_area = centered_rect(60, 20, size);
 
     f.render_widget(Clear, popup_area);
 
-    let content_lines = vec![
-        Line::from(vec![
-            Span::styled(
-                "Key: ",
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            ),
-            Span::styled(&app.input_key, Style::default().fg(Color::LightGreen)),
-        ]),
-        Line::from(vec![
-            Span::styled(
-                "Value: ",
-                Style::default()
-                    .fg(Color::Magenta)
-                    .add_modifier(Modifier::BOLD),
-            ),
-            Span::styled(
-                &app.input_buffer,
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::UNDERLINED),
-            ),
-            Span::styled("█", Style::default().fg(Color::White)), // Cursor
-        ]),
-        Line::from(""),
-        Line::from(Span::styled(
-            "Press Enter to save changes",
+    let mut content_lines = vec![Line::from(vec![
+        Span::styled(
+            "Key: ",
             Style::default()
-                .fg(Color::Gray)
-                .add_modifier(Modifier::ITALIC),
-        )),
-    ];
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(&app.input_key, Style::default().fg(Color::LightGreen)),
+    ])];
+    content_lines.extend(value_input_lines(app));
+    content_lines.push(Line::from(""));
+    content_lines.push(Line::from(Span::styled(
+        "Press Enter to save changes",
+        Style::default()
+            .fg(Color::Gray)
+            .add_modifier(Modifier::ITALIC),
+    )));
 
     let popup = Paragraph::new(content_lines)
         .block(
@@ -606,25 +1223,40 @@ fn draw_confirm_delete_popup(f: &mut Frame, app: &App) {
 
     f.render_widget(Clear, popup_area);
 
+    let selection = app.targets();
     let var_name = app
-        .variables
-        .get(app.selected_var_index)
+        .selected_variable()
         .map(|v| v.key.as_str())
         .unwrap_or("unknown");
 
-    let content_lines = vec![
+    let question = if selection.len() > 1 {
+        Line::from(vec![
+            Span::styled("Delete ", Style::default().fg(Color::White)),
+            Span::styled(
+                format!("{} selected variables", selection.len()),
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled("?", Style::default().fg(Color::White)),
+        ])
+    } else {
         Line::from(vec![
             Span::styled("Delete variable ", Style::default().fg(Color::White)),
             Span::styled("'", Style::default().fg(Color::Gray)),
             Span::styled(
-                var_name,
+                selection.first().map_or(var_name, String::as_str),
                 Style::default()
                     .fg(Color::LightRed)
                     .add_modifier(Modifier::BOLD),
             ),
             Span::styled("'", Style::default().fg(Color::Gray)),
             Span::styled("?", Style::default().fg(Color::White)),
-        ]),
+        ])
+    };
+
+    let content_lines = vec![
+        question,
         Line::from(""),
         Line::from(vec![
             Span::styled(
@@ -661,7 +1293,598 @@ fn draw_confirm_delete_popup(f: &mut Frame, app: &App) {
     f.render_widget(popup, popup_area);
 }
 
-fn draw_help_popup(f: &mut Frame, _app: &App) {
+fn draw_variable_detail_popup(f: &mut Frame, app: &App) {
+    let Some(var) = app.selected_variable() else {
+        return;
+    };
+    let size = f.size();
+    let popup_area = centered_rect(80, 60, size);
+
+    f.render_widget(Clear, popup_area);
+
+    let label_style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let none_style = Style::default().fg(Color::DarkGray);
+    let metadata = &var.metadata;
+    let timestamp = |time: Option<chrono::DateTime<chrono::Utc>>| match time {
+        Some(time) => Span::styled(
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            Style::default().fg(Color::LightBlue),
+        ),
+        None => Span::styled("(unknown)", none_style),
+    };
+    let masked = app.mask_values && !app.reveal_value;
+    let value = if masked {
+        mask(&var.value)
+    } else {
+        var.value.clone()
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            &var.key,
+            Style::default()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(Span::styled("Value:", label_style)),
+    ];
+    // Every line of the value, wrapped rather than cut off
+    lines.extend(value.lines().map(|line| {
+        Line::from(Span::styled(
+            line.to_string(),
+            Style::default().fg(Color::LightGreen),
+        ))
+    }));
+    lines.extend([
+        Line::from(Span::styled(
+            format!("({} characters)", var.value.chars().count()),
+            none_style,
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Description: ", label_style),
+            match &metadata.description {
+                Some(description) => Span::styled(description, Style::default().fg(Color::White)),
+                None => Span::styled("(none)", none_style),
+            },
+        ]),
+        Line::from(vec![
+            Span::styled("Tags: ", label_style),
+            if metadata.tags.is_empty() {
+                Span::styled("(none)", none_style)
+            } else {
+                Span::styled(
+                    metadata.tags.join(", "),
+                    Style::default().fg(Color::LightMagenta),
+                )
+            },
+        ]),
+        Line::from(vec![
+            Span::styled("Secret: ", label_style),
+            if metadata.secret {
+                Span::styled(
+                    "🔒 yes",
+                    Style::default()
+                        .fg(Color::LightRed)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Span::styled("no", none_style)
+            },
+        ]),
+        Line::from(vec![
+            Span::styled("Created: ", label_style),
+            timestamp(metadata.created_at),
+        ]),
+        Line::from(vec![
+            Span::styled("Modified: ", label_style),
+            timestamp(metadata.updated_at),
+        ]),
+    ]);
+    if let Some(expires_at) = metadata.expires_at {
+        let mut line = vec![
+            Span::styled("Expires: ", label_style),
+            timestamp(Some(expires_at)),
+        ];
+        if let Some(expiry) = expiry_span(metadata) {
+            line.extend([Span::raw("  "), expiry]);
+        }
+        lines.push(Line::from(line));
+    }
+
+    let action = |key: &'static str, color: Color| {
+        Span::styled(key, Style::default().fg(color).add_modifier(Modifier::BOLD))
+    };
+    let reveal = if masked { ": Reveal | " } else { ": Hide | " };
+    let mut actions = vec![
+        action("e", Color::Blue),
+        Span::raw(": Edit | "),
+        action("d", Color::Red),
+        Span::raw(": Delete | "),
+        action("c", Color::Green),
+        Span::raw(": Copy to… | "),
+        action("s", Color::LightRed),
+        Span::raw(": Mark secret | "),
+    ];
+    if app.mask_values {
+        actions.extend([action("r", Color::Yellow), Span::raw(reveal)]);
+    }
+    actions.extend([action("Esc", Color::Red), Span::raw(": Close")]);
+    lines.extend([Line::from(""), Line::from(actions)]);
+
+    let popup = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    " 🔍 Variable Details ",
+                    Style::default()
+                        .fg(Color::LightCyan)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(popup, popup_area);
+}
+
+fn draw_copy_to_popup(f: &mut Frame, app: &App) {
+    let size = f.size();
+    let popup_area = centered_rect(50, 40, size);
+
+    f.render_widget(Clear, popup_area);
+
+    let items: Vec<ListItem> = app
+        .copy_targets()
+        .into_iter()
+        .map(|env| ListItem::new(Span::styled(env, Style::default().fg(Color::LightBlue))))
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(app.selected_copy_index));
+
+    let count = app.targets().len();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    format!(" 📋 Copy {} variable(s) to ", count),
+                    Style::default()
+                        .fg(Color::LightGreen)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("❯ ");
+
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+/// Most changed keys the switch confirmation lists
+const SWITCH_PREVIEW_KEYS: usize = 8;
+
+fn draw_confirm_switch_popup(f: &mut Frame, preview: &SwitchPreview) {
+    let size = f.size();
+    let popup_area = centered_rect(60, 50, size);
+
+    f.render_widget(Clear, popup_area);
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::raw("Switch from "),
+            Span::styled(
+                format!("'{}'", preview.from),
+                Style::default().fg(Color::LightBlue),
+            ),
+            Span::raw(" to "),
+            Span::styled(
+                format!("'{}'", preview.to),
+                Style::default()
+                    .fg(Color::LightGreen)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("?"),
+        ]),
+        Line::from(""),
+    ];
+
+    if preview.changes.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "Both environments set the same variables",
+            Style::default().fg(Color::Gray),
+        )));
+    } else {
+        lines.push(Line::from(Span::styled(
+            format!("{} variable(s) differ:", preview.changes.len()),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for change in preview.changes.iter().take(SWITCH_PREVIEW_KEYS) {
+            let (symbol, key, color) = match change {
+                VariableChange::Added { key, .. } => ("+", key, Color::Green),
+                VariableChange::Removed { key, .. } => ("-", key, Color::Red),
+                VariableChange::Changed { key, .. } => ("~", key, Color::Yellow),
+            };
+            lines.push(Line::from(Span::styled(
+                format!("  {} {}", symbol, key),
+                Style::default().fg(color),
+            )));
+        }
+        if preview.changes.len() > SWITCH_PREVIEW_KEYS {
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "  … and {} more",
+                    preview.changes.len() - SWITCH_PREVIEW_KEYS
+                ),
+                Style::default().fg(Color::Gray),
+            )));
+        }
+    }
+
+    if !preview.effects.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Also:",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for effect in &preview.effects {
+            lines.push(Line::from(format!("  • {}", effect)));
+        }
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled(
+            "y",
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(": Switch | "),
+        Span::styled(
+            "n",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(": Cancel"),
+    ]));
+
+    let popup = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    " 🔀 Switch Environment ",
+                    Style::default()
+                        .fg(Color::LightYellow)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(popup, popup_area);
+}
+
+fn draw_validation_popup(f: &mut Frame, view: &ValidationView) {
+    let size = f.size();
+    let popup_area = centered_rect(70, 60, size);
+
+    f.render_widget(Clear, popup_area);
+
+    let items: Vec<ListItem> = view
+        .issues
+        .iter()
+        .map(|issue| {
+            let (label, color) = match issue.kind {
+                IssueKind::Missing => ("missing   ", Color::LightRed),
+                IssueKind::Invalid => ("invalid   ", Color::Red),
+                IssueKind::Expiring => ("expiring  ", Color::Yellow),
+                IssueKind::Deprecated => ("deprecated", Color::LightYellow),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", label),
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    issue.key.clone(),
+                    Style::default()
+                        .fg(Color::LightCyan)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("  {}", issue.message),
+                    Style::default().fg(Color::Gray),
+                ),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(view.selected));
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    format!(" 🔍 {} problem(s) in '{}' ", view.issues.len(), view.env),
+                    Style::default()
+                        .fg(Color::LightRed)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Red)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("❯ ");
+
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+fn draw_projects_popup(f: &mut Frame, view: &ProjectsView, current: Option<&Path>) {
+    let size = f.size();
+    let popup_area = centered_rect(70, 50, size);
+
+    f.render_widget(Clear, popup_area);
+
+    let current = current.and_then(|dir| dir.canonicalize().ok());
+    let items: Vec<ListItem> = view
+        .projects
+        .iter()
+        .map(|project| {
+            let name = project
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| project.display().to_string());
+            let mut spans = vec![
+                Span::styled(
+                    name,
+                    Style::default()
+                        .fg(Color::LightCyan)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("  {}", project.display()),
+                    Style::default().fg(Color::Gray),
+                ),
+            ];
+            if current.as_ref() == Some(project) {
+                spans.push(Span::styled(
+                    "  (current)",
+                    Style::default().fg(Color::LightGreen),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(view.selected));
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    " 📂 Projects ",
+                    Style::default()
+                        .fg(Color::LightBlue)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Blue)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("❯ ");
+
+    f.render_stateful_widget(list, popup_area, &mut state);
+}
+
+fn draw_transfer_popup(f: &mut Frame, app: &App) {
+    let Some(panel) = &app.transfer else {
+        return;
+    };
+    let size = f.size();
+    let popup_area = centered_rect(60, 50, size);
+
+    f.render_widget(Clear, popup_area);
+
+    let tab = |mode: TransferMode, label: &'static str| {
+        let style = if panel.mode == mode {
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        Span::styled(label, style)
+    };
+    let mut lines = vec![
+        Line::from(vec![
+            tab(TransferMode::Export, " Export "),
+            Span::raw(" "),
+            tab(TransferMode::Import, " Import "),
+        ]),
+        Line::from(""),
+    ];
+
+    match (panel.mode, panel.step) {
+        (TransferMode::Export, TransferStep::Choose) => {
+            let count = match app.selection().len() {
+                0 => app.variables.len(),
+                marked => marked,
+            };
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "Format for {} variable(s) of '{}':",
+                    count, app.current_environment
+                ),
+                Style::default().fg(Color::Cyan),
+            )));
+            for (i, format) in EXPORT_FORMATS.iter().enumerate() {
+                let name = format
+                    .to_possible_value()
+                    .map(|value| value.get_name().to_string())
+                    .unwrap_or_default();
+                lines.push(choice_line(&name, i == panel.selected));
+            }
+            lines.push(Line::from(""));
+            lines.push(hint_line("c: copy to clipboard | Enter: write to a file"));
+        }
+        (TransferMode::Import, TransferStep::Choose) => {
+            let (path, entries) = panel
+                .pending
+                .as_ref()
+                .map_or(("", 0), |(path, entries)| (path.as_str(), entries.len()));
+            lines.push(Line::from(Span::styled(
+                format!("{} variable(s) in {}. Keys already set:", entries, path),
+                Style::default().fg(Color::Cyan),
+            )));
+            for (i, strategy) in IMPORT_STRATEGIES.iter().enumerate() {
+                let label = match strategy {
+                    MergeStrategy::Ours => "keep the current values",
+                    _ => "take the imported values",
+                };
+                lines.push(choice_line(label, i == panel.selected));
+            }
+        }
+        (mode, TransferStep::Path) => {
+            let prompt = match mode {
+                TransferMode::Export => "Write to: ",
+                TransferMode::Import => "Import from (.env, .json, .toml, .yaml): ",
+            };
+            lines.push(Line::from(vec![
+                Span::styled(
+                    prompt,
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    &app.input_buffer,
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::UNDERLINED),
+                ),
+                Span::styled("█", Style::default().fg(Color::White)), // Cursor
+            ]));
+        }
+    }
+
+    let popup = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    " 📦 Export / Import ",
+                    Style::default()
+                        .fg(Color::LightGreen)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(popup, popup_area);
+}
+
+fn draw_display_options_popup(f: &mut Frame, app: &App) {
+    let size = f.size();
+    let popup_area = centered_rect(50, 30, size);
+
+    f.render_widget(Clear, popup_area);
+
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let truncate = match app.view.truncate {
+        0 => "off".to_string(),
+        length => format!("{} characters", length),
+    };
+    let options = [
+        format!("Descriptions inline: {}", on_off(app.view.descriptions)),
+        format!("Truncate values: {}", truncate),
+        format!(
+            "Value lengths instead of values: {}",
+            on_off(app.view.value_lengths)
+        ),
+    ];
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Sorted {} (S to change)", sort_name(app.view.sort)),
+            Style::default().fg(Color::Cyan),
+        )),
+        Line::from(""),
+    ];
+    for (i, option) in options.iter().enumerate() {
+        lines.push(choice_line(option, i == app.selected_option));
+    }
+    lines.push(Line::from(""));
+    lines.push(hint_line("Saved to your user config"));
+
+    let popup = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    " 👁 Display Options ",
+                    Style::default()
+                        .fg(Color::LightCyan)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(popup, popup_area);
+}
+
+fn choice_line(label: &str, selected: bool) -> Line<'static> {
+    match selected {
+        true => Line::from(Span::styled(
+            format!("❯ {}", label),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        false => Line::from(Span::styled(
+            format!("  {}", label),
+            Style::default().fg(Color::LightBlue),
+        )),
+    }
+}
+
+fn hint_line(text: &'static str) -> Line<'static> {
+    Line::from(Span::styled(
+        text,
+        Style::default()
+            .fg(Color::Gray)
+            .add_modifier(Modifier::ITALIC),
+    ))
+}
+
+fn draw_help_popup(f: &mut Frame, app: &mut App) {
     let size = f.size();
     let popup_area = centered_rect(80, 70, size);
 
@@ -723,6 +1946,78 @@ fn draw_help_popup(f: &mut Frame, _app: &App) {
             ),
             Span::raw("              Switch to selected environment"),
         ]),
+        Line::from(vec![
+            Span::styled(
+                "  Enter/Space",
+                Style::default()
+                    .fg(Color::LightGreen)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("        Expand/collapse the selected variable group"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  Enter",
+                Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("              Show the selected variable's full value and details"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  Space",
+                Style::default()
+                    .fg(Color::LightMagenta)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("              Mark/unmark the selected variable"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  V",
+                Style::default()
+                    .fg(Color::LightMagenta)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("                  Start/finish marking a range of variables"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  m, then d",
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("         Mark an environment, then diff another against it"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  V",
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("                  In Environments, validate the selected environment"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  p",
+                Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("                  In Environments, switch to another registered project"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  ←/→",
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("                In a diff, copy the value to the other side"),
+        ]),
         Line::from(vec![
             Span::styled(
                 "  q",
@@ -771,7 +2066,72 @@ fn draw_help_popup(f: &mut Frame, _app: &App) {
                     .fg(Color::LightRed)
                     .add_modifier(Modifier::BOLD),
             ),
-            Span::raw("        Delete selected variable"),
+            Span::raw("        Delete selected variable(s)"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  u",
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" / "),
+            Span::styled(
+                "Ctrl+R",
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("         Undo / redo the last add, edit, delete or secret marking"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  c",
+                Style::default()
+                    .fg(Color::LightGreen)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("                  Copy selected variable(s) to another environment"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  s",
+                Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("                  Mark selected variable(s) as secret"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  S",
+                Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("                  Sort by group, alphabetically or by last change"),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  o",
+                Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(
+                "                  Display options: descriptions, value truncation, value lengths",
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "  x",
+                Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(
+                "                  Export or import: marked variables or the whole environment",
+            ),
         ]),
         Line::from(vec![
             Span::styled(
@@ -847,6 +2207,13 @@ fn draw_help_popup(f: &mut Frame, _app: &App) {
                     .add_modifier(Modifier::BOLD),
             ),
             Span::raw(" to toggle this help, "),
+            Span::styled(
+                "↑↓/PgUp/PgDn",
+                Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" to scroll, "),
             Span::styled(
                 "'Esc'",
                 Style::default()
@@ -857,7 +2224,19 @@ fn draw_help_popup(f: &mut Frame, _app: &App) {
         ]),
     ];
 
+    // Keep the scroll within the text, wherever the keys took it. Long lines
+    // wrap, so count the lines they take up on screen.
+    let width = popup_area.width.saturating_sub(2).max(1) as usize;
+    let line_count: usize = help_lines
+        .iter()
+        .map(|line| line.width().max(1).div_ceil(width))
+        .sum();
+    let visible = popup_area.height.saturating_sub(2) as usize;
+    let max_scroll = line_count.saturating_sub(visible);
+    app.help_scroll = app.help_scroll.min(max_scroll as u16);
+
     let help_paragraph = Paragraph::new(help_lines)
+        .scroll((app.help_scroll, 0))
         .block(
             Block::default()
                 .title(Span::styled(
@@ -872,6 +2251,21 @@ fn draw_help_popup(f: &mut Frame, _app: &App) {
         .wrap(Wrap { trim: true });
 
     f.render_widget(help_paragraph, popup_area);
+
+    if max_scroll > 0 {
+        let mut scrollbar_state =
+            ScrollbarState::new(max_scroll + 1).position(app.help_scroll as usize);
+        f.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None),
+            popup_area.inner(&Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut scrollbar_state,
+        );
+    }
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -0,0 +1,370 @@
+use crate::error::{EnvMatchError, Result};
+use colored::*;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::debug;
+
+/// The latest release, from the GitHub API.
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/gonchihernandez/envMatch/releases/latest";
+/// Asset listing the SHA-256 of every other asset, as `sha256sum` prints them
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Debug)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Result<&Asset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| update_failed(format!("release {} has no {}", self.tag_name, name)))
+    }
+}
+
+/// The release asset built for this platform, e.g. `envMatch-x86_64-linux`
+/// or `envMatch-aarch64-macos`, with `.exe` on Windows.
+pub fn asset_name() -> String {
+    format!(
+        "envMatch-{}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+/// Reads `v1.2.3` or `1.2.3` as numbers; anything after the patch number,
+/// such as `-rc.1`, is ignored.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().trim_start_matches('v');
+    let mut parts = version.splitn(3, '.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = parts.next()?;
+    let end = patch
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(patch.len());
+    Some((major, minor, patch[..end].parse().ok()?))
+}
+
+/// Whether the release tagged `tag` is newer than version `current`.
+pub fn is_newer(tag: &str, current: &str) -> bool {
+    match (parse_version(tag), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Checks `bytes` against the line for `name` in a `SHA256SUMS` file.
+pub fn verify_checksum(bytes: &[u8], name: &str, checksums: &str) -> Result<()> {
+    let expected = checksums
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        // `sha256sum` marks binary mode with a `*` before the name
+        .find(|(_, file)| file.trim().trim_start_matches('*') == name)
+        .map(|(hash, _)| hash.to_ascii_lowercase())
+        .ok_or_else(|| update_failed(format!("{} doesn't list {}", CHECKSUMS_ASSET, name)))?;
+    let actual: String = Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    if actual != expected {
+        return Err(EnvMatchError::ChecksumMismatch {
+            asset: name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The package manager `exe` was installed with, and how to update it there.
+pub fn package_manager(exe: &Path) -> Option<(&'static str, &'static str)> {
+    let path = exe.to_string_lossy().replace('\\', "/").to_lowercase();
+    if path.contains("/cellar/") || path.contains("/homebrew/") || path.contains("/linuxbrew/") {
+        Some(("Homebrew", "brew upgrade envmatch"))
+    } else if path.contains("/winget/") {
+        Some(("WinGet", "winget upgrade envMatch"))
+    } else if path.contains("/scoop/") {
+        Some(("Scoop", "scoop update envmatch"))
+    } else if path.contains("/.cargo/bin/") {
+        Some(("cargo", "cargo install envMatch"))
+    } else {
+        None
+    }
+}
+
+struct Updater {
+    agent: ureq::Agent,
+    url: String,
+}
+
+impl Updater {
+    fn new(url: &str) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(TIMEOUT)
+                .user_agent(&format!("envMatch/{}", env!("CARGO_PKG_VERSION")))
+                .build(),
+            url: url.to_string(),
+        }
+    }
+
+    fn latest_release(&self) -> Result<Release> {
+        debug!(url = %self.url, "looking up the latest release");
+        self.agent
+            .get(&self.url)
+            .set("Accept", "application/vnd.github+json")
+            .call()
+            .map_err(|e| match e {
+                ureq::Error::Status(404, _) => {
+                    update_failed("no release has been published yet".to_string())
+                }
+                e => update_failed(e.to_string()),
+            })?
+            .into_json()
+            .map_err(|e| update_failed(e.to_string()))
+    }
+
+    fn download(&self, asset: &Asset) -> Result<Vec<u8>> {
+        debug!(url = %asset.browser_download_url, "downloading");
+        let mut bytes = Vec::new();
+        self.agent
+            .get(&asset.browser_download_url)
+            .call()
+            .map_err(|e| update_failed(e.to_string()))?
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .map_err(|e| update_failed(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    /// Downloads this platform's binary from `release` and checks it
+    /// against the release's checksums.
+    fn fetch_binary(&self, release: &Release) -> Result<Vec<u8>> {
+        let name = asset_name();
+        let binary = release.asset(&name)?;
+        let checksums = release.asset(CHECKSUMS_ASSET)?;
+        let checksums = String::from_utf8(self.download(checksums)?)
+            .map_err(|_| update_failed(format!("{} isn't text", CHECKSUMS_ASSET)))?;
+        let bytes = self.download(binary)?;
+        verify_checksum(&bytes, &name, &checksums)?;
+        Ok(bytes)
+    }
+}
+
+/// Puts `bytes` in place of the executable at `exe`. The new binary is
+/// written next to it first and renamed over it, so an interrupted update
+/// leaves the old one working. Windows can't replace a running executable,
+/// but it can rename it, so there the old one is moved aside first.
+pub fn replace_binary(exe: &Path, bytes: &[u8]) -> Result<()> {
+    let dir = exe.parent().unwrap_or(Path::new("."));
+    let mut staged =
+        tempfile::NamedTempFile::new_in(dir).map_err(|e| EnvMatchError::storage(dir, e))?;
+    staged
+        .write_all(bytes)
+        .map_err(|e| EnvMatchError::storage(staged.path(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(staged.path(), fs::Permissions::from_mode(0o755))
+            .map_err(|e| EnvMatchError::storage(staged.path(), e))?;
+    }
+    let old = exe.with_extension("old.exe");
+    if cfg!(windows) {
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).map_err(|e| EnvMatchError::storage(exe, e))?;
+    }
+    if let Err(e) = staged.persist(exe) {
+        if cfg!(windows) {
+            let _ = fs::rename(&old, exe);
+        }
+        return Err(EnvMatchError::storage(exe, e.error));
+    }
+    Ok(())
+}
+
+/// Looks for a newer release and, unless `check_only`, installs it over the
+/// running executable after verifying its checksum. Installs made by a
+/// package manager are left to it.
+pub fn self_update(check_only: bool) -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let updater = Updater::new(LATEST_RELEASE_URL);
+    let release = updater.latest_release()?;
+
+    if !is_newer(&release.tag_name, current) {
+        println!(
+            "{}",
+            format!("✅ envMatch {} is the latest version", current)
+                .bright_green()
+                .bold()
+        );
+        return Ok(());
+    }
+    println!(
+        "{} {} {}",
+        "⬆️  envMatch".bright_blue().bold(),
+        release
+            .tag_name
+            .trim_start_matches('v')
+            .bright_cyan()
+            .bold(),
+        format!("is available (you have {})", current).bright_white()
+    );
+
+    let exe = std::env::current_exe().map_err(|e| update_failed(e.to_string()))?;
+    let exe: PathBuf = fs::canonicalize(&exe).unwrap_or(exe);
+    let managed = package_manager(&exe);
+    if check_only {
+        let how = match managed {
+            Some((_, command)) => format!("`{}`", command),
+            None => "`envMatch self-update`".to_string(),
+        };
+        println!(
+            "   {} {}",
+            "💡 Update with".bright_magenta(),
+            how.bright_cyan()
+        );
+        return Ok(());
+    }
+    if let Some((manager, command)) = managed {
+        return Err(EnvMatchError::PackageManaged {
+            manager: manager.to_string(),
+            command: command.to_string(),
+        });
+    }
+
+    let bytes = updater.fetch_binary(&release)?;
+    replace_binary(&exe, &bytes)?;
+    println!(
+        "{} {}",
+        "✅ Updated envMatch to".bright_green().bold(),
+        release
+            .tag_name
+            .trim_start_matches('v')
+            .bright_green()
+            .bold()
+    );
+    Ok(())
+}
+
+fn update_failed(message: String) -> EnvMatchError {
+    EnvMatchError::UpdateFailed { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("v0.10.0", "0.9.3"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1.0-rc.1", "0.1.0"));
+        assert!(!is_newer("nightly", "0.1.0"));
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let checksums = "\
+2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  envMatch-x86_64-linux
+0000000000000000000000000000000000000000000000000000000000000000 *envMatch-x86_64-windows.exe
+";
+        verify_checksum(b"hello", "envMatch-x86_64-linux", checksums).unwrap();
+        assert!(matches!(
+            verify_checksum(b"hello", "envMatch-x86_64-windows.exe", checksums),
+            Err(EnvMatchError::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(
+            verify_checksum(b"hello", "envMatch-aarch64-macos", checksums),
+            Err(EnvMatchError::UpdateFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_package_manager() {
+        let managed = |path: &str| package_manager(Path::new(path)).map(|(name, _)| name);
+        assert_eq!(
+            managed("/opt/homebrew/Cellar/envmatch/0.1.0/bin/envMatch"),
+            Some("Homebrew")
+        );
+        assert_eq!(
+            managed(r"C:\Users\me\AppData\Local\Microsoft\WinGet\Packages\envMatch\envMatch.exe"),
+            Some("WinGet")
+        );
+        assert_eq!(managed("/usr/local/bin/envMatch"), None);
+    }
+
+    #[test]
+    fn test_replace_binary() {
+        let dir = TempDir::new().unwrap();
+        let exe = dir.path().join("envMatch");
+        fs::write(&exe, "old").unwrap();
+        replace_binary(&exe, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "new");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&exe).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+    }
+
+    #[test]
+    fn test_fetch_binary_verifies_the_download() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let name = asset_name();
+        let release = Release {
+            tag_name: "v9.0.0".to_string(),
+            assets: [name.as_str(), CHECKSUMS_ASSET]
+                .into_iter()
+                .map(|asset| Asset {
+                    name: asset.to_string(),
+                    browser_download_url: format!("{}/{}", base, asset),
+                })
+                .collect(),
+        };
+        let checksums = format!(
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  {}\n",
+            name
+        );
+        let serve = std::thread::spawn(move || {
+            for body in ["hello", "tampered"] {
+                for _ in 0..2 {
+                    let request = server.recv().unwrap();
+                    let response = match request.url().ends_with(CHECKSUMS_ASSET) {
+                        true => checksums.clone(),
+                        false => body.to_string(),
+                    };
+                    request
+                        .respond(tiny_http::Response::from_string(response))
+                        .unwrap();
+                }
+            }
+        });
+
+        let updater = Updater::new(&base);
+        assert_eq!(updater.fetch_binary(&release).unwrap(), b"hello");
+        assert!(matches!(
+            updater.fetch_binary(&release),
+            Err(EnvMatchError::ChecksumMismatch { .. })
+        ));
+        serve.join().unwrap();
+    }
+}